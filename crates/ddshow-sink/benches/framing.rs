@@ -0,0 +1,116 @@
+//! Compares the write throughput of every framing on chatty `Schedule` and `Messages`
+//! traffic, averaging 2.5 events per logged batch, along with checksumming on its own.
+//! Run it with `cargo bench -p ddshow-sink`
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use ddshow_sink::{
+    framing::{crc32c, Framing},
+    LogWriter,
+};
+use ddshow_types::{
+    timely_logging::{MessagesEvent, ScheduleEvent, StartStop, TimelyEvent},
+    ChannelId, OperatorId, WorkerId,
+};
+use std::time::Duration;
+use timely::dataflow::operators::capture::{Event, EventPusher};
+
+type LogBatch = Event<Duration, (Duration, WorkerId, TimelyEvent)>;
+
+const BATCHES: usize = 10_000;
+
+/// Timely's loggers push a progress update after every batch of events
+fn log_batches() -> Vec<LogBatch> {
+    (0..BATCHES)
+        .flat_map(|idx| {
+            let time = Duration::from_micros(idx as u64);
+            let worker = WorkerId::new(idx % 4);
+
+            let events = (0..1 + idx % 4)
+                .map(|event| {
+                    let event = if (idx + event) % 2 == 0 {
+                        TimelyEvent::Schedule(ScheduleEvent {
+                            id: OperatorId::new(idx % 64),
+                            start_stop: if event % 2 == 0 {
+                                StartStop::Start
+                            } else {
+                                StartStop::Stop
+                            },
+                        })
+                    } else {
+                        TimelyEvent::Messages(MessagesEvent {
+                            is_send: event % 2 == 0,
+                            channel: ChannelId::new(idx % 32),
+                            source: worker,
+                            target: WorkerId::new((idx + 1) % 4),
+                            seq_no: idx,
+                            length: 1 + idx % 1024,
+                        })
+                    };
+
+                    (time, worker, event)
+                })
+                .collect();
+
+            let next = Duration::from_micros(idx as u64 + 1);
+            vec![
+                Event::Messages(time, events),
+                Event::Progress(vec![(next, 1), (time, -1)]),
+            ]
+        })
+        .collect()
+}
+
+fn write_framings(c: &mut Criterion) {
+    let batches = log_batches();
+    let log_events: usize = batches
+        .iter()
+        .map(|batch| match batch {
+            Event::Messages(_, data) => data.len(),
+            Event::Progress(_) => 0,
+        })
+        .sum();
+
+    let formats = [
+        ("bare", Framing::Single { checksums: false }),
+        ("v1 + checksums", Framing::Single { checksums: true }),
+        ("v2", Framing::Batched { checksums: false }),
+        ("v2 + checksums", Framing::Batched { checksums: true }),
+    ];
+
+    let mut group = c.benchmark_group("write");
+    group.throughput(Throughput::Elements(log_events as u64));
+
+    for &(name, framing) in formats.iter() {
+        let mut buffer = Vec::new();
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter(|| {
+                buffer.clear();
+
+                let mut writer = LogWriter::new(&mut buffer, framing);
+                for batch in batches.iter() {
+                    writer.push(batch.clone());
+                }
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn checksums(c: &mut Criterion) {
+    let mut group = c.benchmark_group("crc32c");
+
+    for &len in [64, 4 * 1024, 64 * 1024].iter() {
+        let bytes: Vec<u8> = (0..len).map(|idx| (idx * 31 + 7) as u8).collect();
+
+        group.throughput(Throughput::Bytes(len as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(len), &bytes, |b, bytes| {
+            b.iter(|| crc32c(bytes))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, write_framings, checksums);
+criterion_main!(benches);
@@ -28,16 +28,36 @@ where
     }
 
     /// Publishes a batch of logged events and advances the capability.
-    pub fn publish_batch<D2, T2>(&mut self, &time: &Duration, data: &mut Vec<(Duration, D2, T2)>)
+    pub fn publish_batch<D2, T2>(&mut self, time: &Duration, data: &mut Vec<(Duration, D2, T2)>)
     where
         D: From<D2>,
         T: From<T2>,
+    {
+        self.publish_batch_with(time, data, |_| {});
+    }
+
+    /// Publishes a batch of logged events, calling `inspect` on each
+    /// of them after they're converted, and advances the capability.
+    pub fn publish_batch_with<D2, T2, F>(
+        &mut self,
+        &time: &Duration,
+        data: &mut Vec<(Duration, D2, T2)>,
+        mut inspect: F,
+    ) where
+        D: From<D2>,
+        T: From<T2>,
+        F: FnMut(&mut (Duration, D, T)),
     {
         if !data.is_empty() {
             self.event_pusher.push(RawEvent::Messages(
                 self.time,
                 data.drain(..)
-                    .map(|(time, worker, data)| (time, D::from(worker), T::from(data)))
+                    .map(|(time, worker, data)| {
+                        let mut event = (time, D::from(worker), T::from(data));
+                        inspect(&mut event);
+
+                        event
+                    })
                     .collect(),
             ));
         }
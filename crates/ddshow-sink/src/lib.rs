@@ -1,21 +1,45 @@
 mod batch_logger;
+pub mod framing;
+mod lints;
+pub mod redaction;
+pub mod timestamps;
 mod writer;
 
 pub use batch_logger::BatchLogger;
-pub use writer::EventWriter;
+pub use lints::CheckCapabilities;
+pub use redaction::{RedactionMode, Redactor};
+pub use timestamps::{CommonTimestamps, TimestampConverter};
+pub use writer::{
+    BatchedEventWriter, EventWriter, LogWriter, DEFAULT_BATCH_BYTES, DEFAULT_BATCH_DELAY,
+    DEFAULT_BATCH_EVENTS,
+};
 
 #[cfg(feature = "ddflow")]
 use ddshow_types::differential_logging::DifferentialEvent;
-use ddshow_types::{timely_logging::TimelyEvent, WorkerId};
+#[cfg(feature = "timely-next")]
+use ddshow_types::reachability_logging::TrackerEvent;
+use ddshow_types::{
+    progress_logging::{StructuredProgressEvent, TimelyProgressEvent},
+    timely_logging::TimelyEvent,
+    WorkerId,
+};
 #[cfg(feature = "ddflow")]
 use differential_dataflow::logging::DifferentialEvent as RawDifferentialEvent;
+use framing::Framing;
 use std::{
     any::Any,
+    env,
     fs::{self, File},
     io::{self, BufWriter, Write},
     path::{Path, PathBuf},
 };
-use timely::{communication::Allocate, logging::TimelyEvent as RawTimelyEvent, worker::Worker};
+#[cfg(feature = "timely-next")]
+use timely::progress::reachability::logging::TrackerEvent as RawTrackerEvent;
+use timely::{
+    communication::Allocate,
+    logging::{TimelyEvent as RawTimelyEvent, TimelyProgressEvent as RawTimelyProgressEvent},
+    worker::Worker,
+};
 
 // TODO: Allow configuring what events are saved and support compression
 
@@ -28,6 +52,9 @@ pub const DIFFERENTIAL_ARRANGEMENT_LOGGER_NAME: &str = "differential/arrange";
 /// The name of the timely log stream for timely progress events
 pub const TIMELY_PROGRESS_LOGGER_NAME: &str = "timely/progress";
 
+/// The name of the timely log stream for reachability tracker events
+pub const REACHABILITY_LOGGER_NAME: &str = "timely/reachability";
+
 /// The file that all timely events will be stored in
 pub const TIMELY_LOG_FILE: &str = "timely";
 
@@ -37,6 +64,35 @@ pub const DIFFERENTIAL_ARRANGEMENT_LOG_FILE: &str = "differential";
 /// The file that all timely progress events will be stored in
 pub const TIMELY_PROGRESS_LOG_FILE: &str = "timely-progress";
 
+/// The file that timely progress events with structured timestamps will be stored in
+pub const TIMELY_STRUCTURED_PROGRESS_LOG_FILE: &str = "timely-progress-structured";
+
+/// The file that all reachability tracker events will be stored in
+pub const REACHABILITY_LOG_FILE: &str = "timely-reachability";
+
+/// Setting this environment variable to anything but `0` makes the `enable_*` and
+/// `save_*` helpers write a CRC32C checksum of every frame, see [`EventWriter::with_checksums()`]
+pub const CHECKSUMS_ENV_VAR: &str = "DDSHOW_CHECKSUMS";
+
+/// Setting this environment variable to anything but `0` makes the `enable_*` and
+/// `save_*` helpers write batched version 2 streams, see [`BatchedEventWriter`]
+pub const BATCHED_ENV_VAR: &str = "DDSHOW_BATCHED";
+
+/// The framing of the streams written by the `enable_*` and `save_*` helpers
+fn log_framing() -> Framing {
+    let checksums = env_flag(CHECKSUMS_ENV_VAR);
+
+    if env_flag(BATCHED_ENV_VAR) {
+        Framing::Batched { checksums }
+    } else {
+        Framing::Single { checksums }
+    }
+}
+
+fn env_flag(name: &str) -> bool {
+    matches!(env::var_os(name), Some(value) if !value.is_empty() && value != "0")
+}
+
 /// Constructs the path to a logging file for the given worker
 pub fn log_file_path<A>(worker: &Worker<A>, file_prefix: &str, dir: &Path) -> PathBuf
 where
@@ -88,7 +144,7 @@ where
     );
 
     let mut logger: BatchLogger<TimelyEvent, WorkerId, _> =
-        BatchLogger::new(EventWriter::new(writer));
+        BatchLogger::new(LogWriter::new(writer, log_framing()));
 
     worker
         .log_register()
@@ -97,6 +153,44 @@ where
         })
 }
 
+/// Writes all timely event logs to the given writer with their operator
+/// names redacted by `redactor`
+///
+/// See [`redaction`] for how names are redacted
+pub fn enable_redacted_timely_logging<A, W>(
+    worker: &mut Worker<A>,
+    writer: W,
+    mut redactor: Redactor,
+) -> Option<Box<dyn Any + 'static>>
+where
+    A: Allocate,
+    W: Write + 'static,
+{
+    #[cfg(feature = "tracing")]
+    tracing_dep::info!(
+        worker = worker.index(),
+        logging_stream = TIMELY_LOGGER_NAME,
+        redaction = ?redactor.mode(),
+        "installing a redacted {} event logger on worker {}",
+        TIMELY_LOGGER_NAME,
+        worker.index(),
+    );
+
+    let mut logger: BatchLogger<TimelyEvent, WorkerId, _> =
+        BatchLogger::new(LogWriter::new(writer, log_framing()));
+
+    worker
+        .log_register()
+        .insert::<RawTimelyEvent, _>(TIMELY_LOGGER_NAME, move |time, data| {
+            logger.publish_batch_with(time, data, |event| {
+                if let Err(_err) = redactor.redact(event) {
+                    #[cfg(feature = "tracing")]
+                    tracing_dep::error!("failed to write to the redaction mapping: {:?}", _err);
+                }
+            })
+        })
+}
+
 pub fn save_timely_logs_to_disk<P, A>(
     worker: &mut Worker<A>,
     directory: P,
@@ -172,7 +266,7 @@ where
     );
 
     let mut logger: BatchLogger<DifferentialEvent, WorkerId, _> =
-        BatchLogger::new(EventWriter::new(writer));
+        BatchLogger::new(LogWriter::new(writer, log_framing()));
 
     worker.log_register().insert::<RawDifferentialEvent, _>(
         DIFFERENTIAL_ARRANGEMENT_LOGGER_NAME,
@@ -214,7 +308,119 @@ where
     Ok(enable_differential_logging(worker, writer))
 }
 
-/*
+/// Writes all reachability tracker event logs to the given writer
+///
+/// See [`TrackerEvent`] for the events logged, reachability logging
+/// is only available on unreleased versions of timely so this requires
+/// the `timely-next` feature along with a timely that has
+/// `timely::progress::reachability::logging`, timely 0.12 doesn't
+///
+/// ## Examples
+///
+/// ```rust,ignore
+/// use std::{env, net::TcpStream};
+///
+/// timely::execute_directly(|worker| {
+///     if let Ok(addr) = env::var("TIMELY_REACHABILITY_LOG_ADDR") {
+///         if let Ok(stream) = TcpStream::connect(&addr) {
+///             ddshow_sink::enable_reachability_logging(worker, stream);
+///         }
+///     }
+///
+///     worker.dataflow::<(),_,_>(|scope| {
+///         (0..10).to_stream(scope)
+///             .inspect(|x| println!("seen: {:?}", x));
+///     });
+/// });
+/// ```
+///
+#[cfg(feature = "timely-next")]
+pub fn enable_reachability_logging<A, W>(
+    worker: &mut Worker<A>,
+    writer: W,
+) -> Option<Box<dyn Any + 'static>>
+where
+    A: Allocate,
+    W: Write + 'static,
+{
+    #[cfg(feature = "tracing")]
+    tracing_dep::info!(
+        worker = worker.index(),
+        logging_stream = REACHABILITY_LOGGER_NAME,
+        "installing a {} logger on worker {}",
+        REACHABILITY_LOGGER_NAME,
+        worker.index(),
+    );
+
+    let mut logger: BatchLogger<TrackerEvent, WorkerId, _> =
+        BatchLogger::new(LogWriter::new(writer, log_framing()));
+
+    worker
+        .log_register()
+        .insert::<RawTrackerEvent, _>(REACHABILITY_LOGGER_NAME, move |time, data| {
+            logger.publish_batch(time, data)
+        })
+}
+
+#[cfg(feature = "timely-next")]
+pub fn save_reachability_logs_to_disk<P, A>(
+    worker: &mut Worker<A>,
+    directory: P,
+) -> io::Result<Option<Box<dyn Any + 'static>>>
+where
+    P: AsRef<Path>,
+    A: Allocate,
+{
+    let directory = directory.as_ref();
+    let path = directory.join(format!(
+        "{}.worker-{}.ddshow",
+        REACHABILITY_LOG_FILE,
+        worker.index()
+    ));
+
+    #[cfg(feature = "tracing")]
+    tracing_dep::info!(
+        worker = worker.index(),
+        logging_stream = REACHABILITY_LOGGER_NAME,
+        directory = ?directory,
+        path = ?path,
+        "installing a disk backed {} logger on worker {} pointed at {}",
+        REACHABILITY_LOGGER_NAME,
+        worker.index(),
+        path.display(),
+    );
+
+    fs::create_dir_all(directory)?;
+    let writer = BufWriter::new(File::create(path)?);
+    Ok(enable_reachability_logging(worker, writer))
+}
+
+/// Writes all timely progress event logs to the given writer
+///
+/// See [`TimelyProgressEvent`] for the events logged, timestamps are only
+/// recorded in their string form. Use [`enable_timely_progress_logging_with`]
+/// to also record them in a structured form
+///
+/// ## Examples
+///
+/// ```rust
+/// use std::{env, net::TcpStream};
+/// use timely::dataflow::operators::{Inspect, ToStream};
+///
+/// timely::execute_directly(|worker| {
+///     if let Ok(addr) = env::var("TIMELY_PROGRESS_LOG_ADDR") {
+///         if let Ok(stream) = TcpStream::connect(&addr) {
+///             ddshow_sink::enable_timely_progress_logging(worker, stream);
+///         }
+///     }
+///
+///     worker.dataflow::<(),_,_>(|scope| {
+///         (0..10).to_stream(scope)
+///             .inspect(|x| println!("seen: {:?}", x));
+///     });
+/// });
+/// ```
+///
 pub fn enable_timely_progress_logging<A, W>(
     worker: &mut Worker<A>,
     writer: W,
@@ -233,7 +439,7 @@ where
     );
 
     let mut logger: BatchLogger<TimelyProgressEvent, WorkerId, _> =
-        BatchLogger::new(EventWriter::new(writer));
+        BatchLogger::new(LogWriter::new(writer, log_framing()));
 
     worker
         .log_register()
@@ -273,4 +479,104 @@ where
     let writer = BufWriter::new(File::create(path)?);
     Ok(enable_timely_progress_logging(worker, writer))
 }
-*/
+
+/// Writes all timely progress event logs to the given writer as
+/// [`StructuredProgressEvent`]s, converting every timestamp the converter
+/// understands into a structured form
+///
+/// The written stream holds a different event type than the one written by
+/// [`enable_timely_progress_logging`], so it should be kept apart from plain
+/// progress logs
+///
+/// ## Examples
+///
+/// ```rust
+/// use ddshow_sink::CommonTimestamps;
+/// use std::{env, net::TcpStream};
+/// use timely::dataflow::operators::{Inspect, ToStream};
+///
+/// timely::execute_directly(|worker| {
+///     if let Ok(addr) = env::var("TIMELY_PROGRESS_LOG_ADDR") {
+///         if let Ok(stream) = TcpStream::connect(&addr) {
+///             ddshow_sink::enable_timely_progress_logging_with(worker, stream, CommonTimestamps);
+///         }
+///     }
+///
+///     worker.dataflow::<u64,_,_>(|scope| {
+///         (0..10).to_stream(scope)
+///             .inspect(|x| println!("seen: {:?}", x));
+///     });
+/// });
+/// ```
+///
+pub fn enable_timely_progress_logging_with<A, W, C>(
+    worker: &mut Worker<A>,
+    writer: W,
+    converter: C,
+) -> Option<Box<dyn Any + 'static>>
+where
+    A: Allocate,
+    W: Write + 'static,
+    C: TimestampConverter,
+{
+    #[cfg(feature = "tracing")]
+    tracing_dep::info!(
+        worker = worker.index(),
+        logging_stream = TIMELY_PROGRESS_LOGGER_NAME,
+        "installing a {} logger with structured timestamps on worker {}",
+        TIMELY_PROGRESS_LOGGER_NAME,
+        worker.index(),
+    );
+
+    let mut logger: BatchLogger<StructuredProgressEvent, WorkerId, _> =
+        BatchLogger::new(LogWriter::new(writer, log_framing()));
+    let mut buffer = Vec::new();
+
+    worker.log_register().insert::<RawTimelyProgressEvent, _>(
+        TIMELY_PROGRESS_LOGGER_NAME,
+        move |time, data| {
+            buffer.extend(data.drain(..).map(|(time, worker, event)| {
+                let event = timestamps::convert_progress_event(event, &converter);
+                (time, worker, event)
+            }));
+
+            logger.publish_batch(time, &mut buffer)
+        },
+    )
+}
+
+pub fn save_timely_progress_to_disk_with<P, A, C>(
+    worker: &mut Worker<A>,
+    directory: P,
+    converter: C,
+) -> io::Result<Option<Box<dyn Any + 'static>>>
+where
+    P: AsRef<Path>,
+    A: Allocate,
+    C: TimestampConverter,
+{
+    let directory = directory.as_ref();
+    let path = directory.join(format!(
+        "{}.worker-{}.ddshow",
+        TIMELY_STRUCTURED_PROGRESS_LOG_FILE,
+        worker.index()
+    ));
+
+    #[cfg(feature = "tracing")]
+    tracing_dep::info!(
+        worker = worker.index(),
+        logging_stream = TIMELY_PROGRESS_LOGGER_NAME,
+        directory = ?directory,
+        path = ?path,
+        "installing a disk backed {} logger with structured timestamps on worker {} pointed at {}",
+        TIMELY_PROGRESS_LOGGER_NAME,
+        worker.index(),
+        path.display(),
+    );
+
+    fs::create_dir_all(directory)?;
+    let writer = BufWriter::new(File::create(path)?);
+    Ok(enable_timely_progress_logging_with(
+        worker, writer, converter,
+    ))
+}
@@ -0,0 +1,114 @@
+//! Opt-in operators that check dataflows for common mistakes
+//!
+//! Any problems that are found are logged to the timely log stream as
+//! [`TimelyEvent::Text`](RawTimelyEvent::Text) events which ddshow will
+//! then pick up and report
+
+use crate::TIMELY_LOGGER_NAME;
+use ddshow_types::{lints::CapabilityViolation, OperatorAddr, PortId};
+#[cfg(feature = "ddflow")]
+use differential_dataflow::{collection::AsCollection, Collection};
+use timely::{
+    dataflow::{channels::pact::Pipeline, operators::Operator, Scope, Stream},
+    logging::TimelyEvent as RawTimelyEvent,
+    order::PartialOrder,
+    Data,
+};
+
+/// Checks that every update's time is greater than or equal to the
+/// capability of the message it was sent within
+///
+/// Updates that are sent at times before their message's capability
+/// may be silently mishandled by downstream operators since the frontier
+/// could have already passed them. Any violations are logged as
+/// [`CapabilityViolation`]s attributed to the operator that produced
+/// the stream, the stream's contents are passed through unchanged
+///
+/// ## Examples
+///
+/// ```rust
+/// use ddshow_sink::CheckCapabilities;
+/// use timely::dataflow::operators::{Map, ToStream};
+///
+/// timely::execute_directly(|worker| {
+///     worker.dataflow::<u32, _, _>(|scope| {
+///         (0..10)
+///             .to_stream(scope)
+///             .map(|x| (x, 0u32, 1isize))
+///             .check_capabilities();
+///     });
+/// });
+/// ```
+///
+pub trait CheckCapabilities {
+    fn check_capabilities(&self) -> Self;
+}
+
+impl<S, D, R> CheckCapabilities for Stream<S, (D, S::Timestamp, R)>
+where
+    S: Scope,
+    D: Data,
+    R: Data,
+{
+    fn check_capabilities(&self) -> Self {
+        let scope = self.scope();
+        let logger = scope
+            .log_register()
+            .get::<RawTimelyEvent>(TIMELY_LOGGER_NAME);
+
+        // The operator that produced the stream we're checking
+        let mut operator_addr = OperatorAddr::from(scope.addr());
+        operator_addr.push(PortId::new(self.name().node));
+        let port = PortId::new(self.name().port);
+
+        let mut buffer = Vec::new();
+        self.unary(Pipeline, "CheckCapabilities", move |_capability, _info| {
+            move |input, output| {
+                input.for_each(|capability, data| {
+                    data.swap(&mut buffer);
+
+                    if let Some(logger) = logger.as_ref() {
+                        let mut violations = buffer
+                            .iter()
+                            .filter(|(_, time, _)| !capability.time().less_equal(time));
+
+                        if let Some((_, first, _)) = violations.next() {
+                            let violation = CapabilityViolation::new(
+                                operator_addr.clone(),
+                                port,
+                                violations.count() + 1,
+                                format!("{:?}", capability.time()),
+                                format!("{:?}", first),
+                            );
+
+                            #[cfg(feature = "tracing")]
+                            tracing_dep::warn!(
+                                operator_addr = %violation.operator_addr,
+                                violations = violation.violations,
+                                "sent {} updates from before their message's capability of {}",
+                                violation.violations,
+                                violation.capability,
+                            );
+
+                            logger.log(RawTimelyEvent::Text(violation.to_string()));
+                        }
+                    }
+
+                    output.session(&capability).give_vec(&mut buffer);
+                });
+            }
+        })
+    }
+}
+
+#[cfg(feature = "ddflow")]
+impl<S, D, R> CheckCapabilities for Collection<S, D, R>
+where
+    S: Scope,
+    D: Data,
+    R: differential_dataflow::difference::Semigroup,
+{
+    fn check_capabilities(&self) -> Self {
+        self.inner.check_capabilities().as_collection()
+    }
+}
@@ -0,0 +1,267 @@
+//! Redaction of operator names so that logs can be shared
+//!
+//! Operator names are the only part of the logs that's written by the user and so
+//! they're the part likely to contain things like customer identifiers or the names
+//! of internal tables. A [`Redactor`] replaces them with either a stable hash of the
+//! name or a generic label for the kind of operator, the structure and timings of
+//! the dataflow are left untouched.
+//!
+//! Hashes are stable across runs so that redacted traces can still be compared to
+//! each other, but that also means that names which are easy to guess can be found
+//! by hashing guesses. Kind labels don't have that problem but lose the ability to
+//! tell apart operators of the same kind.
+
+use ddshow_types::{timely_logging::TimelyEvent, WorkerId};
+use std::{
+    fmt::{self, Debug},
+    io::{self, Write},
+    str::FromStr,
+    time::Duration,
+};
+
+/// The label given to operators that aren't one of the [`KIND_LABELS`]
+pub const UNKNOWN_KIND_LABEL: &str = "Operator";
+
+/// The label given to the top-level operator of each dataflow
+pub const DATAFLOW_KIND_LABEL: &str = "Dataflow";
+
+/// The names of the builtin timely and differential operators that are
+/// kept as labels when redacting with [`RedactionMode::KindLabel`]
+pub const KIND_LABELS: &[&str] = &[
+    "Accumulate",
+    "Aggregate",
+    "Antijoin",
+    "Arrange",
+    "ArrangeByKey",
+    "ArrangeBySelf",
+    "AsCollection",
+    "Binary",
+    "Branch",
+    "Broadcast",
+    "Capture",
+    "Concat",
+    "Concatenate",
+    "Consolidate",
+    "Count",
+    "Delay",
+    "Distinct",
+    "Enter",
+    "Exchange",
+    "Feedback",
+    "Filter",
+    "FlatMap",
+    "Input",
+    "Inspect",
+    "InspectBatch",
+    "Iterate",
+    "Join",
+    "JoinCore",
+    "Leave",
+    "Map",
+    "MapInPlace",
+    "Negate",
+    "Partition",
+    "Probe",
+    "Reclock",
+    "Reduce",
+    "Replay",
+    "ResultsIn",
+    "Semijoin",
+    "Subgraph",
+    "Threshold",
+    "ToStream",
+    "Unary",
+    "UnorderedInput",
+];
+
+/// How operator names are replaced when they're redacted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RedactionMode {
+    /// Replace names with a stable hash of the name, e.g. `op-af63bd4c8601b7df`
+    Hash,
+    /// Replace names with the kind of operator they belong to, e.g. `Map` or `Join`
+    KindLabel,
+}
+
+impl RedactionMode {
+    /// Redacts a single operator name, `is_dataflow` should be set for
+    /// the top-level operators of dataflows
+    pub fn redact_name(self, name: &str, is_dataflow: bool) -> String {
+        match self {
+            Self::Hash => format!("op-{:016x}", fnv1a(name.as_bytes())),
+            Self::KindLabel if is_dataflow => DATAFLOW_KIND_LABEL.to_owned(),
+            Self::KindLabel => kind_label(name).to_owned(),
+        }
+    }
+}
+
+impl FromStr for RedactionMode {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let lowercase = string.trim().to_lowercase();
+        match lowercase.as_str() {
+            "hash" => Ok(Self::Hash),
+            "kind" | "kind-label" => Ok(Self::KindLabel),
+            _ => Err(format!(
+                "invalid redaction mode {:?}, only `hash` and `kind` are supported",
+                string,
+            )),
+        }
+    }
+}
+
+/// Redacts the operator names of timely events, optionally writing a
+/// mapping from each redacted operator back to its original name
+pub struct Redactor {
+    mode: RedactionMode,
+    mapping: Option<Box<dyn Write>>,
+}
+
+impl Redactor {
+    /// Creates a redactor that doesn't keep any record of the original names
+    pub fn new(mode: RedactionMode) -> Self {
+        Self {
+            mode,
+            mapping: None,
+        }
+    }
+
+    /// Creates a redactor that writes a tab-separated line for every redacted operator
+    /// to `mapping`, containing its worker, id, address, redacted name and original name
+    ///
+    /// The mapping contains all of the original names so it shouldn't be shared
+    /// alongside the redacted logs
+    pub fn with_mapping<W>(mode: RedactionMode, mut mapping: W) -> io::Result<Self>
+    where
+        W: Write + 'static,
+    {
+        writeln!(mapping, "worker\toperator\taddress\tredacted\toriginal")?;
+
+        Ok(Self {
+            mode,
+            mapping: Some(Box::new(mapping)),
+        })
+    }
+
+    pub const fn mode(&self) -> RedactionMode {
+        self.mode
+    }
+
+    /// Redacts the operator name of an event, if it has one
+    pub fn redact(&mut self, event: &mut (Duration, WorkerId, TimelyEvent)) -> io::Result<()> {
+        let (_, worker, event) = event;
+
+        if let TimelyEvent::Operates(operates) = event {
+            let redacted = self
+                .mode
+                .redact_name(&operates.name, operates.addr.len() == 1);
+
+            if let Some(mapping) = self.mapping.as_mut() {
+                writeln!(
+                    mapping,
+                    "{}\t{}\t{:?}\t{}\t{}",
+                    worker.into_inner(),
+                    operates.id.into_inner(),
+                    operates
+                        .addr
+                        .iter()
+                        .map(|segment| segment.into_inner())
+                        .collect::<Vec<_>>(),
+                    redacted,
+                    escape(&operates.name),
+                )?;
+            }
+
+            operates.name = redacted;
+        }
+
+        Ok(())
+    }
+
+    /// Flushes the mapping file, if there is one
+    pub fn flush(&mut self) -> io::Result<()> {
+        match self.mapping.as_mut() {
+            Some(mapping) => mapping.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Debug for Redactor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Redactor")
+            .field("mode", &self.mode)
+            .field("has_mapping", &self.mapping.is_some())
+            .finish()
+    }
+}
+
+impl Drop for Redactor {
+    fn drop(&mut self) {
+        if let Err(_err) = self.flush() {
+            #[cfg(feature = "tracing")]
+            tracing_dep::error!("failed to flush the redaction mapping: {:?}", _err);
+        }
+    }
+}
+
+/// Gets the kind label of an operator from the leading word of its name, so
+/// that names like `Arrange: customer_orders` are labeled as `Arrange`
+pub fn kind_label(name: &str) -> &'static str {
+    let kind = name
+        .split(|char: char| !char.is_ascii_alphanumeric())
+        .next()
+        .unwrap_or_default();
+
+    KIND_LABELS
+        .iter()
+        .find(|&&label| label == kind)
+        .copied()
+        .unwrap_or(UNKNOWN_KIND_LABEL)
+}
+
+/// The 64-bit FNV-1a hash, used since it's stable across platforms and versions
+fn fnv1a(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    bytes.iter().fold(OFFSET_BASIS, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(PRIME)
+    })
+}
+
+/// Escapes the characters that would break up lines of the mapping file
+fn escape(name: &str) -> String {
+    name.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::redaction::{fnv1a, kind_label, RedactionMode, UNKNOWN_KIND_LABEL};
+
+    #[test]
+    fn redacted_names() {
+        // Known FNV-1a test vectors
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+
+        let hashed = RedactionMode::Hash.redact_name("Arrange: customer_orders", false);
+        assert_eq!(
+            hashed,
+            RedactionMode::Hash.redact_name("Arrange: customer_orders", false),
+        );
+        assert!(!hashed.contains("customer"));
+
+        assert_eq!(kind_label("Arrange: customer_orders"), "Arrange");
+        assert_eq!(kind_label("ArrangeBySelf"), "ArrangeBySelf");
+        assert_eq!(kind_label("load customer_orders"), UNKNOWN_KIND_LABEL);
+        assert_eq!(
+            RedactionMode::KindLabel.redact_name("customer pipeline", true),
+            "Dataflow",
+        );
+    }
+}
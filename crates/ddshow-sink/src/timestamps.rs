@@ -0,0 +1,255 @@
+//! Opt-in conversion of progress timestamps into [`StructuredTimestamp`]s
+//!
+//! Timely erases the types of the timestamps within its progress logs, so by
+//! default only their [`Debug`](std::fmt::Debug) representation is recorded.
+//! Passing a [`TimestampConverter`] to
+//! [`enable_timely_progress_logging_with`](crate::enable_timely_progress_logging_with)
+//! lets ddshow recover the timestamps themselves by downcasting them to types
+//! the converter knows about. Converted events are written as
+//! [`StructuredProgressEvent`]s to their own stream so that plain progress
+//! logs keep their layout
+
+use ddshow_types::progress_logging::{
+    StructuredProgressEvent, StructuredTimestamp, TimelyProgressEvent, TimestampComponent,
+    TimestampOrder,
+};
+use std::time::Duration;
+use timely::{
+    logging::{ProgressEventTimestamp, TimelyProgressEvent as RawTimelyProgressEvent},
+    order::Product,
+};
+
+/// Timestamps that can be converted into a [`StructuredTimestamp`]
+///
+/// ## Examples
+///
+/// ```rust
+/// use ddshow_sink::timestamps::ToStructuredTimestamp;
+/// use ddshow_types::progress_logging::{TimestampComponent, TimestampOrder};
+/// use timely::order::Product;
+///
+/// let timestamp = Product::new(Product::new(10u64, 2u32), 5usize)
+///     .to_structured()
+///     .unwrap();
+///
+/// assert_eq!(timestamp.order, TimestampOrder::Product);
+/// assert_eq!(
+///     timestamp.components,
+///     vec![
+///         TimestampComponent::Unsigned(10),
+///         TimestampComponent::Unsigned(2),
+///         TimestampComponent::Unsigned(5),
+///     ],
+/// );
+///
+/// // Mixing product and lexicographic orders can't be flattened
+/// assert!(Product::new((1u64, 2u64), 3u64).to_structured().is_none());
+/// ```
+///
+pub trait ToStructuredTimestamp {
+    /// Converts the timestamp, returning `None` if it can't be represented
+    fn to_structured(&self) -> Option<StructuredTimestamp>;
+}
+
+macro_rules! impl_total_timestamps {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl ToStructuredTimestamp for $ty {
+                fn to_structured(&self) -> Option<StructuredTimestamp> {
+                    Some(StructuredTimestamp::total(TimestampComponent::from(*self)))
+                }
+            }
+        )*
+    };
+}
+
+impl_total_timestamps! {
+    u8, u16, u32, u64, usize,
+    i8, i16, i32, i64, isize,
+    Duration,
+}
+
+impl ToStructuredTimestamp for () {
+    fn to_structured(&self) -> Option<StructuredTimestamp> {
+        Some(StructuredTimestamp::new(TimestampOrder::Total, Vec::new()))
+    }
+}
+
+impl<TOuter, TInner> ToStructuredTimestamp for Product<TOuter, TInner>
+where
+    TOuter: ToStructuredTimestamp,
+    TInner: ToStructuredTimestamp,
+{
+    fn to_structured(&self) -> Option<StructuredTimestamp> {
+        StructuredTimestamp::combine(
+            TimestampOrder::Product,
+            self.outer.to_structured()?,
+            self.inner.to_structured()?,
+        )
+    }
+}
+
+impl<A, B> ToStructuredTimestamp for (A, B)
+where
+    A: ToStructuredTimestamp,
+    B: ToStructuredTimestamp,
+{
+    fn to_structured(&self) -> Option<StructuredTimestamp> {
+        StructuredTimestamp::combine(
+            TimestampOrder::Lexicographic,
+            self.0.to_structured()?,
+            self.1.to_structured()?,
+        )
+    }
+}
+
+/// Converts the type-erased timestamps of progress events
+///
+/// Implemented for all closures of the form
+/// `Fn(&dyn ProgressEventTimestamp) -> Option<StructuredTimestamp>`,
+/// [`downcast_timestamp`] can be used to write converters for custom
+/// timestamp types
+pub trait TimestampConverter: 'static {
+    /// Converts the timestamp, returning `None` to keep only its string form
+    fn convert(&self, timestamp: &dyn ProgressEventTimestamp) -> Option<StructuredTimestamp>;
+}
+
+impl<F> TimestampConverter for F
+where
+    F: Fn(&dyn ProgressEventTimestamp) -> Option<StructuredTimestamp> + 'static,
+{
+    fn convert(&self, timestamp: &dyn ProgressEventTimestamp) -> Option<StructuredTimestamp> {
+        self(timestamp)
+    }
+}
+
+/// Attempts to convert a timestamp of type `T`, returning `None` if
+/// the timestamp is of some other type
+pub fn downcast_timestamp<T>(timestamp: &dyn ProgressEventTimestamp) -> Option<StructuredTimestamp>
+where
+    T: ToStructuredTimestamp + 'static,
+{
+    timestamp
+        .as_any()
+        .downcast_ref::<T>()
+        .and_then(ToStructuredTimestamp::to_structured)
+}
+
+/// Converts the most common timestamp types: integers, [`Duration`]s,
+/// `()` and any [`Product`] or pair of two of them
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CommonTimestamps;
+
+macro_rules! downcast_common {
+    ($timestamp:ident, [$($ty:ty),* $(,)?]) => {
+        downcast_common!(@leaves $timestamp, [$($ty),*]);
+        downcast_common!(@pairs $timestamp, [$($ty),*], [$($ty),*]);
+    };
+
+    (@leaves $timestamp:ident, [$($ty:ty),*]) => {
+        $(
+            if let Some(structured) = downcast_timestamp::<$ty>($timestamp) {
+                return Some(structured);
+            }
+        )*
+    };
+
+    (@pairs $timestamp:ident, [$($outer:ty),*], $inner:tt) => {
+        $(
+            downcast_common!(@pair $timestamp, $outer, $inner);
+        )*
+    };
+
+    (@pair $timestamp:ident, $outer:ty, [$($inner:ty),*]) => {
+        $(
+            if let Some(structured) = downcast_timestamp::<Product<$outer, $inner>>($timestamp)
+                .or_else(|| downcast_timestamp::<($outer, $inner)>($timestamp))
+            {
+                return Some(structured);
+            }
+        )*
+    };
+}
+
+impl TimestampConverter for CommonTimestamps {
+    fn convert(&self, timestamp: &dyn ProgressEventTimestamp) -> Option<StructuredTimestamp> {
+        if let Some(structured) = downcast_timestamp::<()>(timestamp) {
+            return Some(structured);
+        }
+        downcast_common!(timestamp, [u32, u64, usize, i32, i64, isize, Duration]);
+
+        None
+    }
+}
+
+/// Converts a raw progress event, pairing it with structured forms of
+/// every timestamp that the converter understands
+pub fn convert_progress_event<C>(
+    event: RawTimelyProgressEvent,
+    converter: &C,
+) -> StructuredProgressEvent
+where
+    C: TimestampConverter + ?Sized,
+{
+    let messages = event
+        .messages
+        .iter()
+        .map(|(_, _, timestamp, _)| converter.convert(timestamp))
+        .collect();
+    let internal = event
+        .internal
+        .iter()
+        .map(|(_, _, timestamp, _)| converter.convert(timestamp))
+        .collect();
+
+    StructuredProgressEvent::new(TimelyProgressEvent::from(event), messages, internal)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::timestamps::{convert_progress_event, CommonTimestamps};
+    use ddshow_types::progress_logging::{
+        StructuredTimestamp, TimelyProgressEvent, TimestampComponent, TimestampOrder,
+    };
+    use timely::{logging::TimelyProgressEvent as RawTimelyProgressEvent, order::Product};
+
+    fn raw_event() -> RawTimelyProgressEvent {
+        RawTimelyProgressEvent {
+            is_send: true,
+            source: 1,
+            channel: 4,
+            seq_no: 0,
+            addr: vec![0, 2],
+            messages: Box::new(vec![(1, 0, Product::new(10u64, 9u32), 1)]),
+            // Strings aren't common timestamps, so they only keep their string form
+            internal: Box::new(vec![
+                (1, 0, "epoch".to_owned(), -1),
+                (2, 0, "b".to_owned(), 1),
+            ]),
+        }
+    }
+
+    #[test]
+    fn structured_events_keep_plain_events() {
+        let structured = convert_progress_event(raw_event(), &CommonTimestamps);
+        assert_eq!(structured.event, TimelyProgressEvent::from(raw_event()));
+
+        let product = StructuredTimestamp::new(
+            TimestampOrder::Product,
+            vec![
+                TimestampComponent::Unsigned(10),
+                TimestampComponent::Unsigned(9),
+            ],
+        );
+        let messages: Vec<_> = structured.structured_messages().collect();
+        assert_eq!(
+            messages,
+            vec![(&structured.event.messages[0], Some(&product))]
+        );
+
+        assert_eq!(structured.internal, vec![None, None]);
+        assert!(structured
+            .structured_internal()
+            .all(|(_, timestamp)| timestamp.is_none()));
+    }
+}
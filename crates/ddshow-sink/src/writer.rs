@@ -1,3 +1,8 @@
+use crate::framing::{
+    crc32c, crc32c_append, encode_batch_index, padding_to, FrameHeader, Framing, StreamHeader,
+    BATCHED_VERSION, BATCH_FRAME_ALIGNMENT, FLAG_CHECKSUMS, FRAME_ALIGNMENT, SINGLE_EVENT_VERSION,
+    STREAM_HEADER_LEN,
+};
 use bytecheck::CheckBytes;
 use ddshow_types::Event;
 use rkyv::{
@@ -5,17 +10,31 @@ use rkyv::{
     validation::DefaultArchiveValidator,
     AlignedVec, Serialize,
 };
-use std::{fmt::Debug, io::Write, marker::PhantomData, mem};
+use std::{
+    fmt::Debug,
+    io::{self, Write},
+    marker::PhantomData,
+    time::{Duration, Instant},
+};
 use timely::dataflow::operators::capture::event::{
     Event as TimelyEvent, EventPusher as TimelyEventPusher,
 };
 
 /// A wrapper for a writer that serializes [`rkyv`] encoded types that are FFI compatible
+///
+/// See [`framing`](crate::framing) for the format that events are written in. Events
+/// that fail to be written are dropped, see [`EventWriter::poison_on_error()`] to stop
+/// writing to the stream after the first failure instead
 #[derive(Debug)]
 pub struct EventWriter<T, D, W> {
     stream: W,
     buffer: AlignedVec,
     position: usize,
+    /// The header of the stream, if it has one
+    header: Option<StreamHeader>,
+    framing: Framing,
+    poison_on_error: bool,
+    poisoned: bool,
     __type: PhantomData<(T, D)>,
 }
 
@@ -26,9 +45,44 @@ impl<T, D, W> EventWriter<T, D, W> {
             stream,
             buffer: AlignedVec::with_capacity(512),
             position: 0,
+            header: None,
+            framing: Framing::of(None),
+            poison_on_error: false,
+            poisoned: false,
             __type: PhantomData,
         }
     }
+
+    /// Allocates a new `EventWriter` that writes a CRC32C checksum of every frame,
+    /// allowing readers to detect corruption of the stream
+    ///
+    /// Streams with checksums start with a [`StreamHeader`] and can only
+    /// be read by versions of ddshow that understand it
+    pub fn with_checksums(stream: W) -> Self {
+        let header = StreamHeader::new(SINGLE_EVENT_VERSION, FLAG_CHECKSUMS);
+
+        EventWriter {
+            header: Some(header),
+            framing: Framing::of(Some(header)),
+            ..Self::new(stream)
+        }
+    }
+
+    /// Stops writing to the stream once a write to it fails, dropping every
+    /// later event. The stream can't be read past a partially written
+    /// frame, so this keeps anything after one from being written
+    pub fn poison_on_error(mut self) -> Self {
+        self.poison_on_error = true;
+        self
+    }
+
+    /// Returns `true` if writing to the stream failed while [poisoning] is
+    /// enabled, after which no more events are written
+    ///
+    /// [poisoning]: EventWriter::poison_on_error
+    pub const fn is_poisoned(&self) -> bool {
+        self.poisoned
+    }
 }
 
 impl<T, D, W> TimelyEventPusher<T, D> for EventWriter<T, D, W>
@@ -40,23 +94,38 @@ where
     D::Archived: CheckBytes<DefaultArchiveValidator>,
 {
     fn push(&mut self, event: TimelyEvent<T, D>) {
+        if self.poisoned {
+            return;
+        }
         let event: Event<T, D> = event.into();
 
+        // Streams with headers get them written before their first frame
+        if let (Some(header), 0) = (self.header, self.position) {
+            if let Err(_err) = self.stream.write_all(&header.encode()) {
+                #[cfg(feature = "tracing")]
+                tracing_dep::error!("failed to write stream header: {:?}", _err);
+
+                self.poisoned = self.poison_on_error;
+                return;
+            }
+
+            self.position += STREAM_HEADER_LEN;
+        }
+
         // Align to 16
-        const PADDING: [u8; 15] = [0; 15];
-        match self.position & 15 {
+        const PADDING: [u8; FRAME_ALIGNMENT] = [0; FRAME_ALIGNMENT];
+        match self.framing.padding(self.position) {
             0 => (),
-            x => {
-                let padding = 16 - x;
-
-                if let Err(err) = self.stream.write_all(&PADDING[..padding]) {
+            padding => {
+                if let Err(_err) = self.stream.write_all(&PADDING[..padding]) {
                     #[cfg(feature = "tracing")]
                     tracing_dep::error!(
                         padding_len = padding,
                         "failed to write padding to stream: {:?}",
-                        err,
+                        _err,
                     );
 
+                    self.poisoned = self.poison_on_error;
                     return;
                 }
 
@@ -72,24 +141,424 @@ where
             .serialize_value(&event)
             .unwrap_or_else(|unreachable| match unreachable {});
 
-        let archive_len = serializer.pos() as u128;
+        let archive_len = serializer.pos();
+        let frame_header = FrameHeader {
+            len: archive_len as u64,
+            checksum: if self.framing.has_checksums() {
+                Some(crc32c(&self.buffer))
+            } else {
+                None
+            },
+        };
+
         let result = self
             .stream
-            // This will keep 16-byte alignment because archive_len is a u128
-            .write_all(&archive_len.to_le_bytes())
+            // This will keep 16-byte alignment because the frame header is 16 bytes
+            .write_all(&frame_header.encode(self.framing))
             .and_then(|_| self.stream.write_all(&self.buffer));
 
-        if let Err(err) = result {
+        if let Err(_err) = result {
             #[cfg(feature = "tracing")]
             tracing_dep::error!(
                 archive_len = %archive_len,
                 "failed to write buffer data to stream: {:?}",
-                err,
+                _err,
             );
 
+            self.poisoned = self.poison_on_error;
+            return;
+        }
+
+        self.position += self.framing.header_len() + archive_len;
+    }
+}
+
+/// The default number of events in each batch written by a [`BatchedEventWriter`]
+pub const DEFAULT_BATCH_EVENTS: usize = 256;
+
+/// The default number of bytes of archives in each batch written by a [`BatchedEventWriter`]
+pub const DEFAULT_BATCH_BYTES: usize = 64 * 1024;
+
+/// The default amount of time a [`BatchedEventWriter`] holds onto a batch
+/// before writing it, even if it isn't full
+pub const DEFAULT_BATCH_DELAY: Duration = Duration::from_millis(100);
+
+/// A wrapper for a writer that serializes [`rkyv`] encoded types in batches, which takes
+/// up less space than an [`EventWriter`] for streams of many small events
+///
+/// Events are held onto until their batch fills up, an event is pushed after the batch
+/// has been held for longer than its delay, [`BatchedEventWriter::flush()`] is called or
+/// the writer is dropped. Batches written because of their delay are also flushed, so
+/// consumers of a live stream see new frontiers at most about a delay late. See
+/// [`framing`](crate::framing) for the format that events are written in. The stream can't be read past a partially
+/// written batch, so every event after a failed write is dropped
+#[derive(Debug)]
+pub struct BatchedEventWriter<T, D, W>
+where
+    W: Write,
+{
+    stream: W,
+    header: StreamHeader,
+    /// The archives of the current batch, each one aligned to [`BATCH_FRAME_ALIGNMENT`]
+    archives: AlignedVec,
+    /// The length of each archive in the current batch
+    lengths: Vec<usize>,
+    index: Vec<u8>,
+    position: usize,
+    max_events: usize,
+    max_bytes: usize,
+    max_delay: Duration,
+    /// When the first event of the current batch was pushed
+    batch_started: Option<Instant>,
+    poisoned: bool,
+    __type: PhantomData<(T, D)>,
+}
+
+impl<T, D, W> BatchedEventWriter<T, D, W>
+where
+    W: Write,
+{
+    /// Allocates a new `BatchedEventWriter` wrapping a supplied writer.
+    pub fn new(stream: W) -> Self {
+        Self::with_header(stream, StreamHeader::new(BATCHED_VERSION, 0))
+    }
+
+    /// Allocates a new `BatchedEventWriter` that writes a CRC32C checksum of every
+    /// batch, allowing readers to detect corruption of the stream
+    pub fn with_checksums(stream: W) -> Self {
+        Self::with_header(stream, StreamHeader::new(BATCHED_VERSION, FLAG_CHECKSUMS))
+    }
+
+    fn with_header(stream: W, header: StreamHeader) -> Self {
+        Self {
+            stream,
+            header,
+            archives: AlignedVec::with_capacity(DEFAULT_BATCH_BYTES),
+            lengths: Vec::with_capacity(DEFAULT_BATCH_EVENTS),
+            index: Vec::new(),
+            position: 0,
+            max_events: DEFAULT_BATCH_EVENTS,
+            max_bytes: DEFAULT_BATCH_BYTES,
+            max_delay: DEFAULT_BATCH_DELAY,
+            batch_started: None,
+            poisoned: false,
+            __type: PhantomData,
+        }
+    }
+
+    /// Sets how many events and how many bytes of archives a batch can
+    /// hold before it's written to the stream
+    pub fn with_batch_limits(mut self, max_events: usize, max_bytes: usize) -> Self {
+        self.max_events = max_events.max(1);
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Sets how long a batch can be held onto before it's written to the stream
+    pub fn with_batch_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Returns `true` if writing to the stream failed, after which no more events are written
+    pub const fn is_poisoned(&self) -> bool {
+        self.poisoned
+    }
+
+    /// Writes the current batch to the stream and flushes it
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.write_batch()?;
+        self.stream.flush()
+    }
+
+    fn write_batch(&mut self) -> io::Result<()> {
+        if self.lengths.is_empty() || self.poisoned {
+            return Ok(());
+        }
+
+        // The batch is dropped even if writing it fails, since the stream
+        // can't be read past a partially written frame anyways
+        let result = self.write_frame();
+        self.poisoned = result.is_err();
+        self.archives.clear();
+        self.lengths.clear();
+        self.batch_started = None;
+
+        result
+    }
+
+    fn write_frame(&mut self) -> io::Result<()> {
+        if self.position == 0 {
+            self.stream.write_all(&self.header.encode())?;
+            self.position += STREAM_HEADER_LEN;
+        }
+
+        self.index.clear();
+        encode_batch_index(&self.lengths, &mut self.index);
+
+        let payload_len = self.index.len() + self.archives.len();
+        if payload_len > u32::MAX as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "a batch of {} bytes is too large to be written",
+                    payload_len,
+                ),
+            ));
+        }
+
+        let framing = Framing::of(Some(self.header));
+        let header = FrameHeader {
+            len: payload_len as u64,
+            checksum: if framing.has_checksums() {
+                Some(crc32c_append(crc32c(&self.index), &self.archives))
+            } else {
+                None
+            },
+        };
+
+        let padding = framing.padding(self.position);
+        self.stream
+            .write_all(&[0; BATCH_FRAME_ALIGNMENT][..padding])?;
+        self.stream
+            .write_all(&header.encode(framing)[..framing.header_len()])?;
+        self.stream.write_all(&self.index)?;
+        self.stream.write_all(&self.archives)?;
+
+        self.position += padding + framing.header_len() + payload_len;
+
+        Ok(())
+    }
+}
+
+impl<T, D, W> TimelyEventPusher<T, D> for BatchedEventWriter<T, D, W>
+where
+    W: Write,
+    T: for<'a> Serialize<AlignedSerializer<&'a mut AlignedVec>> + Debug,
+    T::Archived: CheckBytes<DefaultArchiveValidator>,
+    D: for<'a> Serialize<AlignedSerializer<&'a mut AlignedVec>> + Debug,
+    D::Archived: CheckBytes<DefaultArchiveValidator>,
+{
+    fn push(&mut self, event: TimelyEvent<T, D>) {
+        if self.poisoned {
             return;
         }
 
-        self.position += mem::size_of::<u128>() + archive_len as usize;
+        let event: Event<T, D> = event.into();
+
+        // Each archive is aligned within the batch so that it can be read in place
+        let padding = padding_to(self.archives.len(), BATCH_FRAME_ALIGNMENT);
+        self.archives
+            .extend_from_slice(&[0; BATCH_FRAME_ALIGNMENT][..padding]);
+
+        let start = self.archives.len();
+        AlignedSerializer::new(&mut self.archives)
+            .serialize_value(&event)
+            .unwrap_or_else(|unreachable| match unreachable {});
+        self.lengths.push(self.archives.len() - start);
+
+        let batch_started = *self.batch_started.get_or_insert_with(Instant::now);
+
+        // Batches that have been held for too long are also flushed so that readers
+        // of a live stream aren't left waiting on a frontier held within them
+        let result = if batch_started.elapsed() >= self.max_delay {
+            self.flush()
+        } else if self.lengths.len() >= self.max_events || self.archives.len() >= self.max_bytes {
+            self.write_batch()
+        } else {
+            Ok(())
+        };
+
+        if let Err(_err) = result {
+            #[cfg(feature = "tracing")]
+            tracing_dep::error!(
+                position = self.position,
+                "failed to write batch to stream: {:?}",
+                _err,
+            );
+        }
+    }
+}
+
+impl<T, D, W> Drop for BatchedEventWriter<T, D, W>
+where
+    W: Write,
+{
+    fn drop(&mut self) {
+        if let Err(_err) = self.flush() {
+            #[cfg(feature = "tracing")]
+            tracing_dep::error!("failed to write the last batch to stream: {:?}", _err);
+        }
+    }
+}
+
+/// A writer for any of the rkyv log formats, picked by the [`Framing`] it's made with
+#[derive(Debug)]
+pub enum LogWriter<T, D, W>
+where
+    W: Write,
+{
+    Single(EventWriter<T, D, W>),
+    Batched(BatchedEventWriter<T, D, W>),
+}
+
+impl<T, D, W> LogWriter<T, D, W>
+where
+    W: Write,
+{
+    /// Allocates a new `LogWriter` that writes streams with the given framing
+    pub fn new(stream: W, framing: Framing) -> Self {
+        match framing {
+            Framing::Single { checksums: false } => Self::Single(EventWriter::new(stream)),
+            Framing::Single { checksums: true } => {
+                Self::Single(EventWriter::with_checksums(stream))
+            }
+            Framing::Batched { checksums: false } => Self::Batched(BatchedEventWriter::new(stream)),
+            Framing::Batched { checksums: true } => {
+                Self::Batched(BatchedEventWriter::with_checksums(stream))
+            }
+        }
+    }
+
+    /// Returns `true` if writing to the stream failed and no more events will be
+    /// written, see [`EventWriter::is_poisoned()`] and [`BatchedEventWriter::is_poisoned()`]
+    pub const fn is_poisoned(&self) -> bool {
+        match self {
+            Self::Single(writer) => writer.is_poisoned(),
+            Self::Batched(writer) => writer.is_poisoned(),
+        }
+    }
+}
+
+impl<T, D, W> TimelyEventPusher<T, D> for LogWriter<T, D, W>
+where
+    W: Write,
+    T: for<'a> Serialize<AlignedSerializer<&'a mut AlignedVec>> + Debug,
+    T::Archived: CheckBytes<DefaultArchiveValidator>,
+    D: for<'a> Serialize<AlignedSerializer<&'a mut AlignedVec>> + Debug,
+    D::Archived: CheckBytes<DefaultArchiveValidator>,
+{
+    fn push(&mut self, event: TimelyEvent<T, D>) {
+        match self {
+            Self::Single(writer) => writer.push(event),
+            Self::Batched(writer) => writer.push(event),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{framing::Framing, BatchedEventWriter, EventWriter, LogWriter};
+    use std::{
+        io::{self, Write},
+        time::Duration,
+    };
+    use timely::dataflow::operators::capture::{Event, EventPusher};
+
+    /// A writer that fails its first write and accepts every other
+    #[derive(Debug, Default)]
+    struct FailsOnce {
+        failed: bool,
+        written: Vec<u8>,
+    }
+
+    impl Write for &mut FailsOnce {
+        // `io::Error::other()` is newer than the toolchains we support
+        #[allow(unknown_lints, clippy::io_other_error)]
+        fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+            if self.failed {
+                self.written.extend_from_slice(bytes);
+                Ok(bytes.len())
+            } else {
+                self.failed = true;
+                Err(io::Error::new(io::ErrorKind::Other, "disk full"))
+            }
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn push_events<P: EventPusher<u64, u64>>(writer: &mut P) {
+        for time in 0..1000u64 {
+            writer.push(Event::Messages(time, vec![time]));
+        }
+    }
+
+    #[test]
+    fn batched_writers_are_poisoned_by_failed_writes() {
+        for &checksums in [false, true].iter() {
+            let framing = Framing::Batched { checksums };
+
+            let mut stream = FailsOnce::default();
+            {
+                let mut writer = LogWriter::new(&mut stream, framing);
+                push_events(&mut writer);
+
+                assert!(writer.is_poisoned(), "{:?}", framing);
+            }
+
+            // Nothing's written after the failure, so the stream doesn't get a
+            // second header or frames that follow a partial one
+            assert!(stream.written.is_empty(), "{:?}", framing);
+        }
+    }
+
+    #[test]
+    fn event_writers_are_only_poisoned_when_asked() {
+        for &checksums in [false, true].iter() {
+            let framing = Framing::Single { checksums };
+
+            // By default the failed event is dropped and the rest are written
+            let mut stream = FailsOnce::default();
+            let mut writer = LogWriter::new(&mut stream, framing);
+            push_events(&mut writer);
+
+            assert!(!writer.is_poisoned(), "{:?}", framing);
+            drop(writer);
+            assert!(!stream.written.is_empty(), "{:?}", framing);
+
+            let mut stream = FailsOnce::default();
+            let mut writer = if checksums {
+                EventWriter::with_checksums(&mut stream)
+            } else {
+                EventWriter::new(&mut stream)
+            }
+            .poison_on_error();
+            push_events(&mut writer);
+
+            assert!(writer.is_poisoned(), "{:?}", framing);
+            drop(writer);
+            assert!(stream.written.is_empty(), "{:?}", framing);
+        }
+    }
+
+    #[test]
+    fn batches_are_written_by_size_or_delay() {
+        let progress = |time: u64| Event::<u64, u64>::Progress(vec![(time, 1)]);
+
+        // Progress updates don't end a batch on their own
+        let mut stream = Vec::new();
+        let mut writer = BatchedEventWriter::new(&mut stream)
+            .with_batch_limits(4, usize::MAX)
+            .with_batch_delay(Duration::from_secs(60 * 60));
+        for time in 0..3 {
+            writer.push(progress(time));
+        }
+        assert_eq!((writer.lengths.len(), writer.position), (3, 0));
+
+        writer.push(progress(3));
+        assert!(writer.lengths.is_empty());
+        assert!(writer.position > 0);
+        drop(writer);
+
+        // Batches held for longer than the delay are written on the next push
+        let mut stream = Vec::new();
+        let mut writer =
+            BatchedEventWriter::new(&mut stream).with_batch_delay(Duration::from_secs(0));
+        writer.push(progress(0));
+        assert!(writer.lengths.is_empty());
+        assert!(writer.position > 0);
     }
 }
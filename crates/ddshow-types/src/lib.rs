@@ -4,7 +4,9 @@ mod operator_addr;
 
 #[cfg(feature = "ddflow")]
 pub mod differential_logging;
+pub mod lints;
 pub mod progress_logging;
+pub mod reachability_logging;
 pub mod timely_logging;
 
 #[cfg(feature = "rkyv")]
@@ -0,0 +1,184 @@
+//! Dataflow lints reported by instrumented programs
+//!
+//! Lints are produced by operators installed through `ddshow-sink` and are
+//! sent to ddshow as [`TimelyEvent::Text`] events, this module contains the
+//! types used for them along with their (textual) wire format
+//!
+//! [`TimelyEvent::Text`]: crate::timely_logging::TimelyEvent::Text
+
+use crate::{ids::PortId, OperatorAddr, OperatorId};
+#[cfg(feature = "enable_abomonation")]
+use abomonation_derive::Abomonation;
+#[cfg(feature = "rkyv")]
+use bytecheck::CheckBytes;
+#[cfg(feature = "rkyv")]
+use rkyv_dep as rkyv;
+#[cfg(feature = "rkyv")]
+use rkyv_dep::{Archive, Deserialize as RkyvDeserialize, Serialize as RkyvSerialize};
+#[cfg(feature = "serde")]
+use serde_dep::{Deserialize as SerdeDeserialize, Serialize as SerdeSerialize};
+use std::fmt::{self, Display};
+
+/// The prefix given to all text events that contain a lint
+pub const LINT_TEXT_PREFIX: &str = "ddshow::lint::";
+
+/// The lint name used for [`CapabilityViolation`]s
+const CAPABILITY_VIOLATION: &str = "capability_violation";
+
+/// A batch of differential updates that was sent with timestamps that
+/// are not greater than or equal to the capability of the message
+/// they were sent within
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(SerdeSerialize, SerdeDeserialize))]
+#[cfg_attr(feature = "serde", serde(crate = "serde_dep"))]
+#[cfg_attr(feature = "rkyv", derive(Archive, RkyvSerialize, RkyvDeserialize))]
+#[cfg_attr(feature = "rkyv", archive(strict, derive(CheckBytes)))]
+#[cfg_attr(feature = "enable_abomonation", derive(Abomonation))]
+pub struct CapabilityViolation {
+    /// The address of the operator that produced the offending updates
+    pub operator_addr: OperatorAddr,
+    /// The output port of the operator that produced the offending updates
+    pub port: PortId,
+    /// The number of updates within the message that violated its capability
+    pub violations: usize,
+    /// The message's capability, printed via its [`Debug`](`std::fmt::Debug`) implementation
+    pub capability: String,
+    /// The first offending update time, printed via its [`Debug`](`std::fmt::Debug`) implementation
+    pub update_time: String,
+}
+
+impl CapabilityViolation {
+    pub const fn new(
+        operator_addr: OperatorAddr,
+        port: PortId,
+        violations: usize,
+        capability: String,
+        update_time: String,
+    ) -> Self {
+        Self {
+            operator_addr,
+            port,
+            violations,
+            capability,
+            update_time,
+        }
+    }
+
+    /// Parses a capability violation from the contents of a text event,
+    /// returning `None` if the text isn't a capability violation lint
+    pub fn from_text(text: &str) -> Option<Self> {
+        let mut fields = text
+            .strip_prefix(LINT_TEXT_PREFIX)?
+            .strip_prefix(CAPABILITY_VIOLATION)?
+            .strip_prefix('\t')?
+            .splitn(5, '\t');
+
+        let operator_addr = fields
+            .next()?
+            .split(',')
+            .filter(|segment| !segment.is_empty())
+            .map(|segment| segment.parse().map(OperatorId::new))
+            .collect::<Result<OperatorAddr, _>>()
+            .ok()?;
+        let port = PortId::new(fields.next()?.parse().ok()?);
+        let violations = fields.next()?.parse().ok()?;
+        let capability = fields.next()?.to_owned();
+        let update_time = fields.next()?.to_owned();
+
+        Some(Self::new(
+            operator_addr,
+            port,
+            violations,
+            capability,
+            update_time,
+        ))
+    }
+}
+
+/// Formats the violation into its text event form
+impl Display for CapabilityViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}\t", LINT_TEXT_PREFIX, CAPABILITY_VIOLATION)?;
+
+        for (idx, segment) in self.operator_addr.iter().enumerate() {
+            if idx != 0 {
+                f.write_str(",")?;
+            }
+
+            write!(f, "{}", segment)?;
+        }
+
+        write!(
+            f,
+            "\t{}\t{}\t{}\t{}",
+            self.port,
+            self.violations,
+            self.capability.replace('\t', " "),
+            self.update_time.replace('\t', " "),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        lints::{CapabilityViolation, LINT_TEXT_PREFIX},
+        OperatorId, PortId,
+    };
+
+    #[test]
+    fn capability_violation_text() {
+        let violation = |addr: &[usize], capability: &str, update_time: &str| {
+            CapabilityViolation::new(
+                addr.iter().copied().map(OperatorId::new).collect(),
+                PortId::new(1),
+                3,
+                capability.to_owned(),
+                update_time.to_owned(),
+            )
+        };
+
+        let violations = [
+            // Top-level dataflows, nested scopes and operators without an address
+            violation(&[0], "0", "1"),
+            violation(&[0, 4, 2], "(Root, 5)", "(Root, 3)"),
+            violation(&[], "Product { outer: 1, inner: 0 }", "[]"),
+        ];
+        for violation in violations.iter() {
+            let text = violation.to_string();
+            assert!(text.starts_with(LINT_TEXT_PREFIX));
+            assert_eq!(
+                CapabilityViolation::from_text(&text).as_ref(),
+                Some(violation),
+            );
+        }
+
+        // Tabs within timestamps would split the fields, so they're replaced
+        let text = violation(&[0, 1], "(0,\t1)", "\t").to_string();
+        assert_eq!(
+            CapabilityViolation::from_text(&text),
+            Some(violation(&[0, 1], "(0, 1)", " ")),
+        );
+
+        // Text that isn't a capability violation isn't parsed
+        assert_eq!(CapabilityViolation::from_text("capability_violation"), None);
+        assert_eq!(
+            CapabilityViolation::from_text(&format!("{}other_lint\t0", LINT_TEXT_PREFIX)),
+            None,
+        );
+        assert_eq!(
+            CapabilityViolation::from_text(&format!(
+                "{}capability_violation\t0\t1",
+                LINT_TEXT_PREFIX,
+            )),
+            None,
+        );
+        assert_eq!(
+            CapabilityViolation::from_text(&format!(
+                "{}capability_violation\t0,x\t1\t3\t0\t1",
+                LINT_TEXT_PREFIX,
+            )),
+            None,
+        );
+    }
+}
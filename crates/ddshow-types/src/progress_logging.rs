@@ -14,6 +14,7 @@ use rkyv_dep as rkyv;
 use rkyv_dep::{Archive, Deserialize as RkyvDeserialize, Serialize as RkyvSerialize};
 #[cfg(feature = "serde")]
 use serde_dep::{Deserialize as SerdeDeserialize, Serialize as SerdeSerialize};
+use std::time::Duration;
 use timely::logging::TimelyProgressEvent as RawTimelyProgressEvent;
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
@@ -172,3 +173,214 @@ impl CapabilityUpdate {
         }
     }
 }
+
+/// A [`TimelyProgressEvent`] along with the structured forms of its timestamps
+///
+/// Structured timestamps are kept out of [`MessageUpdate`] and [`CapabilityUpdate`]
+/// so that their archived layout stays readable by consumers of plain progress
+/// logs, sinks that convert timestamps write these events to their own stream
+/// instead. `messages` and `internal` hold one entry for each of the event's
+/// updates in the same order, `None` for the timestamps that couldn't be converted
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(SerdeSerialize, SerdeDeserialize))]
+#[cfg_attr(feature = "serde", serde(crate = "serde_dep"))]
+#[cfg_attr(feature = "rkyv", derive(Archive, RkyvSerialize, RkyvDeserialize))]
+#[cfg_attr(feature = "rkyv", archive(strict, derive(CheckBytes)))]
+#[cfg_attr(feature = "enable_abomonation", derive(Abomonation))]
+pub struct StructuredProgressEvent {
+    /// The progress event with its timestamps in their string form
+    pub event: TimelyProgressEvent,
+    /// The structured timestamps of the event's message updates
+    pub messages: Vec<Option<StructuredTimestamp>>,
+    /// The structured timestamps of the event's capability updates
+    pub internal: Vec<Option<StructuredTimestamp>>,
+}
+
+impl StructuredProgressEvent {
+    pub const fn new(
+        event: TimelyProgressEvent,
+        messages: Vec<Option<StructuredTimestamp>>,
+        internal: Vec<Option<StructuredTimestamp>>,
+    ) -> Self {
+        Self {
+            event,
+            messages,
+            internal,
+        }
+    }
+
+    /// Pairs each of the event's message updates with its structured timestamp
+    pub fn structured_messages(
+        &self,
+    ) -> impl Iterator<Item = (&MessageUpdate, Option<&StructuredTimestamp>)> + '_ {
+        self.event
+            .messages
+            .iter()
+            .enumerate()
+            .map(move |(idx, update)| (update, self.messages.get(idx).and_then(Option::as_ref)))
+    }
+
+    /// Pairs each of the event's capability updates with its structured timestamp
+    pub fn structured_internal(
+        &self,
+    ) -> impl Iterator<Item = (&CapabilityUpdate, Option<&StructuredTimestamp>)> + '_ {
+        self.event
+            .internal
+            .iter()
+            .enumerate()
+            .map(move |(idx, update)| (update, self.internal.get(idx).and_then(Option::as_ref)))
+    }
+}
+
+impl From<StructuredProgressEvent> for TimelyProgressEvent {
+    fn from(event: StructuredProgressEvent) -> Self {
+        event.event
+    }
+}
+
+/// A timestamp that has been converted from its [`Debug`](`std::fmt::Debug`)
+/// representation into its individual components
+///
+/// Nested timestamps are flattened, so a `Product<Product<u64, u32>, u64>`
+/// has three components with a product order. Only nestings that keep the same
+/// order (or that combine a single component with another timestamp) can be
+/// flattened, all other timestamps keep only their string form
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(SerdeSerialize, SerdeDeserialize))]
+#[cfg_attr(feature = "serde", serde(crate = "serde_dep"))]
+#[cfg_attr(feature = "rkyv", derive(Archive, RkyvSerialize, RkyvDeserialize))]
+#[cfg_attr(feature = "rkyv", archive(strict, derive(CheckBytes)))]
+#[cfg_attr(feature = "enable_abomonation", derive(Abomonation))]
+pub struct StructuredTimestamp {
+    /// How the timestamp's components are ordered
+    pub order: TimestampOrder,
+    /// The timestamp's components, from outermost to innermost
+    pub components: Vec<TimestampComponent>,
+}
+
+impl StructuredTimestamp {
+    pub const fn new(order: TimestampOrder, components: Vec<TimestampComponent>) -> Self {
+        Self { order, components }
+    }
+
+    /// Creates a timestamp made of a single totally ordered component
+    pub fn total(component: TimestampComponent) -> Self {
+        Self::new(TimestampOrder::Total, vec![component])
+    }
+
+    /// Combines two timestamps into one with the given order, returning `None`
+    /// if either of them has more than one component and a different order
+    pub fn combine(order: TimestampOrder, outer: Self, inner: Self) -> Option<Self> {
+        let compatible =
+            |timestamp: &Self| timestamp.components.len() <= 1 || timestamp.order == order;
+
+        if compatible(&outer) && compatible(&inner) {
+            let mut components = outer.components;
+            components.extend(inner.components);
+
+            Some(Self::new(order, components))
+        } else {
+            None
+        }
+    }
+
+    /// Returns `true` if `self` is less than or equal to `other` under the
+    /// timestamp's order, returning `None` if the two timestamps aren't
+    /// comparable in structure
+    pub fn less_equal(&self, other: &Self) -> Option<bool> {
+        let mut components = self.components.iter().zip(other.components.iter());
+        if self.order != other.order
+            || self.components.len() != other.components.len()
+            || components
+                .clone()
+                .any(|(left, right)| left.kind() != right.kind())
+        {
+            return None;
+        }
+
+        let less_equal = match self.order {
+            TimestampOrder::Total | TimestampOrder::Lexicographic => {
+                self.components <= other.components
+            }
+            TimestampOrder::Product => components.all(|(left, right)| left <= right),
+        };
+
+        Some(less_equal)
+    }
+}
+
+/// The order of a timestamp's components
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(SerdeSerialize, SerdeDeserialize))]
+#[cfg_attr(feature = "serde", serde(crate = "serde_dep"))]
+#[cfg_attr(feature = "rkyv", derive(Archive, RkyvSerialize, RkyvDeserialize))]
+#[cfg_attr(feature = "rkyv", archive(strict, derive(CheckBytes)))]
+#[cfg_attr(feature = "enable_abomonation", derive(Abomonation))]
+pub enum TimestampOrder {
+    /// A single totally ordered component
+    Total,
+    /// Components are partially ordered, like timely's `Product`
+    Product,
+    /// Components are ordered lexicographically, like tuples
+    Lexicographic,
+}
+
+/// A single component of a [`StructuredTimestamp`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(SerdeSerialize, SerdeDeserialize))]
+#[cfg_attr(feature = "serde", serde(crate = "serde_dep"))]
+#[cfg_attr(feature = "rkyv", derive(Archive, RkyvSerialize, RkyvDeserialize))]
+#[cfg_attr(feature = "rkyv", archive(strict, derive(CheckBytes)))]
+#[cfg_attr(feature = "enable_abomonation", derive(Abomonation))]
+pub enum TimestampComponent {
+    Unsigned(u64),
+    Signed(i64),
+    Duration { secs: u64, nanos: u32 },
+}
+
+impl TimestampComponent {
+    /// The component as a single number, durations are given in nanoseconds
+    pub fn as_i128(&self) -> i128 {
+        match *self {
+            Self::Unsigned(int) => int as i128,
+            Self::Signed(int) => int as i128,
+            Self::Duration { secs, nanos } => secs as i128 * 1_000_000_000 + nanos as i128,
+        }
+    }
+
+    const fn kind(&self) -> u8 {
+        match self {
+            Self::Unsigned(_) => 0,
+            Self::Signed(_) => 1,
+            Self::Duration { .. } => 2,
+        }
+    }
+}
+
+impl From<Duration> for TimestampComponent {
+    fn from(duration: Duration) -> Self {
+        Self::Duration {
+            secs: duration.as_secs(),
+            nanos: duration.subsec_nanos(),
+        }
+    }
+}
+
+macro_rules! impl_component_from {
+    ($($variant:ident($cast:ty) => [$($ty:ty),* $(,)?]),* $(,)?) => {
+        $(
+            $(
+                impl From<$ty> for TimestampComponent {
+                    fn from(int: $ty) -> Self {
+                        Self::$variant(int as $cast)
+                    }
+                }
+            )*
+        )*
+    };
+}
+
+impl_component_from! {
+    Unsigned(u64) => [u8, u16, u32, u64, usize],
+    Signed(i64) => [i8, i16, i32, i64, isize],
+}
@@ -0,0 +1,210 @@
+//! Timely reachability tracker events
+
+use crate::{ids::PortId, OperatorAddr};
+#[cfg(feature = "enable_abomonation")]
+use abomonation_derive::Abomonation;
+#[cfg(feature = "rkyv")]
+use bytecheck::CheckBytes;
+#[cfg(feature = "rkyv")]
+use rkyv_dep as rkyv;
+#[cfg(feature = "rkyv")]
+use rkyv_dep::{Archive, Deserialize as RkyvDeserialize, Serialize as RkyvSerialize};
+#[cfg(feature = "serde")]
+use serde_dep::{Deserialize as SerdeDeserialize, Serialize as SerdeSerialize};
+#[cfg(feature = "timely-next")]
+use timely::progress::reachability::logging::{
+    SourceUpdate as RawSourceUpdate, TargetUpdate as RawTargetUpdate,
+    TrackerEvent as RawTrackerEvent,
+};
+
+/// Events that a reachability tracker may record
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(SerdeSerialize, SerdeDeserialize))]
+#[cfg_attr(feature = "serde", serde(crate = "serde_dep"))]
+#[cfg_attr(feature = "rkyv", derive(Archive, RkyvSerialize, RkyvDeserialize))]
+#[cfg_attr(feature = "rkyv", archive(strict, derive(CheckBytes)))]
+#[cfg_attr(feature = "enable_abomonation", derive(Abomonation))]
+pub enum TrackerEvent {
+    /// Updates made at a source of data
+    SourceUpdate(SourceUpdate),
+    /// Updates made at a target of data
+    TargetUpdate(TargetUpdate),
+}
+
+impl TrackerEvent {
+    /// The address of the scope that owns the tracker
+    pub const fn tracker_id(&self) -> &OperatorAddr {
+        match self {
+            Self::SourceUpdate(SourceUpdate { tracker_id, .. })
+            | Self::TargetUpdate(TargetUpdate { tracker_id, .. }) => tracker_id,
+        }
+    }
+
+    /// The pointstamp updates contained within the event
+    pub fn updates(&self) -> &[PointstampUpdate] {
+        match self {
+            Self::SourceUpdate(SourceUpdate { updates, .. })
+            | Self::TargetUpdate(TargetUpdate { updates, .. }) => updates,
+        }
+    }
+
+    /// Returns `true` if the tracker_event is [`TrackerEvent::SourceUpdate`].
+    pub const fn is_source_update(&self) -> bool {
+        matches!(self, Self::SourceUpdate(..))
+    }
+
+    /// Returns `true` if the tracker_event is [`TrackerEvent::TargetUpdate`].
+    pub const fn is_target_update(&self) -> bool {
+        matches!(self, Self::TargetUpdate(..))
+    }
+}
+
+impl From<SourceUpdate> for TrackerEvent {
+    fn from(source: SourceUpdate) -> Self {
+        Self::SourceUpdate(source)
+    }
+}
+
+impl From<TargetUpdate> for TrackerEvent {
+    fn from(target: TargetUpdate) -> Self {
+        Self::TargetUpdate(target)
+    }
+}
+
+#[cfg(feature = "timely-next")]
+impl From<RawTrackerEvent> for TrackerEvent {
+    fn from(event: RawTrackerEvent) -> Self {
+        match event {
+            RawTrackerEvent::SourceUpdate(source) => Self::SourceUpdate(source.into()),
+            RawTrackerEvent::TargetUpdate(target) => Self::TargetUpdate(target.into()),
+        }
+    }
+}
+
+/// Updates made at the output ports of operators within a tracker
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(SerdeSerialize, SerdeDeserialize))]
+#[cfg_attr(feature = "serde", serde(crate = "serde_dep"))]
+#[cfg_attr(feature = "rkyv", derive(Archive, RkyvSerialize, RkyvDeserialize))]
+#[cfg_attr(feature = "rkyv", archive(strict, derive(CheckBytes)))]
+#[cfg_attr(feature = "enable_abomonation", derive(Abomonation))]
+pub struct SourceUpdate {
+    /// The address of the scope that owns the tracker
+    pub tracker_id: OperatorAddr,
+    /// The updates themselves
+    pub updates: Vec<PointstampUpdate>,
+}
+
+impl SourceUpdate {
+    pub const fn new(tracker_id: OperatorAddr, updates: Vec<PointstampUpdate>) -> Self {
+        Self {
+            tracker_id,
+            updates,
+        }
+    }
+}
+
+#[cfg(feature = "timely-next")]
+impl From<RawSourceUpdate> for SourceUpdate {
+    fn from(source: RawSourceUpdate) -> Self {
+        Self {
+            tracker_id: OperatorAddr::from(source.tracker_id),
+            updates: source
+                .updates
+                .iter()
+                .map(|(&node, &port, time, &diff)| {
+                    PointstampUpdate::new(
+                        PortId::new(node),
+                        PortId::new(port),
+                        format!("{:?}", time),
+                        time.type_name().to_owned(),
+                        diff,
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Updates made at the input ports of operators within a tracker
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(SerdeSerialize, SerdeDeserialize))]
+#[cfg_attr(feature = "serde", serde(crate = "serde_dep"))]
+#[cfg_attr(feature = "rkyv", derive(Archive, RkyvSerialize, RkyvDeserialize))]
+#[cfg_attr(feature = "rkyv", archive(strict, derive(CheckBytes)))]
+#[cfg_attr(feature = "enable_abomonation", derive(Abomonation))]
+pub struct TargetUpdate {
+    /// The address of the scope that owns the tracker
+    pub tracker_id: OperatorAddr,
+    /// The updates themselves
+    pub updates: Vec<PointstampUpdate>,
+}
+
+impl TargetUpdate {
+    pub const fn new(tracker_id: OperatorAddr, updates: Vec<PointstampUpdate>) -> Self {
+        Self {
+            tracker_id,
+            updates,
+        }
+    }
+}
+
+#[cfg(feature = "timely-next")]
+impl From<RawTargetUpdate> for TargetUpdate {
+    fn from(target: RawTargetUpdate) -> Self {
+        Self {
+            tracker_id: OperatorAddr::from(target.tracker_id),
+            updates: target
+                .updates
+                .iter()
+                .map(|(&node, &port, time, &diff)| {
+                    PointstampUpdate::new(
+                        PortId::new(node),
+                        PortId::new(port),
+                        format!("{:?}", time),
+                        time.type_name().to_owned(),
+                        diff,
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+/// A change in the number of pointstamps held at a location within a tracker
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(SerdeSerialize, SerdeDeserialize))]
+#[cfg_attr(feature = "serde", serde(crate = "serde_dep"))]
+#[cfg_attr(feature = "rkyv", derive(Archive, RkyvSerialize, RkyvDeserialize))]
+#[cfg_attr(feature = "rkyv", archive(strict, derive(CheckBytes)))]
+#[cfg_attr(feature = "enable_abomonation", derive(Abomonation))]
+pub struct PointstampUpdate {
+    /// The index of the operator within the tracker's scope, zero
+    /// is the scope itself
+    pub node: PortId,
+    pub port: PortId,
+    /// The update's timestamp, printed via its [`Debug`](`std::fmt::Debug`) implementation
+    pub timestamp: String,
+    /// The type of the update's timestamp, printed via its [`Any`](`std::any::Any`) implementation
+    pub timestamp_type: String,
+    /// The change in the number of pointstamps
+    pub diff: i64,
+}
+
+impl PointstampUpdate {
+    pub const fn new(
+        node: PortId,
+        port: PortId,
+        timestamp: String,
+        timestamp_type: String,
+        diff: i64,
+    ) -> Self {
+        Self {
+            node,
+            port,
+            timestamp,
+            timestamp_type,
+            diff,
+        }
+    }
+}
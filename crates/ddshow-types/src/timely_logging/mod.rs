@@ -3,7 +3,7 @@ mod timely_event;
 pub use timely_event::{ArchivedTimelyEvent, TimelyEvent, TimelyEventResolver};
 
 use crate::{
-    ids::{ChannelId, OperatorId, PortId},
+    ids::{ChannelId, OperatorId, PortId, WorkerId},
     OperatorAddr,
 };
 #[cfg(feature = "enable_abomonation")]
@@ -56,6 +56,16 @@ impl From<TimelyOperatesEvent> for OperatesEvent {
     }
 }
 
+impl From<OperatesEvent> for TimelyOperatesEvent {
+    fn from(event: OperatesEvent) -> Self {
+        Self {
+            id: event.id.into_inner(),
+            addr: event.addr.iter().map(|id| id.into_inner()).collect(),
+            name: event.name,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Ord, PartialOrd, Hash)]
 #[cfg_attr(feature = "rkyv", derive(Archive, RkyvSerialize, RkyvDeserialize))]
 #[cfg_attr(feature = "rkyv", archive(strict, derive(CheckBytes)))]
@@ -96,6 +106,17 @@ impl From<TimelyChannelsEvent> for ChannelsEvent {
     }
 }
 
+impl From<ChannelsEvent> for TimelyChannelsEvent {
+    fn from(event: ChannelsEvent) -> Self {
+        Self {
+            id: event.id.into_inner(),
+            scope_addr: event.scope_addr.iter().map(|id| id.into_inner()).collect(),
+            source: (event.source[0].into_inner(), event.source[1].into_inner()),
+            target: (event.target[0].into_inner(), event.target[1].into_inner()),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Ord, PartialOrd, Hash)]
 #[cfg_attr(feature = "rkyv", derive(Archive, RkyvSerialize, RkyvDeserialize))]
 #[cfg_attr(feature = "rkyv", archive(strict, derive(CheckBytes)))]
@@ -114,6 +135,14 @@ impl From<TimelyPushProgressEvent> for PushProgressEvent {
     }
 }
 
+impl From<PushProgressEvent> for TimelyPushProgressEvent {
+    fn from(event: PushProgressEvent) -> Self {
+        Self {
+            op_id: event.op_id.into_inner(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Ord, PartialOrd, Hash)]
 #[cfg_attr(feature = "rkyv", derive(Archive, RkyvSerialize, RkyvDeserialize))]
 #[cfg_attr(feature = "rkyv", archive(strict, derive(CheckBytes)))]
@@ -126,9 +155,9 @@ pub struct MessagesEvent {
     /// Channel identifier
     pub channel: ChannelId,
     /// Source worker index.
-    pub source: OperatorId,
+    pub source: WorkerId,
     /// Target worker index.
-    pub target: OperatorId,
+    pub target: WorkerId,
     /// Message sequence number.
     pub seq_no: usize,
     /// Number of typed records in the message.
@@ -140,8 +169,21 @@ impl From<TimelyMessagesEvent> for MessagesEvent {
         Self {
             is_send: event.is_send,
             channel: ChannelId::new(event.channel),
-            source: OperatorId::new(event.source),
-            target: OperatorId::new(event.target),
+            source: WorkerId::new(event.source),
+            target: WorkerId::new(event.target),
+            seq_no: event.seq_no,
+            length: event.length,
+        }
+    }
+}
+
+impl From<MessagesEvent> for TimelyMessagesEvent {
+    fn from(event: MessagesEvent) -> Self {
+        Self {
+            is_send: event.is_send,
+            channel: event.channel.into_inner(),
+            source: event.source.into_inner(),
+            target: event.target.into_inner(),
             seq_no: event.seq_no,
             length: event.length,
         }
@@ -182,6 +224,15 @@ impl From<TimelyStartStop> for StartStop {
     }
 }
 
+impl From<StartStop> for TimelyStartStop {
+    fn from(start_stop: StartStop) -> Self {
+        match start_stop {
+            StartStop::Start => Self::Start,
+            StartStop::Stop => Self::Stop,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Ord, PartialOrd, Hash)]
 #[cfg_attr(feature = "rkyv", derive(Archive, RkyvSerialize, RkyvDeserialize))]
 #[cfg_attr(feature = "rkyv", archive(strict, derive(CheckBytes)))]
@@ -202,6 +253,15 @@ impl From<TimelyScheduleEvent> for ScheduleEvent {
     }
 }
 
+impl From<ScheduleEvent> for TimelyScheduleEvent {
+    fn from(event: ScheduleEvent) -> Self {
+        Self {
+            id: event.id.into_inner(),
+            start_stop: event.start_stop.into(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Ord, PartialOrd, Hash)]
 #[cfg_attr(feature = "rkyv", derive(Archive, RkyvSerialize, RkyvDeserialize))]
 #[cfg_attr(feature = "rkyv", archive(strict, derive(CheckBytes)))]
@@ -220,6 +280,14 @@ impl From<TimelyShutdownEvent> for ShutdownEvent {
     }
 }
 
+impl From<ShutdownEvent> for TimelyShutdownEvent {
+    fn from(event: ShutdownEvent) -> Self {
+        Self {
+            id: event.id.into_inner(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Ord, PartialOrd, Hash)]
 #[cfg_attr(feature = "rkyv", derive(Archive, RkyvSerialize, RkyvDeserialize))]
 #[cfg_attr(feature = "rkyv", archive(strict, derive(CheckBytes)))]
@@ -241,6 +309,15 @@ impl From<TimelyApplicationEvent> for ApplicationEvent {
     }
 }
 
+impl From<ApplicationEvent> for TimelyApplicationEvent {
+    fn from(event: ApplicationEvent) -> Self {
+        Self {
+            id: event.id,
+            is_start: event.is_start,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Ord, PartialOrd, Hash)]
 #[cfg_attr(feature = "rkyv", derive(Archive, RkyvSerialize, RkyvDeserialize))]
 #[cfg_attr(feature = "rkyv", archive(strict, derive(CheckBytes)))]
@@ -260,6 +337,14 @@ impl From<TimelyGuardedMessageEvent> for GuardedMessageEvent {
     }
 }
 
+impl From<GuardedMessageEvent> for TimelyGuardedMessageEvent {
+    fn from(event: GuardedMessageEvent) -> Self {
+        Self {
+            is_start: event.is_start,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Ord, PartialOrd, Hash)]
 #[cfg_attr(feature = "rkyv", derive(Archive, RkyvSerialize, RkyvDeserialize))]
 #[cfg_attr(feature = "rkyv", archive(strict, derive(CheckBytes)))]
@@ -279,6 +364,14 @@ impl From<TimelyGuardedProgressEvent> for GuardedProgressEvent {
     }
 }
 
+impl From<GuardedProgressEvent> for TimelyGuardedProgressEvent {
+    fn from(event: GuardedProgressEvent) -> Self {
+        Self {
+            is_start: event.is_start,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Ord, PartialOrd, Hash)]
 #[cfg_attr(feature = "rkyv", derive(Archive, RkyvSerialize, RkyvDeserialize))]
 #[cfg_attr(feature = "rkyv", archive(strict, derive(CheckBytes)))]
@@ -299,6 +392,15 @@ impl From<TimelyCommChannelsEvent> for CommChannelsEvent {
     }
 }
 
+impl From<CommChannelsEvent> for TimelyCommChannelsEvent {
+    fn from(event: CommChannelsEvent) -> Self {
+        Self {
+            identifier: event.identifier,
+            kind: event.kind.into(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Ord, PartialOrd, Hash)]
 #[cfg_attr(feature = "rkyv", derive(Archive, RkyvSerialize, RkyvDeserialize))]
 #[cfg_attr(feature = "rkyv", archive(strict, derive(CheckBytes)))]
@@ -331,6 +433,15 @@ impl From<TimelyCommChannelKind> for CommChannelKind {
     }
 }
 
+impl From<CommChannelKind> for TimelyCommChannelKind {
+    fn from(channel_kind: CommChannelKind) -> Self {
+        match channel_kind {
+            CommChannelKind::Progress => Self::Progress,
+            CommChannelKind::Data => Self::Data,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Ord, PartialOrd, Hash)]
 #[cfg_attr(feature = "rkyv", derive(Archive, RkyvSerialize, RkyvDeserialize))]
 #[cfg_attr(feature = "rkyv", archive(strict, derive(CheckBytes)))]
@@ -349,6 +460,14 @@ impl From<TimelyInputEvent> for InputEvent {
     }
 }
 
+impl From<InputEvent> for TimelyInputEvent {
+    fn from(event: InputEvent) -> Self {
+        Self {
+            start_stop: event.start_stop.into(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Ord, PartialOrd, Hash)]
 #[cfg_attr(feature = "rkyv", derive(Archive, RkyvSerialize, RkyvDeserialize))]
 #[cfg_attr(feature = "rkyv", archive(strict, derive(CheckBytes)))]
@@ -389,3 +508,12 @@ impl From<TimelyParkEvent> for ParkEvent {
         }
     }
 }
+
+impl From<ParkEvent> for TimelyParkEvent {
+    fn from(park: ParkEvent) -> Self {
+        match park {
+            ParkEvent::Park(duration) => Self::Park(duration),
+            ParkEvent::Unpark => Self::Unpark,
+        }
+    }
+}
@@ -333,6 +333,26 @@ impl From<RawTimelyEvent> for TimelyEvent {
     }
 }
 
+impl From<TimelyEvent> for RawTimelyEvent {
+    fn from(event: TimelyEvent) -> Self {
+        match event {
+            TimelyEvent::Operates(operates) => Self::Operates(operates.into()),
+            TimelyEvent::Channels(channels) => Self::Channels(channels.into()),
+            TimelyEvent::PushProgress(progress) => Self::PushProgress(progress.into()),
+            TimelyEvent::Messages(inner) => Self::Messages(inner.into()),
+            TimelyEvent::Schedule(inner) => Self::Schedule(inner.into()),
+            TimelyEvent::Shutdown(inner) => Self::Shutdown(inner.into()),
+            TimelyEvent::Application(inner) => Self::Application(inner.into()),
+            TimelyEvent::GuardedMessage(inner) => Self::GuardedMessage(inner.into()),
+            TimelyEvent::GuardedProgress(inner) => Self::GuardedProgress(inner.into()),
+            TimelyEvent::CommChannels(inner) => Self::CommChannels(inner.into()),
+            TimelyEvent::Input(inner) => Self::Input(inner.into()),
+            TimelyEvent::Park(inner) => Self::Park(inner.into()),
+            TimelyEvent::Text(text) => Self::Text(text),
+        }
+    }
+}
+
 impl From<ParkEvent> for TimelyEvent {
     fn from(v: ParkEvent) -> Self {
         Self::Park(v)
@@ -404,3 +424,82 @@ impl From<OperatesEvent> for TimelyEvent {
         Self::Operates(v)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        timely_logging::{
+            ApplicationEvent, ChannelsEvent, CommChannelKind, CommChannelsEvent,
+            GuardedMessageEvent, GuardedProgressEvent, InputEvent, MessagesEvent, OperatesEvent,
+            ParkEvent, PushProgressEvent, ScheduleEvent, ShutdownEvent, StartStop, TimelyEvent,
+        },
+        ChannelId, OperatorAddr, OperatorId, PortId, WorkerId,
+    };
+    use std::time::Duration;
+    use timely::logging::TimelyEvent as RawTimelyEvent;
+
+    #[test]
+    fn raw_event_roundtrip() {
+        let events = vec![
+            TimelyEvent::Operates(OperatesEvent::new(
+                OperatorId::new(3),
+                OperatorAddr::from(vec![OperatorId::new(0), OperatorId::new(3)]),
+                "Map".to_owned(),
+            )),
+            TimelyEvent::Channels(ChannelsEvent::new(
+                ChannelId::new(7),
+                OperatorAddr::from(vec![OperatorId::new(0)]),
+                (PortId::new(1), PortId::new(0)),
+                (PortId::new(2), PortId::new(1)),
+            )),
+            TimelyEvent::PushProgress(PushProgressEvent {
+                op_id: OperatorId::new(4),
+            }),
+            TimelyEvent::Messages(MessagesEvent {
+                is_send: true,
+                channel: ChannelId::new(7),
+                source: WorkerId::new(0),
+                target: WorkerId::new(1),
+                seq_no: 12,
+                length: 1024,
+            }),
+            TimelyEvent::Schedule(ScheduleEvent {
+                id: OperatorId::new(3),
+                start_stop: StartStop::Start,
+            }),
+            TimelyEvent::Schedule(ScheduleEvent {
+                id: OperatorId::new(3),
+                start_stop: StartStop::Stop,
+            }),
+            TimelyEvent::Shutdown(ShutdownEvent {
+                id: OperatorId::new(3),
+            }),
+            TimelyEvent::Application(ApplicationEvent {
+                id: 9,
+                is_start: true,
+            }),
+            TimelyEvent::GuardedMessage(GuardedMessageEvent { is_start: false }),
+            TimelyEvent::GuardedProgress(GuardedProgressEvent { is_start: true }),
+            TimelyEvent::CommChannels(CommChannelsEvent {
+                identifier: 2,
+                kind: CommChannelKind::Progress,
+            }),
+            TimelyEvent::CommChannels(CommChannelsEvent {
+                identifier: 3,
+                kind: CommChannelKind::Data,
+            }),
+            TimelyEvent::Input(InputEvent {
+                start_stop: StartStop::Stop,
+            }),
+            TimelyEvent::Park(ParkEvent::Park(Some(Duration::from_millis(5)))),
+            TimelyEvent::Park(ParkEvent::Park(None)),
+            TimelyEvent::Park(ParkEvent::Unpark),
+            TimelyEvent::Text("ddshow::lint::capability_violation".to_owned()),
+        ];
+
+        for event in events {
+            let raw = RawTimelyEvent::from(event.clone());
+            assert_eq!(TimelyEvent::from(raw), event);
+        }
+    }
+}
@@ -1,5 +1,19 @@
+use crate::{
+    record::SourceKind,
+    trim::{DROPPABLE_EVENT_KINDS, STRUCTURAL_EVENT_KINDS},
+};
 use colorous::Gradient;
-use std::{net::SocketAddr, num::NonZeroUsize, ops::Deref, path::PathBuf, str::FromStr};
+use ddshow_sink::{framing::Framing, RedactionMode};
+use ddshow_types::{OperatorAddr, OperatorId};
+use std::{
+    convert::TryFrom,
+    net::SocketAddr,
+    num::NonZeroUsize,
+    ops::Deref,
+    path::PathBuf,
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
 use structopt::StructOpt;
 use timely::{CommunicationConfig, WorkerConfig};
 
@@ -16,7 +30,6 @@ use timely::{CommunicationConfig, WorkerConfig};
 // TODO: Process logs from file
 // TODO: Progress logging
 // TODO: Build info in help message
-// TODO: Reachability logging
 // TODO: Disable timeline events
 // TODO: This is complex enough to where it may need an
 //       actual config file
@@ -28,31 +41,42 @@ pub struct Args {
     pub workers: NonZeroUsize,
 
     /// The number of timely workers running in the target computation
-    #[structopt(short = "c", long = "connections", default_value = "1")]
+    #[structopt(short = "c", long = "connections", default_value = "1", global = true)]
     pub timely_connections: NonZeroUsize,
 
     /// The address to listen for Timely Dataflow log messages from
-    #[structopt(long = "address", default_value = "127.0.0.1:51317")]
+    #[structopt(long = "address", default_value = "127.0.0.1:51317", global = true)]
     pub timely_address: SocketAddr,
 
     /// Whether or not Differential Dataflow logs should be read from
-    #[structopt(short = "d", long = "differential")]
+    #[structopt(short = "d", long = "differential", global = true)]
     pub differential_enabled: bool,
 
     /// The address to listen for Differential Dataflow log messages from
     // FIXME: `requires("differential")` makes clap panic
-    #[structopt(long, default_value = "127.0.0.1:51318")]
+    #[structopt(long, default_value = "127.0.0.1:51318", global = true)]
     pub differential_address: SocketAddr,
 
     /// Whether or not Timely Dataflow progress logs should be read from
-    #[structopt(short = "p", long = "progress")]
+    #[structopt(short = "p", long = "progress", global = true)]
     pub progress_enabled: bool,
 
     /// The address to listen for Timely Dataflow progress messages from
     // FIXME: `requires("progress")` makes clap panic
-    #[structopt(long, default_value = "127.0.0.1:51319")]
+    #[structopt(long, default_value = "127.0.0.1:51319", global = true)]
     pub progress_address: SocketAddr,
 
+    /// Whether or not Timely Dataflow reachability logs should be read from
+    ///
+    /// Reachability logs are only produced by unreleased versions of timely
+    /// and require rkyv-encoded sources
+    #[structopt(long = "reachability", global = true)]
+    pub reachability_enabled: bool,
+
+    /// The address to listen for Timely Dataflow reachability messages from
+    #[structopt(long, default_value = "127.0.0.1:51320", global = true)]
+    pub reachability_address: SocketAddr,
+
     /// The color palette to use for the generated graphs
     #[structopt(
         long,
@@ -76,13 +100,43 @@ pub struct Args {
     #[structopt(long)]
     pub save_logs: Option<PathBuf>,
 
+    /// Write a CRC32C checksum of every frame into the logs saved by `--save-logs`,
+    /// allowing `ddshow verify` and replays to detect corruption
+    #[structopt(long, requires("save-logs"))]
+    pub checksums: bool,
+
+    /// Save logs with `--save-logs` in the batched format, which takes up less
+    /// space but can only be read by versions of ddshow that understand it
+    #[structopt(long, requires("save-logs"))]
+    pub batched: bool,
+
     /// The directory to replay a recorded set of logs from
     #[structopt(
         long,
-        conflicts_with_all(&["save-logs", "connections", "address", "differential"]),
+        conflicts_with_all(&["save-logs", "connections", "address", "differential", "forward"]),
     )]
     pub replay_logs: Option<PathBuf>,
 
+    /// Only analyze replayed events logged at or after the given time, e.g. `5m`,
+    /// `1h 30m` or `2021-06-01 12:30:00`
+    ///
+    /// Durations are relative to the start of the traced computation. Wall-clock
+    /// times are given in UTC, require a log directory made by `ddshow record` and
+    /// are resolved against the time the recording started, so they're only as
+    /// accurate as the gap between starting the recording and the traced computation
+    /// connecting to it. Operator creation, channel creation and operator shutdown
+    /// events are always kept so that operators created before the window are still
+    /// known, and arrangement events from before the window are kept so that
+    /// arrangement sizes stay accurate. Operator activations crossing the window's
+    /// bounds are clipped to it
+    #[structopt(long, requires("replay-logs"))]
+    pub from: Option<ReplayBound>,
+
+    /// Only analyze replayed events logged before the given time, accepts the same
+    /// durations and wall-clock times as `--from`
+    #[structopt(long, requires("replay-logs"))]
+    pub until: Option<ReplayBound>,
+
     /// The file to output a text report to
     #[structopt(long, default_value = "report.txt")]
     pub report_file: PathBuf,
@@ -92,7 +146,12 @@ pub struct Args {
     pub no_report_file: bool,
 
     /// The coloring to use for terminal output
-    #[structopt(long, default_value = "auto", possible_values = &["auto", "always", "never"])]
+    #[structopt(
+        long,
+        default_value = "auto",
+        possible_values = &["auto", "always", "never"],
+        global = true,
+    )]
     pub color: TerminalColor,
 
     /// Enable profiling for ddshow's internal dataflow
@@ -104,12 +163,413 @@ pub struct Args {
     #[structopt(long)]
     pub disable_timeline: bool,
 
+    /// Bounds the memory used by the dataflow timeline by only keeping the given
+    /// number of slowest events for each operator and event kind on each worker
+    /// over the whole run. All other events are collapsed into at most as many
+    /// spans, with the oldest spans being folded together first
+    #[structopt(long, conflicts_with("disable-timeline"))]
+    pub timeline_limit: Option<NonZeroUsize>,
+
+    /// The largest gap between two events that will be collapsed together
+    /// when `--timeline-limit` is set, larger gaps give smaller timelines
+    /// but make idle time harder to spot
+    #[structopt(long, default_value = "1ms", parse(try_from_str = duration_from_str))]
+    pub timeline_merge_gap: Duration,
+
+    /// Fuses adjacent events of the same kind on the same worker together
+    /// when they're at most this far apart, e.g. `500us`
+    ///
+    /// Fused events show up as a single span within the dataflow timeline,
+    /// events aren't fused unless this is set
+    #[structopt(
+        long,
+        conflicts_with("disable-timeline"),
+        parse(try_from_str = duration_from_str)
+    )]
+    pub timeline_fusion_margin: Option<Duration>,
+
+    /// The width of the time buckets that worker utilization and
+    /// operator statistics are split into, e.g. `500ms` or `1min`
+    #[structopt(long, default_value = "1s", parse(try_from_str = bucket_width_from_str))]
+    pub bucket_width: Duration,
+
+    /// How far apart the timestamps of the analysis dataflow are, e.g. `100ms` or `1min`
+    ///
+    /// Events are rounded up to the next multiple of the granularity before being
+    /// analyzed. Finer granularities update statistics more precisely, which suits
+    /// short benchmarks, but make ddshow do more progress tracking. Coarser
+    /// granularities keep multi-hour traces fast at the cost of precision
+    #[structopt(long, default_value = "5s", parse(try_from_str = granularity_from_str))]
+    pub granularity: Duration,
+
+    /// The amount of work done each time data is extracted, both from
+    /// incoming timely events and from the results of the analysis dataflow
+    ///
+    /// Larger values drain data faster but leave ddshow less responsive to
+    /// input while they run, smaller values stay responsive but let
+    /// unprocessed data pile up in memory
+    #[structopt(long, default_value = "1000000")]
+    pub extraction_fuel: NonZeroUsize,
+
+    /// The number of events each worker replays at a time when
+    /// loading logs from disk with `--replay-logs`
+    ///
+    /// Larger values load logs faster but create larger batches of events
+    /// that take more memory to process, smaller values smooth out memory
+    /// usage but take longer to load
+    #[structopt(long, default_value = "100000000")]
+    pub replay_fuel: NonZeroUsize,
+
+    /// The most memory that extracted timeline events are allowed to take up before
+    /// they're spilled to temporary files on disk, e.g. `512MB` or `2GiB`. Spilled
+    /// events are streamed back in when writing the output files at the cost of
+    /// extra disk I/O
+    ///
+    /// This only bounds the timeline events held after they've been extracted from
+    /// the analysis dataflow. The dataflow's own arrangements are always held in
+    /// memory, use `--timeline-limit` or `--disable-timeline` to shrink those
+    #[structopt(
+        long,
+        conflicts_with("disable-timeline"),
+        parse(try_from_str = byte_size_from_str)
+    )]
+    pub timeline_memory_limit: Option<u64>,
+
     #[structopt(
         long,
         default_value = "abomonation",
         possible_values = &["abomonation", "rkyv"],
+        global = true,
     )]
     pub stream_encoding: StreamEncoding,
+
+    /// Forwards every accepted connection of a kind of log to another consumer while
+    /// ddshow analyzes it, e.g. `timely=127.0.0.1:51400` or `differential=127.0.0.1:51401`
+    ///
+    /// The kind can be `timely`, `differential`, `progress` or `reachability` and defaults
+    /// to `timely` when only an address is given. Can be given multiple times, every
+    /// connection ddshow accepts gets its own connection to each downstream address so
+    /// downstream consumers should expect as many connections as ddshow does
+    #[structopt(
+        long,
+        number_of_values = 1,
+        parse(try_from_str = forward_target_from_str),
+        global = true,
+    )]
+    pub forward: Vec<ForwardTarget>,
+
+    /// How forwarded logs are encoded
+    ///
+    /// `raw` forwards the bytes ddshow receives untouched, so downstream consumers
+    /// need to understand the `--stream-encoding` of the source. `rkyv` re-encodes
+    /// abomonation sources into the rkyv format ddshow uses for `--save-logs`, which
+    /// lets another ddshow consume them with `--stream-encoding rkyv` at the cost of
+    /// re-serializing every event. Rkyv sources are always forwarded untouched
+    #[structopt(
+        long,
+        default_value = "raw",
+        possible_values = &["raw", "rkyv"],
+        global = true,
+    )]
+    pub forward_encoding: ForwardEncoding,
+
+    /// What to do when a downstream consumer can't keep up with the
+    /// logs being forwarded to it
+    ///
+    /// `block` stops reading from the source until the downstream catches up, which
+    /// never loses data but slows down ddshow and eventually the traced computation.
+    /// `disconnect` drops any downstream that falls more than `--forward-buffer`
+    /// behind so that it can never slow down the source
+    #[structopt(
+        long,
+        default_value = "block",
+        possible_values = &["block", "disconnect"],
+        global = true,
+    )]
+    pub forward_backpressure: ForwardBackpressure,
+
+    /// How much forwarded data can be queued for each downstream
+    /// connection before `--forward-backpressure` kicks in, e.g. `16MiB`
+    #[structopt(
+        long,
+        default_value = "64MiB",
+        parse(try_from_str = byte_size_from_str),
+        global = true,
+    )]
+    pub forward_buffer: u64,
+
+    /// What to do when forwarding to a downstream consumer fails, either because it
+    /// couldn't be connected to, it disconnected or it fell too far behind
+    ///
+    /// `ignore` stops forwarding to that downstream and carries on with the analysis,
+    /// `stop` stops collecting logs as if enter had been pressed so that the results
+    /// only cover what every downstream consumer also received
+    #[structopt(
+        long,
+        default_value = "ignore",
+        possible_values = &["ignore", "stop"],
+        global = true,
+    )]
+    pub forward_on_failure: ForwardFailure,
+
+    #[structopt(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Clone, StructOpt)]
+#[structopt(rename_all = "kebab-case")]
+pub enum Command {
+    /// Record the logs of a running computation to disk without analyzing them
+    ///
+    /// Accepts the same connections as a normal run and writes everything it receives
+    /// to the given directory along with a manifest describing the recording, the
+    /// directory can then be analyzed later with `--replay-logs`
+    Record(RecordArgs),
+
+    /// Send a saved log directory back out over TCP as if it came from a live computation
+    ///
+    /// Every log file in the directory gets its own connection to the address of its
+    /// kind of log, e.g. `--address` for timely logs or `--differential-address` for
+    /// differential logs when `--differential` is set. Consumers should expect one
+    /// connection per log file and the encoding set by `--stream-encoding`
+    ReplayServe(ReplayServeArgs),
+
+    /// Write a smaller copy of a saved log directory
+    ///
+    /// Keeps only the selected dataflows, workers and time range and can drop whole
+    /// kinds of events, which is useful for cutting saved logs down to what's needed
+    /// to reproduce a problem. The structure of the kept dataflows is always preserved
+    /// so that the trimmed logs can still be replayed with `--replay-logs`
+    Trim(TrimArgs),
+
+    /// Write a copy of a saved log directory with its operator names redacted
+    ///
+    /// Operator names are replaced with stable hashes or with the kind of operator
+    /// they are, everything else is kept as-is so the redacted logs can be replayed
+    /// and shared without giving away what the dataflow was working on
+    Redact(RedactArgs),
+
+    /// Check the frames of a saved log directory for truncation and corruption
+    ///
+    /// Every frame's length and archive are validated and the offset of anything that
+    /// doesn't check out is reported. Computations that crash often leave a partial
+    /// frame at the end of their logs, which replays will skip over, but corruption
+    /// elsewhere stops a replay early. `--resync` skips past corrupted regions to find
+    /// the next valid frame and `--repair` writes a copy of the logs with only their
+    /// valid frames
+    Verify(VerifyArgs),
+}
+
+#[derive(Debug, Clone, StructOpt)]
+#[structopt(rename_all = "kebab-case")]
+pub struct RecordArgs {
+    /// The directory to write the recorded logs to
+    pub log_dir: PathBuf,
+
+    /// Write a CRC32C checksum of every frame into the recorded logs,
+    /// allowing `ddshow verify` and replays to detect corruption
+    #[structopt(long)]
+    pub checksums: bool,
+
+    /// Record logs in the batched format, which takes up less space but
+    /// can only be read by versions of ddshow that understand it
+    #[structopt(long)]
+    pub batched: bool,
+}
+
+#[derive(Debug, Clone, StructOpt)]
+#[structopt(rename_all = "kebab-case")]
+pub struct ReplayServeArgs {
+    /// The directory of saved logs to send
+    pub log_dir: PathBuf,
+
+    /// How fast to send the logs, either `realtime`, a multiple of
+    /// real time like `2x` or `0.5x`, or `max` to send them as fast
+    /// as the consumer will accept them
+    ///
+    /// Events are paced by the timestamps they were logged with, so `realtime`
+    /// reproduces the timing of the original run while `max` gives a quick and
+    /// deterministic stream for testing consumers
+    #[structopt(long, default_value = "realtime")]
+    pub speed: ReplaySpeed,
+}
+
+#[derive(Debug, Clone, StructOpt)]
+#[structopt(rename_all = "kebab-case")]
+pub struct TrimArgs {
+    /// The directory of saved logs to trim
+    pub log_dir: PathBuf,
+
+    /// The directory to write the trimmed logs to
+    pub output_dir: PathBuf,
+
+    /// Only keep the dataflows with the given name, can be given multiple times
+    #[structopt(long = "dataflow", number_of_values = 1)]
+    pub dataflows: Vec<String>,
+
+    /// Only keep the operators at or within the given address, e.g. `0.3` or `[0, 3]`,
+    /// can be given multiple times
+    ///
+    /// The scopes containing a kept operator are kept as well so that the
+    /// operator's place within the dataflow graph is still known
+    #[structopt(
+        long = "operator-address",
+        number_of_values = 1,
+        parse(try_from_str = operator_addr_from_str)
+    )]
+    pub addresses: Vec<OperatorAddr>,
+
+    /// Only keep events of the given workers, e.g. `--worker 0,2`
+    #[structopt(long = "worker", use_delimiter = true)]
+    pub workers: Vec<usize>,
+
+    /// Only keep events logged at or after the given time, accepts the
+    /// same durations and wall-clock times as `--replay-logs --from`
+    #[structopt(long)]
+    pub from: Option<ReplayBound>,
+
+    /// Only keep events logged before the given time, accepts the same
+    /// durations and wall-clock times as `--replay-logs --until`
+    #[structopt(long)]
+    pub until: Option<ReplayBound>,
+
+    /// Drop every event of the given kind, e.g. `messages` or `merge`,
+    /// can be given multiple times
+    ///
+    /// Operator and channel creation events describe the structure of the
+    /// dataflow and can't be dropped
+    #[structopt(long = "drop", number_of_values = 1, parse(try_from_str = event_kind_from_str))]
+    pub dropped_kinds: Vec<&'static str>,
+}
+
+#[derive(Debug, Clone, StructOpt)]
+#[structopt(rename_all = "kebab-case")]
+pub struct RedactArgs {
+    /// The directory of saved logs to redact
+    pub log_dir: PathBuf,
+
+    /// The directory to write the redacted logs to
+    pub output_dir: PathBuf,
+
+    /// How operator names are replaced, either `hash` or `kind`
+    ///
+    /// `hash` replaces names with a stable hash so that operators can still be told
+    /// apart and compared across traces, but names that are easy to guess can be
+    /// recovered by hashing guesses. `kind` replaces names with the kind of builtin
+    /// operator they are, like `Map` or `Join`, and `Operator` for everything else
+    #[structopt(long, default_value = "hash")]
+    pub mode: RedactionMode,
+
+    /// Write a mapping from each redacted operator back to its original name
+    /// to the given file, which can't be within the output directory
+    ///
+    /// The mapping contains all of the original names, so keep it private
+    #[structopt(long)]
+    pub mapping_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, StructOpt)]
+#[structopt(rename_all = "kebab-case")]
+pub struct VerifyArgs {
+    /// The directory of saved logs to verify
+    pub log_dir: PathBuf,
+
+    /// Keep checking after a corrupted frame by searching for the next valid one
+    ///
+    /// Frames always start on a 16 byte boundary, so every boundary after the corrupted
+    /// frame is checked until one holds a frame that validates
+    #[structopt(long)]
+    pub resync: bool,
+
+    /// Write a copy of the logs containing only their valid frames to the given
+    /// directory, implies `--resync`
+    #[structopt(long)]
+    pub repair: Option<PathBuf>,
+}
+
+/// How fast `replay-serve` sends events
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplaySpeed {
+    /// Send events as fast as possible
+    Max,
+    /// Send events at a multiple of the speed they were originally logged at
+    Scaled(f64),
+}
+
+impl ReplaySpeed {
+    /// Returns how long after the start of the replay an event
+    /// logged at `time` should be sent, `None` if it should be sent immediately
+    pub fn delay(self, time: Duration) -> Option<Duration> {
+        match self {
+            Self::Max => None,
+            Self::Scaled(factor) => Some(time.div_f64(factor)),
+        }
+    }
+}
+
+impl FromStr for ReplaySpeed {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let lowercase = string.trim().to_lowercase();
+        match lowercase.as_str() {
+            "max" => Ok(Self::Max),
+            "realtime" | "real-time" => Ok(Self::Scaled(1.0)),
+
+            speed => {
+                let factor: f64 =
+                    speed
+                        .strip_suffix('x')
+                        .unwrap_or(speed)
+                        .parse()
+                        .map_err(|_| {
+                            format!(
+                                "invalid replay speed {:?}, expected `realtime`, `max` \
+                            or a multiplier like `2x`",
+                                string,
+                            )
+                        })?;
+
+                if factor.is_finite() && factor > 0.0 {
+                    Ok(Self::Scaled(factor))
+                } else {
+                    Err(format!(
+                        "invalid replay speed {:?}, the multiplier must be greater than zero",
+                        string,
+                    ))
+                }
+            }
+        }
+    }
+}
+
+/// One end of the time window selected by `--from` and `--until`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayBound {
+    /// A duration since the start of the traced computation
+    Relative(Duration),
+    /// A wall-clock time, resolved using the recording's manifest
+    WallClock(SystemTime),
+}
+
+impl FromStr for ReplayBound {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let trimmed = string.trim();
+
+        if let Ok(duration) = humantime::parse_duration(trimmed) {
+            Ok(Self::Relative(duration))
+        } else if let Ok(time) = humantime::parse_rfc3339_weak(trimmed) {
+            Ok(Self::WallClock(time))
+        } else {
+            Err(format!(
+                "invalid replay bound {:?}, expected a duration like `5m` \
+                or a time like `2021-06-01 12:30:00`",
+                string,
+            ))
+        }
+    }
 }
 
 impl Args {
@@ -131,6 +591,26 @@ impl Args {
     pub const fn is_file_sourced(&self) -> bool {
         self.replay_logs.is_some()
     }
+
+    /// The framing of the logs written by `--save-logs`
+    pub const fn save_logs_framing(&self) -> Framing {
+        log_framing(self.checksums, self.batched)
+    }
+}
+
+impl RecordArgs {
+    /// The framing of the recorded logs
+    pub const fn framing(&self) -> Framing {
+        log_framing(self.checksums, self.batched)
+    }
+}
+
+const fn log_framing(checksums: bool, batched: bool) -> Framing {
+    if batched {
+        Framing::Batched { checksums }
+    } else {
+        Framing::Single { checksums }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -161,6 +641,79 @@ impl Default for StreamEncoding {
     }
 }
 
+/// A downstream address that a kind of log is forwarded to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ForwardTarget {
+    pub kind: SourceKind,
+    pub address: SocketAddr,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ForwardEncoding {
+    Raw,
+    Rkyv,
+}
+
+impl FromStr for ForwardEncoding {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let lowercase = string.to_lowercase();
+        match lowercase.as_str() {
+            "raw" => Ok(Self::Raw),
+            "rkyv" => Ok(Self::Rkyv),
+            _ => Err(format!(
+                "invalid forwarding encoding {:?}, only `raw` and `rkyv` are supported",
+                string,
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ForwardBackpressure {
+    Block,
+    Disconnect,
+}
+
+impl FromStr for ForwardBackpressure {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let lowercase = string.to_lowercase();
+        match lowercase.as_str() {
+            "block" => Ok(Self::Block),
+            "disconnect" => Ok(Self::Disconnect),
+            _ => Err(format!(
+                "invalid backpressure strategy {:?}, only `block` and `disconnect` are supported",
+                string,
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ForwardFailure {
+    Ignore,
+    Stop,
+}
+
+impl FromStr for ForwardFailure {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let lowercase = string.to_lowercase();
+        match lowercase.as_str() {
+            "ignore" => Ok(Self::Ignore),
+            "stop" => Ok(Self::Stop),
+            _ => Err(format!(
+                "invalid failure strategy {:?}, only `ignore` and `stop` are supported",
+                string,
+            )),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum TerminalColor {
     Auto,
@@ -208,6 +761,119 @@ impl Default for TerminalColor {
     }
 }
 
+fn duration_from_str(src: &str) -> Result<Duration, String> {
+    humantime::parse_duration(src).map_err(|err| format!("invalid duration '{}': {}", src, err))
+}
+
+fn bucket_width_from_str(src: &str) -> Result<Duration, String> {
+    let width = duration_from_str(src)?;
+
+    if width.is_zero() {
+        return Err("the bucket width must be greater than zero".to_owned());
+    }
+
+    Ok(width)
+}
+
+fn granularity_from_str(src: &str) -> Result<Duration, String> {
+    let granularity = duration_from_str(src)?;
+
+    if granularity < Duration::from_micros(1) {
+        return Err("the granularity must be at least one microsecond".to_owned());
+
+    // Rounded timestamps are kept in nanoseconds, so granularities that
+    // can't be held within a `u64` would overflow them
+    } else if u64::try_from(granularity.as_nanos()).is_err() {
+        return Err(format!(
+            "the granularity must be at most {}",
+            humantime::format_duration(Duration::from_nanos(u64::MAX)),
+        ));
+    }
+
+    Ok(granularity)
+}
+
+fn operator_addr_from_str(src: &str) -> Result<OperatorAddr, String> {
+    let segments = src.trim().trim_start_matches('[').trim_end_matches(']');
+    if segments.trim().is_empty() {
+        return Err("the operator address must have at least one segment".to_owned());
+    }
+
+    segments
+        .split(['.', ','])
+        .map(|segment| segment.trim().parse().map(OperatorId::new))
+        .collect::<Result<Vec<_>, _>>()
+        .map(OperatorAddr::from)
+        .map_err(|err| format!("invalid operator address '{}': {}", src, err))
+}
+
+fn event_kind_from_str(src: &str) -> Result<&'static str, String> {
+    let kind = src.trim().to_lowercase();
+
+    if STRUCTURAL_EVENT_KINDS.contains(&kind.as_str()) {
+        Err(format!(
+            "{} events describe the structure of the dataflow and can't be dropped",
+            kind,
+        ))
+    } else if let Some(&kind) = DROPPABLE_EVENT_KINDS.iter().find(|&&known| known == kind) {
+        Ok(kind)
+    } else {
+        Err(format!(
+            "unknown event kind '{}', expected one of {}",
+            src,
+            DROPPABLE_EVENT_KINDS.join(", "),
+        ))
+    }
+}
+
+fn byte_size_from_str(src: &str) -> Result<u64, String> {
+    let trimmed = src.trim();
+    let split = trimmed
+        .find(|char: char| !char.is_ascii_digit())
+        .unwrap_or(trimmed.len());
+    let (number, unit) = trimmed.split_at(split);
+
+    let number: u64 = number
+        .parse()
+        .map_err(|err| format!("invalid byte size '{}': {}", src, err))?;
+    let multiplier: u64 = match unit.trim().to_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" => 1000,
+        "mb" => 1000 * 1000,
+        "gb" => 1000 * 1000 * 1000,
+        "kib" => 1024,
+        "mib" => 1024 * 1024,
+        "gib" => 1024 * 1024 * 1024,
+        unit => {
+            return Err(format!(
+                "invalid byte size '{}': unknown unit '{}', expected one of \
+                B, KB, MB, GB, KiB, MiB or GiB",
+                src, unit,
+            ))
+        }
+    };
+
+    match number.checked_mul(multiplier) {
+        Some(0) => Err("the byte size must be greater than zero".to_owned()),
+        Some(bytes) => Ok(bytes),
+        None => Err(format!("invalid byte size '{}': too large", src)),
+    }
+}
+
+fn forward_target_from_str(src: &str) -> Result<ForwardTarget, String> {
+    let (kind, address) = match src.split_once('=') {
+        Some((kind, address)) => (kind.parse()?, address),
+        None => (SourceKind::Timely, src),
+    };
+
+    let address = address
+        .trim()
+        .parse()
+        .map_err(|err| format!("invalid forwarding address '{}': {}", address, err))?;
+
+    Ok(ForwardTarget { kind, address })
+}
+
 macro_rules! parse_gradient {
     ($($lower:literal => $gradient:ident),* $(,)?) => {
         fn gradient_from_str(src: &str) -> Result<ThreadedGradient, String> {
@@ -299,3 +965,53 @@ impl FromStr for Output {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::args::Args;
+    use structopt::StructOpt;
+
+    // clap only checks for conflicting arguments (like a subcommand reusing the name
+    // of a global one) with debug assertions while parsing, so parse every subcommand
+    // here instead of finding out from a panic
+    #[test]
+    fn arguments_are_unique() {
+        for subcommand in ["record", "trim", "redact", "verify", "replay-serve"] {
+            let help = Args::from_iter_safe(["ddshow", subcommand, "--help"]).unwrap_err();
+            assert_eq!(help.kind, structopt::clap::ErrorKind::HelpDisplayed);
+        }
+
+        let help = Args::from_iter_safe(["ddshow", "--help"]).unwrap_err();
+        assert_eq!(help.kind, structopt::clap::ErrorKind::HelpDisplayed);
+    }
+
+    #[test]
+    fn granularities() {
+        use crate::args::granularity_from_str;
+        use std::time::Duration;
+
+        assert_eq!(granularity_from_str("5s"), Ok(Duration::from_secs(5)));
+        assert_eq!(granularity_from_str("1us"), Ok(Duration::from_micros(1)));
+
+        assert!(granularity_from_str("0s").is_err());
+        assert!(granularity_from_str("999ns").is_err());
+        assert!(granularity_from_str("1000years").is_err());
+    }
+
+    #[test]
+    fn byte_sizes() {
+        use crate::args::byte_size_from_str;
+
+        assert_eq!(byte_size_from_str("512"), Ok(512));
+        assert_eq!(byte_size_from_str("10b"), Ok(10));
+        assert_eq!(byte_size_from_str("512MB"), Ok(512 * 1000 * 1000));
+        assert_eq!(byte_size_from_str(" 2 GiB "), Ok(2 * 1024 * 1024 * 1024));
+        assert_eq!(byte_size_from_str("16kib"), Ok(16 * 1024));
+
+        assert!(byte_size_from_str("0MB").is_err());
+        assert!(byte_size_from_str("MB").is_err());
+        assert!(byte_size_from_str("1.5GB").is_err());
+        assert!(byte_size_from_str("10TB").is_err());
+        assert!(byte_size_from_str("99999999999999GiB").is_err());
+    }
+}
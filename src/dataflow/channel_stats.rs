@@ -0,0 +1,409 @@
+use crate::dataflow::{
+    constants::{LATENCY_PRECISION_BITS, MESSAGE_PAIRING_HORIZON},
+    operators::DelayExt,
+    utils::{granulate, round_to_precision, sorted_percentile, ArrangedVal, Diff, TimelyLogBundle},
+    Channel,
+};
+use abomonation_derive::Abomonation;
+use ddshow_types::{
+    timely_logging::{OperatesEvent, TimelyEvent},
+    ChannelId, WorkerId,
+};
+use differential_dataflow::{
+    operators::{arrange::ArrangeByKey, Join, JoinCore, Reduce},
+    AsCollection, Collection, Hashable,
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, time::Duration};
+use timely::dataflow::{
+    channels::pact::Exchange,
+    operators::{Enter, Map, Operator},
+    Scope, Stream,
+};
+
+/// A graph edge along with the messages sent over its channel
+pub type EdgeWithStats = (
+    WorkerId,
+    OperatesEvent,
+    Channel,
+    OperatesEvent,
+    Option<ChannelMessageStats>,
+);
+
+/// The target worker and number of records of each message sent over
+/// a channel, keyed by the sending worker and the channel
+type ChannelMessages<S> = Collection<S, ((WorkerId, ChannelId), (WorkerId, usize)), Diff>;
+
+/// Identifies a single message by its `(channel, source worker, target worker, seq_no)`,
+/// sequence numbers are only unique between each pair of workers
+type MessageKey = (ChannelId, WorkerId, WorkerId, usize);
+
+/// The number of messages and records sent over a single channel on a single worker
+#[derive(
+    Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Abomonation, Deserialize, Serialize,
+)]
+pub struct ChannelMessageStats {
+    pub channel: ChannelId,
+    pub worker: WorkerId,
+    /// The most records sent within a single message
+    pub max: usize,
+    /// The fewest records sent within a single message
+    pub min: usize,
+    /// The total number of records sent
+    pub total: usize,
+    /// The average number of records sent within each message
+    pub average: usize,
+    /// The number of messages sent
+    pub invocations: usize,
+}
+
+pub fn channel_message_stats<S>(
+    scope: &mut S,
+    channel_messages: &ChannelMessages<S>,
+) -> ArrangedVal<S, (WorkerId, ChannelId), ChannelMessageStats>
+where
+    S: Scope<Timestamp = Duration>,
+{
+    let stats = scope.region_named("Channel Message Stats", |region| {
+        channel_messages
+            .enter_region(region)
+            .map(|(channel, (_target, length))| (channel, length))
+            .reduce_named(
+                "Reduce: Channel Message Stats",
+                |&(worker, channel), lengths, output| {
+                    // Inputs are sorted by length
+                    let min = lengths.first().map_or(0, |&(&length, _)| length);
+                    let max = lengths.last().map_or(0, |&(&length, _)| length);
+
+                    let (total, invocations) = lengths.iter().fold(
+                        (0, 0),
+                        |(total, invocations), &(&length, messages)| {
+                            let messages = messages as usize;
+                            (total + length * messages, invocations + messages)
+                        },
+                    );
+
+                    let stats = ChannelMessageStats {
+                        channel,
+                        worker,
+                        max,
+                        min,
+                        total,
+                        average: total.checked_div(invocations).unwrap_or(0),
+                        invocations,
+                    };
+
+                    output.push((stats, 1));
+                },
+            )
+            .leave_region()
+    });
+
+    stats.arrange_by_key_named("ArrangeByKey: Channel Message Stats")
+}
+
+/// Attaches the message stats of each channel to its graph edge, edges
+/// for channels that never sent any messages get `None`
+pub fn attach_message_stats<S>(
+    scope: &mut S,
+    edges: &Collection<S, (WorkerId, OperatesEvent, Channel, OperatesEvent), Diff>,
+    message_stats: &ArrangedVal<S, (WorkerId, ChannelId), ChannelMessageStats>,
+) -> Collection<S, EdgeWithStats, Diff>
+where
+    S: Scope<Timestamp = Duration>,
+{
+    scope.region_named("Attach Channel Message Stats", |region| {
+        let (edges, message_stats) = (
+            edges.enter_region(region),
+            message_stats.enter_region(region),
+        );
+
+        let edges_by_channel = edges.map(|edge| ((edge.0, edge.2.channel_id()), edge));
+
+        let with_stats = edges_by_channel.join_core(
+            &message_stats,
+            |_, (worker, source, channel, target), stats| {
+                Some((
+                    *worker,
+                    source.clone(),
+                    channel.clone(),
+                    target.clone(),
+                    Some(stats.clone()),
+                ))
+            },
+        );
+
+        let without_stats = edges_by_channel
+            .antijoin(&message_stats.as_collection(|&key, _| key))
+            .map(|(_, (worker, source, channel, target))| (worker, source, channel, target, None));
+
+        with_stats.concat(&without_stats).leave_region()
+    })
+}
+
+/// The records sent from one worker to another over a single channel
+#[derive(
+    Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Abomonation, Deserialize, Serialize,
+)]
+pub struct WorkerExchange {
+    pub channel: ChannelId,
+    pub source: WorkerId,
+    pub target: WorkerId,
+    /// The number of records sent
+    pub records: usize,
+    /// The number of messages sent
+    pub messages: usize,
+}
+
+pub fn worker_exchanges<S>(
+    scope: &mut S,
+    channel_messages: &ChannelMessages<S>,
+) -> Collection<S, WorkerExchange, Diff>
+where
+    S: Scope<Timestamp = Duration>,
+{
+    scope.region_named("Worker Exchanges", |region| {
+        channel_messages
+            .enter_region(region)
+            .map(|((source, channel), (target, length))| ((channel, source, target), length))
+            .reduce_named(
+                "Reduce: Worker Exchanges",
+                |&(channel, source, target), lengths, output| {
+                    let (records, messages) =
+                        lengths
+                            .iter()
+                            .fold((0, 0), |(records, messages), &(&length, count)| {
+                                let count = count as usize;
+                                (records + length * count, messages + count)
+                            });
+
+                    let exchange = WorkerExchange {
+                        channel,
+                        source,
+                        target,
+                        records,
+                        messages,
+                    };
+
+                    output.push((exchange, 1));
+                },
+            )
+            .map(|(_, exchange)| exchange)
+            .leave_region()
+    })
+}
+
+/// The time messages spent between being sent and being received over
+/// a single channel on the receiving worker
+#[derive(
+    Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Abomonation, Deserialize, Serialize,
+)]
+pub struct ChannelLatency {
+    pub channel: ChannelId,
+    /// The worker that received the messages
+    pub worker: WorkerId,
+    /// The number of messages that were both sent and received
+    pub deliveries: usize,
+    pub min: Duration,
+    pub max: Duration,
+    pub mean: Duration,
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+}
+
+/// Pairs up the send and receive events of each message to get the
+/// delivery latency of every channel, messages that were never
+/// received (or whose send was never logged) are ignored and their
+/// events are dropped once the input frontier is [`MESSAGE_PAIRING_HORIZON`]
+/// past them
+pub fn channel_latencies<S>(
+    scope: &mut S,
+    timely_stream: &Stream<S, TimelyLogBundle>,
+    granularity: Duration,
+) -> Collection<S, ChannelLatency, Diff>
+where
+    S: Scope<Timestamp = Duration>,
+{
+    scope.region_named("Channel Latencies", |region| {
+        let mut pending = HashMap::new();
+        let mut latencies = HashMap::new();
+
+        timely_stream
+            .enter(region)
+            .flat_map(|(time, _, event)| {
+                if let TimelyEvent::Messages(message) = event {
+                    let key = (
+                        message.channel,
+                        message.source,
+                        message.target,
+                        message.seq_no,
+                    );
+
+                    Some((key, message.is_send, time))
+                } else {
+                    None
+                }
+            })
+            // Pairing is done eagerly instead of waiting for each timestamp to complete
+            // since which side of a message arrives first doesn't matter, only the
+            // latencies seen within each batch are emitted so that we don't hold onto
+            // a record for every single message
+            .unary_frontier(
+                Exchange::new(|(key, _, _): &(MessageKey, bool, Duration)| key.hashed()),
+                "Pair Message Events",
+                |_capability, _info| {
+                    let (mut buffer, mut pruned_until) = (Vec::new(), Duration::ZERO);
+
+                    move |input, output| {
+                        input.for_each(|capability, data| {
+                            data.swap(&mut buffer);
+
+                            for (key, is_send, time) in buffer.drain(..) {
+                                if let Some((worker, channel, latency)) =
+                                    pair_message_event(&mut pending, key, is_send, time)
+                                {
+                                    *latencies.entry((worker, channel, latency)).or_insert(0) += 1;
+                                }
+                            }
+
+                            let time = *capability.time();
+                            output
+                                .session(&capability)
+                                .give_iterator(latencies.drain().map(
+                                    |((worker, channel, latency), count)| {
+                                        (((worker, channel), latency), time, count)
+                                    },
+                                ));
+                        });
+
+                        // Events too far behind the frontier will never be paired, pruning
+                        // is only done once per granularity to avoid walking every pending
+                        // event each time the frontier advances
+                        let cutoff = input
+                            .frontier()
+                            .frontier()
+                            .first()
+                            .map(|frontier| frontier.saturating_sub(MESSAGE_PAIRING_HORIZON));
+
+                        match cutoff {
+                            Some(cutoff) if cutoff >= pruned_until + granularity => {
+                                let pruned = prune_pending_messages(&mut pending, cutoff);
+                                pruned_until = cutoff;
+
+                                if pruned != 0 {
+                                    tracing::debug!(
+                                        pruned = pruned,
+                                        pending = pending.len(),
+                                        "dropped {} unmatched message events from before {:?}",
+                                        pruned,
+                                        cutoff,
+                                    );
+                                }
+                            }
+
+                            Some(_) => {}
+
+                            // Nothing else is coming, so nothing that's left can be paired
+                            None => pending.clear(),
+                        }
+                    }
+                },
+            )
+            .as_collection()
+            .delay_fast(granulate(granularity))
+            .reduce_named(
+                "Reduce: Channel Latencies",
+                |&(worker, channel), latencies, output| {
+                    // Inputs are sorted by latency
+                    let deliveries = latencies
+                        .iter()
+                        .map(|&(_, count)| count as usize)
+                        .sum::<usize>();
+                    let total = latencies
+                        .iter()
+                        .map(|&(&latency, count)| latency * count as u32)
+                        .sum::<Duration>();
+
+                    let latency = ChannelLatency {
+                        channel,
+                        worker,
+                        deliveries,
+                        min: latencies.first().map_or(Duration::ZERO, |&(&min, _)| min),
+                        max: latencies.last().map_or(Duration::ZERO, |&(&max, _)| max),
+                        mean: total.checked_div(deliveries as u32).unwrap_or_default(),
+                        p50: percentile(latencies, deliveries, 50),
+                        p90: percentile(latencies, deliveries, 90),
+                        p99: percentile(latencies, deliveries, 99),
+                    };
+
+                    output.push((latency, 1));
+                },
+            )
+            .map(|(_, latency)| latency)
+            .leave_region()
+    })
+}
+
+/// Holds onto whichever side of a message arrives first and returns the
+/// receiving worker, channel and (rounded) latency once the other side shows up
+pub(crate) fn pair_message_event(
+    pending: &mut HashMap<MessageKey, (bool, Duration)>,
+    key: MessageKey,
+    is_send: bool,
+    time: Duration,
+) -> Option<(WorkerId, ChannelId, Duration)> {
+    match pending.get(&key) {
+        Some(&(pending_send, pending_time)) if pending_send != is_send => {
+            pending.remove(&key);
+
+            let (sent, received) = if is_send {
+                (time, pending_time)
+            } else {
+                (pending_time, time)
+            };
+
+            // Workers in different processes have different clocks, so
+            // receives can appear to happen before their sends
+            let latency = received.checked_sub(sent).unwrap_or_default();
+
+            let (channel, _, target, _) = key;
+            Some((target, channel, round_latency(latency)))
+        }
+
+        // Duplicate events keep the first one seen
+        Some(_) => None,
+
+        None => {
+            pending.insert(key, (is_send, time));
+            None
+        }
+    }
+}
+
+/// Drops the pending message events from before `cutoff`,
+/// returning the number of events that were dropped
+pub(crate) fn prune_pending_messages(
+    pending: &mut HashMap<MessageKey, (bool, Duration)>,
+    cutoff: Duration,
+) -> usize {
+    let before = pending.len();
+    pending.retain(|_, &mut (_, time)| time >= cutoff);
+
+    before - pending.len()
+}
+
+/// Rounds a latency down to its [`LATENCY_PRECISION_BITS`] most significant
+/// bits so that similar latencies can be counted together
+fn round_latency(latency: Duration) -> Duration {
+    let nanos = latency.as_nanos().min(u64::MAX as u128) as u64;
+
+    Duration::from_nanos(round_to_precision(nanos, LATENCY_PRECISION_BITS))
+}
+
+/// Gets the `percent`th percentile of the given sorted `(latency, count)` pairs
+fn percentile(values: &[(&Duration, Diff)], total: usize, percent: u64) -> Duration {
+    let values = values.iter().map(|&(&value, count)| (value, count as u64));
+
+    sorted_percentile(values, total as u64, percent).unwrap_or_default()
+}
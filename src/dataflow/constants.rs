@@ -1,30 +1,45 @@
 //! Various constants and defaults for ddshow
 
-use std::{num::NonZeroUsize, time::Duration};
-
-/// Only cause the program stats to update every N milliseconds to
-/// prevent this from absolutely thrashing the scheduler
-// TODO: Make this configurable by the user
-pub const PROGRAM_NS_GRANULARITY: u128 = 5_000_000_000;
+use std::time::Duration;
 
 /// The default capacity to initialize extractor maps to
 pub(crate) const DEFAULT_EXTRACTOR_CAPACITY: usize = 1024;
 
-/// The margin to decide whether or not to fuse adjacent events by
-pub(crate) const EVENT_NS_MARGIN: u64 = 500_000;
-
 /// The read timeout to impose on tcp connections
 pub(crate) const TCP_READ_TIMEOUT: Option<Duration> = Some(Duration::from_millis(200));
 
-/// The fuel used to extract data from the dataflow within the
-/// main thread's spin loop
-// Safety: 1,000,000 isn't zero
-pub(crate) const IDLE_EXTRACTION_FUEL: NonZeroUsize =
-    unsafe { NonZeroUsize::new_unchecked(1_000_000) };
-
 /// The delay to reactivate replay operators after
 pub(crate) const DEFAULT_REACTIVATION_DELAY: Duration = Duration::from_millis(200);
 
+/// The width of the steps that the window used to detect hung scopes slides by
+pub(crate) const HANG_DETECTION_STEP_NS: u64 = 1_000_000_000;
+
+/// The number of steps within the window used to detect hung scopes, a scope
+/// that's stalled for the whole window is reported as a possible hang
+pub(crate) const HANG_DETECTION_WINDOW_STEPS: u64 = 5;
+
+/// How far behind the input frontier the unmatched send or receive of a message can fall
+/// before it's dropped, messages that take longer than this to be delivered are ignored
+pub(crate) const MESSAGE_PAIRING_HORIZON: Duration = Duration::from_secs(60);
+
+/// The number of significant bits channel delivery latencies are kept with,
+/// seven bits keeps them within 1/64th of their true value
+pub(crate) const LATENCY_PRECISION_BITS: u32 = 7;
+
+/// The number of significant bits that activation durations and batch sizes are
+/// bucketed by within a [`Histogram`](crate::ui::Histogram). Histograms are kept for
+/// every operator and written into the report, so they use fewer buckets than channel
+/// latencies, at most 32 for every power of two
+pub(crate) const HISTOGRAM_PRECISION_BITS: u32 = 5;
+
+/// How many times slower than the average of the other workers a
+/// worker has to be for an operator to be considered a straggler
+pub(crate) const STRAGGLER_RATIO: f64 = 2.0;
+
+/// The least amount of time the slowest worker has to spend within
+/// an operator for it to be considered a straggler
+pub(crate) const STRAGGLER_MIN_DURATION: Duration = Duration::from_millis(1);
+
 /// The current version of DDShow
 pub const DDSHOW_VERSION: &str = concat!(
     env!("CARGO_PKG_NAME"),
@@ -1,13 +1,19 @@
-use crate::dataflow::{
-    operators::{Max, Min},
-    utils::{Diff, DifferentialLogBundle},
-    FilterMap,
+use crate::{
+    dataflow::{
+        operators::{Max, Min},
+        utils::{Diff, DifferentialLogBundle},
+        FilterMap,
+    },
+    ui::Histogram,
 };
 use abomonation_derive::Abomonation;
 use ddshow_types::{differential_logging::DifferentialEvent, OperatorId, WorkerId};
 #[cfg(not(feature = "timely-next"))]
 use differential_dataflow::difference::DiffPair;
-use differential_dataflow::{operators::CountTotal, AsCollection, Collection};
+use differential_dataflow::{
+    operators::{CountTotal, Join, Reduce},
+    AsCollection, Collection,
+};
 use std::time::Duration;
 use timely::dataflow::{operators::Enter, Scope, Stream};
 
@@ -55,6 +61,7 @@ where
                     max_size: max.value as usize,
                     min_size: min.value as usize,
                     batches: batches as usize,
+                    batch_histogram: Histogram::new(),
                 };
 
                 (key, stats)
@@ -98,21 +105,62 @@ where
                         max_size: max.value as usize,
                         min_size: min.value as usize,
                         batches: batches as usize,
+                        batch_histogram: Histogram::new(),
                     };
 
                     (key, stats)
                 },
             );
 
-        merge_stats.leave_region()
+        let batch_histograms = differential_trace
+            .filter_map(|(time, worker, event)| match event {
+                DifferentialEvent::Batch(batch) => Some((
+                    (
+                        (worker, batch.operator),
+                        Histogram::bucket(batch.length as u64),
+                    ),
+                    time,
+                    1,
+                )),
+
+                DifferentialEvent::Merge(_)
+                | DifferentialEvent::MergeShortfall(_)
+                | DifferentialEvent::Drop(_)
+                | DifferentialEvent::TraceShare(_) => None,
+            })
+            .as_collection()
+            .reduce_named("Reduce: Batch Histograms", |_operator, buckets, output| {
+                let histogram = Histogram::from_sorted_buckets(
+                    buckets
+                        .iter()
+                        .map(|&(&bucket, count)| (bucket, count as u64)),
+                );
+
+                output.push((histogram, 1));
+            });
+
+        // Operators that never created a batch keep an empty histogram
+        merge_stats
+            .antijoin(&batch_histograms.map(|(operator, _)| operator))
+            .concat(
+                &merge_stats.join_map(&batch_histograms, |&operator, stats, histogram| {
+                    let mut stats = stats.clone();
+                    stats.batch_histogram = histogram.clone();
+
+                    (operator, stats)
+                }),
+            )
+            .leave_region()
     })
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Abomonation)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Abomonation)]
 pub struct ArrangementStats {
     pub max_size: usize,
     pub min_size: usize,
     pub batches: usize,
+    /// The distribution of the number of records within each batch
+    pub batch_histogram: Histogram,
     // TODO: Max/min/average batch size
     // TODO: Arrangement growth trend (Linear, logarithmic, quadratic, etc.)?
 }
@@ -0,0 +1,488 @@
+//! Reconstructs the frontiers of each operator's ports from the
+//! pointstamp updates within timely's progress logs
+//!
+//! Each worker logs the pointstamp changes it broadcasts to its peers, so
+//! accumulating the `internal` updates of every sent progress message gives
+//! the number of capabilities held for each timestamp at each operator output
+//! and accumulating their `messages` updates gives the number of messages
+//! waiting on each timestamp at each operator input. A timestamp is considered
+//! complete at a port once nothing is held for it anymore, and the time it took
+//! is measured from the start of the operator activation that first held it
+//!
+//! Timestamps are only logged as their `Debug` representations, so they're ordered
+//! by the numbers within them, which keeps integers, tuples and `Product`s in order
+//!
+//! Every change to an output's frontier is produced as its own [`FrontierStep`]
+//! and the steps are only assembled into [`FrontierTimeline`]s once they've been
+//! extracted, so new capability updates never rebuild a whole timeline
+
+use crate::dataflow::{
+    operators::{DelayExt, ExtractionSink, Fuel},
+    reachability::PointstampLocation,
+    utils::{granulate, Diff, ProgressLogBundle, TimelyLogBundle},
+};
+use abomonation_derive::Abomonation;
+use anyhow::Result;
+use ddshow_types::{
+    timely_logging::{ScheduleEvent, StartStop, TimelyEvent},
+    OperatorAddr, OperatorId, PortId, WorkerId,
+};
+use differential_dataflow::{AsCollection, Collection};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    mem,
+    time::Duration,
+};
+use timely::dataflow::{
+    channels::pact::Exchange,
+    operators::{Capability, Concat, Enter, Filter, Map, Operator},
+    Scope, Stream,
+};
+
+/// The frontier of a single operator port over the course of the program
+#[derive(
+    Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Abomonation, Deserialize, Serialize,
+)]
+pub struct FrontierTimeline {
+    pub worker: WorkerId,
+    pub operator_addr: OperatorAddr,
+    pub port: PortId,
+    /// Whether the port is an output holding capabilities or an input receiving messages
+    pub location: PointstampLocation,
+    /// The timestamps that were held after each change, in the form
+    /// of `(time, timestamps)` and sorted by time
+    pub steps: Vec<(Duration, Vec<String>)>,
+}
+
+/// A single change to the timestamps held at an operator port
+#[derive(
+    Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Abomonation, Deserialize, Serialize,
+)]
+pub struct FrontierStep {
+    pub worker: WorkerId,
+    pub operator_addr: OperatorAddr,
+    pub port: PortId,
+    pub location: PointstampLocation,
+    pub time: Duration,
+    /// The timestamps that were held after the change, in timestamp order
+    pub timestamps: Vec<String>,
+}
+
+/// The time it took for a single timestamp to be completed at an operator port
+#[derive(
+    Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Abomonation, Deserialize, Serialize,
+)]
+pub struct EpochCompletion {
+    pub worker: WorkerId,
+    pub operator_addr: OperatorAddr,
+    pub port: PortId,
+    pub location: PointstampLocation,
+    /// The timestamp, printed via its `Debug` implementation
+    pub timestamp: String,
+    /// The start of the operator activation that first held a capability for
+    /// the timestamp, or the time the capability was first held if the operator
+    /// hadn't been scheduled yet
+    pub first_activation: Duration,
+    /// The time the last capability for the timestamp was dropped
+    pub completed: Duration,
+}
+
+impl EpochCompletion {
+    /// The time between the timestamp's first activation and its completion
+    pub fn lag(&self) -> Duration {
+        self.completed - self.first_activation
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Abomonation)]
+enum FrontierEvent {
+    /// An operator was created
+    Operates(OperatorId, OperatorAddr),
+    /// An operator started an activation
+    Scheduled(OperatorId),
+    /// The capabilities or messages held for a timestamp at an operator port changed
+    Pointstamps(OperatorAddr, PortId, PointstampLocation, String, i64),
+}
+
+impl FrontierEvent {
+    /// Operators are scheduled before the pointstamp updates they cause are logged,
+    /// so pointstamp updates are folded after any other events from the same time
+    const fn is_pointstamp_update(&self) -> bool {
+        matches!(self, Self::Pointstamps(..))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Abomonation)]
+enum FrontierUpdate {
+    /// The set of held timestamps changed
+    Changed(FrontierStep),
+    /// A timestamp was completed
+    Completed(EpochCompletion),
+}
+
+type FrontierBundle = (Duration, WorkerId, FrontierEvent);
+
+/// The pointstamps held for each timestamp along with the start of
+/// the activation that first held them
+type HeldTimestamps = BTreeMap<TimestampKey, (i64, Duration)>;
+
+/// Orders timestamps by the numbers within their `Debug` representations, with
+/// the text between them breaking ties. Numbers are compared by value so that
+/// `9` comes before `10` and `(1, 9)` comes before `(1, 10)`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct TimestampKey {
+    components: Vec<KeyComponent>,
+    timestamp: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum KeyComponent {
+    Number(i128),
+    Text(String),
+}
+
+impl TimestampKey {
+    fn new(timestamp: String) -> Self {
+        let mut components = Vec::new();
+        let mut chars = timestamp.char_indices().peekable();
+
+        while let Some((start, char)) = chars.next() {
+            let is_number = char.is_ascii_digit()
+                || (char == '-'
+                    && matches!(chars.peek(), Some((_, next)) if next.is_ascii_digit()));
+
+            let mut end = start + char.len_utf8();
+            while let Some(&(idx, next)) = chars.peek() {
+                if next.is_ascii_digit() != is_number || (!is_number && next == '-') {
+                    break;
+                }
+
+                end = idx + next.len_utf8();
+                chars.next();
+            }
+
+            let text = &timestamp[start..end];
+            let component = match text.parse() {
+                Ok(number) if is_number => KeyComponent::Number(number),
+                _ => KeyComponent::Text(text.to_owned()),
+            };
+            components.push(component);
+        }
+
+        Self {
+            components,
+            timestamp,
+        }
+    }
+}
+
+/// The operators and port frontiers of a single worker
+#[derive(Debug, Default)]
+struct WorkerFrontiers {
+    operators: HashMap<OperatorAddr, OperatorId>,
+    /// The start of each operator's latest activation
+    last_scheduled: HashMap<OperatorId, Duration>,
+    ports: HashMap<(OperatorAddr, PortId, PointstampLocation), HeldTimestamps>,
+}
+
+pub fn frontier_timelines<S>(
+    scope: &mut S,
+    timely_stream: &Stream<S, TimelyLogBundle>,
+    progress_stream: &Stream<S, ProgressLogBundle>,
+    granularity: Duration,
+) -> (
+    Collection<S, FrontierStep, Diff>,
+    Collection<S, EpochCompletion, Diff>,
+)
+where
+    S: Scope<Timestamp = Duration>,
+{
+    let (steps, completions) = scope.region_named("Frontier Timelines", |region| {
+        let activations = timely_stream
+            .enter(region)
+            .flat_map(|(time, worker, event)| {
+                let event = match event {
+                    TimelyEvent::Operates(operates) => {
+                        FrontierEvent::Operates(operates.id, operates.addr)
+                    }
+                    TimelyEvent::Schedule(ScheduleEvent {
+                        id,
+                        start_stop: StartStop::Start,
+                    }) => FrontierEvent::Scheduled(id),
+                    _ => return None,
+                };
+
+                Some((time, worker, event))
+            });
+
+        let updates = progress_stream
+            .enter(region)
+            // Received messages duplicate the ones that were sent
+            .filter(|(_, _, event)| event.is_send)
+            .flat_map(|(time, _, event)| {
+                let (worker, scope_addr) = (event.worker, event.addr);
+
+                // Capabilities are held at operator outputs and messages are held at
+                // the operator inputs they're sent to
+                let capabilities = event.internal.into_iter().map(|update| {
+                    let location = PointstampLocation::Source;
+                    (
+                        update.node,
+                        update.port,
+                        location,
+                        update.timestamp,
+                        update.diff,
+                    )
+                });
+                let messages = event.messages.into_iter().map(|update| {
+                    let location = PointstampLocation::Target;
+                    (
+                        update.node,
+                        update.port,
+                        location,
+                        update.timestamp,
+                        update.diff,
+                    )
+                });
+
+                capabilities
+                    .chain(messages)
+                    .map(move |(node, port, location, timestamp, diff)| {
+                        // Node zero of a scope's progress updates is the scope itself
+                        let mut operator_addr = scope_addr.clone();
+                        if !node.is_zero() {
+                            operator_addr.push(node);
+                        }
+
+                        let event = FrontierEvent::Pointstamps(
+                            operator_addr,
+                            port,
+                            location,
+                            timestamp,
+                            diff,
+                        );
+                        (time, worker, event)
+                    })
+            })
+            .concat(&activations)
+            .unary_frontier(
+                Exchange::new(|(_, worker, _): &FrontierBundle| worker.into_inner() as u64),
+                "Fold Frontier Updates",
+                |_capability, _info| {
+                    let mut buffer = Vec::new();
+                    // The events of every timestamp that's still being received
+                    let mut pending: Vec<(Capability<Duration>, Vec<FrontierBundle>)> = Vec::new();
+                    let mut workers: HashMap<WorkerId, WorkerFrontiers> = HashMap::new();
+                    let mut updates = Vec::new();
+
+                    move |input, output| {
+                        input.for_each(|capability, data| {
+                            data.swap(&mut buffer);
+
+                            match pending
+                                .iter_mut()
+                                .find(|(pending, _)| pending.time() == capability.time())
+                            {
+                                Some((_, events)) => events.append(&mut buffer),
+                                None => pending.push((capability.retain(), mem::take(&mut buffer))),
+                            }
+                        });
+
+                        // Activations and capability updates come from separate log streams,
+                        // so events are only folded once the frontier has passed them and
+                        // can be processed in the order they happened in
+                        pending.sort_unstable_by(|(left, _), (right, _)| {
+                            left.time().cmp(right.time())
+                        });
+                        let ready = pending
+                            .iter()
+                            .take_while(|(capability, _)| {
+                                !input.frontier().less_equal(capability.time())
+                            })
+                            .count();
+
+                        for (capability, mut events) in pending.drain(..ready) {
+                            events.sort_by_key(|(time, _, event)| {
+                                (*time, event.is_pointstamp_update())
+                            });
+
+                            for (time, worker, event) in events {
+                                workers.entry(worker).or_default().fold(
+                                    worker,
+                                    time,
+                                    event,
+                                    &mut updates,
+                                );
+                            }
+
+                            output.session(&capability).give_vec(&mut updates);
+                        }
+                    }
+                },
+            );
+
+        let steps = updates
+            .flat_map(|update| {
+                if let FrontierUpdate::Changed(step) = update {
+                    let time = step.time;
+                    Some((step, time, 1))
+                } else {
+                    None
+                }
+            })
+            .as_collection()
+            .delay_fast(granulate(granularity));
+
+        let completions = updates
+            .flat_map(|update| {
+                if let FrontierUpdate::Completed(completion) = update {
+                    let time = completion.completed;
+                    Some((completion, time, 1))
+                } else {
+                    None
+                }
+            })
+            .as_collection()
+            .delay_fast(granulate(granularity));
+
+        (steps.leave_region(), completions.leave_region())
+    });
+
+    (steps, completions)
+}
+
+impl WorkerFrontiers {
+    fn fold(
+        &mut self,
+        worker: WorkerId,
+        time: Duration,
+        event: FrontierEvent,
+        updates: &mut Vec<FrontierUpdate>,
+    ) {
+        let (operator_addr, port, location, timestamp, diff) = match event {
+            FrontierEvent::Operates(id, addr) => {
+                self.operators.insert(addr, id);
+                return;
+            }
+
+            FrontierEvent::Scheduled(id) => {
+                self.last_scheduled.insert(id, time);
+                return;
+            }
+
+            FrontierEvent::Pointstamps(operator_addr, port, location, timestamp, diff) => (
+                operator_addr,
+                port,
+                location,
+                TimestampKey::new(timestamp),
+                diff,
+            ),
+        };
+
+        // Pointstamp updates are caused by the operator's latest activation,
+        // if it's been scheduled at all
+        let activation = self
+            .operators
+            .get(&operator_addr)
+            .and_then(|id| self.last_scheduled.get(id).copied())
+            .filter(|&start| start <= time)
+            .unwrap_or(time);
+
+        let held = self
+            .ports
+            .entry((operator_addr.clone(), port, location))
+            .or_default();
+        let (count, first_activation) = held.entry(timestamp.clone()).or_insert((0, activation));
+        let was_held = *count > 0;
+        *count += diff;
+        let is_held = *count > 0;
+
+        if !was_held && is_held {
+            *first_activation = activation;
+        }
+        let first_activation = *first_activation;
+
+        if *count == 0 {
+            held.remove(&timestamp);
+        }
+
+        if was_held != is_held {
+            let timestamps = held
+                .iter()
+                .filter(|(_, &(count, _))| count > 0)
+                .map(|(key, _)| key.timestamp.clone())
+                .collect();
+
+            updates.push(FrontierUpdate::Changed(FrontierStep {
+                worker,
+                operator_addr: operator_addr.clone(),
+                port,
+                location,
+                time,
+                timestamps,
+            }));
+        }
+
+        if was_held && !is_held {
+            updates.push(FrontierUpdate::Completed(EpochCompletion {
+                worker,
+                operator_addr,
+                port,
+                location,
+                timestamp: timestamp.timestamp,
+                first_activation,
+                completed: time,
+            }));
+        }
+    }
+}
+
+/// Assembles extracted [`FrontierStep`]s into the timelines of each operator port
+#[derive(Debug)]
+pub struct FrontierTimelineSink {
+    steps: HashMap<FrontierStep, Diff>,
+}
+
+impl ExtractionSink<FrontierStep, Diff> for FrontierTimelineSink {
+    type Output = Vec<FrontierTimeline>;
+
+    fn new(memory_limit: Option<u64>) -> Self {
+        Self {
+            steps: ExtractionSink::new(memory_limit),
+        }
+    }
+
+    fn insert(&mut self, step: FrontierStep, diff: Diff) -> Result<()> {
+        ExtractionSink::insert(&mut self.steps, step, diff)
+    }
+
+    fn maintain(&mut self, fuel: &mut Fuel) {
+        self.steps.maintain(fuel);
+    }
+
+    fn into_output(self) -> Result<Self::Output> {
+        let mut timelines = HashMap::new();
+        for step in self.steps.into_output()? {
+            timelines
+                .entry((step.worker, step.operator_addr, step.port, step.location))
+                .or_insert_with(Vec::new)
+                .push((step.time, step.timestamps));
+        }
+
+        Ok(timelines
+            .into_iter()
+            .map(|((worker, operator_addr, port, location), mut steps)| {
+                steps.sort_unstable();
+
+                FrontierTimeline {
+                    worker,
+                    operator_addr,
+                    port,
+                    location,
+                    steps,
+                }
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,471 @@
+//! Detection for scopes that appear to be stuck in an infinite loop
+//!
+//! Activations and frontier changes (observed via [`PushProgress`](TimelyEvent::PushProgress)
+//! events) are bucketed into steps of [`HANG_DETECTION_STEP_NS`], and each worker has a
+//! window of [`HANG_DETECTION_WINDOW_STEPS`] steps that slides along with its most recent
+//! events. A scope is considered stalled from its first activation after its last frontier
+//! change, and if it's been stalled since before the window started and operators within
+//! it were scheduled within the window it's reported as a possible hang or livelock, since
+//! it's doing work without its frontier being able to advance. Operators don't have to be
+//! scheduled within every step, so loops that wait between iterations are caught as well
+//!
+//! Scopes can also hang by waiting on something that never happens, which
+//! leaves their worker parked instead of spinning. If a worker's final event
+//! is an indefinite park then any nested scopes that were active right before
+//! it and have yet to shut down are reported as well. Note that this can't
+//! distinguish a deadlock from a program that's idly waiting for more input
+
+use crate::{
+    dataflow::{
+        constants::{HANG_DETECTION_STEP_NS, HANG_DETECTION_WINDOW_STEPS},
+        operators::{DelayExt, FilterMap, JoinArranged},
+        utils::{granulate, ActivationTimes, ArrangedKey, ArrangedVal, Diff, TimelyLogBundle},
+    },
+    ui::Lifespan,
+};
+use abomonation_derive::Abomonation;
+use ddshow_types::{
+    timely_logging::{ParkEvent, TimelyEvent},
+    OperatorAddr, OperatorId, WorkerId,
+};
+#[cfg(not(feature = "timely-next"))]
+use differential_dataflow::difference::DiffPair;
+use differential_dataflow::{
+    operators::{arrange::ArrangeByKey, CountTotal, Join, JoinCore, Reduce, ThresholdTotal},
+    AsCollection, Collection,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::{self, Display},
+    iter,
+    time::Duration,
+};
+use timely::dataflow::{operators::Enter, Scope, Stream};
+
+/// The maximum number of operators to report for each stalled scope
+const HOTTEST_OPERATORS: usize = 5;
+
+/// The reason a scope is suspected of hanging
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Abomonation, Deserialize, Serialize,
+)]
+pub enum HangKind {
+    /// The scope's operators kept running without its frontier advancing
+    Livelock,
+    /// The scope's worker parked indefinitely while the scope was still alive
+    Parked,
+}
+
+impl Display for HangKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Livelock => f.write_str("livelock"),
+            Self::Parked => f.write_str("parked"),
+        }
+    }
+}
+
+/// A scope that's suspected to be hung or livelocked
+#[derive(
+    Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Abomonation, Deserialize, Serialize,
+)]
+pub struct HangSuspect {
+    pub worker: WorkerId,
+    pub scope: OperatorId,
+    pub scope_addr: OperatorAddr,
+    pub scope_name: String,
+    pub kind: HangKind,
+    /// The time at which the scope stopped receiving frontier updates
+    pub stalled_since: Duration,
+    /// The amount of time the scope has been stalled for, always zero
+    /// for parked scopes since their worker never reports back
+    pub stalled_for: Duration,
+    /// The last time the scope received a frontier update, if it ever did
+    pub last_progress: Option<Duration>,
+    /// The operators within the scope that spent the most time
+    /// running while the scope was stalled
+    pub hottest_operators: Vec<HotOperator>,
+}
+
+impl Display for HangSuspect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            HangKind::Livelock => write!(
+                f,
+                "scope `{}` {} on worker {} has been running for {:#?} without its frontier advancing",
+                self.scope_name, self.scope_addr, self.worker, self.stalled_for,
+            )?,
+            HangKind::Parked => write!(
+                f,
+                "worker {} parked indefinitely at {:#?} while scope `{}` {} was still running",
+                self.worker, self.stalled_since, self.scope_name, self.scope_addr,
+            )?,
+        }
+
+        if !self.hottest_operators.is_empty() {
+            f.write_str(", hottest operators: ")?;
+
+            for (idx, operator) in self.hottest_operators.iter().enumerate() {
+                if idx != 0 {
+                    f.write_str(", ")?;
+                }
+
+                write!(
+                    f,
+                    "`{}` {} ({:#?} over {} activations)",
+                    operator.name, operator.addr, operator.total_runtime, operator.activations,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(
+    Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Abomonation, Deserialize, Serialize,
+)]
+pub struct HotOperator {
+    pub id: OperatorId,
+    pub addr: OperatorAddr,
+    pub name: String,
+    pub activations: usize,
+    pub total_runtime: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Abomonation)]
+enum ScopeSample {
+    /// The scope's own id and name
+    Scope { id: OperatorId, name: String },
+    /// The latest step seen on the scope's worker
+    Latest { step: u64 },
+    /// The scope's worker ended on an indefinite park at the given time
+    Parked { time: Duration },
+    /// The scope has shut down
+    Shutdown,
+    /// The scope received frontier updates within the step
+    Progress { step: u64 },
+    /// An operator within the scope was scheduled within the step
+    Activity {
+        step: u64,
+        id: OperatorId,
+        addr: OperatorAddr,
+        name: String,
+        activations: usize,
+        runtime_ns: u64,
+    },
+}
+
+#[cfg(not(feature = "timely-next"))]
+type ActivityDiff = DiffPair<isize, isize>;
+
+#[cfg(feature = "timely-next")]
+type ActivityDiff = (isize, isize);
+
+#[cfg(not(feature = "timely-next"))]
+fn activity_diff(duration: Duration) -> ActivityDiff {
+    DiffPair::new(1, duration.as_nanos() as isize)
+}
+
+#[cfg(feature = "timely-next")]
+fn activity_diff(duration: Duration) -> ActivityDiff {
+    (1, duration.as_nanos() as isize)
+}
+
+#[cfg(not(feature = "timely-next"))]
+fn activity_parts(diff: ActivityDiff) -> (usize, u64) {
+    (diff.element1 as usize, diff.element2 as u64)
+}
+
+#[cfg(feature = "timely-next")]
+fn activity_parts((activations, runtime): ActivityDiff) -> (usize, u64) {
+    (activations as usize, runtime as u64)
+}
+
+fn step(time: Duration) -> u64 {
+    time.as_nanos() as u64 / HANG_DETECTION_STEP_NS
+}
+
+fn step_start(step: u64) -> Duration {
+    Duration::from_nanos(step * HANG_DETECTION_STEP_NS)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn detect_hangs<S>(
+    scope: &mut S,
+    timely_stream: &Stream<S, TimelyLogBundle>,
+    operator_activations: &ActivationTimes<S>,
+    operator_lifespans: &Collection<S, ((WorkerId, OperatorId), Lifespan), Diff>,
+    operator_ids: &ArrangedVal<S, (WorkerId, OperatorId), OperatorAddr>,
+    operator_names: &ArrangedVal<S, (WorkerId, OperatorId), String>,
+    subgraph_ids: &ArrangedKey<S, (WorkerId, OperatorId)>,
+    granularity: Duration,
+) -> Collection<S, HangSuspect, Diff>
+where
+    S: Scope<Timestamp = Duration>,
+{
+    scope.region_named("Detect Hangs", |region| {
+        let (operator_activations, operator_lifespans, operator_ids, operator_names, subgraph_ids) = (
+            operator_activations.enter_region(region),
+            operator_lifespans.enter_region(region),
+            operator_ids.enter_region(region),
+            operator_names.enter_region(region),
+            subgraph_ids.enter_region(region),
+        );
+
+        let operator_info = operator_ids
+            .join_core(&operator_names, |&key, addr, name| {
+                iter::once((key, (addr.clone(), name.clone())))
+            })
+            .arrange_by_key_named("ArrangeByKey: Operator Info");
+
+        // Only nested scopes are considered since dataflows themselves
+        // never receive frontier updates
+        let scope_info = operator_info
+            .semijoin_arranged(&subgraph_ids)
+            .filter(|(_, (addr, _))| addr.len() > 1)
+            .arrange_by_key_named("ArrangeByKey: Nested Scope Info");
+
+        // The total activations and runtime of each operator within each step
+        let activity = operator_activations
+            .explode(|((worker, operator), (start, duration))| {
+                iter::once((((worker, operator), step(start)), activity_diff(duration)))
+            })
+            .count_total()
+            .map(|((key, step), diff)| (key, (step, diff)))
+            .join_core(
+                &operator_info,
+                |&(worker, id), &(step, diff), (addr, name)| {
+                    let (activations, runtime_ns) = activity_parts(diff);
+
+                    // Attribute the activity to every nested scope containing the operator
+                    (2..addr.len())
+                        .map(|len| {
+                            (
+                                (worker, OperatorAddr::from(&addr[..len])),
+                                ScopeSample::Activity {
+                                    step,
+                                    id,
+                                    addr: addr.clone(),
+                                    name: name.clone(),
+                                    activations,
+                                    runtime_ns,
+                                },
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                },
+            );
+
+        // The steps in which each scope received frontier changes
+        let progress = timely_stream
+            .enter(region)
+            .filter_map(|(time, worker, event)| {
+                if let TimelyEvent::PushProgress(progress) = event {
+                    Some((((worker, progress.op_id), step(time)), time, 1))
+                } else {
+                    None
+                }
+            })
+            .as_collection()
+            .delay_fast(granulate(granularity))
+            .distinct_total()
+            .join_core(&scope_info, |&(worker, _), &step, (addr, _)| {
+                iter::once(((worker, addr.clone()), ScopeSample::Progress { step }))
+            });
+
+        let scopes = scope_info.as_collection(|&(worker, id), (addr, name)| {
+            (
+                (worker, addr.clone()),
+                ScopeSample::Scope {
+                    id,
+                    name: name.clone(),
+                },
+            )
+        });
+
+        // The latest step observed on each worker
+        let latest_steps = operator_activations
+            .map(|((worker, _), (start, _))| (worker, step(start)))
+            .distinct_total()
+            .reduce_named("Reduce: Latest Steps", |_worker, steps, output| {
+                // Inputs are sorted, so the last step is the latest one
+                if let Some(&(&step, _)) = steps.last() {
+                    output.push((step, 1));
+                }
+            });
+
+        let latest = scopes
+            .map(|((worker, addr), _)| (worker, addr))
+            .join_map(&latest_steps, |&worker, addr, &step| {
+                ((worker, addr.clone()), ScopeSample::Latest { step })
+            });
+
+        // Workers whose final park or unpark event was an indefinite park
+        let parked_workers = timely_stream
+            .enter(region)
+            .filter_map(|(time, worker, event)| {
+                if let TimelyEvent::Park(park) = event {
+                    Some(((worker, (time, park)), time, 1))
+                } else {
+                    None
+                }
+            })
+            .as_collection()
+            .delay_fast(granulate(granularity))
+            .reduce_named("Reduce: Parked Workers", |_worker, parks, output| {
+                // Inputs are sorted, so the last event is the latest one
+                if let Some(&(&(time, ParkEvent::Park(None)), _)) = parks.last() {
+                    output.push((time, 1));
+                }
+            });
+
+        let parked = scopes
+            .map(|((worker, addr), _)| (worker, addr))
+            .join_map(&parked_workers, |&worker, addr, &time| {
+                ((worker, addr.clone()), ScopeSample::Parked { time })
+            });
+
+        let shutdown = operator_lifespans
+            .map(|(key, _)| (key, ()))
+            .distinct_total()
+            .join_core(&scope_info, |&(worker, _), &(), (addr, _)| {
+                iter::once(((worker, addr.clone()), ScopeSample::Shutdown))
+            });
+
+        scopes
+            .concat(&latest)
+            .concat(&parked)
+            .concat(&shutdown)
+            .concat(&progress)
+            .concat(&activity)
+            .reduce_named(
+                "Reduce: Find Stalled Scopes",
+                |&(worker, ref scope_addr), samples, output| {
+                    if let Some(suspect) = stalled_scope(worker, scope_addr, samples) {
+                        output.push((suspect, 1));
+                    }
+                },
+            )
+            .map(|(_, suspect)| suspect)
+            .leave_region()
+    })
+}
+
+fn stalled_scope(
+    worker: WorkerId,
+    scope_addr: &OperatorAddr,
+    samples: &[(&ScopeSample, Diff)],
+) -> Option<HangSuspect> {
+    let (mut scope, mut latest, mut parked, mut shutdown) = (None, None, None, false);
+    let (mut progress, mut activity) = (BTreeSet::new(), BTreeMap::new());
+
+    for &(sample, diff) in samples {
+        if diff < 1 {
+            continue;
+        }
+
+        match sample {
+            ScopeSample::Scope { id, name } => scope = Some((*id, name)),
+            ScopeSample::Latest { step } => latest = Some(*step),
+            ScopeSample::Parked { time } => parked = Some(*time),
+            ScopeSample::Shutdown => shutdown = true,
+            ScopeSample::Progress { step } => {
+                progress.insert(*step);
+            }
+            ScopeSample::Activity { step, .. } => {
+                activity.entry(*step).or_insert_with(Vec::new).push(sample);
+            }
+        }
+    }
+
+    let ((scope, scope_name), latest) = (scope?, latest?);
+    if shutdown {
+        return None;
+    }
+
+    // The scope's been stalled since its first activation after its last frontier change
+    let last_progress = progress.iter().next_back().copied();
+    let stalled_since = activity
+        .range(last_progress.map_or(0, |step| step + 1)..)
+        .next()
+        .map(|(&step, _)| step);
+
+    // The window ends with the latest step on the scope's worker
+    let window_start = (latest + 1).checked_sub(HANG_DETECTION_WINDOW_STEPS);
+
+    let (kind, stalled_since) = match (stalled_since, window_start) {
+        (Some(stalled_since), Some(window_start))
+            if stalled_since <= window_start
+                && activity.range(window_start..=latest).next().is_some() =>
+        {
+            (HangKind::Livelock, stalled_since)
+        }
+
+        // Parked scopes only need to have been active within the worker's latest step
+        _ if parked.is_some() && activity.contains_key(&latest) => (HangKind::Parked, latest),
+
+        _ => return None,
+    };
+
+    // Sum up the activity of each operator within the stalled steps
+    let mut operators: HashMap<OperatorId, HotOperator> = HashMap::new();
+    for sample in activity
+        .range(stalled_since..)
+        .flat_map(|(_, samples)| samples)
+    {
+        if let ScopeSample::Activity {
+            id,
+            addr,
+            name,
+            activations,
+            runtime_ns,
+            ..
+        } = sample
+        {
+            let operator = operators.entry(*id).or_insert_with(|| HotOperator {
+                id: *id,
+                addr: addr.clone(),
+                name: name.clone(),
+                activations: 0,
+                total_runtime: Duration::from_secs(0),
+            });
+
+            operator.activations += activations;
+            operator.total_runtime += Duration::from_nanos(*runtime_ns);
+        }
+    }
+
+    // Scopes contain the runtime of their children so only leaf operators are reported
+    let mut hottest_operators: Vec<_> = operators
+        .values()
+        .filter(|operator| {
+            !operators.values().any(|other| {
+                other.addr.len() > operator.addr.len() && other.addr.starts_with(&operator.addr)
+            })
+        })
+        .cloned()
+        .collect();
+    hottest_operators.sort_unstable_by_key(|operator| Reverse(operator.total_runtime));
+    hottest_operators.truncate(HOTTEST_OPERATORS);
+
+    Some(HangSuspect {
+        worker,
+        scope,
+        scope_addr: scope_addr.clone(),
+        scope_name: scope_name.clone(),
+        kind,
+        stalled_since: match kind {
+            HangKind::Livelock => step_start(stalled_since),
+            HangKind::Parked => parked.unwrap_or_else(|| step_start(stalled_since)),
+        },
+        stalled_for: match kind {
+            HangKind::Livelock => step_start(latest + 1 - stalled_since),
+            HangKind::Parked => Duration::from_secs(0),
+        },
+        last_progress: last_progress.map(step_start),
+        hottest_operators,
+    })
+}
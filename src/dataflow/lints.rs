@@ -0,0 +1,30 @@
+use crate::dataflow::utils::{ArrangedVal, Diff};
+use ddshow_types::{lints::CapabilityViolation, OperatorAddr, OperatorId, WorkerId};
+use differential_dataflow::{operators::JoinCore, Collection};
+use std::{iter, time::Duration};
+use timely::dataflow::Scope;
+
+/// Attaches operator ids to all capability violations reported by the
+/// `CheckCapabilities` lint within `ddshow-sink`
+pub fn capability_violations<S>(
+    scope: &mut S,
+    violations: &Collection<S, (WorkerId, CapabilityViolation), Diff>,
+    operator_addrs: &ArrangedVal<S, (WorkerId, OperatorAddr), OperatorId>,
+) -> Collection<S, ((WorkerId, OperatorId), CapabilityViolation), Diff>
+where
+    S: Scope<Timestamp = Duration>,
+{
+    scope.region_named("Capability Violations", |region| {
+        let (violations, operator_addrs) = (
+            violations.enter_region(region),
+            operator_addrs.enter_region(region),
+        );
+
+        violations
+            .map(|(worker, violation)| ((worker, violation.operator_addr.clone()), violation))
+            .join_core(&operator_addrs, |&(worker, _), violation, &operator| {
+                iter::once(((worker, operator), violation.clone()))
+            })
+            .leave_region()
+    })
+}
@@ -1,25 +1,36 @@
-// mod channel_stats;
+mod channel_stats;
 pub(crate) mod constants;
 mod differential;
+mod frontiers;
+mod hang_detection;
+mod lints;
 mod operator_stats;
 pub mod operators;
 mod program_stats;
 mod progress_stats;
-#[cfg(feature = "timely-next")]
 mod reachability;
 mod send_recv;
+mod spill;
+mod stragglers;
 mod subgraphs;
 mod summation;
 mod tests;
+mod time_series;
 mod timely_source;
+mod utilization;
 pub(crate) mod utils;
 mod worker;
 mod worker_timeline;
 
-pub use constants::PROGRAM_NS_GRANULARITY;
+pub use channel_stats::{ChannelLatency, EdgeWithStats, WorkerExchange};
+pub use frontiers::{EpochCompletion, FrontierStep, FrontierTimeline};
+pub use hang_detection::{HangKind, HangSuspect};
 pub use operator_stats::OperatorStats;
 pub use progress_stats::{Channel, ProgressInfo};
+pub use reachability::PointstampTimeline;
 pub use send_recv::{DataflowData, DataflowExtractor, DataflowReceivers, DataflowSenders};
+pub use spill::SpillBuffer;
+pub use stragglers::OperatorStraggler;
 pub use worker::worker_runtime;
 pub use worker_timeline::{EventKind, TimelineEvent};
 
@@ -31,14 +42,17 @@ use crate::{
         send_recv::ChannelAddrs,
         subgraphs::rewire_channels,
         utils::{
-            ArrangedKey, ArrangedVal, Diff, DifferentialLogBundle, ProgressLogBundle, Time,
-            TimelyLogBundle,
+            ArrangedKey, ArrangedVal, Diff, DifferentialLogBundle, ProgressLogBundle,
+            ReachabilityLogBundle, Time, TimelyLogBundle,
         },
     },
-    ui::{DataflowStats, Lifespan, ProgramStats, WorkerStats},
+    ui::{DataflowStats, Lifespan, OperatorTimeBucket, ProgramStats, WorkerStats},
 };
 use anyhow::Result;
-use ddshow_types::{timely_logging::OperatesEvent, ChannelId, OperatorAddr, OperatorId, WorkerId};
+use ddshow_types::{
+    lints::CapabilityViolation, timely_logging::OperatesEvent, ChannelId, OperatorAddr, OperatorId,
+    WorkerId,
+};
 use differential_dataflow::{
     difference::{Present, Semigroup},
     lattice::Lattice,
@@ -65,11 +79,8 @@ use timely::{
 //    but you can take an incoming CapabilityRef and turn
 //    it in to a Capability for any output, even those that
 //    the input should not be connected to via the summary.
-//  - Packing `(data, time, diff)` updates in DD where time
-//    is not greater or equal to the message capability.
 // TODO: Timely progress logging
 // TODO: The PDG
-// TODO: Timely reachability logging
 
 pub fn dataflow<S>(
     scope: &mut S,
@@ -77,6 +88,7 @@ pub fn dataflow<S>(
     timely_stream: &Stream<S, TimelyLogBundle>,
     differential_stream: Option<&Stream<S, DifferentialLogBundle>>,
     progress_stream: Option<&Stream<S, ProgressLogBundle>>,
+    reachability_stream: Option<&Stream<S, ReachabilityLogBundle>>,
     senders: DataflowSenders,
 ) -> Result<ProbeHandle<Time>>
 where
@@ -99,21 +111,63 @@ where
         operator_addrs_by_self,
         channel_scopes,
         dataflow_ids,
+        capability_violations,
+        channel_messages,
         timeline_events,
-    ) = timely_source::extract_timely_info(scope, timely_stream, args.disable_timeline);
+    ) = timely_source::extract_timely_info(
+        scope,
+        timely_stream,
+        args.disable_timeline,
+        args.granularity,
+        args.extraction_fuel,
+    );
+
+    let capability_violations =
+        lints::capability_violations(scope, &capability_violations, &operator_addrs);
+
+    let channel_progress = progress_stream.map(|progress_stream| {
+        progress_stats::aggregate_channel_messages(progress_stream, args.granularity)
+    });
 
-    let channel_progress = progress_stream
-        .map(|progress_stream| progress_stats::aggregate_channel_messages(progress_stream));
+    let (frontier_timelines, epoch_completions) = match progress_stream {
+        Some(progress_stream) => {
+            let (timelines, completions) = frontiers::frontier_timelines(
+                scope,
+                timely_stream,
+                progress_stream,
+                args.granularity,
+            );
+
+            (Some(timelines), Some(completions))
+        }
+        None => (None, None),
+    };
+
+    let pointstamp_timelines = reachability_stream.map(|reachability_stream| {
+        reachability::pointstamp_timelines(scope, reachability_stream, args.granularity)
+    });
 
     // FIXME: `invocations` looks off, figure that out
     let operator_stats =
         operator_stats::operator_stats(scope, &operator_activations, differential_stream);
 
-    // FIXME: This is pretty much a guess since there's no way to actually associate
-    //        operators/arrangements/channels across workers
-    // TODO: This should use a specialized struct to hold relevant things like "total size across workers"
-    //       in addition to per-worker stats
-    let aggregated_operator_stats = operator_stats::aggregate_operator_stats(&operator_stats);
+    // Operator ids aren't consistent across workers, so operators are instead
+    // associated with each other by their address and name
+    let aggregated_operator_stats = operator_stats::aggregate_operator_stats(
+        scope,
+        &operator_stats,
+        &operator_ids,
+        &operator_names,
+    );
+    let stragglers = stragglers::operator_stragglers(scope, &aggregated_operator_stats);
+
+    let operator_time_series = time_series::operator_time_series(
+        scope,
+        &operator_activations,
+        differential_stream,
+        &operator_ids,
+        args.bucket_width,
+    );
 
     // TODO: Turn these into collections of `(WorkerId, OperatorId)` and arrange them
     let (leaves, subgraphs) = sift_leaves_and_scopes(scope, &operator_addrs_by_self);
@@ -128,14 +182,39 @@ where
         })
         .arrange_by_self_named("ArrangeBySelf: Dataflow Graph Subgraph Ids");
 
+    let hang_suspects = hang_detection::detect_hangs(
+        scope,
+        timely_stream,
+        &operator_activations,
+        &operator_lifespans,
+        &operator_ids,
+        &operator_names,
+        &subgraph_ids,
+        args.granularity,
+    );
+
     let channels = rewire_channels(scope, &raw_channels, &subgraphs_arranged);
     let edges = attach_operators(scope, &raw_operators, &channels, &leaves_arranged);
 
+    let channel_message_stats = channel_stats::channel_message_stats(scope, &channel_messages);
+    let edges = channel_stats::attach_message_stats(scope, &edges, &channel_message_stats);
+    let worker_exchanges = channel_stats::worker_exchanges(scope, &channel_messages);
+    let channel_latencies =
+        channel_stats::channel_latencies(scope, timely_stream, args.granularity);
+
     // TODO: Make `extract_timely_info()` get the relevant event information
     // TODO: Grabbing events absolutely shits the bed when it comes to large dataflows,
     //       it needs a serious, intrinsic rework and/or disk backed arrangements
     let timeline_events = timeline_events.as_ref().map(|timeline_events| {
-        worker_timeline::worker_timeline(scope, timeline_events, differential_stream)
+        worker_timeline::worker_timeline(
+            scope,
+            timeline_events,
+            differential_stream,
+            args.granularity,
+            args.timeline_fusion_margin,
+            args.timeline_limit,
+            args.timeline_merge_gap,
+        )
     });
 
     let addressed_operators = raw_operators
@@ -148,6 +227,8 @@ where
         &channels,
         &subgraphs_arranged,
         &operator_addrs_by_self,
+        args.bucket_width,
+        args.granularity,
     );
 
     let dataflow_stats = dataflow_stats(
@@ -162,19 +243,30 @@ where
         scope,
         senders,
         &mut probe,
-        program_stats,
-        worker_stats,
-        leaves_arranged,
-        edges,
-        subgraphs_arranged,
-        operator_stats,
-        addressed_operators,
-        aggregated_operator_stats,
-        dataflow_stats,
-        timeline_events,
-        operator_names,
-        operator_ids,
-        channel_progress,
+        ExtractedData {
+            program_stats,
+            worker_stats,
+            nodes: leaves_arranged,
+            edges,
+            subgraphs: subgraphs_arranged,
+            operator_stats,
+            addressed_operators,
+            aggregated_operator_stats,
+            dataflow_stats,
+            timeline_events,
+            operator_names,
+            operator_ids,
+            channel_progress,
+            capability_violations,
+            hang_suspects,
+            pointstamp_timelines,
+            frontier_timelines,
+            epoch_completions,
+            worker_exchanges,
+            channel_latencies,
+            stragglers,
+            operator_time_series,
+        },
     );
 
     // TODO: Save ddflow logs
@@ -186,9 +278,16 @@ where
     // If saving logs is enabled, write all log messages to the `save_logs` directory
     if let Some(save_logs) = args.save_logs.as_ref() {
         tracing::info!(
-            "installing timely{} log sinks",
+            differential = differential_stream.is_some(),
+            reachability = reachability_stream.is_some(),
+            "installing timely{}{} log sinks",
             if differential_stream.is_some() {
-                " and differential"
+                ", differential"
+            } else {
+                ""
+            },
+            if reachability_stream.is_some() {
+                ", reachability"
             } else {
                 ""
             },
@@ -196,37 +295,82 @@ where
 
         utils::logging_event_sink(
             save_logs,
+            args.save_logs_framing(),
             scope,
             timely_stream,
             &mut probe,
             differential_stream,
+            reachability_stream,
         )?;
     }
 
     Ok(probe)
 }
 
-#[allow(clippy::clippy::too_many_arguments, clippy::type_complexity)]
-fn install_data_extraction<S>(
-    scope: &mut S,
-    senders: DataflowSenders,
-    probe: &mut ProbeHandle<Duration>,
+/// The collections produced by the analysis dataflow that get sent back
+/// to the main thread
+#[allow(clippy::type_complexity)]
+struct ExtractedData<S>
+where
+    S: Scope<Timestamp = Duration>,
+{
     program_stats: Collection<S, ProgramStats, Diff>,
     worker_stats: Collection<S, (WorkerId, WorkerStats), Diff>,
     nodes: ArrangedKey<S, (WorkerId, OperatorAddr), Diff>,
-    edges: Collection<S, (WorkerId, OperatesEvent, Channel, OperatesEvent), Diff>,
+    edges: Collection<S, EdgeWithStats, Diff>,
     subgraphs: ArrangedKey<S, (WorkerId, OperatorAddr), Diff>,
     operator_stats: Collection<S, ((WorkerId, OperatorId), OperatorStats), Diff>,
     addressed_operators: ArrangedVal<S, (WorkerId, OperatorAddr), OperatesEvent, Diff>,
-    aggregated_operator_stats: Collection<S, (OperatorId, AggregatedOperatorStats), Diff>,
+    aggregated_operator_stats: Collection<S, AggregatedOperatorStats, Diff>,
     dataflow_stats: Collection<S, DataflowStats, Diff>,
     timeline_events: Option<Collection<S, TimelineEvent, Present>>,
     operator_names: ArrangedVal<S, (WorkerId, OperatorId), String, Diff>,
     operator_ids: ArrangedVal<S, (WorkerId, OperatorId), OperatorAddr, Diff>,
     channel_progress: Option<Collection<S, (OperatorAddr, ProgressInfo), Diff>>,
+    capability_violations: Collection<S, ((WorkerId, OperatorId), CapabilityViolation), Diff>,
+    hang_suspects: Collection<S, HangSuspect, Diff>,
+    pointstamp_timelines: Option<Collection<S, PointstampTimeline, Diff>>,
+    frontier_timelines: Option<Collection<S, FrontierStep, Diff>>,
+    epoch_completions: Option<Collection<S, EpochCompletion, Diff>>,
+    worker_exchanges: Collection<S, WorkerExchange, Diff>,
+    channel_latencies: Collection<S, ChannelLatency, Diff>,
+    stragglers: Collection<S, OperatorStraggler, Diff>,
+    operator_time_series: Collection<S, (OperatorAddr, Vec<OperatorTimeBucket>), Diff>,
+}
+
+fn install_data_extraction<S>(
+    scope: &mut S,
+    senders: DataflowSenders,
+    probe: &mut ProbeHandle<Duration>,
+    data: ExtractedData<S>,
 ) where
     S: Scope<Timestamp = Duration>,
 {
+    let ExtractedData {
+        program_stats,
+        worker_stats,
+        nodes,
+        edges,
+        subgraphs,
+        operator_stats,
+        addressed_operators,
+        aggregated_operator_stats,
+        dataflow_stats,
+        timeline_events,
+        operator_names,
+        operator_ids,
+        channel_progress,
+        capability_violations,
+        hang_suspects,
+        pointstamp_timelines,
+        frontier_timelines,
+        epoch_completions,
+        worker_exchanges,
+        channel_latencies,
+        stragglers,
+        operator_time_series,
+    } = data;
+
     scope.region_named("Data Extraction", |region| {
         let program_stats = program_stats.enter_region(region);
         let worker_stats = worker_stats.enter_region(region);
@@ -241,6 +385,17 @@ fn install_data_extraction<S>(
         let operator_names = operator_names.enter_region(region);
         let operator_ids = operator_ids.enter_region(region);
         let channel_progress = channel_progress.map(|channels| channels.enter_region(region));
+        let capability_violations = capability_violations.enter_region(region);
+        let hang_suspects = hang_suspects.enter_region(region);
+        let pointstamp_timelines =
+            pointstamp_timelines.map(|timelines| timelines.enter_region(region));
+        let frontier_timelines = frontier_timelines.map(|timelines| timelines.enter_region(region));
+        let epoch_completions =
+            epoch_completions.map(|completions| completions.enter_region(region));
+        let worker_exchanges = worker_exchanges.enter_region(region);
+        let channel_latencies = channel_latencies.enter_region(region);
+        let stragglers = stragglers.enter_region(region);
+        let operator_time_series = operator_time_series.enter_region(region);
 
         let worker_stats = worker_stats
             .map(|(worker, stats)| ((), (worker, stats)))
@@ -280,6 +435,24 @@ fn install_data_extraction<S>(
                 &channel_progress.unwrap_or_else(|| operator::empty(region).as_collection()),
                 true,
             ),
+            (&capability_violations, false),
+            (&hang_suspects, true),
+            (
+                &pointstamp_timelines.unwrap_or_else(|| operator::empty(region).as_collection()),
+                true,
+            ),
+            (
+                &frontier_timelines.unwrap_or_else(|| operator::empty(region).as_collection()),
+                true,
+            ),
+            (
+                &epoch_completions.unwrap_or_else(|| operator::empty(region).as_collection()),
+                true,
+            ),
+            (&worker_exchanges, true),
+            (&channel_latencies, true),
+            (&stragglers, true),
+            (&operator_time_series, true),
         );
     })
 }
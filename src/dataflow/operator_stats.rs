@@ -1,14 +1,15 @@
-use crate::dataflow::{
-    differential::{self, ArrangementStats},
-    operators::{DiffDuration, Max, Maybe, Min},
-    summation::{summation, Summation},
-    utils::{Diff, DifferentialLogBundle, Time},
-    OperatorId, WorkerId,
+use crate::{
+    dataflow::{
+        differential::{self, ArrangementStats},
+        summation::{summation, Summation},
+        utils::{ActivationTimes, ArrangedVal, Diff, DifferentialLogBundle, Time},
+        OperatorAddr, OperatorId, WorkerId,
+    },
+    ui::Histogram,
 };
 use abomonation_derive::Abomonation;
 use differential_dataflow::{
-    difference::DiffPair,
-    operators::{CountTotal, Join},
+    operators::{Join, JoinCore, Reduce},
     Collection,
 };
 use std::{iter, time::Duration};
@@ -25,27 +26,55 @@ pub struct OperatorStats {
     pub activations: usize,
     /// Operator activation times `(start, duration)`
     pub activation_durations: Vec<(Duration, Duration)>,
+    /// The distribution of activation durations in nanoseconds
+    pub activation_histogram: Histogram,
     pub arrangement_size: Option<ArrangementStats>,
     // pub messages_sent: usize,
     // pub messages_received: usize,
 }
 
+/// The stats of a single operator across every worker it exists on
+///
+/// Operators are matched across workers by their address and name since
+/// operator ids aren't guaranteed to line up between workers
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Abomonation)]
 pub struct AggregatedOperatorStats {
-    pub id: OperatorId,
+    pub addr: OperatorAddr,
+    pub name: String,
+    /// The longest activation on any worker
     pub max: Duration,
+    /// The shortest activation on any worker
     pub min: Duration,
+    /// The average activation time across all workers
     pub average: Duration,
+    /// The operator's summed runtime across all workers
     pub total: Duration,
+    /// The operator's summed activations across all workers
     pub activations: usize,
-    /// Operator activation times `(start, duration)`
-    pub activation_durations: Vec<(Duration, Duration)>,
+    /// The distribution of activation durations across all workers in nanoseconds
+    pub activation_histogram: Histogram,
     pub arrangement_size: Option<ArrangementStats>,
-    // pub messages_sent: usize,
-    // pub messages_received: usize,
+    /// The operator's stats on each worker, sorted by worker
+    pub per_worker: Vec<OperatorStats>,
 }
 
-type ActivationTimes<S> = Collection<S, ((WorkerId, OperatorId), (Duration, Duration)), Diff>;
+impl AggregatedOperatorStats {
+    /// The number of workers the operator was found on
+    pub fn workers(&self) -> usize {
+        self.per_worker.len()
+    }
+
+    /// The operator's id, if it's the same on every worker
+    pub fn id(&self) -> Option<OperatorId> {
+        let id = self.per_worker.first()?.id;
+
+        if self.per_worker.iter().all(|stats| stats.id == id) {
+            Some(id)
+        } else {
+            None
+        }
+    }
+}
 
 pub fn operator_stats<S>(
     scope: &mut S,
@@ -64,17 +93,34 @@ where
         let execution_statistics =
             summation(&activation_times.map(|(operator, (_start, duration))| (operator, duration)));
 
-        let operator_stats = execution_statistics.map(
-            |(
-                (worker, id),
-                Summation {
-                    max,
-                    min,
-                    total,
-                    average,
-                    count: activations,
+        let activation_histograms = activation_times
+            .map(|(operator, (_start, duration))| {
+                (operator, Histogram::bucket(duration.as_nanos() as u64))
+            })
+            .reduce_named(
+                "Reduce: Activation Histograms",
+                |_operator, buckets, output| {
+                    let histogram = Histogram::from_sorted_buckets(
+                        buckets
+                            .iter()
+                            .map(|&(&bucket, count)| (bucket, count as u64)),
+                    );
+
+                    output.push((histogram, 1));
                 },
-            )| {
+            );
+
+        let operator_stats = execution_statistics.join_map(
+            &activation_histograms,
+            |&(worker, id),
+             &Summation {
+                 max,
+                 min,
+                 total,
+                 average,
+                 count: activations,
+             },
+             activation_histogram| {
                 let stats = OperatorStats {
                     id,
                     worker,
@@ -84,6 +130,7 @@ where
                     total,
                     activations,
                     activation_durations: Vec::new(),
+                    activation_histogram: activation_histogram.clone(),
                     arrangement_size: None,
                 };
 
@@ -98,9 +145,9 @@ where
 
                 let modified_stats = operator_stats.join_map(
                     &arrangement_stats,
-                    |&operator, stats, &arrangement_stats| {
+                    |&operator, stats, arrangement_stats| {
                         let mut stats = stats.clone();
-                        stats.arrangement_size = Some(arrangement_stats);
+                        stats.arrangement_size = Some(arrangement_stats.clone());
 
                         (operator, stats)
                     },
@@ -116,145 +163,80 @@ where
 }
 
 pub(crate) fn aggregate_operator_stats<S>(
+    scope: &mut S,
     operator_stats: &Collection<S, ((WorkerId, OperatorId), OperatorStats), Diff>,
-) -> Collection<S, (OperatorId, AggregatedOperatorStats), Diff>
+    operator_ids: &ArrangedVal<S, (WorkerId, OperatorId), OperatorAddr>,
+    operator_names: &ArrangedVal<S, (WorkerId, OperatorId), String>,
+) -> Collection<S, AggregatedOperatorStats, Diff>
 where
     S: Scope<Timestamp = Time>,
 {
-    let operator_stats = operator_stats.map(|((_worker, operator), stats)| (operator, stats));
-
-    operator_stats
-        .explode(
-            |(
-                operator,
-                OperatorStats {
-                    max,
-                    min,
-                    average,
-                    total,
-                    activations,
-                    arrangement_size,
-                    ..
-                },
-            )| {
-                let diff = DiffPair::new(
-                    1,
-                    DiffPair::new(
-                        Max::new(DiffDuration::new(max)),
-                        DiffPair::new(
-                            Min::new(DiffDuration::new(min)),
-                            DiffPair::new(
-                                DiffDuration::new(average),
-                                DiffPair::new(
-                                    DiffDuration::new(total),
-                                    DiffPair::new(
-                                        activations as isize,
-                                        DiffPair::new(
-                                            Maybe::from(
-                                                arrangement_size
-                                                    .map(|arr| Max::new(arr.max_size as isize)),
-                                            ),
-                                            DiffPair::new(
-                                                Maybe::from(
-                                                    arrangement_size
-                                                        .map(|arr| Min::new(arr.min_size as isize)),
-                                                ),
-                                                Maybe::from(
-                                                    arrangement_size
-                                                        .map(|arr| arr.batches as isize),
-                                                ),
-                                            ),
-                                        ),
-                                    ),
-                                ),
-                            ),
-                        ),
-                    ),
-                );
+    scope.region_named("Aggregate Operator Stats", |region| {
+        let (operator_stats, operator_ids, operator_names) = (
+            operator_stats.enter_region(region),
+            operator_ids.enter_region(region),
+            operator_names.enter_region(region),
+        );
 
-                iter::once((operator, diff))
-            },
-        )
-        .count_total()
-        .map(
-            |(
-                operator,
-                DiffPair {
-                    element1: total_workers,
-                    element2:
-                        DiffPair {
-                            element1:
-                                Max {
-                                    value: max,
-                                },
-                            element2:
-                                DiffPair {
-                                    element1:
-                                        Min {
-                                            value: min,
-                                        },
-                                    element2:
-                                        DiffPair {
-                                            element1: summed_average,
-                                            element2:
-                                                DiffPair {
-                                                    element1: summed_total,
-                                                    element2: DiffPair {
-                                                        element1: activations,
-                                                        element2: DiffPair {
-                                                            element1: arrangement_max_size,
-                                                            element2:
-                                                                DiffPair {
-                                                                    element1: arrangement_min_size,
-                                                                    element2:
-                                                                        arrangement_total_batches,
-                                                                },
-                                                        },
-                                                    },
-                                                },
-                                        },
-                                },
-                        },
+        operator_stats
+            .join_core(&operator_ids, |&key, stats, addr| {
+                iter::once((key, (addr.clone(), stats.clone())))
+            })
+            .join_core(&operator_names, |_, (addr, stats), name| {
+                iter::once(((addr.clone(), name.clone()), stats.clone()))
+            })
+            .reduce_named(
+                "Reduce: Aggregate Operator Stats",
+                |(addr, name), workers, output| {
+                    let mut per_worker: Vec<OperatorStats> =
+                        workers.iter().map(|&(stats, _)| stats.clone()).collect();
+                    per_worker.sort_unstable_by_key(|stats| stats.worker);
+
+                    let total: Duration = per_worker.iter().map(|stats| stats.total).sum();
+                    let activations = per_worker.iter().map(|stats| stats.activations).sum();
+
+                    let mut activation_histogram = Histogram::new();
+                    for stats in per_worker.iter() {
+                        activation_histogram.merge(&stats.activation_histogram);
+                    }
+
+                    let arrangement_size = per_worker
+                        .iter()
+                        .filter_map(|stats| stats.arrangement_size.clone())
+                        .reduce(|mut acc, arrangement| {
+                            acc.max_size = acc.max_size.max(arrangement.max_size);
+                            acc.min_size = acc.min_size.min(arrangement.min_size);
+                            acc.batches += arrangement.batches;
+                            acc.batch_histogram.merge(&arrangement.batch_histogram);
+
+                            acc
+                        });
+
+                    let stats = AggregatedOperatorStats {
+                        addr: addr.clone(),
+                        name: name.clone(),
+                        max: per_worker
+                            .iter()
+                            .map(|stats| stats.max)
+                            .max()
+                            .unwrap_or_default(),
+                        min: per_worker
+                            .iter()
+                            .map(|stats| stats.min)
+                            .min()
+                            .unwrap_or_default(),
+                        average: total.checked_div(activations as u32).unwrap_or_default(),
+                        total,
+                        activations,
+                        activation_histogram,
+                        arrangement_size,
+                        per_worker,
+                    };
+
+                    output.push((stats, 1));
                 },
-            )| {
-                let average = summed_average
-                    .to_duration()
-                    .checked_div(total_workers as u32)
-                    .unwrap_or_else(|| Duration::from_secs(0));
-                let total = summed_total
-                    .to_duration()
-                    .checked_div(total_workers as u32)
-                    .unwrap_or_else(|| Duration::from_secs(0));
-
-                let arrangement_size = {
-                    let max_size = Option::from(arrangement_max_size);
-                    let min_size = Option::from(arrangement_min_size);
-                    let batches = Option::from(arrangement_total_batches);
-
-                    max_size
-                        .zip(min_size)
-                        .zip(batches)
-                        .map(|((Max { value: max_size }, Min { value: min_size }), batches): (_, isize)| {
-                            ArrangementStats {
-                                max_size: max_size as usize,
-                                min_size: min_size as usize,
-                                batches: batches as usize,
-                            }
-                        })
-                };
-
-                let stats = AggregatedOperatorStats {
-                    id: operator,
-                    max: max.to_duration(),
-                    min: min.to_duration(),
-                    average,
-                    total,
-                    activations: activations as usize,
-                    activation_durations: Vec::new(),
-                    arrangement_size,
-                };
-
-                (operator, stats)
-            },
-        )
+            )
+            .map(|(_, stats)| stats)
+            .leave_region()
+    })
 }
@@ -27,7 +27,7 @@ pub use filter_split::FilterSplit;
 pub use inspect::InspectExt;
 pub use iterate_ext::IterateExt;
 pub use map::{MapExt, MapInPlace};
-pub use min_max::{DiffDuration, Max, Maybe, Min};
+pub use min_max::{DiffDuration, Max, Min};
 pub use negate::NegateExt;
 pub use reduce::HierarchicalReduce;
 pub use replay_with_shutdown::{EventIterator, EventReader, ReplayWithShutdown};
@@ -35,4 +35,4 @@ pub use rkyv_capture::RkyvEventReader;
 pub use sort::SortBy;
 pub use split::Split;
 pub use timely_version_hack::Multiply;
-pub use util::{CrossbeamExtractor, CrossbeamPusher, Fuel, OperatorExt};
+pub use util::{CrossbeamExtractor, CrossbeamPusher, ExtractionSink, Fuel, OperatorExt};
@@ -2,21 +2,158 @@
 
 use crate::dataflow::operators::EventIterator;
 use bytecheck::CheckBytes;
+use ddshow_sink::framing::{
+    self, crc32c, FrameHeader, Framing, StreamHeader, FRAME_ALIGNMENT, STREAM_HEADER_LEN,
+};
 use ddshow_types::Event;
 use rkyv::{
     check_archived_root, de::deserializers::AllocDeserializer, validation::DefaultArchiveValidator,
-    AlignedVec, Archive, Deserialize,
+    AlignedVec, Archive, Archived, Deserialize,
 };
 use std::{
-    convert::TryInto,
-    fmt::Debug,
-    io::{self, Read},
+    error::Error,
+    fmt::{self, Debug, Display},
+    io::{self, ErrorKind, Read},
     marker::PhantomData,
     mem,
+    ops::Range,
 };
 use timely::dataflow::operators::capture::event::Event as TimelyEvent;
 
+/// Checks a frame's payload against the checksum in its header, if it has one
+pub(crate) fn check_checksum(header: FrameHeader, payload: &[u8]) -> Result<(), FrameErrorKind> {
+    if let Some(expected) = header.checksum {
+        let actual = crc32c(payload);
+        if actual != expected {
+            return Err(FrameErrorKind::ChecksumMismatch { expected, actual });
+        }
+    }
+
+    Ok(())
+}
+
+/// Validates the archive of a single event
+///
+/// Archives too short to hold their root are rejected before validation, since
+/// `check_archived_root()` underflows on them
+pub(crate) fn check_archive<T, D>(archive: &[u8]) -> Result<&Archived<Event<T, D>>, String>
+where
+    T: Archive,
+    T::Archived: CheckBytes<DefaultArchiveValidator>,
+    D: Archive,
+    D::Archived: CheckBytes<DefaultArchiveValidator>,
+{
+    let root_len = mem::size_of::<Archived<Event<T, D>>>();
+    if archive.len() < root_len {
+        return Err(format!(
+            "the archive is {} bytes long but its root takes up {} bytes",
+            archive.len(),
+            root_len,
+        ));
+    }
+
+    check_archived_root::<Event<T, D>>(archive).map_err(|err| err.to_string())
+}
+
+/// A frame that couldn't be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameError {
+    /// Where the frame came from, e.g. a file name
+    pub source: String,
+    /// The index of the frame within its stream
+    pub frame: u64,
+    /// The offset of the frame within its stream
+    pub offset: u64,
+    pub kind: FrameErrorKind,
+}
+
+impl Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let FrameErrorKind::UnsupportedStream(_) = self.kind {
+            write!(f, "{} in the stream header of {}", self.kind, self.source)
+        } else {
+            write!(
+                f,
+                "{} in frame {} at byte {} of {}",
+                self.kind, self.frame, self.offset, self.source,
+            )
+        }
+    }
+}
+
+impl Error for FrameError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FrameErrorKind {
+    /// The stream's header has a version or flags that aren't supported
+    UnsupportedStream(String),
+    /// The frame's checksum doesn't match its archive
+    ChecksumMismatch { expected: u32, actual: u32 },
+    /// The frame's archive failed validation
+    Invalid(String),
+    /// One of the archives within a batched frame failed validation
+    InvalidBatchEvent { event: usize, reason: String },
+    /// The frame's length runs past the end of a finished stream that has more bytes
+    /// left than the frame could've been cut off from
+    LengthPastEnd { len: u64, remaining: u64 },
+}
+
+impl Display for FrameErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedStream(reason) => f.write_str(reason),
+            Self::ChecksumMismatch { expected, actual } => write!(
+                f,
+                "checksum mismatch (expected {:#010x}, found {:#010x})",
+                expected, actual,
+            ),
+            Self::Invalid(reason) => write!(f, "invalid archive ({})", reason),
+            Self::InvalidBatchEvent { event, reason } => write!(
+                f,
+                "invalid archive for event {} of the batch ({})",
+                event, reason,
+            ),
+            Self::LengthPastEnd { len, remaining } => write!(
+                f,
+                "corrupted length of {} bytes, only {} bytes are left in the stream",
+                len, remaining,
+            ),
+        }
+    }
+}
+
+/// What an [`RkyvEventReader`] has read from its stream
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReadStats {
+    /// The number of complete frames that were read
+    pub frames: u64,
+    /// The number of bytes up to the end of the last complete frame, including
+    /// the stream's header and the headers and padding of each frame
+    pub bytes: u64,
+    /// The number of bytes read after the last complete frame that had to be discarded
+    pub discarded: u64,
+    /// The frame that stopped the stream from being read, if there was one
+    pub error: Option<FrameError>,
+}
+
+/// The events of a batched frame that's been checked but not fully read yet
+#[derive(Debug)]
+struct Batch {
+    /// Where each event's archive lies within the reader's buffer
+    entries: Vec<Range<usize>>,
+    /// The index of the next event to be read
+    next_event: usize,
+    /// The start of the frame within the reader's buffer
+    start: usize,
+    /// The length of the frame, including the padding before it
+    length: usize,
+}
+
 /// A Wrapper for `R: Read` implementing `EventIterator<T, D>`.
+///
+/// A stream that ends partway through a frame, like the log of a computation
+/// that crashed, or that contains a frame that fails its checksum or validation
+/// is finished early, keeping all of the events before that point
 #[derive(Debug)]
 pub struct RkyvEventReader<T, D, R> {
     reader: R,
@@ -24,7 +161,20 @@ pub struct RkyvEventReader<T, D, R> {
     buffer1: AlignedVec,
     buffer2: AlignedVec,
     consumed: usize,
+    /// The offset of the start of `buffer1` within the stream
+    buffer_offset: u64,
+    /// The stream's header, `None` until enough of the stream has been
+    /// read to tell whether it has one
+    stream_header: Option<Option<StreamHeader>>,
+    /// The batched frame whose events are currently being read
+    batch: Option<Batch>,
     peer_finished: bool,
+    finished: bool,
+    stats: ReadStats,
+    /// The length of the longest frame that's been read, including its padding and header
+    longest_frame: usize,
+    /// A description of where the events are coming from, used in diagnostics
+    source: Option<String>,
     __type: PhantomData<(T, D)>,
 }
 
@@ -37,10 +187,141 @@ impl<T, D, R> RkyvEventReader<T, D, R> {
             buffer1: AlignedVec::new(),
             buffer2: AlignedVec::new(),
             consumed: 0,
+            buffer_offset: 0,
+            stream_header: None,
+            batch: None,
             peer_finished: false,
+            finished: false,
+            stats: ReadStats::default(),
+            longest_frame: 0,
+            source: None,
             __type: PhantomData,
         }
     }
+
+    /// Sets the description of where the events are coming from, e.g. a file name
+    pub fn with_source<S>(mut self, source: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.source = Some(source.into());
+        self
+    }
+
+    #[cfg(test)]
+    pub const fn stats(&self) -> &ReadStats {
+        &self.stats
+    }
+
+    fn source(&self) -> &str {
+        self.source.as_deref().unwrap_or("a log stream")
+    }
+
+    fn frame_error(&self, offset: u64, kind: FrameErrorKind) -> FrameError {
+        FrameError {
+            source: self.source().to_owned(),
+            frame: self.stats.frames,
+            offset,
+            kind,
+        }
+    }
+
+    /// Stops reading from the stream at the frame starting at `consumed` within the buffer
+    fn fail(&mut self, consumed: usize, kind: FrameErrorKind) {
+        let error = self.frame_error(self.buffer_offset + consumed as u64, kind);
+        self.consumed = consumed;
+        self.finish(Some(error));
+    }
+
+    /// Records a frame that's been completely read
+    fn read_frame(&mut self, length: usize) {
+        self.consumed += length;
+        self.stats.frames += 1;
+        self.stats.bytes += length as u64;
+        self.longest_frame = self.longest_frame.max(length);
+    }
+
+    /// Checks whether the bytes left over in a finished stream start with a frame whose
+    /// length is corrupt. A frame that was cut off while being written leaves less than
+    /// a frame's worth of bytes behind it, so a frame that runs past the end of the stream
+    /// with more bytes left than the longest frame read so far is taken to be corrupt.
+    /// Until a frame has been read there's nothing to compare against, so a short first
+    /// frame is taken to be truncated
+    fn corrupted_length(&self, framing: Framing) -> Option<(usize, FrameErrorKind)> {
+        if self.longest_frame == 0 {
+            return None;
+        }
+
+        let consumed = self.consumed + framing.padding(self.consumed);
+        let header = FrameHeader::decode(self.buffer1.get(consumed..)?, framing)?;
+        let remaining = (self.buffer1.len() - consumed) as u64;
+
+        if remaining > self.longest_frame as u64 {
+            Some((
+                consumed,
+                FrameErrorKind::LengthPastEnd {
+                    len: header.len,
+                    remaining,
+                },
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Stops reading from the stream, reporting anything that had to be discarded
+    fn finish(&mut self, error: Option<FrameError>) {
+        self.finished = true;
+        self.stats.discarded = (self.buffer1.len() - self.consumed.min(self.buffer1.len())) as u64;
+
+        let source = self.source();
+        let ReadStats {
+            frames,
+            bytes,
+            discarded,
+            ..
+        } = self.stats;
+
+        if let Some(error) = error.as_ref() {
+            if let FrameErrorKind::UnsupportedStream(_) = error.kind {
+                tracing::error!(source = source, "couldn't read {}", error);
+            } else {
+                tracing::error!(
+                    source = source,
+                    frames = frames,
+                    bytes = bytes,
+                    discarded = discarded,
+                    "stopped reading after recovering {} frames ({} bytes), {}, \
+                    `ddshow verify --repair` can salvage the valid frames after it",
+                    frames,
+                    bytes,
+                    error,
+                );
+            }
+        } else if discarded != 0 {
+            tracing::warn!(
+                source = source,
+                frames = frames,
+                bytes = bytes,
+                discarded = discarded,
+                "{} ended partway through a frame, recovered {} frames ({} bytes) and \
+                discarded the last {} bytes",
+                source,
+                frames,
+                bytes,
+                discarded,
+            );
+        } else {
+            tracing::debug!(
+                source = source,
+                frames = frames,
+                bytes = bytes,
+                "finished reading log stream",
+            );
+        }
+
+        self.stats.error = error;
+    }
 }
 
 impl<T, D, R> EventIterator<T, D> for RkyvEventReader<T, D, R>
@@ -52,92 +333,213 @@ where
     D::Archived: Deserialize<D, AllocDeserializer> + CheckBytes<DefaultArchiveValidator>,
 {
     fn next(&mut self, is_finished: &mut bool) -> io::Result<Option<TimelyEvent<T, D>>> {
-        if self.peer_finished
-            && self.bytes.is_empty()
-            && self.buffer1.is_empty()
-            && self.buffer2.is_empty()
-        {
+        if self.finished {
             *is_finished = true;
             return Ok(None);
         }
 
-        // Align to read
-        let alignment_offset = match self.consumed & 15 {
-            0 => 0,
-            x => 16 - x,
-        };
-        let consumed = self.consumed + alignment_offset;
-
-        if let Some(header_slice) = self
-            .buffer1
-            .get(consumed..consumed + mem::size_of::<u128>())
+        // Streams either start with a header or with their first frame
+        if self.stream_header.is_none()
+            && (self.buffer1.len() >= STREAM_HEADER_LEN || self.peer_finished)
         {
-            let archive_length = u128::from_le_bytes(header_slice.try_into().unwrap()) as usize;
-
-            let archive_start = consumed + mem::size_of::<u128>();
-
-            if let Some(slice) = self
-                .buffer1
-                .get(archive_start..archive_start + archive_length)
-            {
-                // let archive = unsafe { archived_root::<Event<T, D>>(slice) };
-                // let event = archive
-                //     .deserialize(&mut AllocDeserializer)
-                //     .unwrap_or_else(|unreachable| match unreachable {});
-                //
-                // self.consumed += alignment_offset + archive_length + mem::size_of::<u128>();
-                // return Ok(Some(event.into()));
-
-                match check_archived_root::<Event<T, D>>(slice) {
-                    Ok(archive) => {
-                        let event = archive
-                            .deserialize(&mut AllocDeserializer)
-                            .unwrap_or_else(|unreachable| match unreachable {})
-                            .into();
-
-                        self.consumed += alignment_offset + archive_length + mem::size_of::<u128>();
+            let header = StreamHeader::decode(&self.buffer1);
+            self.stream_header = Some(header);
+
+            if let Some(header) = header {
+                if let Some(reason) = header.unsupported() {
+                    let error = self.frame_error(0, FrameErrorKind::UnsupportedStream(reason));
+                    self.finish(Some(error));
+                    *is_finished = true;
+
+                    return Ok(None);
+                }
+
+                self.consumed = STREAM_HEADER_LEN;
+                self.stats.bytes += STREAM_HEADER_LEN as u64;
+            }
+        }
+
+        if let Some(stream_header) = self.stream_header {
+            let framing = Framing::of(stream_header);
+
+            loop {
+                // Events from a batch that's already been checked come first
+                if let Some(batch) = self.batch.as_mut() {
+                    if let Some(entry) = batch.entries.get(batch.next_event).cloned() {
+                        let event = batch.next_event;
+                        batch.next_event += 1;
+
+                        match check_archive::<T, D>(&self.buffer1[entry]) {
+                            Ok(archive) => {
+                                let event = archive
+                                    .deserialize(&mut AllocDeserializer)
+                                    .unwrap_or_else(|unreachable| match unreachable {})
+                                    .into();
+
+                                return Ok(Some(event));
+                            }
+
+                            Err(reason) => {
+                                let start = batch.start;
+                                self.fail(
+                                    start,
+                                    FrameErrorKind::InvalidBatchEvent { event, reason },
+                                );
+                                *is_finished = true;
+
+                                return Ok(None);
+                            }
+                        }
+                    }
+
+                    let length = batch.length;
+                    self.batch = None;
+                    self.read_frame(length);
+                }
+
+                // Align to read
+                let alignment_offset = framing.padding(self.consumed);
+                let consumed = self.consumed + alignment_offset;
+
+                let header = match self
+                    .buffer1
+                    .get(consumed..)
+                    .and_then(|bytes| FrameHeader::decode(bytes, framing))
+                {
+                    Some(header) => header,
+                    None => break,
+                };
+
+                let payload_length = header.len as usize;
+                let payload_start = consumed + framing.header_len();
+                let payload = match payload_start
+                    .checked_add(payload_length)
+                    .and_then(|payload_end| self.buffer1.get(payload_start..payload_end))
+                {
+                    Some(payload) => payload,
+                    None => break,
+                };
+                let frame_length = alignment_offset + framing.header_len() + payload_length;
+
+                let checked = match check_checksum(header, payload) {
+                    Ok(()) if framing.is_batched() => match framing::batch_entries(payload) {
+                        Ok(entries) => {
+                            let entries = entries
+                                .into_iter()
+                                .map(|entry| entry.start + payload_start..entry.end + payload_start)
+                                .collect();
+
+                            self.batch = Some(Batch {
+                                entries,
+                                next_event: 0,
+                                start: consumed,
+                                length: frame_length,
+                            });
+
+                            Ok(None)
+                        }
+
+                        Err(reason) => Err(FrameErrorKind::Invalid(reason)),
+                    },
+
+                    Ok(()) => check_archive::<T, D>(payload)
+                        .map(|archive| {
+                            Some(
+                                archive
+                                    .deserialize(&mut AllocDeserializer)
+                                    .unwrap_or_else(|unreachable| match unreachable {})
+                                    .into(),
+                            )
+                        })
+                        .map_err(FrameErrorKind::Invalid),
+
+                    Err(kind) => Err(kind),
+                };
+
+                match checked {
+                    Ok(Some(event)) => {
+                        self.read_frame(frame_length);
 
                         return Ok(Some(event));
                     }
 
-                    Err(err) => {
+                    // Batched frames have their events read by the next iteration
+                    Ok(None) => {}
+
+                    Err(kind) => {
                         tracing::error!(
                             type_name = std::any::type_name::<Event<T, D>>(),
                             consumed = self.consumed,
                             alignment_offset = alignment_offset,
-                            archive_length = archive_length,
-                            header_size = mem::size_of::<u128>(),
-                            new_consumed = self.consumed
-                                + alignment_offset
-                                + archive_length
-                                + mem::size_of::<u128>(),
-                            "failed to check archived event: {:?}",
-                            err,
+                            payload_length = payload_length,
+                            header_size = framing.header_len(),
+                            "failed to check archived event: {}",
+                            kind,
                         );
 
-                        panic!("failed to check archived event: {:?}", err);
+                        self.fail(consumed, kind);
+                        *is_finished = true;
+
+                        return Ok(None);
                     }
                 }
             }
         }
 
+        // Nothing else is coming, so whatever's left can never become a complete frame
+        if self.peer_finished {
+            let corrupted = self
+                .stream_header
+                .and_then(|stream_header| self.corrupted_length(Framing::of(stream_header)));
+
+            match corrupted {
+                Some((consumed, kind)) => self.fail(consumed, kind),
+                None => self.finish(None),
+            }
+            *is_finished = true;
+
+            return Ok(None);
+        }
+
         // if we exhaust data we should shift back while preserving our alignment
         // of 16 bytes
-        if self.consumed > 15 {
+        if self.consumed >= FRAME_ALIGNMENT {
+            let shifted = self.consumed & !(FRAME_ALIGNMENT - 1);
+
             self.buffer2.clear();
-            self.buffer2
-                .extend_from_slice(&self.buffer1[self.consumed & !15..]);
+            self.buffer2.extend_from_slice(&self.buffer1[shifted..]);
 
             mem::swap(&mut self.buffer1, &mut self.buffer2);
-            self.consumed &= 15;
+            self.consumed -= shifted;
+            self.buffer_offset += shifted as u64;
         }
 
-        if let Ok(len) = self.reader.read(&mut self.bytes[..]) {
-            if len == 0 {
-                self.peer_finished = true;
+        match self.reader.read(&mut self.bytes[..]) {
+            Ok(len) => {
+                if len == 0 {
+                    self.peer_finished = true;
+                }
+
+                self.buffer1.extend_from_slice(&self.bytes[..len]);
             }
 
-            self.buffer1.extend_from_slice(&self.bytes[..len]);
+            // Sockets time out while waiting for more events
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    ErrorKind::WouldBlock | ErrorKind::TimedOut | ErrorKind::Interrupted,
+                ) => {}
+
+            // Anything else means we won't be getting any more data
+            Err(err) => {
+                tracing::warn!(
+                    source = self.source(),
+                    "failed to read from log stream: {:?}",
+                    err,
+                );
+
+                self.peer_finished = true;
+            }
         }
 
         Ok(None)
@@ -162,13 +564,13 @@ where
 #[cfg(test)]
 mod tests {
     use crate::dataflow::{operators::RkyvEventReader, tests::init_test_logging};
-    use ddshow_sink::EventWriter;
+    use ddshow_sink::{BatchedEventWriter, EventWriter};
     use ddshow_types::{
         differential_logging::{DifferentialEvent, MergeEvent},
         timely_logging::{OperatesEvent, TimelyEvent},
         OperatorAddr, OperatorId,
     };
-    use std::time::Duration;
+    use std::{convert::TryInto, time::Duration};
     use timely::dataflow::operators::capture::{Event, EventPusher};
 
     // FIXME: Make this a proptest
@@ -319,4 +721,266 @@ mod tests {
 
         assert_eq!(events, vec![first, second, third]);
     }
+
+    #[test]
+    fn truncated_tail() {
+        use crate::dataflow::operators::EventIterator;
+
+        init_test_logging();
+
+        let events: Vec<Event<Duration, TimelyEvent>> = (0..4)
+            .map(|idx| {
+                Event::Messages(
+                    Duration::from_secs(idx),
+                    vec![TimelyEvent::Operates(OperatesEvent::new(
+                        OperatorId::new(idx as usize),
+                        OperatorAddr::from_elem(OperatorId::new(idx as usize)),
+                        format!("operator {}", idx),
+                    ))],
+                )
+            })
+            .collect();
+
+        let encode = |events: &[Event<Duration, TimelyEvent>]| {
+            let mut buffer = Vec::new();
+            let mut writer = EventWriter::new(&mut buffer);
+            for event in events {
+                writer.push(event.clone());
+            }
+            drop(writer);
+
+            buffer
+        };
+        let complete_len = encode(&events[..3]).len();
+        let mut buffer = encode(&events);
+
+        // Cut the last frame off partway through
+        let truncated = buffer.len() - 7;
+        buffer.truncate(truncated);
+
+        let mut reader = RkyvEventReader::new(&buffer[..]);
+        let (mut is_finished, mut recovered) = (false, Vec::new());
+        while !is_finished {
+            if let Some(event) = EventIterator::next(&mut reader, &mut is_finished).unwrap() {
+                recovered.push(event);
+            }
+        }
+
+        assert_eq!(recovered, events[..3]);
+
+        let stats = reader.stats();
+        assert_eq!(stats.frames, 3);
+        assert_eq!(stats.bytes, complete_len as u64);
+        assert_eq!(stats.discarded, (truncated - complete_len) as u64);
+        assert_eq!(stats.error, None);
+    }
+
+    #[test]
+    fn truncated_first_frame() {
+        use crate::dataflow::operators::EventIterator;
+
+        init_test_logging();
+
+        let event: Event<Duration, TimelyEvent> = Event::Messages(
+            Duration::from_secs(0),
+            vec![TimelyEvent::Operates(OperatesEvent::new(
+                OperatorId::new(0),
+                OperatorAddr::from_elem(OperatorId::new(0)),
+                "operator 0".to_owned(),
+            ))],
+        );
+
+        let mut buffer = Vec::new();
+        EventWriter::new(&mut buffer).push(event);
+
+        // With no complete frames to compare against, a short first
+        // frame is reported as truncated rather than corrupt
+        buffer.truncate(buffer.len() - 7);
+
+        let mut reader: RkyvEventReader<Duration, TimelyEvent, _> =
+            RkyvEventReader::new(&buffer[..]);
+        let mut is_finished = false;
+        while !is_finished {
+            assert!(EventIterator::next(&mut reader, &mut is_finished)
+                .unwrap()
+                .is_none());
+        }
+
+        let stats = reader.stats();
+        assert_eq!(stats.frames, 0);
+        assert_eq!(stats.discarded, buffer.len() as u64);
+        assert_eq!(stats.error, None);
+    }
+
+    #[test]
+    fn checksum_mismatch() {
+        use crate::dataflow::operators::{rkyv_capture::FrameErrorKind, EventIterator};
+
+        init_test_logging();
+
+        let events: Vec<Event<Duration, TimelyEvent>> = (0..3)
+            .map(|idx| {
+                Event::Messages(
+                    Duration::from_secs(idx),
+                    vec![TimelyEvent::Operates(OperatesEvent::new(
+                        OperatorId::new(idx as usize),
+                        OperatorAddr::from_elem(OperatorId::new(idx as usize)),
+                        format!("operator {}", idx),
+                    ))],
+                )
+            })
+            .collect();
+
+        let encode = |events: &[Event<Duration, TimelyEvent>]| {
+            let mut buffer = Vec::new();
+            let mut writer = EventWriter::with_checksums(&mut buffer);
+            for event in events {
+                writer.push(event.clone());
+            }
+            drop(writer);
+
+            buffer
+        };
+        let corrupted_frame = encode(&events[..2]).len();
+        let mut buffer = encode(&events);
+
+        // Flip a byte of the last frame's archive, past the padding and frame header
+        let corrupted_byte = buffer.len() - 8;
+        buffer[corrupted_byte] ^= 0xFF;
+
+        let mut reader = RkyvEventReader::new(&buffer[..]).with_source("timely.worker-0.ddshow");
+        let (mut is_finished, mut recovered) = (false, Vec::new());
+        while !is_finished {
+            if let Some(event) = EventIterator::next(&mut reader, &mut is_finished).unwrap() {
+                recovered.push(event);
+            }
+        }
+
+        assert_eq!(recovered, events[..2]);
+
+        let error = reader.stats().error.clone().unwrap();
+        assert!(matches!(
+            error.kind,
+            FrameErrorKind::ChecksumMismatch { .. },
+        ));
+        assert_eq!(error.frame, 2);
+        // The frame starts after the padding that aligns it
+        assert_eq!(error.offset, (corrupted_frame as u64 + 15) & !15);
+        assert!(error.to_string().contains("frame 2"));
+        assert!(error.to_string().contains("timely.worker-0.ddshow"));
+    }
+
+    #[test]
+    fn corrupted_length() {
+        use crate::dataflow::operators::{rkyv_capture::FrameErrorKind, EventIterator};
+
+        init_test_logging();
+
+        let events: Vec<Event<Duration, TimelyEvent>> = (0..4)
+            .map(|idx| {
+                Event::Messages(
+                    Duration::from_secs(idx),
+                    vec![TimelyEvent::Operates(OperatesEvent::new(
+                        OperatorId::new(idx as usize),
+                        OperatorAddr::from_elem(OperatorId::new(idx as usize)),
+                        format!("operator {}", idx),
+                    ))],
+                )
+            })
+            .collect();
+
+        let encode = |events: &[Event<Duration, TimelyEvent>]| {
+            let mut buffer = Vec::new();
+            let mut writer = EventWriter::new(&mut buffer);
+            for event in events {
+                writer.push(event.clone());
+            }
+            drop(writer);
+
+            buffer
+        };
+        let corrupted_frame = (encode(&events[..1]).len() + 15) & !15;
+        let mut buffer = encode(&events);
+
+        // Give the second frame a length that runs past the end of the stream, the
+        // frames after it mean it can't have been cut off while being written
+        buffer[corrupted_frame + 5] = 0x01;
+
+        let mut reader = RkyvEventReader::new(&buffer[..]);
+        let (mut is_finished, mut recovered) = (false, Vec::new());
+        while !is_finished {
+            if let Some(event) = EventIterator::next(&mut reader, &mut is_finished).unwrap() {
+                recovered.push(event);
+            }
+        }
+
+        assert_eq!(recovered, events[..1]);
+
+        let error = reader.stats().error.clone().unwrap();
+        assert_eq!(
+            error.kind,
+            FrameErrorKind::LengthPastEnd {
+                len: u64::from_le_bytes(
+                    buffer[corrupted_frame..corrupted_frame + 8]
+                        .try_into()
+                        .unwrap(),
+                ),
+                remaining: (buffer.len() - corrupted_frame) as u64,
+            },
+        );
+        assert_eq!(error.frame, 1);
+        assert_eq!(error.offset, corrupted_frame as u64);
+    }
+
+    #[test]
+    fn batched_roundtrip() {
+        use crate::dataflow::operators::EventIterator;
+
+        init_test_logging();
+
+        let events: Vec<Event<Duration, TimelyEvent>> = (0..10)
+            .map(|idx| {
+                if idx % 3 == 0 {
+                    Event::Progress(vec![(Duration::from_secs(idx), idx as i64)])
+                } else {
+                    Event::Messages(
+                        Duration::from_secs(idx),
+                        vec![TimelyEvent::Operates(OperatesEvent::new(
+                            OperatorId::new(idx as usize),
+                            OperatorAddr::from_elem(OperatorId::new(idx as usize)),
+                            format!("operator {}", idx),
+                        ))],
+                    )
+                }
+            })
+            .collect();
+
+        let mut buffer = Vec::new();
+
+        {
+            // Batches end after two events, progress updates don't end them early
+            let mut writer = BatchedEventWriter::with_checksums(&mut buffer)
+                .with_batch_limits(2, usize::MAX)
+                .with_batch_delay(Duration::from_secs(60 * 60));
+            for event in events.iter() {
+                writer.push(event.clone());
+            }
+        }
+
+        let mut reader = RkyvEventReader::new(&buffer[..]);
+        let (mut is_finished, mut recovered) = (false, Vec::new());
+        while !is_finished {
+            if let Some(event) = EventIterator::next(&mut reader, &mut is_finished).unwrap() {
+                recovered.push(event);
+            }
+        }
+
+        assert_eq!(recovered, events);
+
+        let stats = reader.stats();
+        assert_eq!(stats.frames, 5);
+        assert_eq!(stats.bytes, buffer.len() as u64);
+        assert_eq!(stats.discarded, 0);
+        assert_eq!(stats.error, None);
+    }
 }
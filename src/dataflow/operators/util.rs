@@ -1,6 +1,9 @@
+use crate::dataflow::{constants::DEFAULT_EXTRACTOR_CAPACITY, spill::SpillBuffer};
+use abomonation::Abomonation;
+use anyhow::{Context, Result};
 use crossbeam_channel::{Receiver, Sender, TryRecvError};
 use differential_dataflow::{
-    difference::{Abelian, Semigroup},
+    difference::{Abelian, Present, Semigroup},
     lattice::Lattice,
     operators::Threshold,
     Collection, Data,
@@ -63,12 +66,15 @@ where
     /// Extracts in a non-blocking manner, exerting fuel for any data pulled from the channel
     /// and returning when the fuel is exhausted or the channel is empty. Returns `true` if
     /// channel's sending side disconnects and `false` otherwise
-    pub fn extract_with_fuel(
+    pub fn extract_with_fuel<S>(
         &self,
         fuel: &mut Fuel,
-        sink: &mut HashMap<D, R>,
+        sink: &mut S,
         consumed: &mut ChangeBatch<T>,
-    ) -> bool {
+    ) -> Result<bool>
+    where
+        S: ExtractionSink<D, R>,
+    {
         while !fuel.is_exhausted() {
             // Exert one fuel for the channel receive
             fuel.exert(1);
@@ -92,7 +98,7 @@ where
 
                     // Add all the data to the given sink
                     for (data, _time, diff) in data {
-                        sink.entry(data).and_modify(|d| *d += &diff).or_insert(diff);
+                        sink.insert(data, diff)?;
                     }
                 }
 
@@ -103,32 +109,16 @@ where
                 Err(TryRecvError::Empty) => break,
 
                 // If the sending side disconnects then return
-                Err(TryRecvError::Disconnected) => return true,
+                Err(TryRecvError::Disconnected) => return Ok(true),
             }
         }
 
         // If we've got some spare fuel around, do some maintenance on the sink
-        // by removing all values with weights of zero
-        //
-        // Note that this only removes entries with weights *exactly* equal to zero,
-        // entries with negative weights won't be touched
         if !fuel.is_exhausted() {
-            // Exert fuel for each entry in the sink since `.retain()` visits all of them
-            fuel.exert(sink.len());
-
-            // Only retain entries where the weight is zero
-            sink.retain(|_, diff| !diff.is_zero());
-
-            // If we've got a bunch of extra allocated capacity, drop it all
-            // to keep our memory usage from exploding. This is overly
-            // aggressive but there's not an alternative until `.shrink_to()`
-            // stabilizes.
-            if sink.capacity() > sink.len() * 2 {
-                sink.shrink_to_fit();
-            }
+            sink.maintain(fuel);
         }
 
-        false
+        Ok(false)
     }
 
     #[allow(dead_code)]
@@ -175,6 +165,94 @@ impl<T: Ord, D: Ord> Extract<T, D> for CrossbeamExtractor<Event<T, D>> {
     }
 }
 
+/// A destination for the data pulled out of a dataflow by a [`CrossbeamExtractor`]
+pub trait ExtractionSink<D, R> {
+    /// The data produced once extraction is finished
+    type Output;
+
+    /// Creates a new sink, sinks that are able to offload their data
+    /// should keep their in-memory usage below `memory_limit` bytes
+    fn new(memory_limit: Option<u64>) -> Self;
+
+    /// Adds data to the sink, sinks that offload their data
+    /// return any errors they run into while doing so
+    fn insert(&mut self, data: D, diff: R) -> Result<()>;
+
+    /// Does upkeep on the sink, exerting fuel for the work done
+    fn maintain(&mut self, fuel: &mut Fuel);
+
+    /// Finishes extraction, sinks that offload their data return
+    /// any errors they run into while reading it back
+    fn into_output(self) -> Result<Self::Output>;
+}
+
+impl<D, R> ExtractionSink<D, R> for HashMap<D, R>
+where
+    D: Hash + Eq,
+    R: Semigroup,
+{
+    type Output = Vec<D>;
+
+    fn new(_memory_limit: Option<u64>) -> Self {
+        HashMap::with_capacity(DEFAULT_EXTRACTOR_CAPACITY)
+    }
+
+    fn insert(&mut self, data: D, diff: R) -> Result<()> {
+        self.entry(data).and_modify(|d| *d += &diff).or_insert(diff);
+        Ok(())
+    }
+
+    // Note that this only removes entries with weights *exactly* equal to zero,
+    // entries with negative weights won't be touched
+    fn maintain(&mut self, fuel: &mut Fuel) {
+        // Exert fuel for each entry in the sink since `.retain()` visits all of them
+        fuel.exert(self.len());
+
+        // Only retain entries where the weight is zero
+        self.retain(|_, diff| !diff.is_zero());
+
+        // If we've got a bunch of extra allocated capacity, drop it all
+        // to keep our memory usage from exploding. This is overly
+        // aggressive but there's not an alternative until `.shrink_to()`
+        // stabilizes.
+        if self.capacity() > self.len() * 2 {
+            self.shrink_to_fit();
+        }
+    }
+
+    fn into_output(self) -> Result<Self::Output> {
+        Ok(self
+            .into_iter()
+            .filter_map(|(data, diff)| if !diff.is_zero() { Some(data) } else { None })
+            .collect())
+    }
+}
+
+impl<D> ExtractionSink<D, Present> for SpillBuffer<D>
+where
+    D: Abomonation + Clone + Ord + 'static,
+{
+    type Output = Self;
+
+    fn new(memory_limit: Option<u64>) -> Self {
+        SpillBuffer::new(memory_limit)
+    }
+
+    fn insert(&mut self, data: D, _diff: Present) -> Result<()> {
+        self.push(data)
+            .context("failed to spill extracted data to disk")
+    }
+
+    // Data is spilled as it's inserted so there's no maintenance to do
+    fn maintain(&mut self, _fuel: &mut Fuel) {}
+
+    fn into_output(mut self) -> Result<Self::Output> {
+        self.finish().context("failed to merge spilled data")?;
+
+        Ok(self)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Fuel {
     Unlimited,
@@ -2,6 +2,7 @@ use crate::{
     dataflow::{
         operators::{DelayExt, DiffDuration, JoinArranged, MapExt, Max, Min},
         send_recv::ChannelAddrs,
+        utilization::worker_time_buckets,
         utils::{granulate, ArrangedKey, DifferentialLogBundle, TimelyLogBundle},
         Channel, Diff, OperatorAddr,
     },
@@ -50,6 +51,8 @@ pub fn aggregate_worker_stats<S>(
     channels: &Collection<S, (WorkerId, Channel), Diff>,
     subgraph_addresses: &ChannelAddrs<S, Diff>,
     operator_addrs_by_self: &ArrangedKey<S, (WorkerId, OperatorAddr), Diff>,
+    bucket_width: Duration,
+    granularity: Duration,
 ) -> AggregatedStats<S>
 where
     S: Scope<Timestamp = Duration>,
@@ -131,7 +134,7 @@ where
         |(time, worker, _)| (worker, time, 1),
     )
     .as_collection()
-    .delay_fast(granulate)
+    .delay_fast(granulate(granularity))
     .count_total();
 
     let create_timestamps = |time, worker| {
@@ -143,6 +146,15 @@ where
         (worker, time, diff)
     };
 
+    let time_buckets = worker_time_buckets(&mut timely.scope(), timely, bucket_width, granularity);
+
+    // Add back any workers that didn't log any time
+    let time_buckets = time_buckets.concat(
+        &total_channels
+            .antijoin(&time_buckets.map(|(worker, _)| worker))
+            .map(|(worker, _)| (worker, Vec::new())),
+    );
+
     let total_runtime = combine_events(
         timely,
         move |(time, worker, _)| create_timestamps(time, worker),
@@ -150,7 +162,7 @@ where
         move |(time, worker, _)| create_timestamps(time, worker),
     )
     .as_collection()
-    .delay_fast(granulate)
+    .delay_fast(granulate(granularity))
     .count_total();
 
     // TODO: For whatever reason this part of the dataflow graph is de-prioritized,
@@ -171,18 +183,22 @@ where
         .join(&total_arrangements)
         .join(&total_events)
         .join(&total_runtime)
+        .join(&time_buckets)
         .map(
             |(
                 worker,
                 (
                     (
                         (
-                            ((((dataflow_addrs, dataflows), operators), subgraphs), channels),
-                            arrangements,
+                            (
+                                ((((dataflow_addrs, dataflows), operators), subgraphs), channels),
+                                arrangements,
+                            ),
+                            events,
                         ),
-                        events,
+                        runtime,
                     ),
-                    runtime,
+                    time_buckets,
                 ),
             )| {
                 let runtime =
@@ -200,6 +216,7 @@ where
                         events: events as usize,
                         runtime,
                         dataflow_addrs,
+                        time_buckets,
                     },
                 )
             },
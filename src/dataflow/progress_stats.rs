@@ -16,17 +16,6 @@ use serde::{Deserialize, Serialize};
 use std::{iter, time::Duration};
 use timely::dataflow::{operators::Map, Scope, Stream};
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Abomonation)]
-pub struct ChannelMessageStats {
-    pub channel: ChannelId,
-    pub worker: WorkerId,
-    pub max: usize,
-    pub min: usize,
-    pub total: usize,
-    pub average: usize,
-    pub invocations: usize,
-}
-
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Abomonation)]
 pub struct ChannelCapabilityStats {
     pub channel: ChannelId,
@@ -134,6 +123,7 @@ pub enum ConnectionKind {
 
 pub fn aggregate_channel_messages<S>(
     progress_stream: &Stream<S, ProgressLogBundle>,
+    granularity: Duration,
 ) -> Collection<S, (OperatorAddr, ProgressInfo), Diff>
 where
     S: Scope<Timestamp = Duration>,
@@ -154,7 +144,7 @@ where
             })
         })
         .as_collection()
-        .delay_fast(granulate)
+        .delay_fast(granulate(granularity))
         .count_total()
         .arrange_by_key();
 
@@ -180,7 +170,7 @@ where
             })
         })
         .as_collection()
-        .delay_fast(granulate)
+        .delay_fast(granulate(granularity))
         .count_total()
         .arrange_by_key();
 
@@ -1,125 +1,161 @@
-use super::ReachabilityLogBundle;
+use crate::dataflow::{
+    operators::DelayExt,
+    utils::{granulate, Diff, ReachabilityLogBundle},
+};
 use abomonation_derive::Abomonation;
-use std::{collections::HashMap, time::Duration};
+use ddshow_types::{reachability_logging::TrackerEvent, OperatorAddr, PortId, WorkerId};
+use differential_dataflow::{
+    operators::{Consolidate, Reduce},
+    AsCollection, Collection,
+};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
 use timely::dataflow::{
-    channels::pact::Pipeline,
-    operators::{
-        flow_controlled::{iterator_source, IteratorSourceInput},
-        probe::{Handle, Probe},
-        Operator, Reclock,
-    },
+    operators::{Enter, Map},
     Scope, Stream,
 };
 
-pub fn display_reachability<S>(
+/// Whether a pointstamp is held at an operator's output or input port
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Abomonation, Deserialize, Serialize,
+)]
+pub enum PointstampLocation {
+    Source,
+    Target,
+}
+
+/// The number of pointstamps held at a single location within a
+/// reachability tracker over the course of the program
+#[derive(
+    Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Abomonation, Deserialize, Serialize,
+)]
+pub struct PointstampTimeline {
+    pub worker: WorkerId,
+    /// The address of the scope that owns the tracker
+    pub tracker: OperatorAddr,
+    /// The address of the operator the pointstamps are held at, node zero
+    /// of a tracker is the scope itself
+    pub operator_addr: OperatorAddr,
+    pub port: PortId,
+    pub location: PointstampLocation,
+    /// The pointstamp's timestamp, printed via its `Debug` implementation
+    pub timestamp: String,
+    /// The number of pointstamps held after each change, in the form
+    /// of `(time, count)` and sorted by time
+    pub counts: Vec<(Duration, i64)>,
+    /// The total amount of time that pointstamps were held for
+    pub held_for: Duration,
+    /// Whether or not pointstamps were still held at the end of the trace
+    pub still_held: bool,
+}
+
+pub fn pointstamp_timelines<S>(
     scope: &mut S,
-    window_size: Duration,
-    reachability_trace: &Stream<S, ReachabilityLogBundle>,
-) where
+    reachability_stream: &Stream<S, ReachabilityLogBundle>,
+    granularity: Duration,
+) -> Collection<S, PointstampTimeline, Diff>
+where
     S: Scope<Timestamp = Duration>,
 {
-    let mut clock_probe = Handle::new();
-    let clock = iterator_source(
-        scope,
-        "Reachability Clock",
-        move |&previous_time| {
-            Some(IteratorSourceInput {
-                lower_bound: Default::default(),
-                data: vec![(previous_time + window_size, vec![()])],
-                target: previous_time,
-            })
-        },
-        clock_probe.clone(),
-    );
+    scope.region_named("Pointstamp Timelines", |region| {
+        reachability_stream
+            .enter(region)
+            .flat_map(|(time, worker, event)| {
+                let location = if event.is_source_update() {
+                    PointstampLocation::Source
+                } else {
+                    PointstampLocation::Target
+                };
+
+                let (tracker, updates) = match event {
+                    TrackerEvent::SourceUpdate(source) => (source.tracker_id, source.updates),
+                    TrackerEvent::TargetUpdate(target) => (target.tracker_id, target.updates),
+                };
 
-    reachability_trace
-        .probe_with(&mut clock_probe)
-        .reclock(&clock)
-        .sink(Pipeline, "Display Reachability Updates", |input| {
-            let mut buffer = Vec::new();
-            let mut capabilities = HashMap::new();
+                updates.into_iter().map(move |update| {
+                    (
+                        (
+                            (
+                                worker,
+                                tracker.clone(),
+                                update.node,
+                                update.port,
+                                location,
+                                update.timestamp,
+                            ),
+                            // Pointstamp diffs can be negative, so they're carried
+                            // within the data instead of as the collection's diff
+                            (time, update.diff),
+                        ),
+                        time,
+                        1 as Diff,
+                    )
+                })
+            })
+            .as_collection()
+            .delay_fast(granulate(granularity))
+            // Changes to the same pointstamp can arrive in separate batches at
+            // the same time, so they're merged before being accumulated
+            .consolidate_named("Consolidate: Pointstamp Changes")
+            .reduce_named(
+                "Reduce: Pointstamp Timelines",
+                |(worker, tracker, node, port, location, timestamp), changes, output| {
+                    let mut operator_addr = tracker.clone();
+                    if !node.is_zero() {
+                        operator_addr.push(*node);
+                    }
 
-            input.for_each(move |_capability, data| {
-                data.swap(&mut buffer);
+                    let mut timeline = PointstampTimeline {
+                        worker: *worker,
+                        tracker: tracker.clone(),
+                        operator_addr,
+                        port: *port,
+                        location: *location,
+                        timestamp: timestamp.clone(),
+                        counts: Vec::with_capacity(changes.len()),
+                        held_for: Duration::from_secs(0),
+                        still_held: false,
+                    };
 
-                for (_time, _worker, event) in buffer.drain(..) {
-                    match event {
-                        TrackerEvent::SourceUpdate(SourceUpdate {
-                            tracker_id,
-                            updates,
-                        }) => {
-                            let tracker_map =
-                                capabilities.entry(tracker_id).or_insert_with(HashMap::new);
+                    // Inputs are sorted by time, so accumulating them in order
+                    // gives the number of pointstamps held after each change.
+                    // Changes made at the same time are combined into one
+                    let mut net_changes: Vec<(Duration, i64)> = Vec::with_capacity(changes.len());
+                    for &(&(time, diff), repeats) in changes {
+                        let diff = diff * repeats as i64;
 
-                            for (node, port, time, diff) in updates {
-                                tracker_map
-                                    .entry((node, port, time))
-                                    .and_modify(|current_diff| *current_diff += diff)
-                                    .or_insert(diff);
-                            }
+                        match net_changes.last_mut() {
+                            Some((last, net)) if *last == time => *net += diff,
+                            _ => net_changes.push((time, diff)),
                         }
+                    }
 
-                        TrackerEvent::TargetUpdate(TargetUpdate {
-                            tracker_id,
-                            updates,
-                        }) => {
-                            let tracker_map =
-                                capabilities.entry(tracker_id).or_insert_with(HashMap::new);
+                    let (mut count, mut held_since) = (0, None);
+                    for (time, diff) in net_changes {
+                        count += diff;
+                        timeline.counts.push((time, count));
 
-                            for (node, port, time, diff) in updates {
-                                tracker_map
-                                    .entry((node, port, time))
-                                    .and_modify(|current_diff| *current_diff += diff)
-                                    .or_insert(diff);
+                        match (count > 0, held_since) {
+                            (true, None) => held_since = Some(time),
+                            (false, Some(since)) => {
+                                timeline.held_for += time - since;
+                                held_since = None;
                             }
+                            _ => {}
                         }
                     }
-                }
-            });
-        })
-}
-
-/// Events that the tracker may record.
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Abomonation)]
-pub enum TrackerEvent {
-    /// Updates made at a source of data.
-    SourceUpdate(SourceUpdate),
-    /// Updates made at a target of data.
-    TargetUpdate(TargetUpdate),
-}
-
-/// An update made at a source of data.
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Abomonation)]
-pub struct SourceUpdate {
-    /// An identifier for the tracker.
-    pub tracker_id: Vec<usize>,
-    /// Updates themselves, as `(node, port, time, diff)`.
-    pub updates: Vec<(usize, usize, SerializedTime, i64)>,
-}
-
-/// An update made at a target of data.
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Abomonation)]
-pub struct TargetUpdate {
-    /// An identifier for the tracker.
-    pub tracker_id: Vec<usize>,
-    /// Updates themselves, as `(node, port, time, diff)`.
-    pub updates: Vec<(usize, usize, SerializedTime, i64)>,
-}
-
-impl From<SourceUpdate> for TrackerEvent {
-    fn from(source: SourceUpdate) -> TrackerEvent {
-        Self::SourceUpdate(source)
-    }
-}
 
-impl From<TargetUpdate> for TrackerEvent {
-    fn from(target: TargetUpdate) -> TrackerEvent {
-        Self::TargetUpdate(target)
-    }
-}
+                    // Pointstamps that are never released are held until at
+                    // least the last change we saw
+                    if let (Some(since), Some(&(last, _))) = (held_since, timeline.counts.last()) {
+                        timeline.held_for += last - since;
+                        timeline.still_held = true;
+                    }
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Abomonation)]
-pub struct SerializedTime {
-    pub timestamp: String,
-    pub type_name: String,
+                    output.push((timeline, 1));
+                },
+            )
+            .map(|(_, timeline)| timeline)
+            .leave_region()
+    })
 }
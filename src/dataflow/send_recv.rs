@@ -1,18 +1,26 @@
 use crate::{
     dataflow::{
-        constants::DEFAULT_EXTRACTOR_CAPACITY,
+        channel_stats::{ChannelLatency, EdgeWithStats, WorkerExchange},
+        frontiers::{EpochCompletion, FrontierStep, FrontierTimelineSink},
+        hang_detection::HangSuspect,
         operator_stats::{AggregatedOperatorStats, OperatorStats},
-        operators::{CrossbeamExtractor, Fuel},
-        progress_stats::{Channel, ProgressInfo},
+        operators::{CrossbeamExtractor, ExtractionSink, Fuel},
+        progress_stats::ProgressInfo,
+        reachability::PointstampTimeline,
+        spill::SpillBuffer,
+        stragglers::OperatorStraggler,
         utils::{channel_sink, Diff, Time},
         worker_timeline::TimelineEvent,
     },
-    ui::{DataflowStats, ProgramStats, WorkerStats},
+    ui::{DataflowStats, OperatorTimeBucket, ProgramStats, WorkerStats},
 };
+use anyhow::Result;
 use crossbeam_channel::{Receiver, Sender};
-use ddshow_types::{timely_logging::OperatesEvent, OperatorAddr, OperatorId, WorkerId};
+use ddshow_types::{
+    lints::CapabilityViolation, timely_logging::OperatesEvent, OperatorAddr, OperatorId, WorkerId,
+};
 use differential_dataflow::{
-    difference::{Present, Semigroup},
+    difference::Present,
     operators::arrange::{Arranged, TraceAgent},
     trace::implementations::ord::OrdKeySpine,
     Collection,
@@ -37,7 +45,7 @@ type EReceiver<D, R = Diff> = Receiver<Event<Time, Bundled<D, R>>>;
 type Extractor<D, R = Diff> = CrossbeamExtractor<Event<Time, Bundled<D, R>>>;
 
 macro_rules! make_send_recv {
-    ($($name:ident : $ty:ty $(= $diff:ty)?),* $(,)?) => {
+    ($($name:ident : $ty:ty $(= $diff:ty)? $(=> $sink:ty)?),* $(,)?) => {
         #[derive(Clone, Debug)]
         pub struct DataflowSenders {
             $($name: (ESender<$ty, $($diff)?>, bool),)*
@@ -128,9 +136,10 @@ macro_rules! make_send_recv {
                 }
             }
 
-            /// Make a [`DataflowExtractor`] for the target dataflow
-            pub fn into_extractor(self) -> DataflowExtractor {
-                DataflowExtractor::new($(self.$name,)*)
+            /// Make a [`DataflowExtractor`] for the target dataflow, sinks that are
+            /// able to spill to disk will hold at most `memory_limit` bytes in memory
+            pub fn into_extractor(self, memory_limit: Option<u64>) -> DataflowExtractor {
+                DataflowExtractor::new(memory_limit, $(self.$name,)*)
             }
         }
 
@@ -140,9 +149,8 @@ macro_rules! make_send_recv {
             1
         })*;
 
-        #[derive(Clone)]
         pub struct DataflowExtractor {
-            $(pub $name: (Extractor<$ty, $($diff)?>, HashMap<$ty, make_send_recv!(@diff $($diff)?)>),)*
+            $(pub $name: (Extractor<$ty, $($diff)?>, make_send_recv!(@sink $ty, make_send_recv!(@diff $($diff)?) $(, $sink)?)),)*
             step: Cycle<DataflowStepIter>,
             consumed: ChangeBatch<Duration>,
             last_consumed: Duration,
@@ -151,12 +159,13 @@ macro_rules! make_send_recv {
         impl DataflowExtractor {
             #[allow(clippy::too_many_arguments)]
             pub fn new(
+                memory_limit: Option<u64>,
                 $($name: EReceiver<$ty, $($diff)?>,)*
             ) -> Self {
                 Self {
                     $($name: (
                         CrossbeamExtractor::new($name),
-                        HashMap::with_capacity(DEFAULT_EXTRACTOR_CAPACITY),
+                        ExtractionSink::new(memory_limit),
                     ),)*
                     step: DataflowStep::iter().cycle(),
                     consumed: ChangeBatch::new(),
@@ -166,7 +175,7 @@ macro_rules! make_send_recv {
 
             /// Extract data from the current dataflow in a non-blocking manner
             #[inline(never)]
-            pub fn extract_with_fuel(&mut self, fuel: &mut Fuel) {
+            pub fn extract_with_fuel(&mut self, fuel: &mut Fuel) -> Result<()> {
                 for step in self.step.by_ref().take(NUM_VARIANTS) {
                     if fuel.is_exhausted() {
                         break;
@@ -177,11 +186,13 @@ macro_rules! make_send_recv {
                             DataflowStep::$name => {
                                 let (extractor, sink) = &mut self.$name;
 
-                                extractor.extract_with_fuel(fuel, sink, &mut self.consumed);
+                                extractor.extract_with_fuel(fuel, sink, &mut self.consumed)?;
                             },
                         )*
                     }
                 }
+
+                Ok(())
             }
 
             // // FIXME: Use the tracker progress infrastructure for a more
@@ -204,7 +215,7 @@ macro_rules! make_send_recv {
 
             // TODO: Does this need to guard against never-disconnected channels?
             #[inline(never)]
-            pub fn extract_all(mut self) -> DataflowData {
+            pub fn extract_all(mut self) -> Result<DataflowData> {
                 let mut fuel = Fuel::unlimited();
                 let mut extractor_status = Vec::with_capacity(NUM_VARIANTS);
 
@@ -213,7 +224,7 @@ macro_rules! make_send_recv {
                         extractor_status.push({
                             let (extractor, sink) = &mut self.$name;
 
-                            extractor.extract_with_fuel(&mut fuel, sink, &mut self.consumed)
+                            extractor.extract_with_fuel(&mut fuel, sink, &mut self.consumed)?
                         });
                     )*
 
@@ -225,10 +236,7 @@ macro_rules! make_send_recv {
                 }
 
                 $(
-                    let $name: Vec<_> = self.$name.1
-                        .into_iter()
-                        .filter_map(|(data, diff)| if !diff.is_zero() { Some(data) } else { None })
-                        .collect();
+                    let $name = self.$name.1.into_output()?;
 
                     tracing::debug!(
                         "extracted {} {} events",
@@ -237,19 +245,19 @@ macro_rules! make_send_recv {
                     );
                 )*
 
-                DataflowData::new($($name,)*)
+                Ok(DataflowData::new($($name,)*))
             }
         }
 
-        #[derive(Clone, Debug)]
+        #[derive(Debug)]
         pub struct DataflowData {
-            $(pub $name: Vec<$ty>,)*
+            $(pub $name: make_send_recv!(@output $ty, make_send_recv!(@diff $($diff)?) $(, $sink)?),)*
         }
 
         impl DataflowData {
             #[allow(clippy::too_many_arguments)]
             pub fn new(
-                $($name: Vec<$ty>,)*
+                $($name: make_send_recv!(@output $ty, make_send_recv!(@diff $($diff)?) $(, $sink)?),)*
             ) -> Self {
                 Self {
                     $($name,)*
@@ -266,24 +274,27 @@ macro_rules! make_send_recv {
 
     (@diff) => { Diff };
     (@diff $diff:ty) => { $diff };
+
+    (@sink $ty:ty, $diff:ty) => { HashMap<$ty, $diff> };
+    (@sink $ty:ty, $diff:ty, $sink:ty) => { $sink };
+
+    (@output $ty:ty, $diff:ty $(, $sink:ty)?) => {
+        <make_send_recv!(@sink $ty, $diff $(, $sink)?) as ExtractionSink<$ty, $diff>>::Output
+    };
 }
 
 type WorkerStatsData = Vec<(WorkerId, WorkerStats)>;
 type NodeData = ((WorkerId, OperatorAddr), OperatesEvent);
-type EdgeData = (
-    WorkerId,
-    OperatesEvent,
-    Channel,
-    OperatesEvent,
-    // Option<ChannelMessageStats>,
-);
+type EdgeData = EdgeWithStats;
 type SubgraphData = ((WorkerId, OperatorAddr), OperatesEvent);
 type OperatorStatsData = ((WorkerId, OperatorId), OperatorStats);
-type AggOperatorStatsData = (OperatorId, AggregatedOperatorStats);
+type AggOperatorStatsData = AggregatedOperatorStats;
 type TimelineEventData = TimelineEvent;
 type NameLookupData = ((WorkerId, OperatorId), String);
 type AddrLookupData = ((WorkerId, OperatorId), OperatorAddr);
 type ChannelProgressData = (OperatorAddr, ProgressInfo);
+type CapabilityViolationData = ((WorkerId, OperatorId), CapabilityViolation);
+type OperatorTimeSeriesData = (OperatorAddr, Vec<OperatorTimeBucket>);
 
 make_send_recv! {
     program_stats: ProgramStats,
@@ -294,8 +305,17 @@ make_send_recv! {
     operator_stats: OperatorStatsData,
     aggregated_operator_stats: AggOperatorStatsData,
     dataflow_stats: DataflowStats,
-    timeline_events: TimelineEventData = Present,
+    timeline_events: TimelineEventData = Present => SpillBuffer<TimelineEventData>,
     name_lookup: NameLookupData,
     addr_lookup: AddrLookupData,
     channel_progress: ChannelProgressData,
+    capability_violations: CapabilityViolationData,
+    hang_suspects: HangSuspect,
+    pointstamp_timelines: PointstampTimeline,
+    frontier_timelines: FrontierStep => FrontierTimelineSink,
+    epoch_completions: EpochCompletion,
+    worker_exchanges: WorkerExchange,
+    channel_latencies: ChannelLatency,
+    stragglers: OperatorStraggler,
+    operator_time_series: OperatorTimeSeriesData,
 }
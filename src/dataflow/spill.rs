@@ -0,0 +1,468 @@
+//! Storage for extracted data that spills to disk once it grows too large
+//!
+//! Data is held in memory until the encoded size of the buffered elements, including
+//! any data they own on the heap, reaches the memory limit. At that point the in-memory
+//! elements are sorted, deduplicated and appended to a temporary file as a sorted run,
+//! each element encoded with abomonation on its own so that runs can be streamed back
+//! one element at a time.
+//!
+//! Like the `HashMap` sinks used for other extracted data, duplicate elements are
+//! removed. Runs are only deduplicated against themselves while they're being spilled,
+//! so [`SpillBuffer::finish()`] merges every run and the remaining in-memory elements
+//! into a single sorted run without duplicates. Merging reads a single element from
+//! each run at a time, so it stays within the memory limit
+
+use abomonation::Abomonation;
+use anyhow::{Context, Result};
+use serde::{
+    ser::{Error as _, SerializeSeq},
+    Serialize, Serializer,
+};
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    fmt::{self, Debug},
+    fs::{self, File, OpenOptions},
+    io::{self, BufReader, BufWriter, ErrorKind, Read, Seek, SeekFrom, Write},
+    iter,
+    marker::PhantomData,
+    mem,
+    path::PathBuf,
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Used to give each spill file a unique name
+static SPILL_FILE_ID: AtomicUsize = AtomicUsize::new(0);
+
+pub struct SpillBuffer<D> {
+    /// The data that hasn't been spilled to disk yet
+    buffer: Vec<D>,
+    /// The encoded size of the elements in `buffer`
+    buffered_bytes: u64,
+    /// The most bytes that can be held in memory, `None` if
+    /// the buffer should never spill
+    memory_limit: Option<u64>,
+    /// The file that spilled data is written to, created on the first spill
+    spill: Option<SpillFile>,
+    /// The total number of elements in the buffer, both spilled and in-memory
+    len: usize,
+}
+
+impl<D> SpillBuffer<D>
+where
+    D: Abomonation + Clone + Ord,
+{
+    /// Creates a buffer that spills to disk once the elements it holds
+    /// would take up more than `memory_limit` bytes
+    pub fn new(memory_limit: Option<u64>) -> Self {
+        Self {
+            buffer: Vec::new(),
+            buffered_bytes: 0,
+            memory_limit,
+            spill: None,
+            len: 0,
+        }
+    }
+
+    pub fn push(&mut self, data: D) -> Result<()> {
+        // Measures the element itself along with everything it owns on the heap
+        self.buffered_bytes += abomonation::measure(&data) as u64;
+        self.buffer.push(data);
+        self.len += 1;
+
+        if matches!(self.memory_limit, Some(limit) if self.buffered_bytes >= limit) {
+            self.spill()?;
+        }
+
+        Ok(())
+    }
+
+    /// Removes duplicate elements from the in-memory data
+    pub fn dedup(&mut self) {
+        let buffered = self.buffer.len();
+        self.buffer.sort_unstable();
+        self.buffer.dedup();
+
+        self.len -= buffered - self.buffer.len();
+    }
+
+    /// Removes duplicate elements from the whole buffer, merging all spilled
+    /// runs along with the in-memory data into a single run if anything has
+    /// been spilled
+    pub fn finish(&mut self) -> Result<()>
+    where
+        D: 'static,
+    {
+        self.dedup();
+
+        let spill = match self.spill.take() {
+            Some(spill) => spill,
+            None => return Ok(()),
+        };
+        if spill.runs.len() == 1 && self.buffer.is_empty() {
+            self.spill = Some(spill);
+            return Ok(());
+        }
+
+        let mut merged = SpillFile::create()?;
+        let mut sources: Vec<Box<dyn Iterator<Item = Result<D>>>> = spill
+            .runs()?
+            .into_iter()
+            .map(|run| Box::new(run) as Box<dyn Iterator<Item = _>>)
+            .collect();
+        sources.push(Box::new(mem::take(&mut self.buffer).into_iter().map(Ok)));
+
+        // Each source is sorted, so repeatedly taking the smallest head of all of
+        // them gives every element in order with duplicates next to each other
+        let mut heads = BinaryHeap::with_capacity(sources.len());
+        for (idx, source) in sources.iter_mut().enumerate() {
+            if let Some(head) = source.next() {
+                heads.push(Reverse((head?, idx)));
+            }
+        }
+
+        let mut writer = merged.run_writer();
+        let mut last: Option<D> = None;
+        while let Some(Reverse((element, idx))) = heads.pop() {
+            if let Some(next) = sources[idx].next() {
+                heads.push(Reverse((next?, idx)));
+            }
+
+            if last.as_ref() != Some(&element) {
+                writer.write(&element)?;
+                last = Some(element);
+            }
+        }
+        let len = writer.finish()?;
+
+        tracing::debug!(
+            path = %merged.path.display(),
+            "merged {} spilled runs into {} elements ({} duplicates removed)",
+            spill.runs.len(),
+            len,
+            self.len - len,
+        );
+
+        self.len = len;
+        self.buffered_bytes = 0;
+        self.spill = Some(merged);
+
+        Ok(())
+    }
+
+    /// The total number of elements within the buffer
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if any data has been written to disk
+    pub const fn has_spilled(&self) -> bool {
+        self.spill.is_some()
+    }
+
+    /// Iterates over every element of the buffer, reading any spilled data
+    /// back from disk as it goes
+    pub fn iter(&self) -> impl Iterator<Item = Result<D>> + '_ {
+        let spilled = self
+            .spill
+            .as_ref()
+            .map(|spill| match spill.runs() {
+                Ok(runs) => Box::new(runs.into_iter().flatten()) as Box<dyn Iterator<Item = _>>,
+                Err(err) => Box::new(iter::once(Err(err))),
+            })
+            .into_iter()
+            .flatten();
+
+        spilled.chain(self.buffer.iter().cloned().map(Ok))
+    }
+
+    /// Writes all in-memory data to the spill file as a new run
+    fn spill(&mut self) -> Result<()> {
+        self.dedup();
+
+        if self.spill.is_none() {
+            self.spill = Some(SpillFile::create()?);
+        }
+
+        if let Some(spill) = self.spill.as_mut() {
+            let mut writer = spill.run_writer();
+            for element in self.buffer.iter() {
+                writer.write(element)?;
+            }
+            writer.finish()?;
+
+            tracing::debug!(
+                path = %spill.path.display(),
+                "spilled {} elements to disk ({} runs spilled)",
+                self.buffer.len(),
+                spill.runs.len(),
+            );
+        }
+
+        self.buffer.clear();
+        self.buffered_bytes = 0;
+
+        Ok(())
+    }
+}
+
+impl<D> Default for SpillBuffer<D> {
+    fn default() -> Self {
+        Self {
+            buffer: Vec::new(),
+            buffered_bytes: 0,
+            memory_limit: None,
+            spill: None,
+            len: 0,
+        }
+    }
+}
+
+impl<D> Debug for SpillBuffer<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpillBuffer")
+            .field("len", &self.len)
+            .field("in_memory", &self.buffer.len())
+            .field("buffered_bytes", &self.buffered_bytes)
+            .field("memory_limit", &self.memory_limit)
+            .field("spill", &self.spill.as_ref().map(|spill| &spill.path))
+            .finish()
+    }
+}
+
+impl<D> Serialize for SpillBuffer<D>
+where
+    D: Abomonation + Clone + Ord + Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut seq = serializer.serialize_seq(Some(self.len))?;
+        for element in self.iter() {
+            seq.serialize_element(&element.map_err(S::Error::custom)?)?;
+        }
+
+        seq.end()
+    }
+}
+
+/// A run of sorted elements within a spill file
+#[derive(Debug, Clone, Copy)]
+struct SpillRun {
+    /// The offset of the run's first element from the start of the file
+    offset: u64,
+    /// The number of elements in the run
+    len: usize,
+}
+
+/// A temporary file holding runs of length-prefixed abomonation-encoded
+/// elements, removed once it's dropped
+struct SpillFile {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    /// The number of bytes written to the file
+    written: u64,
+    runs: Vec<SpillRun>,
+}
+
+impl SpillFile {
+    /// The number of names to try before giving up on creating a spill file
+    const CREATE_ATTEMPTS: usize = 64;
+
+    fn create() -> Result<Self> {
+        let mut attempts = 0;
+
+        // The temporary directory is shared, so the file is always created fresh
+        // (never opening an existing file or following a symlink) and is only
+        // readable by us
+        let (path, file) = loop {
+            let path = std::env::temp_dir().join(format!(
+                "ddshow-spill-{}-{}",
+                process::id(),
+                SPILL_FILE_ID.fetch_add(1, Ordering::Relaxed),
+            ));
+
+            let mut options = OpenOptions::new();
+            options.write(true).create_new(true);
+            #[cfg(unix)]
+            options.mode(0o600);
+
+            match options.open(&path) {
+                Ok(file) => break (path, file),
+
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => {
+                    attempts += 1;
+                    if attempts == Self::CREATE_ATTEMPTS {
+                        anyhow::bail!(
+                            "failed to create a spill file in {}, {} file names were already taken",
+                            std::env::temp_dir().display(),
+                            attempts,
+                        );
+                    }
+                }
+
+                Err(err) => {
+                    return Err(err).with_context(|| {
+                        format!("failed to create spill file at {}", path.display())
+                    })
+                }
+            }
+        };
+        tracing::info!(path = %path.display(), "spilling extracted data to disk");
+
+        Ok(Self {
+            path,
+            writer: BufWriter::new(file),
+            written: 0,
+            runs: Vec::new(),
+        })
+    }
+
+    /// Starts writing a new run at the end of the file
+    fn run_writer<D>(&mut self) -> RunWriter<'_, D> {
+        RunWriter {
+            run: SpillRun {
+                offset: self.written,
+                len: 0,
+            },
+            file: self,
+            bytes: Vec::new(),
+            __type: PhantomData,
+        }
+    }
+
+    /// Opens a reader for each of the file's runs
+    fn runs<D>(&self) -> Result<Vec<RunReader<D>>>
+    where
+        D: Abomonation + Clone,
+    {
+        self.runs
+            .iter()
+            .map(|&run| {
+                let mut file = File::open(&self.path)?;
+                file.seek(SeekFrom::Start(run.offset))?;
+
+                Ok(RunReader {
+                    reader: BufReader::new(file),
+                    remaining: run.len,
+                    bytes: Vec::new(),
+                    __type: PhantomData,
+                })
+            })
+            .collect::<io::Result<_>>()
+            .with_context(|| format!("failed to open spill file {}", self.path.display()))
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        if let Err(err) = fs::remove_file(&self.path) {
+            tracing::warn!(
+                path = %self.path.display(),
+                "failed to remove spill file: {:?}",
+                err,
+            );
+        }
+    }
+}
+
+/// Appends a single run to a spill file, elements must be written in sorted order
+struct RunWriter<'a, D> {
+    file: &'a mut SpillFile,
+    run: SpillRun,
+    /// A scratch buffer for encoding elements into
+    bytes: Vec<u8>,
+    __type: PhantomData<D>,
+}
+
+impl<D> RunWriter<'_, D>
+where
+    D: Abomonation,
+{
+    fn write(&mut self, element: &D) -> Result<()> {
+        self.bytes.clear();
+        // Safety: Encoding into a `Vec` can't fail
+        unsafe { abomonation::encode(element, &mut self.bytes) }
+            .context("failed to encode spilled element")?;
+
+        let (bytes, file) = (&self.bytes, &mut *self.file);
+        file.writer
+            .write_all(&(bytes.len() as u64).to_le_bytes())
+            .and_then(|()| file.writer.write_all(bytes))
+            .with_context(|| format!("failed to write to spill file {}", file.path.display()))?;
+
+        file.written += (8 + bytes.len()) as u64;
+        self.run.len += 1;
+
+        Ok(())
+    }
+
+    /// Finishes the run, returning the number of elements within it
+    fn finish(self) -> Result<usize> {
+        // Flush after every run so that the file can be read back at any time
+        self.file.writer.flush().with_context(|| {
+            format!("failed to write to spill file {}", self.file.path.display())
+        })?;
+        self.file.runs.push(self.run);
+
+        Ok(self.run.len)
+    }
+}
+
+/// Streams the elements of a single run back from a spill file
+struct RunReader<D> {
+    reader: BufReader<File>,
+    remaining: usize,
+    /// A scratch buffer for decoding elements from
+    bytes: Vec<u8>,
+    __type: PhantomData<D>,
+}
+
+impl<D> RunReader<D>
+where
+    D: Abomonation + Clone,
+{
+    fn read_element(&mut self) -> Result<D> {
+        let mut len = [0; 8];
+        self.reader.read_exact(&mut len)?;
+
+        self.bytes.clear();
+        self.bytes.resize(u64::from_le_bytes(len) as usize, 0);
+        self.reader.read_exact(&mut self.bytes)?;
+
+        // Safety: The bytes were produced by `abomonation::encode()` within `RunWriter::write()`
+        let (element, _) = unsafe { abomonation::decode::<D>(&mut self.bytes) }
+            .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "corrupted spilled element"))?;
+
+        Ok(element.clone())
+    }
+}
+
+impl<D> Iterator for RunReader<D>
+where
+    D: Abomonation + Clone,
+{
+    type Item = Result<D>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+
+        let element = self
+            .read_element()
+            .context("failed to read from spill file");
+
+        // Stop after the first error
+        if element.is_err() {
+            self.remaining = 0;
+        }
+
+        Some(element)
+    }
+}
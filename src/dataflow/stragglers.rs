@@ -0,0 +1,160 @@
+//! Detection for operators where a single worker does most of the work
+//!
+//! Operators are matched across workers by their address, which is the same
+//! on every worker since they all construct the same dataflows. For every
+//! operator that exists on more than one worker, the worker that spent the
+//! most time within it is compared against the average of all the other
+//! workers. If it's at least [`STRAGGLER_RATIO`] times slower, either in total
+//! or within its longest activation, the operator is reported as a straggler.
+//! Operators where the slowest worker spent less than [`STRAGGLER_MIN_DURATION`]
+//! are ignored since tiny differences make for huge (and meaningless) ratios
+
+use crate::dataflow::{
+    constants::{STRAGGLER_MIN_DURATION, STRAGGLER_RATIO},
+    operator_stats::AggregatedOperatorStats,
+    utils::Diff,
+};
+use abomonation_derive::Abomonation;
+use ddshow_types::{OperatorAddr, WorkerId};
+use differential_dataflow::Collection;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use timely::dataflow::Scope;
+
+/// An operator where a single worker spent far more time than the others
+#[derive(
+    Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Abomonation, Deserialize, Serialize,
+)]
+pub struct OperatorStraggler {
+    pub addr: OperatorAddr,
+    pub name: String,
+    /// The number of workers the operator was run on
+    pub workers: usize,
+    /// The total activation time of the operator on each worker
+    pub total: WorkerSpread,
+    /// The longest single activation of the operator on each worker
+    pub max: WorkerSpread,
+}
+
+impl OperatorStraggler {
+    /// Returns `true` if the worker that spent the most total time within
+    /// the operator dominates the others
+    pub fn total_dominated(&self) -> bool {
+        self.total.is_dominated()
+    }
+
+    /// Returns `true` if the worker with the longest activation dominates the others
+    pub fn max_dominated(&self) -> bool {
+        self.max.is_dominated()
+    }
+}
+
+/// How a single statistic of an operator varies across workers
+#[derive(
+    Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Abomonation, Deserialize, Serialize,
+)]
+pub struct WorkerSpread {
+    /// The worker with the largest value
+    pub slowest_worker: WorkerId,
+    /// The largest value out of all workers
+    pub slowest: Duration,
+    /// The smallest value out of all workers
+    pub fastest: Duration,
+    /// The average value of every worker besides the slowest one
+    pub others_average: Duration,
+}
+
+impl WorkerSpread {
+    /// The difference between the slowest and fastest workers
+    pub fn spread(&self) -> Duration {
+        self.slowest - self.fastest
+    }
+
+    /// How many times slower the slowest worker was than the others' average,
+    /// infinite if the other workers didn't spend any time at all
+    pub fn ratio(&self) -> f64 {
+        let others = self.others_average.as_secs_f64();
+
+        if others == 0.0 {
+            if self.slowest.is_zero() {
+                1.0
+            } else {
+                f64::INFINITY
+            }
+        } else {
+            self.slowest.as_secs_f64() / others
+        }
+    }
+
+    fn is_dominated(&self) -> bool {
+        self.slowest >= STRAGGLER_MIN_DURATION && self.ratio() >= STRAGGLER_RATIO
+    }
+
+    fn new(values: impl Iterator<Item = (WorkerId, Duration)> + Clone) -> Self {
+        let (slowest_worker, slowest) = values
+            .clone()
+            .max_by_key(|&(worker, value)| (value, worker))
+            .unwrap_or_default();
+        let fastest = values
+            .clone()
+            .map(|(_, value)| value)
+            .min()
+            .unwrap_or_default();
+
+        let (others_total, others) = values
+            .filter(|&(worker, _)| worker != slowest_worker)
+            .fold((Duration::ZERO, 0), |(total, count), (_, value)| {
+                (total + value, count + 1)
+            });
+
+        Self {
+            slowest_worker,
+            slowest,
+            fastest,
+            others_average: others_total.checked_div(others).unwrap_or_default(),
+        }
+    }
+}
+
+pub fn operator_stragglers<S>(
+    scope: &mut S,
+    aggregated_operator_stats: &Collection<S, AggregatedOperatorStats, Diff>,
+) -> Collection<S, OperatorStraggler, Diff>
+where
+    S: Scope<Timestamp = Duration>,
+{
+    scope.region_named("Operator Stragglers", |region| {
+        aggregated_operator_stats
+            .enter_region(region)
+            .flat_map(|stats| {
+                // Operators that only exist on a single worker can't straggle
+                if stats.workers() < 2 {
+                    return None;
+                }
+
+                let totals = stats
+                    .per_worker
+                    .iter()
+                    .map(|worker| (worker.worker, worker.total));
+                let maxes = stats
+                    .per_worker
+                    .iter()
+                    .map(|worker| (worker.worker, worker.max));
+
+                let straggler = OperatorStraggler {
+                    workers: stats.workers(),
+                    total: WorkerSpread::new(totals),
+                    max: WorkerSpread::new(maxes),
+                    addr: stats.addr,
+                    name: stats.name,
+                };
+
+                if straggler.total_dominated() || straggler.max_dominated() {
+                    Some(straggler)
+                } else {
+                    None
+                }
+            })
+            .leave_region()
+    })
+}
@@ -3,22 +3,52 @@
 mod proptest_utils;
 mod proptests;
 
-use crate::dataflow::{
-    operators::DelayExt,
-    utils::granulate,
-    worker_timeline::{
-        collect_differential_events, process_timely_event, EventKind, EventProcessor, TimelineEvent,
+use crate::{
+    dataflow::{
+        channel_stats::{
+            channel_latencies, channel_message_stats, pair_message_event, prune_pending_messages,
+            worker_exchanges, ChannelLatency, ChannelMessageStats, WorkerExchange,
+        },
+        differential::ArrangementStats,
+        frontiers::{
+            frontier_timelines, EpochCompletion, FrontierStep, FrontierTimeline,
+            FrontierTimelineSink,
+        },
+        hang_detection::{detect_hangs, HangKind, HangSuspect, HotOperator},
+        operator_stats::{aggregate_operator_stats, OperatorStats},
+        operators::DelayExt,
+        reachability::{pointstamp_timelines, PointstampLocation, PointstampTimeline},
+        spill::SpillBuffer,
+        stragglers::{operator_stragglers, OperatorStraggler, WorkerSpread},
+        time_series::operator_time_series,
+        utilization::worker_time_buckets,
+        utils::granulate,
+        worker_timeline::{
+            bound_timeline, collapse_events, collect_differential_events, process_timely_event,
+            EventKind, EventProcessor, TimelineEvent,
+        },
+        TimelyLogBundle,
     },
-    TimelyLogBundle,
+    ui::{Histogram, OperatorTimeBucket, Percentiles, WorkerTimeBucket},
 };
 use ddshow_types::{
     differential_logging::{DifferentialEvent, MergeEvent, MergeShortfall},
-    timely_logging::{ScheduleEvent, StartStop, TimelyEvent},
-    OperatorId, WorkerId,
+    progress_logging::{CapabilityUpdate, MessageUpdate, TimelyProgressEvent},
+    reachability_logging::{PointstampUpdate, SourceUpdate, TrackerEvent},
+    timely_logging::{
+        MessagesEvent, OperatesEvent, ParkEvent, PushProgressEvent, ScheduleEvent, StartStop,
+        TimelyEvent,
+    },
+    ChannelId, OperatorAddr, OperatorId, PortId, WorkerId,
+};
+use differential_dataflow::{
+    difference::Present,
+    operators::arrange::{ArrangeByKey, ArrangeBySelf},
+    AsCollection, Collection,
 };
-use differential_dataflow::{difference::Present, AsCollection, Collection};
 use std::{
     collections::HashMap,
+    num::NonZeroUsize,
     sync::{mpsc, Arc, Mutex},
     time::Duration,
 };
@@ -32,6 +62,12 @@ use tracing_subscriber::{
     EnvFilter,
 };
 
+/// The granularity ddshow uses by default
+pub(crate) const GRANULARITY: Duration = Duration::from_secs(5);
+
+/// The extraction fuel ddshow uses by default
+pub(crate) const EXTRACTION_FUEL: usize = 1_000_000;
+
 #[test]
 fn timely_event_association() {
     init_test_logging();
@@ -76,7 +112,7 @@ fn timely_event_association() {
 
     let data = recv.extract();
     let expected = vec![(
-        granulate(&Duration::from_nanos(2)),
+        granulate(GRANULARITY)(&Duration::from_nanos(2)),
         vec![(
             TimelineEvent::new(
                 WorkerId::new(0),
@@ -84,7 +120,7 @@ fn timely_event_association() {
                 Duration::from_nanos(1000),
                 Duration::from_nanos(9000),
             ),
-            granulate(&Duration::from_nanos(2)),
+            granulate(GRANULARITY)(&Duration::from_nanos(2)),
             Present,
         )],
     )];
@@ -101,7 +137,7 @@ fn differential_event_association() {
     timely::execute_directly(move |worker| {
         let (mut input, probe) = worker.dataflow(|scope| {
             let (input, stream) = scope.new_input();
-            let partial_events = collect_differential_events(&stream);
+            let partial_events = collect_differential_events(&stream, GRANULARITY);
             partial_events
                 .inner
                 .capture_into(send.lock().unwrap().take().unwrap());
@@ -164,12 +200,12 @@ fn differential_event_association() {
     });
 
     assert_eq!(
-        granulate(&Duration::from_nanos(2)),
-        granulate(&Duration::from_nanos(4)),
+        granulate(GRANULARITY)(&Duration::from_nanos(2)),
+        granulate(GRANULARITY)(&Duration::from_nanos(4)),
         "need to update the layout of ddflow tests",
     );
     let expected = vec![(
-        granulate(&Duration::from_nanos(2)),
+        granulate(GRANULARITY)(&Duration::from_nanos(2)),
         vec![
             (
                 TimelineEvent::new(
@@ -178,7 +214,7 @@ fn differential_event_association() {
                     Duration::from_nanos(1000),
                     Duration::from_nanos(9000),
                 ),
-                granulate(&Duration::from_nanos(2)),
+                granulate(GRANULARITY)(&Duration::from_nanos(2)),
                 Present,
             ),
             (
@@ -188,7 +224,7 @@ fn differential_event_association() {
                     Duration::from_nanos(20_000),
                     Duration::from_nanos(1000),
                 ),
-                granulate(&Duration::from_nanos(4)),
+                granulate(GRANULARITY)(&Duration::from_nanos(4)),
                 Present,
             ),
         ],
@@ -198,6 +234,1489 @@ fn differential_event_association() {
     assert_eq!(data, expected);
 }
 
+#[test]
+fn pointstamp_timeline_accumulation() {
+    init_test_logging();
+
+    let (send, recv) = mpsc::channel();
+    let send = Arc::new(Mutex::new(Some(send)));
+
+    timely::execute_directly(move |worker| {
+        let (mut input, probe) = worker.dataflow(|scope| {
+            let (input, stream) = scope.new_input();
+            let timelines = pointstamp_timelines(scope, &stream, GRANULARITY);
+            timelines
+                .inner
+                .capture_into(send.lock().unwrap().take().unwrap());
+
+            (input, timelines.probe())
+        });
+
+        let update = |diff| {
+            TrackerEvent::from(SourceUpdate::new(
+                OperatorAddr::from(vec![OperatorId::new(0)]),
+                vec![PointstampUpdate::new(
+                    PortId::new(2),
+                    PortId::new(0),
+                    "0".to_owned(),
+                    "u64".to_owned(),
+                    diff,
+                )],
+            ))
+        };
+
+        input.advance_to(Duration::from_nanos(1));
+        input.send((Duration::from_nanos(1000), WorkerId::new(0), update(1)));
+
+        input.advance_to(Duration::from_nanos(2));
+        input.send((Duration::from_nanos(5000), WorkerId::new(0), update(-1)));
+
+        input.advance_to(Duration::from_nanos(3));
+        worker.step_or_park_while(None, || probe.less_than(input.time()));
+    });
+
+    let expected = vec![(
+        granulate(GRANULARITY)(&Duration::from_nanos(2)),
+        vec![(
+            PointstampTimeline {
+                worker: WorkerId::new(0),
+                tracker: OperatorAddr::from(vec![OperatorId::new(0)]),
+                operator_addr: OperatorAddr::from(vec![OperatorId::new(0), OperatorId::new(2)]),
+                port: PortId::new(0),
+                location: PointstampLocation::Source,
+                timestamp: "0".to_owned(),
+                counts: vec![
+                    (Duration::from_nanos(1000), 1),
+                    (Duration::from_nanos(5000), 0),
+                ],
+                held_for: Duration::from_nanos(4000),
+                still_held: false,
+            },
+            granulate(GRANULARITY)(&Duration::from_nanos(2)),
+            1,
+        )],
+    )];
+
+    let data = recv.extract();
+    assert_eq!(data, expected);
+}
+
+#[test]
+fn epoch_completion_from_capabilities() {
+    init_test_logging();
+
+    let (send, recv) = mpsc::channel();
+    let send = Arc::new(Mutex::new(Some(send)));
+
+    timely::execute_directly(move |worker| {
+        let (mut timely_input, mut input, probe) = worker.dataflow(|scope| {
+            let (timely_input, timely_stream) = scope.new_input();
+            let (input, stream) = scope.new_input();
+            let (_, completions) = frontier_timelines(scope, &timely_stream, &stream, GRANULARITY);
+            completions
+                .inner
+                .capture_into(send.lock().unwrap().take().unwrap());
+
+            (timely_input, input, completions.probe())
+        });
+
+        let update = |is_send, seq_no, diff| {
+            TimelyProgressEvent::new(
+                is_send,
+                WorkerId::new(0),
+                ChannelId::new(0),
+                seq_no,
+                OperatorAddr::from(vec![OperatorId::new(0)]),
+                Vec::new(),
+                vec![CapabilityUpdate::new(
+                    PortId::new(2),
+                    PortId::new(0),
+                    "0".to_owned(),
+                    "u64".to_owned(),
+                    diff,
+                )],
+            )
+        };
+
+        let operator = OperatorId::new(5);
+        let schedule = |start_stop| {
+            TimelyEvent::Schedule(ScheduleEvent {
+                id: operator,
+                start_stop,
+            })
+        };
+
+        timely_input.advance_to(Duration::from_nanos(1));
+        timely_input.send((
+            Duration::from_nanos(100),
+            WorkerId::new(0),
+            TimelyEvent::Operates(OperatesEvent::new(
+                operator,
+                OperatorAddr::from(vec![OperatorId::new(0), OperatorId::new(2)]),
+                "Operator".to_owned(),
+            )),
+        ));
+        timely_input.send((
+            Duration::from_nanos(200),
+            WorkerId::new(0),
+            schedule(StartStop::Start),
+        ));
+        timely_input.send((
+            Duration::from_nanos(300),
+            WorkerId::new(0),
+            schedule(StartStop::Stop),
+        ));
+        // The activation that first holds the capability
+        timely_input.send((
+            Duration::from_nanos(800),
+            WorkerId::new(0),
+            schedule(StartStop::Start),
+        ));
+        timely_input.send((
+            Duration::from_nanos(900),
+            WorkerId::new(0),
+            schedule(StartStop::Stop),
+        ));
+
+        input.advance_to(Duration::from_nanos(1));
+        input.send((
+            Duration::from_nanos(1000),
+            WorkerId::new(0),
+            update(true, 0, 1),
+        ));
+        // Received messages shouldn't be counted twice
+        input.send((
+            Duration::from_nanos(1500),
+            WorkerId::new(0),
+            update(false, 0, 1),
+        ));
+
+        timely_input.advance_to(Duration::from_nanos(2));
+        // Later activations don't change when the timestamp was first held
+        timely_input.send((
+            Duration::from_nanos(4000),
+            WorkerId::new(0),
+            schedule(StartStop::Start),
+        ));
+
+        input.advance_to(Duration::from_nanos(2));
+        input.send((
+            Duration::from_nanos(5000),
+            WorkerId::new(0),
+            update(true, 1, -1),
+        ));
+
+        timely_input.advance_to(Duration::from_nanos(3));
+        input.advance_to(Duration::from_nanos(3));
+        worker.step_or_park_while(None, || probe.less_than(input.time()));
+    });
+
+    let completion = EpochCompletion {
+        worker: WorkerId::new(0),
+        operator_addr: OperatorAddr::from(vec![OperatorId::new(0), OperatorId::new(2)]),
+        port: PortId::new(0),
+        location: PointstampLocation::Source,
+        timestamp: "0".to_owned(),
+        first_activation: Duration::from_nanos(800),
+        completed: Duration::from_nanos(5000),
+    };
+    assert_eq!(completion.lag(), Duration::from_nanos(4200));
+
+    let expected = vec![(
+        granulate(GRANULARITY)(&Duration::from_nanos(2)),
+        vec![(
+            completion,
+            granulate(GRANULARITY)(&Duration::from_nanos(5000)),
+            1,
+        )],
+    )];
+
+    let data = recv.extract();
+    assert_eq!(data, expected);
+}
+
+#[test]
+fn frontier_steps_hold_messages_in_timestamp_order() {
+    init_test_logging();
+
+    let (send, recv) = mpsc::channel();
+    let send = Arc::new(Mutex::new(Some(send)));
+
+    timely::execute_directly(move |worker| {
+        let (mut timely_input, mut input, probe) = worker.dataflow(|scope| {
+            let (timely_input, timely_stream) = scope.new_input();
+            let (input, stream) = scope.new_input();
+            let (steps, _) = frontier_timelines(scope, &timely_stream, &stream, GRANULARITY);
+            steps
+                .inner
+                .capture_into(send.lock().unwrap().take().unwrap());
+
+            (timely_input, input, steps.probe())
+        });
+
+        let update = |seq_no, internal: &[(&str, i64)], messages: &[(&str, i64)]| {
+            TimelyProgressEvent::new(
+                true,
+                WorkerId::new(0),
+                ChannelId::new(0),
+                seq_no,
+                OperatorAddr::from(vec![OperatorId::new(0)]),
+                messages
+                    .iter()
+                    .map(|&(timestamp, diff)| {
+                        MessageUpdate::new(
+                            PortId::new(3),
+                            PortId::new(0),
+                            timestamp.to_owned(),
+                            "u64".to_owned(),
+                            diff,
+                        )
+                    })
+                    .collect(),
+                internal
+                    .iter()
+                    .map(|&(timestamp, diff)| {
+                        CapabilityUpdate::new(
+                            PortId::new(2),
+                            PortId::new(0),
+                            timestamp.to_owned(),
+                            "u64".to_owned(),
+                            diff,
+                        )
+                    })
+                    .collect(),
+            )
+        };
+
+        input.advance_to(Duration::from_nanos(1));
+        input.send((
+            Duration::from_nanos(1000),
+            WorkerId::new(0),
+            update(0, &[("10", 1)], &[("9", 1), ("10", 1)]),
+        ));
+        input.send((
+            Duration::from_nanos(2000),
+            WorkerId::new(0),
+            update(1, &[], &[("9", -1)]),
+        ));
+
+        timely_input.advance_to(Duration::from_nanos(2));
+        input.advance_to(Duration::from_nanos(2));
+        worker.step_or_park_while(None, || probe.less_than(input.time()));
+    });
+
+    let mut steps: Vec<_> = recv
+        .extract()
+        .into_iter()
+        .flat_map(|(_, steps)| steps)
+        .map(|(step, _, diff)| {
+            (
+                step.location,
+                step.operator_addr,
+                step.time.as_nanos() as u64,
+                step.timestamps,
+                diff,
+            )
+        })
+        .collect();
+    steps.sort();
+
+    let addr = |node| OperatorAddr::from(vec![OperatorId::new(0), OperatorId::new(node)]);
+    let timestamps = |timestamps: &[&str]| -> Vec<String> {
+        timestamps
+            .iter()
+            .map(|&timestamp| timestamp.to_owned())
+            .collect()
+    };
+
+    // Messages hold their timestamps at the input they were sent to,
+    // and `9` is ordered before `10` rather than after it
+    let expected = vec![
+        (
+            PointstampLocation::Source,
+            addr(2),
+            1000,
+            timestamps(&["10"]),
+            1,
+        ),
+        (
+            PointstampLocation::Target,
+            addr(3),
+            1000,
+            timestamps(&["9"]),
+            1,
+        ),
+        (
+            PointstampLocation::Target,
+            addr(3),
+            1000,
+            timestamps(&["9", "10"]),
+            1,
+        ),
+        (
+            PointstampLocation::Target,
+            addr(3),
+            2000,
+            timestamps(&["10"]),
+            1,
+        ),
+    ];
+    assert_eq!(steps, expected);
+}
+
+#[test]
+fn frontier_steps_form_timelines() {
+    use crate::dataflow::operators::ExtractionSink;
+
+    let step = |port, time, timestamps: &[&str]| FrontierStep {
+        worker: WorkerId::new(0),
+        operator_addr: OperatorAddr::from(vec![OperatorId::new(0)]),
+        port: PortId::new(port),
+        location: PointstampLocation::Source,
+        time: Duration::from_nanos(time),
+        timestamps: timestamps
+            .iter()
+            .map(|&timestamp| timestamp.to_owned())
+            .collect(),
+    };
+
+    let mut sink: FrontierTimelineSink = ExtractionSink::new(None);
+    for (step, diff) in [
+        (step(0, 20, &[]), 1),
+        (step(0, 10, &["0"]), 1),
+        (step(1, 10, &["0"]), 1),
+        // Retracted steps aren't part of the timeline
+        (step(0, 15, &["0", "1"]), 1),
+        (step(0, 15, &["0", "1"]), -1),
+    ] {
+        ExtractionSink::insert(&mut sink, step, diff).unwrap();
+    }
+
+    let mut timelines = sink.into_output().unwrap();
+    timelines.sort();
+
+    let timeline = |port, steps: Vec<(u64, Vec<&str>)>| FrontierTimeline {
+        worker: WorkerId::new(0),
+        operator_addr: OperatorAddr::from(vec![OperatorId::new(0)]),
+        port: PortId::new(port),
+        location: PointstampLocation::Source,
+        steps: steps
+            .into_iter()
+            .map(|(time, timestamps)| {
+                let timestamps = timestamps.into_iter().map(str::to_owned).collect();
+                (Duration::from_nanos(time), timestamps)
+            })
+            .collect(),
+    };
+    let expected = vec![
+        timeline(0, vec![(10, vec!["0"]), (20, Vec::new())]),
+        timeline(1, vec![(10, vec!["0"])]),
+    ];
+    assert_eq!(timelines, expected);
+}
+
+#[test]
+fn channel_message_aggregation() {
+    init_test_logging();
+
+    let (send, recv) = mpsc::channel();
+    let send = Arc::new(Mutex::new(Some(send)));
+
+    timely::execute_directly(move |worker| {
+        let (mut input, probe) = worker.dataflow(|scope| {
+            let (input, stream) = scope.new_input();
+            let stats = channel_message_stats(scope, &stream.as_collection())
+                .as_collection(|_, stats| stats.clone());
+            stats
+                .inner
+                .capture_into(send.lock().unwrap().take().unwrap());
+
+            (input, stats.probe())
+        });
+
+        let channel = (WorkerId::new(0), ChannelId::new(4));
+
+        input.advance_to(Duration::from_nanos(1));
+        for &length in &[10, 2, 10, 6] {
+            input.send((
+                (channel, (WorkerId::new(1), length)),
+                Duration::from_nanos(1),
+                1,
+            ));
+        }
+
+        input.advance_to(Duration::from_nanos(2));
+        worker.step_or_park_while(None, || probe.less_than(input.time()));
+    });
+
+    let expected = vec![(
+        Duration::from_nanos(1),
+        vec![(
+            ChannelMessageStats {
+                channel: ChannelId::new(4),
+                worker: WorkerId::new(0),
+                max: 10,
+                min: 2,
+                total: 28,
+                average: 7,
+                invocations: 4,
+            },
+            Duration::from_nanos(1),
+            1,
+        )],
+    )];
+
+    let data = recv.extract();
+    assert_eq!(data, expected);
+}
+
+#[test]
+fn worker_exchange_matrix() {
+    init_test_logging();
+
+    let (send, recv) = mpsc::channel();
+    let send = Arc::new(Mutex::new(Some(send)));
+
+    timely::execute_directly(move |worker| {
+        let (mut input, probe) = worker.dataflow(|scope| {
+            let (input, stream) = scope.new_input();
+            let exchanges = worker_exchanges(scope, &stream.as_collection());
+            exchanges
+                .inner
+                .capture_into(send.lock().unwrap().take().unwrap());
+
+            (input, exchanges.probe())
+        });
+
+        let message = |source, target, length| {
+            (
+                (
+                    (WorkerId::new(source), ChannelId::new(4)),
+                    (WorkerId::new(target), length),
+                ),
+                Duration::from_nanos(1),
+                1,
+            )
+        };
+
+        input.advance_to(Duration::from_nanos(1));
+        input.send(message(0, 1, 10));
+        input.send(message(0, 1, 5));
+        input.send(message(1, 1, 3));
+
+        input.advance_to(Duration::from_nanos(2));
+        worker.step_or_park_while(None, || probe.less_than(input.time()));
+    });
+
+    let exchange = |source, target, records, messages| {
+        (
+            WorkerExchange {
+                channel: ChannelId::new(4),
+                source: WorkerId::new(source),
+                target: WorkerId::new(target),
+                records,
+                messages,
+            },
+            Duration::from_nanos(1),
+            1,
+        )
+    };
+    let expected = vec![(
+        Duration::from_nanos(1),
+        vec![exchange(0, 1, 15, 2), exchange(1, 1, 3, 1)],
+    )];
+
+    let mut data = recv.extract();
+    for (_, exchanges) in data.iter_mut() {
+        exchanges.sort();
+    }
+    assert_eq!(data, expected);
+}
+
+#[test]
+fn channel_delivery_latency() {
+    init_test_logging();
+
+    let (send, recv) = mpsc::channel();
+    let send = Arc::new(Mutex::new(Some(send)));
+
+    timely::execute_directly(move |worker| {
+        let (mut input, probe) = worker.dataflow(|scope| {
+            let (input, stream) = scope.new_input();
+            let latencies = channel_latencies(scope, &stream, GRANULARITY);
+            latencies
+                .inner
+                .capture_into(send.lock().unwrap().take().unwrap());
+
+            (input, latencies.probe())
+        });
+
+        let event = |seq_no, is_send, time| {
+            let worker = if is_send { 0 } else { 1 };
+            let message = MessagesEvent {
+                is_send,
+                channel: ChannelId::new(4),
+                source: WorkerId::new(0),
+                target: WorkerId::new(1),
+                seq_no,
+                length: 1,
+            };
+
+            (
+                Duration::from_nanos(time),
+                WorkerId::new(worker),
+                TimelyEvent::Messages(message),
+            )
+        };
+
+        input.advance_to(Duration::from_nanos(1));
+        input.send(event(0, true, 10));
+        input.send(event(0, false, 20));
+        input.send(event(1, true, 20));
+        input.send(event(1, false, 40));
+        // Receives can be seen before their sends
+        input.send(event(2, false, 130));
+        input.send(event(2, true, 30));
+        // Messages that were never received are ignored
+        input.send(event(3, true, 40));
+
+        input.advance_to(Duration::from_nanos(2));
+        worker.step_or_park_while(None, || probe.less_than(input.time()));
+    });
+
+    let latencies: Vec<_> = recv
+        .extract()
+        .into_iter()
+        .flat_map(|(_, latencies)| latencies)
+        .map(|(latency, _, diff)| (latency, diff))
+        .collect();
+
+    let expected = vec![(
+        ChannelLatency {
+            channel: ChannelId::new(4),
+            worker: WorkerId::new(1),
+            deliveries: 3,
+            min: Duration::from_nanos(10),
+            max: Duration::from_nanos(100),
+            mean: Duration::from_nanos(43),
+            p50: Duration::from_nanos(20),
+            p90: Duration::from_nanos(100),
+            p99: Duration::from_nanos(100),
+        },
+        1,
+    )];
+    assert_eq!(latencies, expected);
+}
+
+#[test]
+fn unmatched_messages_are_pruned() {
+    let mut pending = HashMap::new();
+    let key = |seq_no| {
+        (
+            ChannelId::new(4),
+            WorkerId::new(0),
+            WorkerId::new(1),
+            seq_no,
+        )
+    };
+
+    // A send that's never received, a receive whose send was never logged and
+    // a send that's received after the others are pruned
+    assert_eq!(
+        pair_message_event(&mut pending, key(0), true, Duration::from_nanos(10)),
+        None,
+    );
+    assert_eq!(
+        pair_message_event(&mut pending, key(1), false, Duration::from_nanos(20)),
+        None,
+    );
+    assert_eq!(
+        pair_message_event(&mut pending, key(2), true, Duration::from_nanos(30)),
+        None,
+    );
+
+    assert_eq!(
+        prune_pending_messages(&mut pending, Duration::from_nanos(30)),
+        2
+    );
+    assert_eq!(pending.len(), 1);
+
+    // Pruned events can't be paired anymore, the ones that are left still can
+    assert_eq!(
+        pair_message_event(&mut pending, key(0), false, Duration::from_nanos(94)),
+        None,
+    );
+    assert_eq!(
+        pair_message_event(&mut pending, key(2), false, Duration::from_nanos(94)),
+        Some((
+            WorkerId::new(1),
+            ChannelId::new(4),
+            Duration::from_nanos(64)
+        )),
+    );
+    assert_eq!(pending.len(), 1);
+}
+
+#[test]
+fn worker_time_allocation() {
+    init_test_logging();
+
+    let (send, recv) = mpsc::channel();
+    let send = Arc::new(Mutex::new(Some(send)));
+
+    timely::execute_directly(move |worker| {
+        let (mut input, probe) = worker.dataflow(|scope| {
+            let (input, stream) = scope.new_input();
+            let buckets = worker_time_buckets(scope, &stream, Duration::from_secs(1), GRANULARITY);
+            buckets
+                .inner
+                .capture_into(send.lock().unwrap().take().unwrap());
+
+            (input, buckets.probe())
+        });
+
+        let schedule = |millis, id, start_stop| {
+            (
+                Duration::from_millis(millis),
+                WorkerId::new(0),
+                TimelyEvent::Schedule(ScheduleEvent {
+                    id: OperatorId::new(id),
+                    start_stop,
+                }),
+            )
+        };
+        let park = |millis, event| {
+            (
+                Duration::from_millis(millis),
+                WorkerId::new(0),
+                TimelyEvent::Park(event),
+            )
+        };
+
+        input.advance_to(Duration::from_nanos(1));
+        input.send(schedule(200, 0, StartStop::Start));
+        // Nested operators aren't counted twice
+        input.send(schedule(300, 1, StartStop::Start));
+        input.send(schedule(400, 1, StartStop::Stop));
+        input.send(schedule(600, 0, StartStop::Stop));
+        // Parking across buckets is split between them
+        input.send(park(700, ParkEvent::Park(None)));
+        input.send(park(1500, ParkEvent::Unpark));
+        input.send(schedule(1600, 0, StartStop::Start));
+        input.send(schedule(1800, 0, StartStop::Stop));
+
+        input.advance_to(Duration::from_nanos(2));
+        worker.step_or_park_while(None, || probe.less_than(input.time()));
+    });
+
+    let buckets: Vec<_> = recv
+        .extract()
+        .into_iter()
+        .flat_map(|(_, buckets)| buckets)
+        .map(|(buckets, _, diff)| (buckets, diff))
+        .collect();
+
+    let bucket = |start, parked, scheduled, overhead| WorkerTimeBucket {
+        start: Duration::from_secs(start),
+        width: Duration::from_secs(1),
+        parked: Duration::from_millis(parked),
+        scheduled: Duration::from_millis(scheduled),
+        overhead: Duration::from_millis(overhead),
+    };
+    let expected = vec![(
+        (
+            WorkerId::new(0),
+            vec![bucket(0, 300, 400, 100), bucket(1, 500, 200, 100)],
+        ),
+        1,
+    )];
+    assert_eq!(buckets, expected);
+}
+
+#[test]
+fn cross_worker_operator_aggregation() {
+    init_test_logging();
+
+    let (send, recv) = mpsc::channel();
+    let send = Arc::new(Mutex::new(Some(send)));
+
+    timely::execute_directly(move |worker| {
+        let (mut stats_input, mut info_input, probe) = worker.dataflow(|scope| {
+            let (stats_input, stats) = scope.new_input();
+            let (info_input, info) = scope.new_input();
+
+            let info = info.as_collection();
+            let operator_ids = info
+                .map(|(key, (addr, _)): (_, (OperatorAddr, String))| (key, addr))
+                .arrange_by_key();
+            let operator_names = info
+                .map(|(key, (_, name)): (_, (OperatorAddr, String))| (key, name))
+                .arrange_by_key();
+
+            let aggregated = aggregate_operator_stats(
+                scope,
+                &stats.as_collection(),
+                &operator_ids,
+                &operator_names,
+            );
+            aggregated
+                .inner
+                .capture_into(send.lock().unwrap().take().unwrap());
+
+            (stats_input, info_input, aggregated.probe())
+        });
+
+        stats_input.advance_to(Duration::from_nanos(1));
+        info_input.advance_to(Duration::from_nanos(1));
+
+        // The same operator has a different id on each worker
+        let workers = [
+            (2, 10, 4, 1, Some((100, 10, 3))),
+            (5, 20, 6, 2, Some((50, 5, 2))),
+        ];
+        for &(worker, total, activations, id, arrangement) in workers.iter() {
+            let key = (WorkerId::new(worker), OperatorId::new(id));
+            let stats = OperatorStats {
+                id: OperatorId::new(id),
+                worker: WorkerId::new(worker),
+                max: Duration::from_millis(total / 2),
+                min: Duration::from_millis(worker as u64),
+                total: Duration::from_millis(total),
+                activations,
+                activation_histogram: Histogram::from_sorted_buckets(vec![
+                    (1, 1),
+                    (2, activations as u64),
+                ]),
+                arrangement_size: arrangement.map(|(max_size, min_size, batches)| {
+                    ArrangementStats {
+                        max_size,
+                        min_size,
+                        batches,
+                        batch_histogram: Histogram::from_sorted_buckets(vec![(
+                            max_size as u64,
+                            batches as u64,
+                        )]),
+                    }
+                }),
+                ..Default::default()
+            };
+
+            stats_input.send(((key, stats), Duration::from_nanos(1), 1));
+            info_input.send((
+                (
+                    key,
+                    (
+                        OperatorAddr::from(vec![OperatorId::new(0), OperatorId::new(3)]),
+                        "Join".to_owned(),
+                    ),
+                ),
+                Duration::from_nanos(1),
+                1,
+            ));
+        }
+
+        stats_input.advance_to(Duration::from_nanos(2));
+        info_input.advance_to(Duration::from_nanos(2));
+        worker.step_or_park_while(None, || probe.less_than(stats_input.time()));
+    });
+
+    let aggregated: Vec<_> = recv
+        .extract()
+        .into_iter()
+        .flat_map(|(_, stats)| stats)
+        .map(|(stats, _, diff)| (stats, diff))
+        .collect();
+
+    assert_eq!(aggregated.len(), 1);
+    let (stats, diff) = &aggregated[0];
+    assert_eq!(*diff, 1);
+    assert_eq!(stats.name, "Join");
+    assert_eq!(
+        stats.addr,
+        OperatorAddr::from(vec![OperatorId::new(0), OperatorId::new(3)]),
+    );
+    assert_eq!(stats.workers(), 2);
+    assert_eq!(stats.id(), None);
+    assert_eq!(stats.total, Duration::from_millis(30));
+    assert_eq!(stats.activations, 10);
+    assert_eq!(stats.average, Duration::from_millis(3));
+    assert_eq!(stats.max, Duration::from_millis(10));
+    assert_eq!(stats.min, Duration::from_millis(2));
+    assert_eq!(
+        stats.arrangement_size,
+        Some(ArrangementStats {
+            max_size: 100,
+            min_size: 5,
+            batches: 5,
+            batch_histogram: Histogram::from_sorted_buckets(vec![(50, 2), (100, 3)]),
+        }),
+    );
+    assert_eq!(
+        stats.activation_histogram,
+        Histogram::from_sorted_buckets(vec![(1, 2), (2, 10)]),
+    );
+    assert_eq!(
+        stats
+            .per_worker
+            .iter()
+            .map(|stats| stats.worker)
+            .collect::<Vec<_>>(),
+        vec![WorkerId::new(2), WorkerId::new(5)],
+    );
+}
+
+#[test]
+fn histogram_percentiles() {
+    // Values are kept to their five most significant bits
+    assert_eq!(Histogram::bucket(31), 31);
+    assert_eq!(Histogram::bucket(1000), 992);
+    assert_eq!(Histogram::bucket(1_000_000), 983_040);
+
+    let mut fast = Histogram::new();
+    for value in 1..=90 {
+        fast.record(value, 1);
+    }
+
+    let mut slow = Histogram::new();
+    slow.record(1000, 9);
+    slow.record(1_000_000, 1);
+
+    let mut histogram = fast.clone();
+    histogram.merge(&slow);
+    assert_eq!(histogram.count(), 100);
+    assert_eq!(
+        histogram.percentiles(),
+        Percentiles {
+            p50: 50,
+            p90: 88,
+            p99: 992,
+        },
+    );
+    assert_eq!(histogram.percentile(100), 983_040);
+
+    // Merging is commutative
+    slow.merge(&fast);
+    assert_eq!(slow, histogram);
+
+    assert_eq!(Histogram::new().percentile(50), 0);
+}
+
+#[test]
+fn operator_activity_over_time() {
+    init_test_logging();
+
+    let (send, recv) = mpsc::channel();
+    let send = Arc::new(Mutex::new(Some(send)));
+
+    let addr = OperatorAddr::from(vec![OperatorId::new(0), OperatorId::new(4)]);
+    let operator_addr = addr.clone();
+
+    timely::execute_directly(move |worker| {
+        let (mut input, mut ids_input, probe) = worker.dataflow(|scope| {
+            let (input, activations) = scope.new_input();
+            let (ids_input, operator_ids) = scope.new_input();
+
+            let series = operator_time_series(
+                scope,
+                &activations.as_collection(),
+                None,
+                &operator_ids.as_collection().arrange_by_key(),
+                Duration::from_secs(1),
+            );
+            series
+                .inner
+                .capture_into(send.lock().unwrap().take().unwrap());
+
+            (input, ids_input, series.probe())
+        });
+
+        input.advance_to(Duration::from_nanos(1));
+        ids_input.advance_to(Duration::from_nanos(1));
+
+        // The same operator has a different id on each worker
+        let (first, second) = (
+            (WorkerId::new(0), OperatorId::new(4)),
+            (WorkerId::new(1), OperatorId::new(7)),
+        );
+        for &operator in [first, second].iter() {
+            ids_input.send((
+                (operator, operator_addr.clone()),
+                Duration::from_nanos(1),
+                1,
+            ));
+        }
+
+        let activations = [
+            (
+                first,
+                Duration::from_millis(100),
+                Duration::from_millis(200),
+            ),
+            // Straddles the first and second buckets
+            (
+                first,
+                Duration::from_millis(900),
+                Duration::from_millis(300),
+            ),
+            (
+                first,
+                Duration::from_millis(3500),
+                Duration::from_millis(50),
+            ),
+            (
+                second,
+                Duration::from_millis(1500),
+                Duration::from_millis(100),
+            ),
+        ];
+        for &(operator, start, duration) in activations.iter() {
+            input.send(((operator, (start, duration)), Duration::from_nanos(1), 1));
+        }
+
+        input.advance_to(Duration::from_nanos(2));
+        ids_input.advance_to(Duration::from_nanos(2));
+        worker.step_or_park_while(None, || probe.less_than(input.time()));
+    });
+
+    let series: Vec<_> = recv
+        .extract()
+        .into_iter()
+        .flat_map(|(_, series)| series)
+        .map(|(series, _, diff)| (series, diff))
+        .collect();
+
+    let bucket = |start, total, activations| OperatorTimeBucket {
+        start: Duration::from_secs(start),
+        width: Duration::from_secs(1),
+        total: Duration::from_millis(total),
+        activations,
+        batched_records: 0,
+    };
+    let expected = vec![(
+        (
+            addr,
+            vec![bucket(0, 300, 2), bucket(1, 300, 1), bucket(3, 50, 1)],
+        ),
+        1,
+    )];
+    assert_eq!(series, expected);
+}
+
+#[test]
+fn bounded_timeline_keeps_slowest_events() {
+    init_test_logging();
+
+    let (send, recv) = mpsc::channel();
+    let send = Arc::new(Mutex::new(Some(send)));
+
+    timely::execute_directly(move |worker| {
+        let (mut input, probe) = worker.dataflow(|scope| {
+            let (input, events) = scope.new_input();
+
+            let bounded = bound_timeline(
+                scope,
+                &events.as_collection(),
+                NonZeroUsize::new(2).unwrap(),
+                Duration::from_nanos(10),
+            );
+            bounded
+                .inner
+                .capture_into(send.lock().unwrap().take().unwrap());
+
+            (input, bounded.probe())
+        });
+
+        let event = |start, duration| {
+            TimelineEvent::new(
+                WorkerId::new(0),
+                EventKind::activation(OperatorId::new(0)),
+                Duration::from_nanos(start),
+                Duration::from_nanos(duration),
+            )
+        };
+
+        let events = [(0, 5), (10, 1), (12, 1), (20, 9), (1000, 1)];
+        input.advance_to(Duration::from_nanos(1));
+        for &(start, duration) in events.iter() {
+            input.send((event(start, duration), *input.time(), Present));
+        }
+
+        // Events are bounded over the whole run, so nothing is output until
+        // the input completes even after the first timestamp has passed
+        input.advance_to(Duration::from_nanos(2));
+        for _ in 0..10 {
+            worker.step();
+        }
+        assert!(probe.less_than(&Duration::from_nanos(2)));
+
+        input.send((event(2000, 1), *input.time(), Present));
+        input.close();
+        worker.step_or_park_while(None, || !probe.done());
+    });
+
+    let mut events: Vec<_> = recv
+        .extract()
+        .into_iter()
+        .flat_map(|(_, events)| events)
+        .map(|(event, time, _)| {
+            (
+                time.as_nanos() as u64,
+                event.start_time,
+                event.duration,
+                event.collapsed_events,
+            )
+        })
+        .collect();
+    events.sort_unstable();
+
+    // The two slowest events are kept as-is and the nearby fast events are collapsed
+    // into a span covering both of them. The far off events from both timestamps add
+    // up to too many spans, so the oldest ones are folded together
+    let expected = vec![
+        (1, 0, 5, 1),
+        (1, 10, 991, 3),
+        (1, 20, 9, 1),
+        (1, 2000, 1, 1),
+    ];
+    assert_eq!(events, expected);
+}
+
+#[test]
+fn nearby_timeline_events_are_fused() {
+    init_test_logging();
+
+    let (send, recv) = mpsc::channel();
+    let send = Arc::new(Mutex::new(Some(send)));
+
+    timely::execute_directly(move |worker| {
+        let mut input = worker.dataflow(|scope| {
+            let (input, events) = scope.new_input();
+
+            collapse_events(&events.as_collection(), Duration::from_nanos(2))
+                .inner
+                .capture_into(send.lock().unwrap().take().unwrap());
+
+            input
+        });
+
+        let event = |start, duration| {
+            TimelineEvent::new(
+                WorkerId::new(0),
+                EventKind::activation(OperatorId::new(0)),
+                Duration::from_nanos(start),
+                Duration::from_nanos(duration),
+            )
+        };
+
+        input.advance_to(Duration::from_nanos(1));
+        for &(start, duration) in [(0, 5), (6, 2), (100, 1)].iter() {
+            input.send((event(start, duration), *input.time(), Present));
+        }
+    });
+
+    let mut events: Vec<_> = recv
+        .extract()
+        .into_iter()
+        .flat_map(|(_, events)| events)
+        .map(|(event, _, _)| (event.start_time, event.duration, event.collapsed_events))
+        .collect();
+    events.sort_unstable();
+
+    // The first two events are within the margin of each other so they're
+    // fused together, the far off one is left on its own
+    assert_eq!(events, vec![(0, 7, 2), (100, 1, 1)]);
+}
+
+#[test]
+fn operator_straggler_detection() {
+    init_test_logging();
+
+    let (send, recv) = mpsc::channel();
+    let send = Arc::new(Mutex::new(Some(send)));
+
+    timely::execute_directly(move |worker| {
+        let (mut stats_input, mut info_input, probe) = worker.dataflow(|scope| {
+            let (stats_input, stats) = scope.new_input();
+            let (info_input, info) = scope.new_input();
+
+            let info = info.as_collection();
+            let operator_ids = info
+                .map(|(key, (addr, _)): (_, (OperatorAddr, String))| (key, addr))
+                .arrange_by_key();
+            let operator_names = info
+                .map(|(key, (_, name)): (_, (OperatorAddr, String))| (key, name))
+                .arrange_by_key();
+
+            let aggregated = aggregate_operator_stats(
+                scope,
+                &stats.as_collection(),
+                &operator_ids,
+                &operator_names,
+            );
+            let stragglers = operator_stragglers(scope, &aggregated);
+            stragglers
+                .inner
+                .capture_into(send.lock().unwrap().take().unwrap());
+
+            (stats_input, info_input, stragglers.probe())
+        });
+
+        stats_input.advance_to(Duration::from_nanos(1));
+        info_input.advance_to(Duration::from_nanos(1));
+
+        let operators = [
+            ("Map", [10, 1, 1], [5, 1, 1]),
+            ("Balanced", [2, 2, 2], [1, 1, 1]),
+        ];
+        for (id, (name, totals, maxes)) in operators.iter().enumerate() {
+            for worker in 0..3 {
+                let key = (WorkerId::new(worker), OperatorId::new(id));
+                let stats = OperatorStats {
+                    id: OperatorId::new(id),
+                    worker: WorkerId::new(worker),
+                    max: Duration::from_millis(maxes[worker]),
+                    total: Duration::from_millis(totals[worker]),
+                    ..Default::default()
+                };
+
+                stats_input.send(((key, stats), Duration::from_nanos(1), 1));
+                info_input.send((
+                    (
+                        key,
+                        (
+                            OperatorAddr::from_elem(OperatorId::new(id)),
+                            (*name).to_owned(),
+                        ),
+                    ),
+                    Duration::from_nanos(1),
+                    1,
+                ));
+            }
+        }
+
+        stats_input.advance_to(Duration::from_nanos(2));
+        info_input.advance_to(Duration::from_nanos(2));
+        worker.step_or_park_while(None, || probe.less_than(stats_input.time()));
+    });
+
+    let stragglers: Vec<_> = recv
+        .extract()
+        .into_iter()
+        .flat_map(|(_, stragglers)| stragglers)
+        .map(|(straggler, _, diff)| (straggler, diff))
+        .collect();
+
+    let expected = vec![(
+        OperatorStraggler {
+            addr: OperatorAddr::from_elem(OperatorId::new(0)),
+            name: "Map".to_owned(),
+            workers: 3,
+            total: WorkerSpread {
+                slowest_worker: WorkerId::new(0),
+                slowest: Duration::from_millis(10),
+                fastest: Duration::from_millis(1),
+                others_average: Duration::from_millis(1),
+            },
+            max: WorkerSpread {
+                slowest_worker: WorkerId::new(0),
+                slowest: Duration::from_millis(5),
+                fastest: Duration::from_millis(1),
+                others_average: Duration::from_millis(1),
+            },
+        },
+        1,
+    )];
+    assert_eq!(stragglers, expected);
+}
+
+#[test]
+fn granulate_rounds_up_to_granularity() {
+    let granulate_millis = granulate(Duration::from_millis(100));
+    assert_eq!(
+        granulate_millis(&Duration::from_millis(1)),
+        Duration::from_millis(100),
+    );
+    assert_eq!(
+        granulate_millis(&Duration::from_millis(250)),
+        Duration::from_millis(300),
+    );
+    // Times on a window's boundary are moved into the next window
+    assert_eq!(
+        granulate_millis(&Duration::from_millis(300)),
+        Duration::from_millis(400),
+    );
+
+    assert_eq!(
+        granulate(GRANULARITY)(&Duration::from_millis(250)),
+        Duration::from_secs(5),
+    );
+
+    // Timestamps past what nanoseconds can fit in a `u64` aren't truncated
+    let late = Duration::from_secs(u64::MAX / 1_000_000_000 + 7);
+    assert_eq!(granulate_millis(&late), late + Duration::from_millis(100));
+    assert_eq!(granulate_millis(&Duration::MAX), Duration::MAX);
+}
+
+#[test]
+fn spill_buffer_streams_spilled_data() {
+    // Each element takes 8 bytes, so the buffer spills every four pushes
+    let mut buffer = SpillBuffer::<u64>::new(Some(32));
+    for value in 0..10 {
+        buffer.push(value).unwrap();
+    }
+
+    assert!(buffer.has_spilled());
+    assert_eq!(buffer.len(), 10);
+
+    let values = buffer.iter().collect::<anyhow::Result<Vec<_>>>().unwrap();
+    assert_eq!(values, (0..10).collect::<Vec<_>>());
+    assert_eq!(
+        serde_json::to_string(&buffer).unwrap(),
+        "[0,1,2,3,4,5,6,7,8,9]",
+    );
+
+    // Buffers without a limit never touch the disk
+    let mut buffer = SpillBuffer::<u64>::new(None);
+    for value in 0..10 {
+        buffer.push(value).unwrap();
+    }
+    assert!(!buffer.has_spilled());
+}
+
+#[test]
+fn spill_buffer_counts_heap_data() {
+    // Each string owns 100 bytes on the heap, which is what fills the buffer
+    let mut buffer = SpillBuffer::<String>::new(Some(250));
+    buffer.push("a".repeat(100)).unwrap();
+    buffer.push("b".repeat(100)).unwrap();
+    assert!(!buffer.has_spilled());
+
+    buffer.push("c".repeat(100)).unwrap();
+    assert!(buffer.has_spilled());
+
+    let values = buffer.iter().collect::<anyhow::Result<Vec<_>>>().unwrap();
+    assert_eq!(
+        values,
+        vec!["a".repeat(100), "b".repeat(100), "c".repeat(100)],
+    );
+}
+
+#[test]
+fn spill_buffer_sink_removes_duplicates() {
+    use crate::dataflow::operators::ExtractionSink;
+    use differential_dataflow::difference::Present;
+    use std::collections::HashMap;
+
+    let values = [3u64, 1, 3, 2, 1, 3];
+
+    // Timeline events used to be extracted into a `HashMap`, which only kept one
+    // copy of each event, so spill buffers have to do the same
+    let mut map: HashMap<u64, Present> = ExtractionSink::new(None);
+    let mut buffer: SpillBuffer<u64> = ExtractionSink::new(None);
+    for &value in values.iter() {
+        ExtractionSink::insert(&mut map, value, Present).unwrap();
+        ExtractionSink::insert(&mut buffer, value, Present).unwrap();
+    }
+
+    let mut expected = map.into_output().unwrap();
+    expected.sort_unstable();
+
+    let buffer = buffer.into_output().unwrap();
+    assert_eq!(buffer.len(), 3);
+    assert_eq!(
+        buffer.iter().collect::<anyhow::Result<Vec<_>>>().unwrap(),
+        expected,
+    );
+}
+
+#[test]
+fn spill_buffer_removes_duplicates_across_runs() {
+    // Each element takes 8 bytes, so every run holds at most four distinct elements
+    // and every value ends up spilled within several different runs
+    let mut buffer = SpillBuffer::<u64>::new(Some(32));
+    for value in (0..40).map(|idx| (idx * 7) % 10) {
+        buffer.push(value).unwrap();
+    }
+    assert!(buffer.has_spilled());
+
+    buffer.finish().unwrap();
+    assert_eq!(buffer.len(), 10);
+    assert_eq!(
+        buffer.iter().collect::<anyhow::Result<Vec<_>>>().unwrap(),
+        (0..10).collect::<Vec<_>>(),
+    );
+
+    // Finishing a merged buffer again leaves it as it is
+    buffer.finish().unwrap();
+    assert_eq!(
+        serde_json::to_string(&buffer).unwrap(),
+        "[0,1,2,3,4,5,6,7,8,9]",
+    );
+}
+
+/// Runs hang detection over a scope `[0, 1]` holding the operator `[0, 1, 2]`, which
+/// is scheduled half a second into each of `active_steps` while the scope receives
+/// frontier changes a quarter of a second into each of `progress_steps`
+fn detected_hangs(active_steps: &[u64], progress_steps: &[u64]) -> Vec<HangSuspect> {
+    let (active_steps, progress_steps) = (active_steps.to_vec(), progress_steps.to_vec());
+    let (send, recv) = mpsc::channel();
+    let send = Arc::new(Mutex::new(Some(send)));
+
+    timely::execute_directly(move |worker| {
+        let (mut events, mut activations, mut lifespans, mut operators, probe) =
+            worker.dataflow(|scope| {
+                let (events_input, events) = scope.new_input();
+                let (activations_input, activations) = scope.new_input();
+                let (lifespans_input, lifespans) = scope.new_input();
+                let (operators_input, operators) = scope.new_input();
+
+                let operators = operators.as_collection();
+                let operator_ids = operators
+                    .map(|(key, (addr, _)): (_, (OperatorAddr, String))| (key, addr))
+                    .arrange_by_key();
+                let operator_names = operators
+                    .map(|(key, (_, name)): (_, (OperatorAddr, String))| (key, name))
+                    .arrange_by_key();
+                // Everything but the leaf operator is a subgraph
+                let subgraph_ids = operators
+                    .filter(|(_, (addr, _))| addr.len() < 3)
+                    .map(|(key, _)| key)
+                    .arrange_by_self();
+
+                let hangs = detect_hangs(
+                    scope,
+                    &events,
+                    &activations.as_collection(),
+                    &lifespans.as_collection(),
+                    &operator_ids,
+                    &operator_names,
+                    &subgraph_ids,
+                    GRANULARITY,
+                );
+                hangs
+                    .inner
+                    .capture_into(send.lock().unwrap().take().unwrap());
+
+                (
+                    events_input,
+                    activations_input,
+                    lifespans_input,
+                    operators_input,
+                    hangs.probe(),
+                )
+            });
+
+        let time = Duration::from_nanos(1);
+        events.advance_to(time);
+        activations.advance_to(time);
+        lifespans.advance_to(time);
+        operators.advance_to(time);
+
+        let worker_id = WorkerId::new(0);
+        for (id, name) in ["Dataflow", "Iterate", "Map"].iter().enumerate() {
+            let addr = OperatorAddr::from((0..=id).map(OperatorId::new).collect::<Vec<_>>());
+            operators.send((
+                ((worker_id, OperatorId::new(id)), (addr, (*name).to_owned())),
+                time,
+                1,
+            ));
+        }
+
+        for &step in active_steps.iter() {
+            let start = Duration::from_millis(step * 1000 + 500);
+            activations.send((
+                (
+                    (worker_id, OperatorId::new(2)),
+                    (start, Duration::from_millis(100)),
+                ),
+                time,
+                1,
+            ));
+        }
+
+        for &step in progress_steps.iter() {
+            events.send((
+                Duration::from_millis(step * 1000 + 250),
+                worker_id,
+                TimelyEvent::PushProgress(PushProgressEvent {
+                    op_id: OperatorId::new(1),
+                }),
+            ));
+        }
+
+        let time = Duration::from_nanos(2);
+        events.advance_to(time);
+        activations.advance_to(time);
+        lifespans.advance_to(time);
+        operators.advance_to(time);
+        worker.step_or_park_while(None, || probe.less_than(&time));
+    });
+
+    let mut hangs = HashMap::new();
+    for (hang, _, diff) in recv.extract().into_iter().flat_map(|(_, hangs)| hangs) {
+        *hangs.entry(hang).or_insert(0) += diff;
+    }
+
+    hangs
+        .into_iter()
+        .filter(|&(_, diff)| diff != 0)
+        .map(|(hang, diff)| {
+            assert_eq!(diff, 1);
+            hang
+        })
+        .collect()
+}
+
+#[test]
+fn stalled_scopes_are_detected() {
+    init_test_logging();
+
+    // The scope stops receiving frontier changes after the first step while its
+    // operator keeps being scheduled, with gaps that span entire steps
+    let hangs = detected_hangs(&[0, 1, 3, 4, 6, 7], &[0]);
+
+    let expected = vec![HangSuspect {
+        worker: WorkerId::new(0),
+        scope: OperatorId::new(1),
+        scope_addr: OperatorAddr::from(vec![OperatorId::new(0), OperatorId::new(1)]),
+        scope_name: "Iterate".to_owned(),
+        kind: HangKind::Livelock,
+        stalled_since: Duration::from_secs(1),
+        stalled_for: Duration::from_secs(7),
+        last_progress: Some(Duration::from_secs(0)),
+        hottest_operators: vec![HotOperator {
+            id: OperatorId::new(2),
+            addr: OperatorAddr::from(vec![
+                OperatorId::new(0),
+                OperatorId::new(1),
+                OperatorId::new(2),
+            ]),
+            name: "Map".to_owned(),
+            activations: 5,
+            total_runtime: Duration::from_millis(500),
+        }],
+    }];
+    assert_eq!(hangs, expected);
+}
+
+#[test]
+fn progressing_scopes_are_not_stalled() {
+    init_test_logging();
+
+    // The scope's frontier advanced within the window
+    assert_eq!(
+        detected_hangs(&[0, 1, 2, 3, 4, 5, 6, 7], &[0, 4]),
+        Vec::new()
+    );
+
+    // The scope hasn't been scheduled within the window
+    assert_eq!(detected_hangs(&[0, 1, 2], &[0, 6]), Vec::new());
+    assert_eq!(detected_hangs(&[0, 1, 2, 8], &[0, 3]), Vec::new());
+
+    // The scope hasn't been running for long enough to fill the window
+    assert_eq!(detected_hangs(&[0, 1, 2, 3], &[]), Vec::new());
+}
+
 pub(crate) fn init_test_logging() {
     let env_layer = EnvFilter::new("debug,ddshow::dataflow::worker_timeline=error");
     let fmt_layer = tracing_subscriber::fmt::layer()
@@ -255,5 +1774,5 @@ where
             },
         )
         .as_collection()
-        .delay_fast(granulate)
+        .delay_fast(granulate(GRANULARITY))
 }
@@ -1,5 +1,6 @@
 use crate::dataflow::{
     operators::ActivateCapabilitySet,
+    tests::GRANULARITY,
     utils::granulate,
     worker_timeline::{EventKind, TimelineEvent},
 };
@@ -34,7 +35,7 @@ pub struct EventPair<E> {
 impl<E> EventPair<E> {
     fn build_expected(&self, event: EventKind) -> ExpectedEvent {
         (
-            granulate(&self.end.recv_timestamp),
+            granulate(GRANULARITY)(&self.end.recv_timestamp),
             (
                 TimelineEvent::new(
                     self.worker,
@@ -42,7 +43,7 @@ impl<E> EventPair<E> {
                     self.start.timestamp,
                     self.end.timestamp - self.start.timestamp,
                 ),
-                granulate(&self.end.recv_timestamp),
+                granulate(GRANULARITY)(&self.end.recv_timestamp),
                 Present,
             ),
         )
@@ -3,6 +3,7 @@ use crate::dataflow::{
     tests::{
         collect_timely_events, init_test_logging,
         proptest_utils::{gen_event_pair, EventPair, Expected},
+        EXTRACTION_FUEL, GRANULARITY,
     },
     timely_source::extract_timely_info,
     worker_timeline::{collect_differential_events, worker_timeline, TimelineEvent},
@@ -14,6 +15,7 @@ use differential_dataflow::{difference::Present, ExchangeData};
 use proptest::{collection::vec as propvec, prop_assert_eq, proptest, test_runner::TestCaseError};
 use std::{
     collections::HashMap,
+    num::NonZeroUsize,
     sync::{mpsc, Mutex},
     time::Duration,
 };
@@ -52,12 +54,12 @@ proptest! {
 
     #[test]
     fn differential_events(pair in gen_event_pair(true)) {
-        events_inner::<DifferentialEvent, _>(pair, |events| collect_differential_events(events).inner)?;
+        events_inner::<DifferentialEvent, _>(pair, |events| collect_differential_events(events, GRANULARITY).inner)?;
     }
 
     #[test]
     fn differential_events_stress(pairs in propvec(gen_event_pair(true), 1..500)) {
-        events_stress_inner::<DifferentialEvent, _>(pairs, |events| collect_differential_events(events).inner)?;
+        events_stress_inner::<DifferentialEvent, _>(pairs, |events| collect_differential_events(events, GRANULARITY).inner)?;
     }
 }
 
@@ -109,11 +111,20 @@ fn timeline_events_inner(
             let (timely_input, timely_stream) = scope.new_unordered_input();
             let (differential_input, differential_stream) = scope.new_unordered_input();
 
-            let (_, _, _, _, _, _, _, _, _, _, _, _, timely_events) =
-                extract_timely_info(scope, &timely_stream, false);
+            let fuel = NonZeroUsize::new(EXTRACTION_FUEL).unwrap();
+            let (_, _, _, _, _, _, _, _, _, _, _, _, _, _, timely_events) =
+                extract_timely_info(scope, &timely_stream, false, GRANULARITY, fuel);
             let timely_events = timely_events.unwrap();
 
-            let partial_events = worker_timeline(scope, &timely_events, Some(&differential_stream));
+            let partial_events = worker_timeline(
+                scope,
+                &timely_events,
+                Some(&differential_stream),
+                GRANULARITY,
+                None,
+                None,
+                Duration::from_millis(1),
+            );
 
             partial_events
                 .inner
@@ -0,0 +1,139 @@
+//! Splits each operator's activity into fixed-width windows of time
+
+use crate::{
+    dataflow::utils::{ActivationTimes, ArrangedVal, Diff, DifferentialLogBundle},
+    ui::OperatorTimeBucket,
+};
+use abomonation_derive::Abomonation;
+use ddshow_types::{differential_logging::DifferentialEvent, OperatorAddr, OperatorId, WorkerId};
+use differential_dataflow::{
+    operators::{arrange::ArrangeByKey, JoinCore, Reduce},
+    AsCollection, Collection,
+};
+use std::{iter, time::Duration};
+use timely::dataflow::{
+    operators::{Enter, Map},
+    Scope, Stream,
+};
+
+/// The kinds of activity an operator's time buckets record
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Abomonation)]
+enum ActivityKind {
+    /// Nanoseconds spent within the operator
+    Busy,
+    /// Activations started
+    Activations,
+    /// Records batched into the operator's arrangement
+    Records,
+}
+
+/// Buckets the activity of every operator, operators are matched across
+/// workers by their address and their activity is summed across all of them
+pub fn operator_time_series<S>(
+    scope: &mut S,
+    activation_times: &ActivationTimes<S>,
+    differential_stream: Option<&Stream<S, DifferentialLogBundle>>,
+    operator_ids: &ArrangedVal<S, (WorkerId, OperatorId), OperatorAddr>,
+    bucket_width: Duration,
+) -> Collection<S, (OperatorAddr, Vec<OperatorTimeBucket>), Diff>
+where
+    S: Scope<Timestamp = Duration>,
+{
+    scope.region_named("Operator Time Series", |region| {
+        let width = bucket_width.as_nanos() as u64;
+
+        // The totals of each bucket are carried within the collection's diffs so
+        // that they're summed up without holding onto every activation
+        let activity =
+            activation_times
+                .enter_region(region)
+                .explode(move |(operator, (start, duration))| {
+                    let (start, end) = (
+                        start.as_nanos() as u64,
+                        (start + duration).as_nanos() as u64,
+                    );
+
+                    let first_bucket = start - start % width;
+                    let mut activity =
+                        vec![((operator, (first_bucket, ActivityKind::Activations)), 1)];
+
+                    // Activations that span multiple buckets are split between them
+                    let mut current = start;
+                    while current < end {
+                        let bucket = current - current % width;
+                        let bucket_end = (bucket + width).min(end);
+
+                        activity.push((
+                            (operator, (bucket, ActivityKind::Busy)),
+                            (bucket_end - current) as Diff,
+                        ));
+                        current = bucket_end;
+                    }
+
+                    activity
+                });
+
+        let activity = if let Some(differential_stream) = differential_stream {
+            let batched_records = differential_stream
+                .enter(region)
+                .flat_map(move |(time, worker, event)| match event {
+                    DifferentialEvent::Batch(batch) => {
+                        let nanos = time.as_nanos() as u64;
+
+                        Some((
+                            (
+                                (worker, batch.operator),
+                                (nanos - nanos % width, ActivityKind::Records),
+                            ),
+                            time,
+                            batch.length as Diff,
+                        ))
+                    }
+
+                    _ => None,
+                })
+                .as_collection();
+
+            activity.concat(&batched_records)
+        } else {
+            activity
+        };
+
+        // Operator ids aren't consistent across workers, so each operator's activity
+        // is keyed by its address instead
+        activity
+            .arrange_by_key_named("ArrangeByKey: Operator Activity")
+            .join_core(&operator_ids.enter_region(region), |_, &activity, addr| {
+                iter::once((addr.clone(), activity))
+            })
+            .reduce_named(
+                "Reduce: Operator Time Series",
+                move |_operator, totals, output| {
+                    // Inputs are sorted by bucket and then by kind
+                    let mut buckets: Vec<OperatorTimeBucket> = Vec::new();
+                    for &(&(start, kind), total) in totals {
+                        let start = Duration::from_nanos(start);
+                        if !matches!(buckets.last(), Some(bucket) if bucket.start == start) {
+                            buckets.push(OperatorTimeBucket {
+                                start,
+                                width: bucket_width,
+                                ..Default::default()
+                            });
+                        }
+
+                        let bucket = buckets.last_mut().unwrap();
+                        match kind {
+                            ActivityKind::Busy => {
+                                bucket.total += Duration::from_nanos(total as u64)
+                            }
+                            ActivityKind::Activations => bucket.activations += total as usize,
+                            ActivityKind::Records => bucket.batched_records += total as usize,
+                        }
+                    }
+
+                    output.push((buckets, 1));
+                },
+            )
+            .leave_region()
+    })
+}
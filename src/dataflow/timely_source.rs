@@ -1,6 +1,5 @@
 use crate::{
     dataflow::{
-        constants::IDLE_EXTRACTION_FUEL,
         operators::{DelayExt, Fuel},
         utils::{granulate, Time},
         worker_timeline::{process_timely_event, EventMap, EventProcessor},
@@ -9,7 +8,10 @@ use crate::{
     },
     ui::Lifespan,
 };
-use ddshow_types::timely_logging::{ChannelsEvent, OperatesEvent, StartStop, TimelyEvent};
+use ddshow_types::{
+    lints::CapabilityViolation,
+    timely_logging::{ChannelsEvent, OperatesEvent, StartStop, TimelyEvent},
+};
 use differential_dataflow::{
     collection::AsCollection,
     difference::{Present, Semigroup},
@@ -19,6 +21,7 @@ use differential_dataflow::{
 };
 use std::{
     collections::{HashMap, VecDeque},
+    num::NonZeroUsize,
     time::Duration,
 };
 use timely::{
@@ -66,6 +69,10 @@ type TimelyCollections<S> = (
     ArrangedVal<S, (WorkerId, ChannelId), OperatorAddr>,
     // Dataflow operator ids
     ArrangedKey<S, (WorkerId, OperatorId)>,
+    // Capability violations reported by the `CheckCapabilities` lint
+    Collection<S, (WorkerId, CapabilityViolation), Diff>,
+    // The target worker and number of records of each message sent over a channel
+    Collection<S, ((WorkerId, ChannelId), (WorkerId, usize)), Diff>,
     // Timely event data, will be `None` if timeline analysis is disabled
     Option<Collection<S, TimelineEvent, Present>>,
 );
@@ -77,6 +84,8 @@ pub(super) fn extract_timely_info<S>(
     scope: &mut S,
     timely_stream: &Stream<S, TimelyLogBundle>,
     disable_timeline: bool,
+    granularity: Duration,
+    fuel: NonZeroUsize,
 ) -> TimelyCollections<S>
 where
     S: Scope<Timestamp = Duration>,
@@ -121,7 +130,7 @@ where
 
         let mut work_list = VecDeque::new();
         let mut work_list_buffers = Vec::new();
-        let mut fuel = Fuel::limited(IDLE_EXTRACTION_FUEL);
+        let mut fuel = Fuel::limited(fuel);
 
         move |_frontiers| {
             // Activate all the outputs
@@ -174,8 +183,10 @@ where
         operator_addrs_by_self,
         channel_scope_addrs,
         dataflow_ids,
+        capability_violations,
+        channel_messages,
         worker_events,
-    } = streams.into_collections();
+    } = streams.into_collections(granularity);
 
     // TODO: Granulate the times within the operator
     let operator_names = operator_names.arrange_by_key_named("ArrangeByKey: Operator Names");
@@ -203,6 +214,8 @@ where
         operator_addrs_by_self,
         channel_scope_addrs,
         dataflow_ids,
+        capability_violations,
+        channel_messages,
         // Note: Don't granulate this
         worker_events,
     )
@@ -475,15 +488,41 @@ fn ingest_event(
                 .give((((worker, channel.id), channel.scope_addr), session_time, 1));
         }
 
+        // Lints emitted by `ddshow-sink` are sent as text events
+        TimelyEvent::Text(text) => {
+            if let Some(violation) = CapabilityViolation::from_text(&text) {
+                handles
+                    .capability_violations
+                    .session(&capabilities.capability_violations)
+                    .give(((worker, violation), session_time, 1));
+            }
+        }
+
+        // Messages are logged by both their sender and receiver, so only
+        // sends are counted to keep from counting each message twice
+        TimelyEvent::Messages(messages) => {
+            if messages.is_send {
+                handles
+                    .channel_messages
+                    .session(&capabilities.channel_messages)
+                    .give((
+                        (
+                            (worker, messages.channel),
+                            (messages.target, messages.length),
+                        ),
+                        session_time,
+                        1,
+                    ));
+            }
+        }
+
         TimelyEvent::PushProgress(_)
-        | TimelyEvent::Messages(_)
         | TimelyEvent::Application(_)
         | TimelyEvent::GuardedMessage(_)
         | TimelyEvent::GuardedProgress(_)
         | TimelyEvent::CommChannels(_)
         | TimelyEvent::Input(_)
-        | TimelyEvent::Park(_)
-        | TimelyEvent::Text(_) => {}
+        | TimelyEvent::Park(_) => {}
     }
 }
 
@@ -601,9 +640,9 @@ macro_rules! timely_source_processor {
         where
             S: Scope<Timestamp = Time>,
         {
-            fn into_collections(self) -> Collections<S> {
+            fn into_collections(self, granularity: Duration) -> Collections<S> {
                 Collections {
-                    $($name: timely_source_processor!(@as_collection self, $name, $($cond)?),)*
+                    $($name: timely_source_processor!(@as_collection self, granularity, $name, $($cond)?),)*
                 }
             }
         }
@@ -762,12 +801,12 @@ macro_rules! timely_source_processor {
         Collection<S, $data, $diff>
     };
 
-    (@as_collection $self:ident, $name:ident, $cond:ident) => {
-        $self.$name.map(|$name| $name.as_collection().delay_fast(granulate))
+    (@as_collection $self:ident, $granularity:ident, $name:ident, $cond:ident) => {
+        $self.$name.map(|$name| $name.as_collection().delay_fast(granulate($granularity)))
     };
 
-    (@as_collection $self:ident, $name:ident,) => {
-        $self.$name.as_collection().delay_fast(granulate)
+    (@as_collection $self:ident, $granularity:ident, $name:ident,) => {
+        $self.$name.as_collection().delay_fast(granulate($granularity))
     };
 }
 
@@ -784,5 +823,7 @@ timely_source_processor! {
     operator_addrs_by_self: ((WorkerId, OperatorAddr), ()),
     channel_scope_addrs: ((WorkerId, ChannelId), OperatorAddr),
     dataflow_ids: ((WorkerId, OperatorId), ()),
+    capability_violations: (WorkerId, CapabilityViolation),
+    channel_messages: ((WorkerId, ChannelId), (WorkerId, usize)),
     worker_events: TimelineEvent; if timeline_enabled = Present,
 }
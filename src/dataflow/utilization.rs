@@ -0,0 +1,225 @@
+//! Splits each worker's time into parked, scheduled and scheduler overhead
+
+use crate::{
+    dataflow::{
+        operators::DelayExt,
+        utils::{granulate, Diff, TimelyLogBundle},
+    },
+    ui::WorkerTimeBucket,
+};
+use abomonation_derive::Abomonation;
+use ddshow_types::{
+    timely_logging::{ParkEvent, StartStop, TimelyEvent},
+    WorkerId,
+};
+use differential_dataflow::{operators::Reduce, AsCollection, Collection};
+use std::{collections::HashMap, time::Duration};
+use timely::dataflow::{
+    channels::pact::Exchange,
+    operators::{Enter, Operator},
+    Scope, Stream,
+};
+
+/// The kinds of time a worker's events cover
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Abomonation)]
+enum TimeKind {
+    /// The time between the worker's first and last events
+    Active,
+    /// The time spent parked
+    Parked,
+    /// The time spent within operators
+    Scheduled,
+}
+
+type TimeTotals = HashMap<(WorkerId, Duration, TimeKind), u64>;
+
+pub fn worker_time_buckets<S>(
+    scope: &mut S,
+    timely_stream: &Stream<S, TimelyLogBundle>,
+    bucket_width: Duration,
+    granularity: Duration,
+) -> Collection<S, (WorkerId, Vec<WorkerTimeBucket>), Diff>
+where
+    S: Scope<Timestamp = Duration>,
+{
+    scope.region_named("Worker Time Buckets", |region| {
+        timely_stream
+            .enter(region)
+            // Park and schedule events are paired up so each worker's events
+            // need to be processed in order by a single ddshow worker
+            .unary(
+                Exchange::new(|(_, worker, _): &TimelyLogBundle| worker.into_inner() as u64),
+                "Split Worker Time",
+                move |_capability, _info| {
+                    let (mut activity, mut totals, mut buffer) =
+                        (HashMap::new(), HashMap::new(), Vec::new());
+
+                    move |input, output| {
+                        input.for_each(|capability, data| {
+                            data.swap(&mut buffer);
+
+                            for (time, worker, event) in buffer.drain(..) {
+                                activity
+                                    .entry(worker)
+                                    .or_insert_with(WorkerActivity::default)
+                                    .ingest(&mut totals, bucket_width, worker, time, &event);
+                            }
+
+                            // Only emit the totals of each batch so that we don't
+                            // produce a record for every event
+                            let time = *capability.time();
+                            output
+                                .session(&capability)
+                                .give_iterator(totals.drain().map(
+                                    |((worker, bucket, kind), nanos)| {
+                                        ((worker, (bucket, kind)), time, nanos as Diff)
+                                    },
+                                ));
+                        });
+                    }
+                },
+            )
+            .as_collection()
+            .delay_fast(granulate(granularity))
+            .reduce_named(
+                "Reduce: Worker Time Buckets",
+                move |_worker, totals, output| {
+                    // Inputs are sorted by bucket and then by kind
+                    let mut buckets: Vec<WorkerTimeBucket> = Vec::new();
+                    for &(&(start, kind), nanos) in totals {
+                        if !matches!(buckets.last(), Some(bucket) if bucket.start == start) {
+                            buckets.push(WorkerTimeBucket {
+                                start,
+                                width: bucket_width,
+                                ..Default::default()
+                            });
+                        }
+
+                        let bucket = buckets.last_mut().unwrap();
+                        let time = Duration::from_nanos(nanos as u64);
+                        match kind {
+                            // Overhead starts out as the whole active time and has
+                            // the parked and scheduled times removed from it
+                            TimeKind::Active => bucket.overhead += time,
+                            TimeKind::Parked => bucket.parked += time,
+                            TimeKind::Scheduled => bucket.scheduled += time,
+                        }
+                    }
+
+                    for bucket in buckets.iter_mut() {
+                        bucket.overhead = bucket
+                            .overhead
+                            .saturating_sub(bucket.parked + bucket.scheduled);
+                    }
+
+                    output.push((buckets, 1));
+                },
+            )
+            .leave_region()
+    })
+}
+
+/// The in-progress state of a single worker
+#[derive(Debug, Default)]
+struct WorkerActivity {
+    /// The time of the last event the worker logged
+    last_event: Option<Duration>,
+    /// The number of nested operators currently scheduled, subgraphs
+    /// schedule their children so only the outermost one is counted
+    schedule_depth: usize,
+    /// When the outermost scheduled operator started
+    scheduled_since: Duration,
+    /// When the worker parked, if it's currently parked
+    parked_since: Option<Duration>,
+}
+
+impl WorkerActivity {
+    fn ingest(
+        &mut self,
+        totals: &mut TimeTotals,
+        bucket_width: Duration,
+        worker: WorkerId,
+        time: Duration,
+        event: &TimelyEvent,
+    ) {
+        if let Some(last_event) = self.last_event {
+            add_interval(
+                totals,
+                bucket_width,
+                worker,
+                TimeKind::Active,
+                last_event,
+                time,
+            );
+        }
+        self.last_event = Some(self.last_event.map_or(time, |last| last.max(time)));
+
+        match event {
+            TimelyEvent::Schedule(schedule) => match schedule.start_stop {
+                StartStop::Start => {
+                    if self.schedule_depth == 0 {
+                        self.scheduled_since = time;
+                    }
+                    self.schedule_depth += 1;
+                }
+
+                StartStop::Stop => {
+                    if self.schedule_depth != 0 {
+                        self.schedule_depth -= 1;
+
+                        if self.schedule_depth == 0 {
+                            add_interval(
+                                totals,
+                                bucket_width,
+                                worker,
+                                TimeKind::Scheduled,
+                                self.scheduled_since,
+                                time,
+                            );
+                        }
+                    }
+                }
+            },
+
+            TimelyEvent::Park(ParkEvent::Park(_)) => self.parked_since = Some(time),
+
+            TimelyEvent::Park(ParkEvent::Unpark) => {
+                if let Some(parked_since) = self.parked_since.take() {
+                    add_interval(
+                        totals,
+                        bucket_width,
+                        worker,
+                        TimeKind::Parked,
+                        parked_since,
+                        time,
+                    );
+                }
+            }
+
+            _ => {}
+        }
+    }
+}
+
+/// Adds the time between `start` and `end` to every bucket it overlaps
+fn add_interval(
+    totals: &mut TimeTotals,
+    bucket_width: Duration,
+    worker: WorkerId,
+    kind: TimeKind,
+    start: Duration,
+    end: Duration,
+) {
+    let width = bucket_width.as_nanos() as u64;
+    let (mut start, end) = (start.as_nanos() as u64, end.as_nanos() as u64);
+
+    while start < end {
+        let bucket = start - start % width;
+        let bucket_end = (bucket + width).min(end);
+
+        *totals
+            .entry((worker, Duration::from_nanos(bucket), kind))
+            .or_insert(0) += bucket_end - start;
+        start = bucket_end;
+    }
+}
@@ -1,12 +1,13 @@
-#[cfg(feature = "timely-next")]
-use crate::dataflow::reachability::TrackerEvent;
-use crate::dataflow::{operators::CrossbeamPusher, PROGRAM_NS_GRANULARITY};
+use crate::dataflow::operators::CrossbeamPusher;
 use anyhow::{Context, Result};
 use crossbeam_channel::Sender;
-use ddshow_sink::{EventWriter, DIFFERENTIAL_ARRANGEMENT_LOG_FILE, TIMELY_LOG_FILE};
+use ddshow_sink::{
+    framing::Framing, LogWriter, DIFFERENTIAL_ARRANGEMENT_LOG_FILE, REACHABILITY_LOG_FILE,
+    TIMELY_LOG_FILE,
+};
 use ddshow_types::{
     differential_logging::DifferentialEvent, progress_logging::TimelyProgressEvent,
-    timely_logging::TimelyEvent, WorkerId,
+    reachability_logging::TrackerEvent, timely_logging::TimelyEvent, OperatorId, WorkerId,
 };
 use differential_dataflow::{
     difference::Semigroup,
@@ -42,28 +43,70 @@ pub(crate) type ArrangedVal<S, K, V, D = Diff> =
 pub(crate) type ArrangedKey<S, K, D = Diff> =
     Arranged<S, TraceAgent<OrdKeySpine<K, <S as ScopeParent>::Timestamp, D>>>;
 
+/// Operator activations in the form of `((worker, operator), (start_time, duration))`
+pub(crate) type ActivationTimes<S> =
+    Collection<S, ((WorkerId, OperatorId), (Duration, Duration)), Diff>;
+
 pub type TimelyLogBundle<Id = WorkerId, Event = TimelyEvent> = (Time, Id, Event);
 pub type DifferentialLogBundle<Id = WorkerId, Event = DifferentialEvent> = (Time, Id, Event);
 pub type ProgressLogBundle<Id = WorkerId> = (Time, Id, TimelyProgressEvent);
 
-#[cfg(feature = "timely-next")]
 pub type ReachabilityLogBundle<Id = WorkerId> = (Time, Id, TrackerEvent);
 
-/// Puts timestamps into non-overlapping buckets that contain
-/// the timestamps from `last_bucket..PROGRAM_NS_GRANULARITY`
-/// to reduce the load on timely
-pub(crate) fn granulate(&time: &Duration) -> Duration {
-    let timestamp = time.as_nanos();
-    let window_idx = (timestamp / PROGRAM_NS_GRANULARITY) + 1;
-
-    let minted = Duration::from_nanos((window_idx * PROGRAM_NS_GRANULARITY) as u64);
-    debug_assert_eq!(
-        u64::try_from(window_idx * PROGRAM_NS_GRANULARITY).map(|res| res as u128),
-        Ok(window_idx * PROGRAM_NS_GRANULARITY),
-    );
-    debug_assert!(time <= minted);
+/// Rounds `value` down to its `precision_bits` most significant bits so that
+/// similar values can be counted together, keeping it within `1 / 2^(precision_bits - 1)`
+/// of its true value
+pub(crate) const fn round_to_precision(value: u64, precision_bits: u32) -> u64 {
+    let shift = (u64::BITS - value.leading_zeros()).saturating_sub(precision_bits);
+
+    value >> shift << shift
+}
+
+/// Gets the `percent`th percentile of `(value, count)` pairs sorted by value
+/// that hold `total` values, `None` if there aren't any values
+pub(crate) fn sorted_percentile<T, I>(values: I, total: u64, percent: u64) -> Option<T>
+where
+    I: IntoIterator<Item = (T, u64)>,
+{
+    // The rank of the value we're looking for, rounded up and starting at one
+    let rank = (total * percent).saturating_sub(1) / 100 + 1;
+
+    let (mut seen, mut last) = (0, None);
+    for (value, count) in values {
+        seen += count;
+        if seen >= rank {
+            return Some(value);
+        }
+
+        last = Some(value);
+    }
+
+    last
+}
 
-    minted
+/// Creates a function that puts timestamps into non-overlapping buckets
+/// that contain the timestamps from `last_bucket..granularity` to reduce
+/// the load on timely. Timestamps too close to [`Duration::MAX`] to be
+/// rounded up are left as they are
+pub(crate) fn granulate(granularity: Duration) -> impl Fn(&Duration) -> Duration + Copy + 'static {
+    const NANOS_PER_SEC: u128 = 1_000_000_000;
+    let granularity = granularity.as_nanos();
+
+    move |&time| {
+        let timestamp = time.as_nanos();
+        let window_idx = (timestamp / granularity) + 1;
+
+        let minted = window_idx
+            .checked_mul(granularity)
+            .and_then(|minted| {
+                let secs = u64::try_from(minted / NANOS_PER_SEC).ok()?;
+                Some(Duration::new(secs, (minted % NANOS_PER_SEC) as u32))
+            })
+            .unwrap_or(time);
+        debug_assert!(time <= minted);
+
+        minted
+    }
 }
 
 #[allow(clippy::type_complexity)]
@@ -95,13 +138,15 @@ pub(super) fn channel_sink<S, D, R>(
     );
 }
 
-/// Store all timely and differential events to disk
+/// Store all timely, differential and reachability events to disk
 pub(super) fn logging_event_sink<S>(
     save_logs: &Path,
+    framing: Framing,
     scope: &mut S,
     timely_stream: &Stream<S, (Duration, WorkerId, TimelyEvent)>,
     probe: &mut ProbeHandle<Duration>,
     differential_stream: Option<&Stream<S, (Duration, WorkerId, DifferentialEvent)>>,
+    reachability_stream: Option<&Stream<S, ReachabilityLogBundle>>,
 ) -> Result<()>
 where
     S: Scope<Timestamp = Duration>,
@@ -123,7 +168,7 @@ where
 
     timely_stream
         .probe_with(probe)
-        .capture_into(EventWriter::new(timely_file));
+        .capture_into(LogWriter::new(timely_file, framing));
 
     if let Some(differential_stream) = differential_stream {
         let differential_path =
@@ -142,14 +187,33 @@ where
 
         differential_stream
             .probe_with(probe)
-            .capture_into(EventWriter::new(differential_file));
+            .capture_into(LogWriter::new(differential_file, framing));
+    }
+
+    if let Some(reachability_stream) = reachability_stream {
+        let reachability_path = log_file_path(REACHABILITY_LOG_FILE, save_logs, scope.index());
+
+        tracing::debug!(
+            "installing reachability file sink on worker {} pointed at {}",
+            scope.index(),
+            reachability_path.display(),
+        );
+
+        let reachability_file = BufWriter::new(
+            File::create(reachability_path)
+                .context("failed to create `--save-logs` reachability file")?,
+        );
+
+        reachability_stream
+            .probe_with(probe)
+            .capture_into(LogWriter::new(reachability_file, framing));
     }
 
     Ok(())
 }
 
 /// Constructs the path to a logging file for the given worker
-pub(super) fn log_file_path(file_prefix: &str, dir: &Path, worker_id: usize) -> PathBuf {
+pub(crate) fn log_file_path(file_prefix: &str, dir: &Path, worker_id: usize) -> PathBuf {
     dir.join(format!(
         "{}.replay-worker-{}.ddshow",
         file_prefix, worker_id,
@@ -7,19 +7,19 @@ use crate::{
     },
     logging,
     replay_loading::{
-        DifferentialReplaySource, ProgressReplaySource, ReplaySource, TimelyReplaySource,
+        ConvertedEvents, DifferentialReplaySource, ProgressReplaySource, ReachabilityReplaySource,
+        ReplaySource, ReplayWindow, StructuralEvent, TimelyReplaySource, WindowedEvents,
     },
 };
 use anyhow::Result;
 use ddshow_types::{
     differential_logging::DifferentialEvent, progress_logging::TimelyProgressEvent,
-    timely_logging::TimelyEvent, WorkerId,
+    reachability_logging::TrackerEvent, timely_logging::TimelyEvent, WorkerId,
 };
 use differential_dataflow::{logging::DifferentialEvent as RawDifferentialEvent, Data};
 use indicatif::{MultiProgress, ProgressBar, ProgressFinish, ProgressStyle};
 use std::{
     fmt::Debug,
-    num::NonZeroUsize,
     panic::Location,
     sync::{
         atomic::{AtomicBool, AtomicUsize, Ordering},
@@ -30,7 +30,7 @@ use std::{
 };
 use timely::{
     communication::Allocate,
-    dataflow::{operators::Map, Scope, Stream},
+    dataflow::{Scope, Stream},
     logging::TimelyEvent as RawTimelyEvent,
     worker::Worker,
 };
@@ -47,6 +47,8 @@ pub fn worker_runtime<A>(
     timely_traces: TimelyReplaySource,
     differential_traces: Option<DifferentialReplaySource>,
     progress_traces: Option<ProgressReplaySource>,
+    reachability_traces: Option<ReachabilityReplaySource>,
+    replay_window: ReplayWindow,
 ) -> Result<()>
 where
     A: Allocate,
@@ -68,26 +70,29 @@ where
     let dataflow_id = worker.next_dataflow_index();
     let mut progress_bars = Vec::new();
 
-    let (index, peers, differential, progress) = (
+    let (index, peers, differential, progress, reachability) = (
         worker.index(),
         worker.peers(),
         args.differential_enabled as usize,
         args.progress_enabled as usize,
+        args.reachability_enabled as usize,
     );
 
     let mut source_counter = {
         let timely_offset = index;
         let differential_offset = (index * differential) + differential;
         let progress_offset = (index * progress) + progress;
+        let reachability_offset = (index * reachability) + reachability;
 
-        timely_offset + differential_offset + progress_offset
+        timely_offset + differential_offset + progress_offset + reachability_offset
     };
     let total_sources = {
         let timely_sources = worker.peers();
         let differential_sources = peers * differential;
         let progress_sources = peers * progress;
+        let reachability_sources = peers * reachability;
 
-        timely_sources + differential_sources + progress_sources
+        timely_sources + differential_sources + progress_sources + reachability_sources
     };
 
     tracing::debug!(
@@ -95,6 +100,7 @@ where
         peers = peers,
         differential = differential,
         progress = progress,
+        reachability = reachability,
         source_counter = source_counter,
         total_sources = total_sources,
     );
@@ -109,7 +115,7 @@ where
         //       enough to take precedence over the possible performance
         //       impact? (is there a perf impact?)
         let fuel = if args.is_file_sourced() {
-            Fuel::limited(NonZeroUsize::new(100_000_000).unwrap())
+            Fuel::limited(args.replay_fuel)
 
         // If the dataflow is being sourced from a running program,
         // take as many events as we possibly can so that we don't
@@ -134,6 +140,7 @@ where
                 timely_traces,
                 replay_shutdown.clone(),
                 fuel.clone(),
+                replay_window,
                 &multi_progress,
                 "Timely",
                 &mut progress_bars,
@@ -150,6 +157,7 @@ where
                     traces,
                     replay_shutdown.clone(),
                     fuel.clone(),
+                    replay_window,
                     &multi_progress,
                     "Differential",
                     &mut progress_bars,
@@ -176,6 +184,7 @@ where
                     traces,
                     replay_shutdown.clone(),
                     fuel.clone(),
+                    replay_window,
                     &multi_progress,
                     "Progress",
                     &mut progress_bars,
@@ -190,6 +199,35 @@ where
             }
         })?;
 
+        let span = tracing::info_span!("replay reachability logs", worker_id = scope.index());
+        let reachability_stream = span.in_scope(|| {
+            if let Some(traces) = reachability_traces {
+                if traces.is_abomonation() {
+                    anyhow::bail!(
+                        "Timely reachability logging is only supported with rkyv sources"
+                    );
+                }
+
+                let stream = replay_traces::<_, TrackerEvent, TrackerEvent, _, _>(
+                    scope,
+                    traces,
+                    replay_shutdown.clone(),
+                    fuel.clone(),
+                    replay_window,
+                    &multi_progress,
+                    "Reachability",
+                    &mut progress_bars,
+                    &mut source_counter,
+                    total_sources,
+                );
+
+                Ok(Some(stream))
+            } else {
+                tracing::trace!("no reachability sources were provided");
+                Ok(None)
+            }
+        })?;
+
         let span = tracing::info_span!("dataflow construction", worker_id = scope.index());
         span.in_scope(|| {
             dataflow::dataflow(
@@ -198,6 +236,7 @@ where
                 &timely_stream,
                 differential_stream.as_ref(),
                 progress_stream.as_ref(),
+                reachability_stream.as_ref(),
                 senders.clone(),
             )
         })
@@ -251,6 +290,7 @@ fn replay_traces<S, Event, RawEvent, R, A>(
     traces: ReplaySource<R, A>,
     replay_shutdown: Arc<AtomicBool>,
     fuel: Fuel,
+    window: ReplayWindow,
     multi_progress: &MultiProgress,
     source: &'static str,
     progress_bars: &mut Vec<(ProgressBar, ProgressStyle)>,
@@ -259,7 +299,7 @@ fn replay_traces<S, Event, RawEvent, R, A>(
 ) -> Stream<S, (Duration, WorkerId, Event)>
 where
     S: Scope<Timestamp = Duration>,
-    Event: Data + From<RawEvent>,
+    Event: Data + From<RawEvent> + StructuralEvent,
     RawEvent: Debug + Clone + 'static,
     R: EventIterator<Duration, (Duration, WorkerId, Event)> + 'static,
     A: EventIterator<Duration, (Duration, usize, RawEvent)> + 'static,
@@ -316,19 +356,15 @@ where
     );
 
     match traces {
-        ReplaySource::Rkyv(rkyv) => rkyv.replay_with_shutdown_into_named(
-            &name,
-            scope,
-            replay_shutdown,
-            fuel,
-            Some(progress),
-        ),
+        ReplaySource::Rkyv(rkyv) => rkyv
+            .into_iter()
+            .map(|events| WindowedEvents::new(events, window))
+            .replay_with_shutdown_into_named(&name, scope, replay_shutdown, fuel, Some(progress)),
 
         ReplaySource::Abomonation(abomonation) => abomonation
-            .replay_with_shutdown_into_named(&name, scope, replay_shutdown, fuel, Some(progress))
-            .map(|(time, worker, event): (Duration, usize, RawEvent)| {
-                (time, WorkerId::new(worker), Event::from(event))
-            }),
+            .into_iter()
+            .map(|events| WindowedEvents::new(ConvertedEvents::new(events), window))
+            .replay_with_shutdown_into_named(&name, scope, replay_shutdown, fuel, Some(progress)),
     }
     .debug_inspect(move |x| tracing::trace!("{} event: {:?}", source, x))
 }
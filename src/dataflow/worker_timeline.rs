@@ -1,6 +1,5 @@
 use crate::dataflow::{
-    constants::EVENT_NS_MARGIN,
-    operators::{DelayExt, Multiply, Split},
+    operators::{DelayExt, Split},
     utils::{granulate, DifferentialLogBundle},
 };
 use abomonation_derive::Abomonation;
@@ -9,15 +8,20 @@ use ddshow_types::{
     timely_logging::{ParkEvent, StartStop, TimelyEvent},
     OperatorId, WorkerId,
 };
-use differential_dataflow::{
-    difference::{Abelian, Present},
-    lattice::Lattice,
-    AsCollection, Collection, ExchangeData,
-};
+use differential_dataflow::{difference::Present, lattice::Lattice, AsCollection, Collection};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, mem, time::Duration};
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BinaryHeap, HashMap},
+    mem,
+    num::NonZeroUsize,
+    time::Duration,
+};
 use timely::dataflow::{
-    channels::{pact::Pipeline, pushers::Tee},
+    channels::{
+        pact::{Exchange, Pipeline},
+        pushers::Tee,
+    },
     operators::{
         aggregation::StateMachine, generic::OutputHandle, Capability, Concat, Delay, Enter, Map,
         Operator,
@@ -30,6 +34,10 @@ pub(super) fn worker_timeline<S>(
     scope: &mut S,
     timely_events: &Collection<S, TimelineEvent, Present>,
     differential_stream: Option<&Stream<S, DifferentialLogBundle>>,
+    granularity: Duration,
+    fusion_margin: Option<Duration>,
+    timeline_limit: Option<NonZeroUsize>,
+    merge_gap: Duration,
 ) -> Collection<S, TimelineEvent, Present>
 where
     S: Scope<Timestamp = Duration>,
@@ -43,13 +51,23 @@ where
         // TODO: Emit trace drops & shares to a separate stream so that we can make markers
         //       with `timeline.setCustomTime()`
         // TODO: Emit the # of batches received
-        let differential_events =
-            differential_stream.map(|event_stream| collect_differential_events(&event_stream));
+        let differential_events = differential_stream
+            .map(|event_stream| collect_differential_events(&event_stream, granularity));
 
-        differential_events
+        let events = differential_events
             .as_ref()
             .map(|differential_events| timely_events.concat(differential_events))
-            .unwrap_or(timely_events)
+            .unwrap_or(timely_events);
+
+        let events = fusion_margin
+            .map(|margin| collapse_events(&events, margin))
+            .unwrap_or(events);
+
+        // Events are bounded before they leave the region so that
+        // only the retained ones are ever passed along
+        timeline_limit
+            .map(|limit| bound_timeline(region, &events, limit, merge_gap))
+            .unwrap_or(events)
             .leave_region()
     })
 }
@@ -126,6 +144,7 @@ pub(super) fn process_timely_event(
 // TODO: Wire operator shutdown events into this as well
 pub(super) fn collect_differential_events<S>(
     event_stream: &Stream<S, DifferentialLogBundle>,
+    granularity: Duration,
 ) -> Collection<S, TimelineEvent, Present>
 where
     S: Scope<Timestamp = Duration>,
@@ -163,7 +182,7 @@ where
             },
         )
         .as_collection()
-        .delay_fast(granulate)
+        .delay_fast(granulate(granularity))
 }
 
 fn process_differential_event(
@@ -420,21 +439,20 @@ impl<'a, 'b> EventProcessor<'a, 'b> {
     }
 }
 
+/// Fuses adjacent events of the same kind that are at most `margin` apart
 // TODO: This may be slightly unreliable
-#[allow(dead_code)]
-fn collapse_events<S, R>(
-    events: &Collection<S, TimelineEvent, R>,
-) -> Collection<S, TimelineEvent, R>
+pub(super) fn collapse_events<S>(
+    events: &Collection<S, TimelineEvent, Present>,
+    margin: Duration,
+) -> Collection<S, TimelineEvent, Present>
 where
     S: Scope<Timestamp = Duration>,
-    S::Timestamp: Lattice,
-    R: Abelian + ExchangeData + Multiply<Output = R> + From<i8>,
 {
-    fn fold_timeline_events(
-        _key: &WorkerId,
-        input: State,
-        state: &mut Option<TimelineEvent>,
-    ) -> (bool, impl IntoIterator<Item = TimelineEvent> + 'static) {
+    let margin = margin.as_nanos() as u64;
+    let fold_timeline_events = move |_key: &WorkerId,
+                                     input: State,
+                                     state: &mut Option<TimelineEvent>|
+          -> (bool, Option<TimelineEvent>) {
         match input {
             State::Event(input) => {
                 (
@@ -456,11 +474,11 @@ where
                             // Make sure the events are the same and are also overlapping
                             // in their time windows (`event_start..event_end`) by using
                             // a simple bounding box. Note that the state's time window
-                            // is expanded by `EVENT_NS_MARGIN` so that there's a small grace
+                            // is expanded by `margin` so that there's a small grace
                             // period that allows events not directly adjacent to be collapsed
                             if old_state.event == input.event
-                                && state_start.saturating_sub(EVENT_NS_MARGIN) <= input_end
-                                && (state_end + EVENT_NS_MARGIN) >= input_start
+                                && state_start.saturating_sub(margin) <= input_end
+                                && (state_end + margin) >= input_start
                             {
                                 old_state.duration += input.duration;
                                 old_state.collapsed_events += 1;
@@ -486,7 +504,7 @@ where
                 (false, None)
             }
         }
-    }
+    };
 
     #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Abomonation)]
     pub enum State {
@@ -511,8 +529,8 @@ where
         //       `stream_time + event_duration`. The purpose of delaying the flush stream is so that the
         //       flush message arrives *after* any potentially collapsible messages, thereby making sure
         //       that there's actually an opportunity for events to be collapsed
-        .split(|(event, time, _diff)| {
-            let end_time = time + Duration::from_nanos(event.duration + EVENT_NS_MARGIN);
+        .split(move |(event, time, _diff)| {
+            let end_time = time + Duration::from_nanos(event.duration + margin);
 
             (
                 // Note: the time of this stream is entirely ignored
@@ -527,10 +545,26 @@ where
         .state_machine(fold_timeline_events, move |&worker_id| {
             worker_id.into_inner() as u64
         })
-        .map(|event| {
-            let timestamp = Duration::from_nanos(event.start_time + event.duration);
-            (event, timestamp, R::from(1))
-        })
+        // Collapsed events are only released once their flush arrives, so
+        // they're emitted at the time they're released at
+        .unary(
+            Pipeline,
+            "Timestamp Collapsed Events",
+            |_capability, _info| {
+                let mut buffer = Vec::new();
+
+                move |input, output| {
+                    input.for_each(|capability, data| {
+                        data.swap(&mut buffer);
+
+                        let time = *capability.time();
+                        output
+                            .session(&capability)
+                            .give_iterator(buffer.drain(..).map(|event| (event, time, Present)));
+                    });
+                }
+            },
+        )
         .as_collection();
 
     if cfg!(debug_assertions) {
@@ -597,3 +631,253 @@ impl TimelineEvent {
         }
     }
 }
+
+/// Bounds the number of timeline events kept for each worker's operators and event kinds.
+/// Over the whole run the `limit` slowest events of each kind are kept at full fidelity
+/// while every other event is collapsed into at most `limit` spans, merging events within
+/// `merge_gap` of each other and folding the oldest spans together once there are too many.
+/// The retained events are output at the earliest timestamp they were received at
+/// once the input has completed
+pub(super) fn bound_timeline<S>(
+    scope: &mut S,
+    events: &Collection<S, TimelineEvent, Present>,
+    limit: NonZeroUsize,
+    merge_gap: Duration,
+) -> Collection<S, TimelineEvent, Present>
+where
+    S: Scope<Timestamp = Duration>,
+{
+    scope.region_named("Bound Worker Timelines", |region| {
+        let merge_gap = merge_gap.as_nanos() as u64;
+
+        events
+            .enter_region(region)
+            .inner
+            // Events of the same kind need to be processed by a single ddshow worker
+            .unary_frontier(
+                Exchange::new(|(event, _, _): &TimelineStreamEvent| {
+                    event.worker.into_inner() as u64
+                }),
+                "Bound Timeline Events",
+                |_capability, _info| {
+                    let mut buffer = Vec::new();
+                    // The earliest timestamp that's been received, held until
+                    // the input completes so the retained events can be output
+                    let mut held: Option<Capability<Duration>> = None;
+                    let mut timelines: HashMap<_, BoundedTimeline> = HashMap::new();
+
+                    move |input, output| {
+                        input.for_each(|capability, data| {
+                            data.swap(&mut buffer);
+
+                            match &held {
+                                Some(held) if held.time() <= capability.time() => {}
+                                _ => held = Some(capability.retain()),
+                            }
+
+                            for (event, _time, _diff) in buffer.drain(..) {
+                                timelines
+                                    .entry((event.worker, event.event))
+                                    .or_insert_with(BoundedTimeline::default)
+                                    .insert(event, limit, merge_gap);
+                            }
+                        });
+
+                        // Events can arrive at any point in the run, so they're only
+                        // output once there's nothing left to receive
+                        if input.frontier().is_empty() {
+                            if let Some(capability) = held.take() {
+                                let time = *capability.time();
+                                let mut session = output.session(&capability);
+
+                                for (_, timeline) in timelines.drain() {
+                                    session.give_iterator(
+                                        timeline.into_events().map(|event| (event, time, Present)),
+                                    );
+                                }
+                            }
+                        }
+                    }
+                },
+            )
+            .as_collection()
+            .leave_region()
+    })
+}
+
+/// The retained events for a single worker's event kind
+#[derive(Debug, Default)]
+struct BoundedTimeline {
+    /// The slowest events seen so far
+    slowest: BinaryHeap<Reverse<(u64, u64, TimelineEvent)>>,
+    /// Events that didn't make the cut, collapsed into spans keyed by their start time
+    collapsed: BTreeMap<u64, TimelineEvent>,
+}
+
+impl BoundedTimeline {
+    /// Inserts an event into the timeline, collapsing whichever
+    /// event is no longer one of the `limit` slowest
+    fn insert(&mut self, event: TimelineEvent, limit: NonZeroUsize, merge_gap: u64) {
+        self.slowest
+            .push(Reverse((event.duration, event.start_time, event)));
+
+        if self.slowest.len() > limit.get() {
+            if let Some(Reverse((_, _, evicted))) = self.slowest.pop() {
+                self.collapse(evicted, limit, merge_gap);
+            }
+        }
+    }
+
+    /// Merges an event into the collapsed spans, folding the oldest
+    /// spans together if there are more than `limit` of them
+    fn collapse(&mut self, mut event: TimelineEvent, limit: NonZeroUsize, merge_gap: u64) {
+        // Absorb the spans that end within `merge_gap` of the event's start
+        while let Some((&start, previous)) = self.collapsed.range(..=event.start_time).next_back() {
+            if end_time(previous) + merge_gap < event.start_time {
+                break;
+            }
+
+            let previous = self.collapsed.remove(&start).unwrap();
+            event = merge_spans(previous, event);
+        }
+
+        // Absorb the spans that start within `merge_gap` of the event's end
+        while let Some((&start, _)) = self.collapsed.range(event.start_time..).next() {
+            if start > end_time(&event) + merge_gap {
+                break;
+            }
+
+            let next = self.collapsed.remove(&start).unwrap();
+            event = merge_spans(event, next);
+        }
+
+        self.collapsed.insert(event.start_time, event);
+
+        if self.collapsed.len() > limit.get() {
+            let mut oldest = self.collapsed.keys().copied();
+            if let (Some(first), Some(second)) = (oldest.next(), oldest.next()) {
+                let first = self.collapsed.remove(&first).unwrap();
+                let second = self.collapsed.remove(&second).unwrap();
+
+                let merged = merge_spans(first, second);
+                self.collapsed.insert(merged.start_time, merged);
+            }
+        }
+    }
+
+    /// Consumes the timeline, returning all retained events
+    fn into_events(self) -> impl Iterator<Item = TimelineEvent> {
+        self.slowest
+            .into_iter()
+            .map(|Reverse((_, _, event))| event)
+            .chain(self.collapsed.into_values())
+    }
+}
+
+const fn end_time(event: &TimelineEvent) -> u64 {
+    event.start_time + event.duration
+}
+
+/// Merges two events into a single event spanning both of them
+fn merge_spans(first: TimelineEvent, second: TimelineEvent) -> TimelineEvent {
+    let start_time = first.start_time.min(second.start_time);
+    let end_time = end_time(&first).max(end_time(&second));
+
+    TimelineEvent {
+        start_time,
+        duration: end_time - start_time,
+        collapsed_events: first.collapsed_events + second.collapsed_events,
+        ..first
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dataflow::worker_timeline::{BoundedTimeline, EventKind, TimelineEvent};
+    use ddshow_types::{OperatorId, WorkerId};
+    use std::{num::NonZeroUsize, time::Duration};
+
+    fn event(start: u64, duration: u64) -> TimelineEvent {
+        TimelineEvent::new(
+            WorkerId::new(0),
+            EventKind::activation(OperatorId::new(0)),
+            Duration::from_nanos(start),
+            Duration::from_nanos(duration),
+        )
+    }
+
+    fn bound(events: &[(u64, u64)], limit: usize, merge_gap: u64) -> Vec<(u64, u64, usize)> {
+        let mut timeline = BoundedTimeline::default();
+        for &(start, duration) in events {
+            timeline.insert(
+                event(start, duration),
+                NonZeroUsize::new(limit).unwrap(),
+                merge_gap,
+            );
+        }
+
+        let mut events: Vec<_> = timeline
+            .into_events()
+            .map(|event| (event.start_time, event.duration, event.collapsed_events))
+            .collect();
+        events.sort_unstable();
+
+        events
+    }
+
+    #[test]
+    fn collapsed_spans_cover_their_events() {
+        // The fast events at 10..11 and 12..13 collapse into 10..13, which
+        // only adds up to 2ns of activity but spans 3ns
+        let events = [(0, 50), (10, 1), (12, 1), (20, 90)];
+        assert_eq!(
+            bound(&events, 2, 5),
+            vec![(0, 50, 1), (10, 3, 2), (20, 90, 1)],
+        );
+
+        // Events are evicted out of time order but still merge into time ordered spans
+        let events = [(100, 1), (40, 30), (50, 2), (10, 1), (1000, 500)];
+        assert_eq!(bound(&events, 1, 10), vec![(10, 91, 4), (1000, 500, 1)]);
+    }
+
+    #[test]
+    fn oldest_spans_are_folded_first() {
+        // With a limit of two only the two slowest events are kept, the rest
+        // are too far apart to merge so the oldest spans get folded together
+        let events = [(0, 1), (100, 1), (200, 1), (300, 1), (400, 50), (500, 60)];
+        assert_eq!(
+            bound(&events, 2, 0),
+            vec![(0, 201, 3), (300, 1, 1), (400, 50, 1), (500, 60, 1)],
+        );
+    }
+
+    #[test]
+    fn timelines_stay_bounded() {
+        let limit = 8;
+        let events: Vec<_> = (0..10_000).map(|idx| (idx * 100, idx % 37)).collect();
+        let bounded = bound(&events, limit, 10);
+
+        // At most `limit` slow events and `limit` collapsed spans are kept
+        assert!(bounded.len() <= limit * 2);
+        // Every event is accounted for
+        assert_eq!(
+            bounded
+                .iter()
+                .map(|&(_, _, collapsed)| collapsed)
+                .sum::<usize>(),
+            events.len(),
+        );
+        // The spans still cover the whole run
+        assert_eq!(bounded.first().unwrap().0, 0);
+        assert_eq!(
+            bounded
+                .iter()
+                .map(|&(start, duration, _)| start + duration)
+                .max(),
+            events
+                .iter()
+                .map(|&(start, duration)| start + duration)
+                .max(),
+        );
+    }
+}
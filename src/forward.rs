@@ -0,0 +1,486 @@
+//! Forwarding live log streams to other consumers
+//!
+//! Timely workers can only send their logs to a single address, so ddshow can pass
+//! every connection it accepts along to other consumers while analyzing it. Each
+//! accepted connection opens its own connection to every downstream address of the
+//! same kind, meaning that downstream consumers see the same connections ddshow does
+
+use crate::{
+    args::{Args, ForwardBackpressure, ForwardEncoding, ForwardFailure},
+    dataflow::operators::{EventIterator, EventReader},
+    record::{self, SourceKind},
+};
+use abomonation::Abomonation;
+use anyhow::{Context, Result};
+use bytecheck::CheckBytes;
+use crossbeam_channel::{Receiver, Sender};
+use ddshow_sink::EventWriter;
+use ddshow_types::WorkerId;
+use indicatif::HumanBytes;
+use rkyv::{
+    ser::serializers::AlignedSerializer, validation::DefaultArchiveValidator, AlignedVec,
+    Serialize as RkyvSerialize,
+};
+use std::{
+    fmt::Debug,
+    io::{self, Read, Write},
+    marker::PhantomData,
+    net::{Shutdown, SocketAddr, TcpStream},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+use timely::dataflow::operators::capture::{Event, EventPusher};
+
+/// How often a blocked source checks whether its downstreams have caught up
+const BACKPRESSURE_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// An event stream accepted from a live abomonation source
+pub(crate) type AbomonationSource<T, D> = Box<dyn EventIterator<T, D> + Send + 'static>;
+
+type ReEncoder<T, D> = fn(EventReader<T, D, TcpStream>, Forwarder) -> AbomonationSource<T, D>;
+
+/// How the accepted connections of a single kind of log are forwarded
+pub(crate) struct Forwarding<T, D> {
+    kind: SourceKind,
+    addresses: Vec<SocketAddr>,
+    encoding: ForwardEncoding,
+    config: Arc<ForwardConfig>,
+    re_encode: ReEncoder<T, D>,
+}
+
+impl<T, D> Forwarding<T, D> {
+    /// Collects the forwarding addresses for the given kind of log, `re_encode`
+    /// is used to re-encode abomonation sources when `--forward-encoding rkyv` is set
+    pub(crate) fn new(
+        args: &Args,
+        kind: SourceKind,
+        running: &Arc<AtomicBool>,
+        re_encode: ReEncoder<T, D>,
+    ) -> Self {
+        let addresses = args
+            .forward
+            .iter()
+            .filter(|target| target.kind == kind)
+            .map(|target| target.address)
+            .collect();
+
+        let config = ForwardConfig {
+            backpressure: args.forward_backpressure,
+            on_failure: args.forward_on_failure,
+            buffer_size: args.forward_buffer as usize,
+            running: running.clone(),
+        };
+
+        Self {
+            kind,
+            addresses,
+            encoding: args.forward_encoding,
+            config: Arc::new(config),
+            re_encode,
+        }
+    }
+
+    /// Wraps an accepted abomonation connection so that it gets forwarded
+    pub(crate) fn abomonation_source(&self, socket: TcpStream) -> Result<AbomonationSource<T, D>>
+    where
+        Event<T, D>: Clone,
+        T: Abomonation + Send + 'static,
+        D: Abomonation + Send + 'static,
+    {
+        let forwarder = match self.connect()? {
+            Some(forwarder) => forwarder,
+            None => return Ok(Box::new(EventReader::new(socket))),
+        };
+
+        let source = match self.encoding {
+            ForwardEncoding::Raw => Box::new(EventReader::new(TeeReader::new(socket, forwarder))),
+            ForwardEncoding::Rkyv => (self.re_encode)(EventReader::new(socket), forwarder),
+        };
+
+        Ok(source)
+    }
+
+    /// Wraps an accepted rkyv connection so that it gets forwarded, rkyv sources
+    /// are already in the format ddshow re-encodes into so they're always
+    /// forwarded byte-for-byte
+    pub(crate) fn rkyv_source(&self, socket: TcpStream) -> Result<Box<dyn Read + Send + 'static>> {
+        let source = match self.connect()? {
+            Some(forwarder) => Box::new(TeeReader::new(socket, forwarder)) as _,
+            None => Box::new(socket) as _,
+        };
+
+        Ok(source)
+    }
+
+    /// Opens a connection to each downstream address for a newly accepted connection
+    fn connect(&self) -> Result<Option<Forwarder>> {
+        if self.addresses.is_empty() {
+            return Ok(None);
+        }
+
+        let mut downstreams = Vec::with_capacity(self.addresses.len());
+        for &address in self.addresses.iter() {
+            match Downstream::connect(address, &self.config) {
+                Ok(downstream) => {
+                    tracing::info!(
+                        "forwarding a {} connection to {}",
+                        self.kind.name(),
+                        address,
+                    );
+
+                    downstreams.push(downstream);
+                }
+
+                Err(err) if self.config.on_failure == ForwardFailure::Stop => {
+                    return Err(err).with_context(|| {
+                        format!(
+                            "failed to connect to {} forwarding address {}",
+                            self.kind.name(),
+                            address,
+                        )
+                    });
+                }
+
+                Err(err) => self.config.fail(address, &err.to_string()),
+            }
+        }
+
+        Ok(Some(Forwarder {
+            downstreams,
+            config: self.config.clone(),
+        }))
+    }
+}
+
+struct ForwardConfig {
+    backpressure: ForwardBackpressure,
+    on_failure: ForwardFailure,
+    buffer_size: usize,
+    /// Shuts down the replay when a downstream fails with `--forward-on-failure stop`
+    running: Arc<AtomicBool>,
+}
+
+impl ForwardConfig {
+    fn fail(&self, address: SocketAddr, reason: &str) {
+        match self.on_failure {
+            ForwardFailure::Ignore => {
+                tracing::warn!("stopped forwarding to {}: {}", address, reason);
+                eprintln!("Stopped forwarding to {}: {}", address, reason);
+            }
+
+            ForwardFailure::Stop => {
+                tracing::error!("forwarding to {} failed, stopping: {}", address, reason);
+                eprintln!(
+                    "Forwarding to {} failed, stopping log collection: {}",
+                    address, reason,
+                );
+
+                self.running.store(false, Ordering::Release);
+            }
+        }
+    }
+}
+
+/// Forwards the data of a single accepted connection to all of its downstreams,
+/// dropping the forwarder closes all of the downstream connections
+pub(crate) struct Forwarder {
+    downstreams: Vec<Downstream>,
+    config: Arc<ForwardConfig>,
+}
+
+impl Forwarder {
+    fn forward(&mut self, bytes: &[u8]) {
+        if bytes.is_empty() {
+            return;
+        }
+
+        let config = &self.config;
+        self.downstreams
+            .retain(|downstream| downstream.send(bytes, config));
+    }
+}
+
+impl Write for Forwarder {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.forward(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A connection to a downstream consumer, the actual writes happen on
+/// a separate thread so that slow consumers don't stall ddshow
+struct Downstream {
+    address: SocketAddr,
+    sender: Sender<Vec<u8>>,
+    /// The number of bytes waiting to be written to the downstream
+    queued: Arc<AtomicUsize>,
+    failed: Arc<AtomicBool>,
+}
+
+impl Downstream {
+    fn connect(address: SocketAddr, config: &Arc<ForwardConfig>) -> io::Result<Self> {
+        let stream = TcpStream::connect(address)?;
+
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let (queued, failed) = (
+            Arc::new(AtomicUsize::new(0)),
+            Arc::new(AtomicBool::new(false)),
+        );
+
+        let (thread_queued, thread_failed, thread_config) =
+            (queued.clone(), failed.clone(), config.clone());
+        thread::Builder::new()
+            .name(format!("ddshow-forward-{}", address))
+            .spawn(move || {
+                write_downstream(
+                    stream,
+                    address,
+                    receiver,
+                    &thread_queued,
+                    &thread_failed,
+                    &thread_config,
+                )
+            })?;
+
+        Ok(Self {
+            address,
+            sender,
+            queued,
+            failed,
+        })
+    }
+
+    /// Queues bytes to be sent downstream, returns `false` if
+    /// the downstream has failed and should be dropped
+    fn send(&self, bytes: &[u8], config: &ForwardConfig) -> bool {
+        if self.failed.load(Ordering::Acquire) {
+            return false;
+        }
+
+        let is_full = || {
+            let queued = self.queued.load(Ordering::Acquire);
+
+            // Chunks larger than the buffer are still let through
+            // once everything before them has been written
+            queued != 0 && queued + bytes.len() > config.buffer_size
+        };
+
+        if is_full() {
+            match config.backpressure {
+                ForwardBackpressure::Block => {
+                    while is_full() {
+                        if self.failed.load(Ordering::Acquire)
+                            || !config.running.load(Ordering::Acquire)
+                        {
+                            return false;
+                        }
+
+                        thread::sleep(BACKPRESSURE_POLL_INTERVAL);
+                    }
+                }
+
+                ForwardBackpressure::Disconnect => {
+                    config.fail(
+                        self.address,
+                        &format!(
+                            "it fell more than {} behind",
+                            HumanBytes(config.buffer_size as u64),
+                        ),
+                    );
+
+                    return false;
+                }
+            }
+        }
+
+        self.queued.fetch_add(bytes.len(), Ordering::Release);
+        self.sender.send(bytes.to_owned()).is_ok()
+    }
+}
+
+fn write_downstream(
+    mut stream: TcpStream,
+    address: SocketAddr,
+    receiver: Receiver<Vec<u8>>,
+    queued: &AtomicUsize,
+    failed: &AtomicBool,
+    config: &ForwardConfig,
+) {
+    for bytes in receiver.iter() {
+        if let Err(err) = stream.write_all(&bytes) {
+            failed.store(true, Ordering::Release);
+            config.fail(address, &err.to_string());
+
+            return;
+        }
+
+        queued.fetch_sub(bytes.len(), Ordering::Release);
+    }
+
+    // The source finished, so let the downstream know that it did too
+    if let Err(err) = stream.shutdown(Shutdown::Write) {
+        tracing::warn!(
+            "failed to shut down forwarded connection to {}: {:?}",
+            address,
+            err
+        );
+    }
+}
+
+/// A reader that forwards everything it reads byte-for-byte
+struct TeeReader<R> {
+    reader: R,
+    forwarder: Option<Forwarder>,
+}
+
+impl<R> TeeReader<R> {
+    const fn new(reader: R, forwarder: Forwarder) -> Self {
+        Self {
+            reader,
+            forwarder: Some(forwarder),
+        }
+    }
+}
+
+impl<R: Read> Read for TeeReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.reader.read(buf)?;
+
+        if len == 0 {
+            // Close the downstream connections once the source finishes
+            self.forwarder = None;
+        } else if let Some(forwarder) = self.forwarder.as_mut() {
+            forwarder.forward(&buf[..len]);
+        }
+
+        Ok(len)
+    }
+}
+
+/// Creates an abomonation source that re-encodes every event it
+/// receives into ddshow's rkyv format before forwarding it
+pub(crate) fn re_encode<RawEvent, E>(
+    reader: EventReader<Duration, (Duration, usize, RawEvent), TcpStream>,
+    forwarder: Forwarder,
+) -> AbomonationSource<Duration, (Duration, usize, RawEvent)>
+where
+    RawEvent: Abomonation + Clone + Send + 'static,
+    E: From<RawEvent> + Send + 'static,
+    (Duration, WorkerId, E): for<'a> RkyvSerialize<AlignedSerializer<&'a mut AlignedVec>> + Debug,
+    <(Duration, WorkerId, E) as rkyv::Archive>::Archived: CheckBytes<DefaultArchiveValidator>,
+{
+    Box::new(ReEncodeEvents {
+        reader,
+        writer: Some(EventWriter::new(forwarder)),
+        __type: PhantomData,
+    })
+}
+
+struct ReEncodeEvents<A, RawEvent, E> {
+    reader: A,
+    writer: Option<EventWriter<Duration, (Duration, WorkerId, E), Forwarder>>,
+    __type: PhantomData<fn() -> RawEvent>,
+}
+
+impl<A, RawEvent, E> EventIterator<Duration, (Duration, usize, RawEvent)>
+    for ReEncodeEvents<A, RawEvent, E>
+where
+    A: EventIterator<Duration, (Duration, usize, RawEvent)>,
+    RawEvent: Clone,
+    E: From<RawEvent>,
+    (Duration, WorkerId, E): for<'a> RkyvSerialize<AlignedSerializer<&'a mut AlignedVec>> + Debug,
+    <(Duration, WorkerId, E) as rkyv::Archive>::Archived: CheckBytes<DefaultArchiveValidator>,
+{
+    fn next(
+        &mut self,
+        is_finished: &mut bool,
+    ) -> io::Result<Option<Event<Duration, (Duration, usize, RawEvent)>>> {
+        let event = self.reader.next(is_finished)?;
+
+        if let (Some(event), Some(writer)) = (event.as_ref(), self.writer.as_mut()) {
+            writer.push(record::convert_event(event.clone()));
+        }
+
+        // Close the downstream connections once the source finishes
+        if *is_finished {
+            self.writer = None;
+        }
+
+        Ok(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Downstream, ForwardConfig, Forwarder, TeeReader};
+    use crate::args::{ForwardBackpressure, ForwardFailure};
+    use std::{
+        io::{Cursor, Read},
+        net::TcpListener,
+        sync::{atomic::AtomicBool, Arc},
+    };
+
+    #[test]
+    fn forwarded_bytes_reach_every_downstream() {
+        let listeners: Vec<_> = (0..2)
+            .map(|_| TcpListener::bind("127.0.0.1:0").unwrap())
+            .collect();
+
+        let config = Arc::new(ForwardConfig {
+            backpressure: ForwardBackpressure::Block,
+            on_failure: ForwardFailure::Stop,
+            // Small enough to make the source wait on the downstreams
+            buffer_size: 64,
+            running: Arc::new(AtomicBool::new(true)),
+        });
+        let downstreams = listeners
+            .iter()
+            .map(|listener| Downstream::connect(listener.local_addr().unwrap(), &config))
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        let source: Vec<u8> = (0..10_000u32).map(|byte| byte as u8).collect();
+        let forwarder = Forwarder {
+            downstreams,
+            config: config.clone(),
+        };
+
+        let mut reader = TeeReader::new(Cursor::new(source.clone()), forwarder);
+        let readers = listeners
+            .iter()
+            .map(|listener| {
+                let (mut stream, _) = listener.accept().unwrap();
+
+                std::thread::spawn(move || {
+                    let mut received = Vec::new();
+                    stream.read_to_end(&mut received).unwrap();
+                    received
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut read = Vec::new();
+        let mut buf = [0; 100];
+        loop {
+            let len = reader.read(&mut buf).unwrap();
+            if len == 0 {
+                break;
+            }
+
+            read.extend_from_slice(&buf[..len]);
+        }
+
+        assert_eq!(read, source);
+        for received in readers {
+            assert_eq!(received.join().unwrap(), source);
+        }
+    }
+}
@@ -1,23 +1,39 @@
 mod args;
 mod colormap;
 mod dataflow;
+mod forward;
 mod logging;
+mod record;
+mod redact;
 mod replay_loading;
+mod replay_serve;
 mod report;
+mod trim;
 mod ui;
+mod verify;
 
 use crate::{
-    args::Args,
+    args::{Args, Command},
     colormap::{select_color, Color},
-    dataflow::{constants::DDSHOW_VERSION, Channel, DataflowData, DataflowSenders, OperatorStats},
-    replay_loading::{connect_to_sources, wait_for_input},
-    ui::{ActivationDuration, DDShowStats, EdgeKind, Lifespan, TimelineEvent},
+    dataflow::{
+        constants::DDSHOW_VERSION, Channel, DataflowData, DataflowSenders, OperatorStats,
+        SpillBuffer, TimelineEvent as RawTimelineEvent,
+    },
+    replay_loading::{connect_to_sources, wait_for_input, ReplayWindow},
+    ui::{
+        ActivationDuration, AggregatedStats, ChannelKind, ChannelStats, DDShowStats, EdgeKind,
+        Lifespan, NodeKind, NodeStats, TimelineEvent,
+    },
 };
 use anyhow::{Context, Result};
 use ddshow_types::{timely_logging::OperatesEvent, OperatorAddr, OperatorId, WorkerId};
 use indicatif::MultiProgress;
+use serde::{
+    ser::{Error as _, SerializeSeq},
+    Serialize, Serializer,
+};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fs::{self, File},
     io::BufWriter,
     path::Path,
@@ -40,21 +56,34 @@ fn main() -> Result<()> {
 
     tracing::trace!("initialized and received cli args: {:?}", args);
 
+    match args.command.as_ref() {
+        Some(Command::Record(record_args)) => return record::record(&args, record_args),
+        Some(Command::ReplayServe(serve_args)) => {
+            return replay_serve::replay_serve(&args, serve_args);
+        }
+        Some(Command::Trim(trim_args)) => return trim::trim(trim_args),
+        Some(Command::Redact(redact_args)) => return redact::redact(redact_args),
+        Some(Command::Verify(verify_args)) => return verify::verify(verify_args),
+        None => {}
+    }
+
     let (communication_config, worker_config) = args.timely_config();
+    let replay_window = ReplayWindow::from_args(&args)?;
+    let running = Arc::new(AtomicBool::new(true));
 
     let (
         timely_event_receivers,
         differential_event_receivers,
         progress_event_receivers,
+        reachability_event_receivers,
         _total_sources,
-    ) = if let Some(sources) = connect_to_sources(&args)? {
+    ) = if let Some(sources) = connect_to_sources(&args, &running)? {
         sources
     } else {
         return Ok(());
     };
 
-    let (running, workers_finished, progress_bars) = (
-        Arc::new(AtomicBool::new(true)),
+    let (workers_finished, progress_bars) = (
         Arc::new(AtomicUsize::new(0)),
         Arc::new(MultiProgress::new()),
     );
@@ -98,6 +127,12 @@ fn main() -> Result<()> {
                     .expect("failed to receive progress traces")
             });
 
+            let reachability_traces = reachability_event_receivers.as_ref().map(|recv| {
+                recv[worker.index()]
+                    .recv()
+                    .expect("failed to receive reachability traces")
+            });
+
             // Start the analysis worker's runtime
             dataflow::worker_runtime(
                 worker,
@@ -109,6 +144,8 @@ fn main() -> Result<()> {
                 timely_traces,
                 differential_traces,
                 progress_traces,
+                reachability_traces,
+                replay_window,
             )
         })
         .map_err(|err| anyhow::anyhow!("failed to start up timely computation: {}", err))?;
@@ -157,7 +194,7 @@ fn main() -> Result<()> {
     }
 
     let mut edge_events = data.edges;
-    edge_events.sort_unstable_by_key(|(worker, _, channel, _)| (*worker, channel.channel_id()));
+    edge_events.sort_unstable_by_key(|(worker, _, channel, ..)| (*worker, channel.channel_id()));
     tracing::debug!("finished extracting {} edge events", edge_events.len());
 
     let (max_time, min_time) = (
@@ -167,10 +204,25 @@ fn main() -> Result<()> {
 
     let timeline_events = data.timeline_events;
     tracing::debug!(
+        spilled = timeline_events.has_spilled(),
         "finished extracting {} timeline events",
         timeline_events.len(),
     );
 
+    // Operators are associated across workers by their address and name
+    let aggregated_operator_stats: HashMap<_, _> = data
+        .aggregated_operator_stats
+        .iter()
+        .map(|stats| ((&stats.addr, stats.name.as_str()), stats))
+        .collect();
+
+    // Operators' activity is summed across every worker they exist on
+    let operator_time_series: HashMap<_, _> = data
+        .operator_time_series
+        .iter()
+        .map(|(addr, buckets)| (addr, buckets))
+        .collect();
+
     let html_nodes: Vec<_> = node_events
         .into_iter()
         .filter_map(|((worker, addr), OperatesEvent { id, name, .. })| {
@@ -181,6 +233,7 @@ fn main() -> Result<()> {
                 total,
                 activations: invocations,
                 ref activation_durations,
+                ref activation_histogram,
                 ref arrangement_size,
                 ..
             } = operator_stats.get(&(worker, id))?;
@@ -188,6 +241,24 @@ fn main() -> Result<()> {
             let fill_color = select_color(&args.palette, total, (max_time, min_time));
             let text_color = fill_color.text_color();
 
+            let (total_across_workers, worker_totals) = aggregated_operator_stats
+                .get(&(&addr, name.as_str()))
+                .map(|stats| {
+                    let worker_totals = stats
+                        .per_worker
+                        .iter()
+                        .map(|worker| (worker.worker, format!("{:#?}", worker.total)))
+                        .collect();
+
+                    (stats.total, worker_totals)
+                })
+                .unwrap_or_else(|| (total, vec![(worker, format!("{:#?}", total))]));
+
+            let time_series = operator_time_series
+                .get(&addr)
+                .map(|&buckets| buckets.clone())
+                .unwrap_or_default();
+
             Some(ui::Node {
                 id,
                 worker,
@@ -209,6 +280,16 @@ fn main() -> Result<()> {
                     .collect(),
                 max_arrangement_size: arrangement_size.as_ref().map(|arr| arr.max_size),
                 min_arrangement_size: arrangement_size.as_ref().map(|arr| arr.min_size),
+                activation_percentiles: activation_histogram
+                    .percentiles()
+                    .map(|nanos| format!("{:#?}", Duration::from_nanos(nanos))),
+                activation_histogram: activation_histogram.clone(),
+                batch_size_percentiles: arrangement_size
+                    .as_ref()
+                    .map(|arr| arr.batch_histogram.percentiles()),
+                time_series,
+                total_across_workers: format!("{:#?}", total_across_workers),
+                worker_totals,
             })
         })
         .collect();
@@ -244,10 +325,16 @@ fn main() -> Result<()> {
         })
         .collect();
 
+    let delivery_latencies: HashMap<_, _> = data
+        .channel_latencies
+        .iter()
+        .map(|latency| ((latency.worker, latency.channel), latency.clone()))
+        .collect();
+
     let html_edges: Vec<_> = edge_events
         // .clone()
         .into_iter()
-        .map(|(worker, _, channel, _)| ui::Edge {
+        .map(|(worker, _, channel, _, message_stats)| ui::Edge {
             src: channel.source_addr(),
             dest: channel.target_addr(),
             worker,
@@ -256,6 +343,11 @@ fn main() -> Result<()> {
                 Channel::Normal { .. } => EdgeKind::Normal,
                 Channel::ScopeCrossing { .. } => EdgeKind::Crossing,
             },
+            messages: message_stats.as_ref().map_or(0, |stats| stats.invocations),
+            records: message_stats.as_ref().map_or(0, |stats| stats.total),
+            delivery_latency: delivery_latencies
+                .get(&(worker, channel.channel_id()))
+                .cloned(),
         })
         .collect();
 
@@ -266,14 +358,29 @@ fn main() -> Result<()> {
         pos += 0.1;
     }
 
+    let worker_time_buckets = data
+        .worker_stats
+        .last()
+        .map(|stats| {
+            stats
+                .iter()
+                .map(|(worker, stats)| (*worker, stats.time_buckets.clone()))
+                .collect()
+        })
+        .unwrap_or_default();
+
     ui::render(
         &args,
         html_nodes,
         html_subgraphs,
         html_edges,
         palette_colors,
-        timeline_events,
+        &timeline_events,
         data.channel_progress,
+        data.pointstamp_timelines,
+        data.frontier_timelines,
+        data.worker_exchanges,
+        worker_time_buckets,
     )?;
 
     println!(" done!");
@@ -305,27 +412,100 @@ fn dump_program_json(
     args: &Args,
     file: &Path,
     data: &DataflowData,
-    _name_lookup: &HashMap<(WorkerId, OperatorId), String>,
-    _addr_lookup: &HashMap<(WorkerId, OperatorId), OperatorAddr>,
+    name_lookup: &HashMap<(WorkerId, OperatorId), String>,
+    addr_lookup: &HashMap<(WorkerId, OperatorId), OperatorAddr>,
 ) -> Result<()> {
     let file = BufWriter::new(File::create(file).context("failed to create json file")?);
 
+    let timeline_events = &data.timeline_events;
     let program = data.program_stats[0].clone();
     let workers = data.worker_stats[0]
         .iter()
         .map(|(_, stats)| stats.clone())
         .collect();
     let dataflows = data.dataflow_stats.clone();
-    let events = data
-        .timeline_events
+    let subgraphs: HashSet<_> = data
+        .subgraphs
+        .iter()
+        .map(|((worker, addr), _)| (*worker, addr))
+        .collect();
+
+    let time_series: HashMap<_, _> = data
+        .operator_time_series
+        .iter()
+        .map(|(addr, buckets)| (addr, buckets))
+        .collect();
+
+    let nodes = data
+        .operator_stats
+        .iter()
+        .filter_map(|&(operator, ref stats)| {
+            let (worker, id) = operator;
+            let addr = addr_lookup.get(&operator)?;
+
+            let kind = if addr.len() == 1 {
+                NodeKind::Dataflow
+            } else if subgraphs.contains(&(worker, addr)) {
+                NodeKind::Subgraph
+            } else {
+                NodeKind::Operator
+            };
+
+            Some(NodeStats {
+                id,
+                addr: addr.clone(),
+                worker,
+                name: name_lookup.get(&operator).cloned().unwrap_or_default(),
+                // FIXME: Operator ports aren't tracked
+                inputs: Vec::new(),
+                outputs: Vec::new(),
+                // FIXME: Operator lifespans aren't tracked
+                lifespan: Lifespan::default(),
+                kind,
+                activations: AggregatedStats {
+                    total: stats.activations,
+                    max: stats.max,
+                    min: stats.min,
+                    average: stats.average,
+                    data_points: Vec::new(),
+                },
+                activation_percentiles: stats
+                    .activation_histogram
+                    .percentiles()
+                    .map(Duration::from_nanos),
+                activation_histogram: stats.activation_histogram.clone(),
+                time_series: time_series
+                    .get(addr)
+                    .map(|&buckets| buckets.clone())
+                    .unwrap_or_default(),
+            })
+        })
+        .collect();
+
+    let channels = data
+        .edges
         .iter()
-        .map(|event| TimelineEvent {
-            worker: event.worker,
-            event: (),
-            lifespan: Lifespan::new(
-                Duration::from_nanos(event.start_time),
-                Duration::from_nanos(event.start_time + event.duration),
-            ),
+        .map(|(worker, source, channel, target, message_stats)| {
+            let (source_addr, target_addr) = (channel.source_addr(), channel.target_addr());
+
+            ChannelStats {
+                id: channel.channel_id(),
+                addr: source_addr.clone(),
+                worker: *worker,
+                source_node: source.id,
+                dest_node: target.id,
+                kind: match channel {
+                    Channel::Normal { .. } => ChannelKind::Normal,
+                    Channel::ScopeCrossing { .. } if target_addr.len() > source_addr.len() => {
+                        ChannelKind::Ingress
+                    }
+                    Channel::ScopeCrossing { .. } => ChannelKind::Egress,
+                },
+                // FIXME: Channel lifespans aren't tracked
+                lifespan: Lifespan::default(),
+                messages: message_stats.as_ref().map_or(0, |stats| stats.invocations),
+                records: message_stats.as_ref().map_or(0, |stats| stats.total),
+            }
         })
         .collect();
 
@@ -333,17 +513,56 @@ fn dump_program_json(
         program,
         workers,
         dataflows,
+        nodes,
+        channels,
         // FIXME: Do these
-        nodes: Vec::new(),
-        channels: Vec::new(),
         arrangements: Vec::new(),
-        events,
+        // Timeline events are streamed into the file separately
+        events: Vec::new(),
         differential_enabled: args.differential_enabled,
         progress_enabled: false, // args.progress_enabled,
         ddshow_version: DDSHOW_VERSION.to_string(),
     };
 
-    serde_json::to_writer(file, &data).context("failed to write json to file")?;
+    let dump = DumpedStats {
+        stats: data,
+        events: DumpedEvents(timeline_events),
+    };
+    serde_json::to_writer(file, &dump).context("failed to write json to file")?;
 
     Ok(())
 }
+
+/// The program's stats along with its timeline events, which are
+/// streamed from the extracted data as they're written
+#[derive(Serialize)]
+struct DumpedStats<'a> {
+    #[serde(flatten)]
+    stats: DDShowStats,
+    events: DumpedEvents<'a>,
+}
+
+struct DumpedEvents<'a>(&'a SpillBuffer<RawTimelineEvent>);
+
+impl Serialize for DumpedEvents<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
+        for event in self.0.iter() {
+            let event = event.map_err(S::Error::custom)?;
+
+            seq.serialize_element(&TimelineEvent {
+                worker: event.worker,
+                event: (),
+                lifespan: Lifespan::new(
+                    Duration::from_nanos(event.start_time),
+                    Duration::from_nanos(event.start_time + event.duration),
+                ),
+            })?;
+        }
+
+        seq.end()
+    }
+}
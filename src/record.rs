@@ -0,0 +1,809 @@
+//! Recording the logs of a running computation straight to disk
+//!
+//! Record mode accepts the same connections as a normal run but skips building the
+//! analysis dataflow entirely, each connection gets its own thread that re-encodes
+//! incoming events into the same rkyv format that `--save-logs` produces so that
+//! recordings can be analyzed later with `--replay-logs`
+
+use crate::{
+    args::{Args, RecordArgs, StreamEncoding},
+    dataflow::{
+        constants::DDSHOW_VERSION,
+        operators::EventIterator,
+        utils::{
+            self, DifferentialLogBundle, ProgressLogBundle, ReachabilityLogBundle, TimelyLogBundle,
+        },
+    },
+    replay_loading::{connect_to_sources, ReplaySource},
+    replay_serve::TrackErrors,
+};
+use anyhow::{Context, Result};
+use bytecheck::CheckBytes;
+use crossbeam_channel::Receiver;
+use ddshow_sink::{
+    framing::Framing, LogWriter, DIFFERENTIAL_ARRANGEMENT_LOG_FILE, REACHABILITY_LOG_FILE,
+    TIMELY_LOG_FILE, TIMELY_PROGRESS_LOG_FILE,
+};
+use ddshow_types::WorkerId;
+use indicatif::{HumanBytes, MultiProgress, ProgressBar, ProgressStyle};
+use rkyv::{
+    ser::serializers::AlignedSerializer, validation::DefaultArchiveValidator, AlignedVec,
+    Serialize as RkyvSerialize,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::RefCell,
+    fmt::Debug,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    rc::Rc,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, SystemTime},
+};
+use timely::dataflow::operators::capture::{Event, EventPusher};
+
+/// The name of the manifest file written into recorded log directories
+pub(crate) const RECORD_MANIFEST_FILE: &str = "manifest.json";
+
+/// How often the recording counters are refreshed
+const COUNTER_REFRESH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// A description of a recorded log directory
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordManifest {
+    /// The version of ddshow that made the recording
+    pub ddshow_version: String,
+    /// The encoding the recorded sources sent their events with,
+    /// recorded files are always rkyv-encoded
+    pub source_encoding: String,
+    /// The number of timely workers the recorded computation was running
+    pub timely_connections: usize,
+    /// When the recording started, in milliseconds since the unix epoch
+    pub started_at: u64,
+    /// How long the recording ran for, in milliseconds
+    pub duration: u64,
+    pub sources: Vec<RecordedSource>,
+}
+
+impl RecordManifest {
+    pub fn write(&self, log_dir: &Path) -> Result<()> {
+        let path = log_dir.join(RECORD_MANIFEST_FILE);
+        let file = File::create(&path)
+            .with_context(|| format!("failed to create manifest file {}", path.display()))?;
+
+        serde_json::to_writer_pretty(BufWriter::new(file), self)
+            .with_context(|| format!("failed to write manifest file {}", path.display()))
+    }
+
+    /// Reads the manifest of a log directory, returning `None` if
+    /// it wasn't made by `ddshow record`
+    pub fn read(log_dir: &Path) -> Result<Option<Self>> {
+        let path = log_dir.join(RECORD_MANIFEST_FILE);
+        if !path.is_file() {
+            return Ok(None);
+        }
+
+        let file = File::open(&path)
+            .with_context(|| format!("failed to open manifest file {}", path.display()))?;
+
+        serde_json::from_reader(BufReader::new(file))
+            .map(Some)
+            .with_context(|| format!("failed to read manifest file {}", path.display()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedSource {
+    pub kind: SourceKind,
+    /// The name of the source's log file within the log directory
+    pub file: String,
+    /// The number of log events received from the source
+    pub events: u64,
+    /// The number of bytes written to the source's log file
+    pub bytes: u64,
+    /// Whether the source disconnected before the recording was stopped
+    pub complete: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceKind {
+    Timely,
+    Differential,
+    Progress,
+    Reachability,
+}
+
+impl SourceKind {
+    pub(crate) const fn file_prefix(self) -> &'static str {
+        match self {
+            Self::Timely => TIMELY_LOG_FILE,
+            Self::Differential => DIFFERENTIAL_ARRANGEMENT_LOG_FILE,
+            Self::Progress => TIMELY_PROGRESS_LOG_FILE,
+            Self::Reachability => REACHABILITY_LOG_FILE,
+        }
+    }
+
+    pub(crate) const fn name(self) -> &'static str {
+        match self {
+            Self::Timely => "timely",
+            Self::Differential => "differential",
+            Self::Progress => "progress",
+            Self::Reachability => "reachability",
+        }
+    }
+}
+
+impl FromStr for SourceKind {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let lowercase = string.trim().to_lowercase();
+        match lowercase.as_str() {
+            "timely" => Ok(Self::Timely),
+            "differential" => Ok(Self::Differential),
+            "progress" => Ok(Self::Progress),
+            "reachability" => Ok(Self::Reachability),
+            _ => Err(format!(
+                "invalid log kind {:?}, only `timely`, `differential`, `progress` \
+                and `reachability` are supported",
+                string,
+            )),
+        }
+    }
+}
+
+/// Live counters for a single recorded source
+#[derive(Debug, Default)]
+struct SourceCounters {
+    events: AtomicU64,
+    bytes: AtomicU64,
+    finished: AtomicBool,
+}
+
+struct RecordingSource {
+    kind: SourceKind,
+    path: PathBuf,
+    counters: Arc<SourceCounters>,
+    progress: ProgressBar,
+    handle: JoinHandle<Result<()>>,
+}
+
+pub fn record(args: &Args, record_args: &RecordArgs) -> Result<()> {
+    if args.is_file_sourced() {
+        anyhow::bail!("`ddshow record` can't be used with `--replay-logs`");
+    }
+
+    let log_dir = &record_args.log_dir;
+    fs::create_dir_all(log_dir)
+        .with_context(|| format!("failed to create log directory {}", log_dir.display()))?;
+
+    let running = Arc::new(AtomicBool::new(true));
+    let (timely, differential, progress, reachability, _total_sources) =
+        if let Some(sources) = connect_to_sources(args, &running)? {
+            sources
+        } else {
+            return Ok(());
+        };
+
+    let started_at = SystemTime::now();
+    let multi_progress = MultiProgress::new();
+
+    let mut sources = Vec::new();
+    record_sources::<TimelyLogBundle>(
+        SourceKind::Timely,
+        collect_readers(&timely),
+        log_dir,
+        record_args.framing(),
+        &running,
+        &multi_progress,
+        &mut sources,
+    )?;
+
+    if let Some(differential) = differential.as_deref() {
+        record_sources::<DifferentialLogBundle>(
+            SourceKind::Differential,
+            collect_readers(differential),
+            log_dir,
+            record_args.framing(),
+            &running,
+            &multi_progress,
+            &mut sources,
+        )?;
+    }
+
+    if let Some(progress) = progress.as_deref() {
+        record_sources::<ProgressLogBundle>(
+            SourceKind::Progress,
+            collect_readers(progress),
+            log_dir,
+            record_args.framing(),
+            &running,
+            &multi_progress,
+            &mut sources,
+        )?;
+    }
+
+    if let Some(reachability) = reachability.as_deref() {
+        record_sources::<ReachabilityLogBundle>(
+            SourceKind::Reachability,
+            collect_readers(reachability),
+            log_dir,
+            record_args.framing(),
+            &running,
+            &multi_progress,
+            &mut sources,
+        )?;
+    }
+
+    let manifest = |sources: &[RecordingSource]| RecordManifest {
+        ddshow_version: DDSHOW_VERSION.to_owned(),
+        source_encoding: match args.stream_encoding {
+            StreamEncoding::Abomonation => "abomonation",
+            StreamEncoding::Rkyv => "rkyv",
+        }
+        .to_owned(),
+        timely_connections: args.timely_connections.get(),
+        started_at: started_at
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64,
+        duration: started_at.elapsed().unwrap_or_default().as_millis() as u64,
+        sources: sources
+            .iter()
+            .map(|source| RecordedSource {
+                kind: source.kind,
+                file: source
+                    .path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                events: source.counters.events.load(Ordering::Acquire),
+                bytes: source.counters.bytes.load(Ordering::Acquire),
+                complete: source.counters.finished.load(Ordering::Acquire),
+            })
+            .collect(),
+    };
+
+    // Write the manifest up front so that interrupted recordings still have one
+    manifest(&sources).write(log_dir)?;
+
+    println!("Press enter to finish recording...");
+    let stdin = wait_for_stdin();
+    let drawer = thread::spawn(move || multi_progress.join());
+
+    loop {
+        for source in sources.iter() {
+            source.progress.set_message(format!(
+                "{} events, {}",
+                source.counters.events.load(Ordering::Acquire),
+                HumanBytes(source.counters.bytes.load(Ordering::Acquire)),
+            ));
+        }
+
+        if sources
+            .iter()
+            .all(|source| source.counters.finished.load(Ordering::Acquire))
+        {
+            tracing::info!("all recorded sources disconnected");
+            break;
+        }
+
+        // Forwarding and write failures can stop the recording
+        if !running.load(Ordering::Acquire) {
+            tracing::info!("recording was stopped, `running` was set to false");
+            break;
+        }
+
+        if stdin.recv_timeout(COUNTER_REFRESH_INTERVAL).is_ok() {
+            tracing::info!("received input from user, stopping recording");
+            break;
+        }
+    }
+
+    running.store(false, Ordering::Release);
+
+    let manifest = manifest(&sources);
+    for source in sources {
+        source.progress.finish_with_message(format!(
+            "{} events, {}",
+            source.counters.events.load(Ordering::Acquire),
+            HumanBytes(source.counters.bytes.load(Ordering::Acquire)),
+        ));
+
+        let path = source.path;
+        source
+            .handle
+            .join()
+            .map_err(|_| anyhow::anyhow!("a recording thread panicked"))?
+            .with_context(|| format!("failed to record {}", path.display()))?;
+    }
+
+    drawer
+        .join()
+        .map_err(|_| anyhow::anyhow!("the progress bar thread panicked"))?
+        .context("failed to draw progress bars")?;
+
+    manifest.write(log_dir)?;
+    println!(
+        "Recorded {} events ({}) to {}",
+        manifest
+            .sources
+            .iter()
+            .map(|source| source.events)
+            .sum::<u64>(),
+        HumanBytes(manifest.sources.iter().map(|source| source.bytes).sum()),
+        log_dir.display(),
+    );
+
+    Ok(())
+}
+
+type RecordedEvents<D> = Box<dyn EventIterator<Duration, D> + Send + 'static>;
+
+/// Pulls every connection out of the receivers made for the analysis workers
+fn collect_readers<R, A, D, RawEvent>(
+    receivers: &[Receiver<ReplaySource<R, A>>],
+) -> Vec<RecordedEvents<(Duration, WorkerId, D)>>
+where
+    R: EventIterator<Duration, (Duration, WorkerId, D)> + Send + 'static,
+    A: EventIterator<Duration, (Duration, usize, RawEvent)> + Send + 'static,
+    D: From<RawEvent> + 'static,
+    RawEvent: 'static,
+{
+    receivers
+        .iter()
+        .filter_map(|receiver| receiver.try_recv().ok())
+        .flat_map(|source| -> Vec<RecordedEvents<_>> {
+            match source {
+                ReplaySource::Rkyv(rkyv) => rkyv
+                    .into_iter()
+                    .map(|reader| Box::new(reader) as RecordedEvents<_>)
+                    .collect(),
+
+                ReplaySource::Abomonation(abomonation) => abomonation
+                    .into_iter()
+                    .map(|reader| Box::new(ConvertEvents::new(reader)) as RecordedEvents<_>)
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+fn record_sources<D>(
+    kind: SourceKind,
+    readers: Vec<RecordedEvents<D>>,
+    log_dir: &Path,
+    framing: Framing,
+    running: &Arc<AtomicBool>,
+    multi_progress: &MultiProgress,
+    sources: &mut Vec<RecordingSource>,
+) -> Result<()>
+where
+    D: for<'a> RkyvSerialize<AlignedSerializer<&'a mut AlignedVec>> + Debug + Send + 'static,
+    D::Archived: CheckBytes<DefaultArchiveValidator>,
+{
+    let style = ProgressStyle::default_spinner()
+        .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈ ")
+        .template("[{elapsed}] {spinner} {prefix}: {wide_msg}");
+
+    for (idx, events) in readers.into_iter().enumerate() {
+        let path = utils::log_file_path(kind.file_prefix(), log_dir, idx);
+        let file = File::create(&path)
+            .with_context(|| format!("failed to create log file {}", path.display()))?;
+
+        let progress = multi_progress.add(
+            ProgressBar::new_spinner()
+                .with_style(style.clone())
+                .with_prefix(format!("Recording {} source {}", kind.name(), idx)),
+        );
+        utils::set_steady_tick(&progress, sources.len() + 1);
+
+        let counters = Arc::new(SourceCounters::default());
+        let handle = {
+            let (counters, running) = (counters.clone(), running.clone());
+
+            thread::Builder::new()
+                .name(format!("ddshow-record-{}-{}", kind.name(), idx))
+                .spawn(move || record_events(events, file, framing, &counters, &running))
+                .context("failed to spawn recording thread")?
+        };
+
+        sources.push(RecordingSource {
+            kind,
+            path,
+            counters,
+            progress,
+            handle,
+        });
+    }
+
+    Ok(())
+}
+
+fn record_events<D, W>(
+    events: RecordedEvents<D>,
+    file: W,
+    framing: Framing,
+    counters: &SourceCounters,
+    running: &AtomicBool,
+) -> Result<()>
+where
+    D: for<'a> RkyvSerialize<AlignedSerializer<&'a mut AlignedVec>> + Debug,
+    D::Archived: CheckBytes<DefaultArchiveValidator>,
+    W: Write,
+{
+    let result = write_events(events, file, framing, counters, running);
+
+    // A failed source stops the recording instead of silently dropping its events
+    if result.is_err() {
+        running.store(false, Ordering::Release);
+    }
+
+    result
+}
+
+fn write_events<D, W>(
+    mut events: RecordedEvents<D>,
+    file: W,
+    framing: Framing,
+    counters: &SourceCounters,
+    running: &AtomicBool,
+) -> Result<()>
+where
+    D: for<'a> RkyvSerialize<AlignedSerializer<&'a mut AlignedVec>> + Debug,
+    D::Archived: CheckBytes<DefaultArchiveValidator>,
+    W: Write,
+{
+    // Bytes are counted once they leave the buffer so that only written bytes are counted
+    let mut file = BufWriter::new(CountingWriter::new(file, &counters.bytes));
+
+    let error = Rc::new(RefCell::new(None));
+    {
+        let tracked = TrackErrors {
+            writer: &mut file,
+            error: error.clone(),
+        };
+        let mut writer = LogWriter::new(tracked, framing);
+        let mut is_finished = false;
+
+        while running.load(Ordering::Acquire) {
+            match events.next(&mut is_finished)? {
+                Some(event) => {
+                    let received = match &event {
+                        Event::Messages(_, data) => data.len() as u64,
+                        Event::Progress(_) => 0,
+                    };
+                    writer.push(event);
+
+                    // The rkyv writer only logs its errors so we have to pick them up ourselves
+                    let error = error.borrow_mut().take();
+                    error
+                        .map_or(Ok(()), Err)
+                        .context("failed to write to log file")?;
+
+                    counters.events.fetch_add(received, Ordering::Release);
+                }
+
+                None if is_finished => {
+                    counters.finished.store(true, Ordering::Release);
+                    break;
+                }
+
+                None => thread::sleep(Duration::from_millis(1)),
+            }
+        }
+    }
+
+    // Batched writers write their last batch when they're dropped
+    let error = error.borrow_mut().take();
+    error
+        .map_or(Ok(()), Err)
+        .context("failed to write to log file")?;
+
+    file.flush().context("failed to flush log file")
+}
+
+/// Converts the raw events sent by abomonation sources into their ddshow equivalents
+struct ConvertEvents<A, RawEvent> {
+    reader: A,
+    __type: PhantomData<fn() -> RawEvent>,
+}
+
+impl<A, RawEvent> ConvertEvents<A, RawEvent> {
+    const fn new(reader: A) -> Self {
+        Self {
+            reader,
+            __type: PhantomData,
+        }
+    }
+}
+
+impl<A, D, RawEvent> EventIterator<Duration, (Duration, WorkerId, D)> for ConvertEvents<A, RawEvent>
+where
+    A: EventIterator<Duration, (Duration, usize, RawEvent)>,
+    D: From<RawEvent>,
+{
+    fn next(
+        &mut self,
+        is_finished: &mut bool,
+    ) -> io::Result<Option<Event<Duration, (Duration, WorkerId, D)>>> {
+        Ok(self.reader.next(is_finished)?.map(convert_event))
+    }
+}
+
+/// Converts a raw event sent by an abomonation source into its ddshow equivalent
+pub(crate) fn convert_event<D, RawEvent>(
+    event: Event<Duration, (Duration, usize, RawEvent)>,
+) -> Event<Duration, (Duration, WorkerId, D)>
+where
+    D: From<RawEvent>,
+{
+    match event {
+        Event::Progress(progress) => Event::Progress(progress),
+        Event::Messages(time, data) => Event::Messages(
+            time,
+            data.into_iter()
+                .map(|(time, worker, event)| (time, WorkerId::new(worker), D::from(event)))
+                .collect(),
+        ),
+    }
+}
+
+/// A writer that keeps a running count of the bytes written through it
+struct CountingWriter<'a, W> {
+    writer: W,
+    bytes: &'a AtomicU64,
+}
+
+impl<'a, W> CountingWriter<'a, W> {
+    const fn new(writer: W, bytes: &'a AtomicU64) -> Self {
+        Self { writer, bytes }
+    }
+}
+
+impl<W: Write> Write for CountingWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.writer.write(buf)?;
+        self.bytes.fetch_add(written as u64, Ordering::Release);
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Spawns a thread that sends a message once the user presses enter
+fn wait_for_stdin() -> Receiver<()> {
+    let (send, recv) = crossbeam_channel::bounded(1);
+
+    thread::spawn(move || {
+        let _ = io::stdin().read(&mut [0]);
+
+        tracing::debug!("stdin thread got input from stdin");
+        let _ = send.send(());
+    });
+
+    recv
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        dataflow::{operators::EventIterator, utils::TimelyLogBundle},
+        record::{
+            record_events, RecordManifest, RecordedSource, SourceCounters, SourceKind,
+            RECORD_MANIFEST_FILE,
+        },
+        replay_serve::read_events,
+    };
+    use ddshow_sink::framing::Framing;
+    use ddshow_types::{
+        timely_logging::{OperatesEvent, TimelyEvent},
+        OperatorAddr, OperatorId, WorkerId,
+    };
+    use indicatif::ProgressBar;
+    use std::{
+        collections::VecDeque,
+        fs::{self, File},
+        io::{self, Write},
+        path::PathBuf,
+        process,
+        sync::atomic::{AtomicBool, Ordering},
+        time::Duration,
+    };
+    use timely::dataflow::operators::capture::Event;
+
+    type LogEvent = Event<Duration, TimelyLogBundle>;
+
+    const FRAMINGS: [Framing; 4] = [
+        Framing::Single { checksums: false },
+        Framing::Single { checksums: true },
+        Framing::Batched { checksums: false },
+        Framing::Batched { checksums: true },
+    ];
+
+    /// A source that sends a fixed set of events and then disconnects
+    struct Source(VecDeque<LogEvent>);
+
+    impl EventIterator<Duration, TimelyLogBundle> for Source {
+        fn next(&mut self, is_finished: &mut bool) -> io::Result<Option<LogEvent>> {
+            let event = self.0.pop_front();
+            *is_finished = event.is_none();
+
+            Ok(event)
+        }
+    }
+
+    /// A disk that's run out of space
+    struct FullDisk;
+
+    impl Write for FullDisk {
+        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            Err(io::Error::new(
+                io::ErrorKind::WriteZero,
+                "no space left on device",
+            ))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Pairs of messages and progress updates
+    fn events() -> Vec<LogEvent> {
+        (0..100)
+            .flat_map(|idx| {
+                let time = Duration::from_millis(idx);
+                let operator = OperatorId::new(idx as usize);
+
+                vec![
+                    Event::Messages(
+                        time,
+                        vec![(
+                            time,
+                            WorkerId::new(0),
+                            TimelyEvent::Operates(OperatesEvent::new(
+                                operator,
+                                OperatorAddr::from_elem(operator),
+                                format!("Operator {}", idx),
+                            )),
+                        )],
+                    ),
+                    Event::Progress(vec![(time + Duration::from_millis(1), 1), (time, -1)]),
+                ]
+            })
+            .collect()
+    }
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("ddshow-record-test-{}-{}", process::id(), name));
+        fs::create_dir_all(&dir).unwrap();
+
+        dir
+    }
+
+    #[test]
+    fn manifests() {
+        let dir = test_dir("manifest");
+        assert_eq!(RecordManifest::read(&dir).unwrap(), None);
+
+        let manifest = RecordManifest {
+            ddshow_version: "0.2.2".to_owned(),
+            source_encoding: "rkyv".to_owned(),
+            timely_connections: 2,
+            started_at: 1_600_000_000_000,
+            duration: 1500,
+            sources: vec![
+                RecordedSource {
+                    kind: SourceKind::Timely,
+                    file: "timely.worker-0.ddshow".to_owned(),
+                    events: 200,
+                    bytes: 4096,
+                    complete: true,
+                },
+                RecordedSource {
+                    kind: SourceKind::Reachability,
+                    file: "reachability.worker-0.ddshow".to_owned(),
+                    events: 0,
+                    bytes: 16,
+                    complete: false,
+                },
+            ],
+        };
+        manifest.write(&dir).unwrap();
+        assert_eq!(RecordManifest::read(&dir).unwrap(), Some(manifest));
+
+        // Source kinds are written in lowercase
+        let written = fs::read_to_string(dir.join(RECORD_MANIFEST_FILE)).unwrap();
+        assert!(written.contains("\"kind\": \"reachability\""));
+
+        fs::write(dir.join(RECORD_MANIFEST_FILE), "{").unwrap();
+        assert!(RecordManifest::read(&dir).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn recordings_replay() {
+        let events = events();
+        let dir = test_dir("replay");
+        let path = dir.join("timely.worker-0.ddshow");
+
+        for framing in FRAMINGS.iter().copied() {
+            let (counters, running) = (SourceCounters::default(), AtomicBool::new(true));
+            let source = Box::new(Source(events.iter().cloned().collect()));
+            record_events(
+                source,
+                File::create(&path).unwrap(),
+                framing,
+                &counters,
+                &running,
+            )
+            .unwrap();
+
+            assert_eq!(
+                counters.events.load(Ordering::Acquire),
+                100,
+                "{:?}",
+                framing
+            );
+            assert_eq!(
+                counters.bytes.load(Ordering::Acquire),
+                fs::metadata(&path).unwrap().len(),
+                "{:?}",
+                framing,
+            );
+            assert!(counters.finished.load(Ordering::Acquire));
+
+            let mut replayed = Vec::new();
+            read_events::<TimelyLogBundle, _>(&path, &ProgressBar::hidden(), |event| {
+                replayed.push(event);
+                Ok(())
+            })
+            .unwrap();
+            assert_eq!(replayed, events, "{:?}", framing);
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn write_errors_stop_recording() {
+        for framing in FRAMINGS.iter().copied() {
+            let (counters, running) = (SourceCounters::default(), AtomicBool::new(true));
+            // Enough events to fill several batches, so that batched
+            // writers fail before they reach the end of the source
+            let source = Box::new(Source(events().into_iter().cycle().take(2000).collect()));
+
+            let error = record_events(source, FullDisk, framing, &counters, &running).unwrap_err();
+            assert!(
+                format!("{:#}", error).contains("no space left on device"),
+                "{:?}: {:#}",
+                framing,
+                error,
+            );
+
+            // Events that couldn't be written aren't counted as recorded
+            assert!(
+                counters.events.load(Ordering::Acquire) < 1000,
+                "{:?}",
+                framing
+            );
+            assert_eq!(counters.bytes.load(Ordering::Acquire), 0);
+            assert!(!counters.finished.load(Ordering::Acquire));
+            assert!(!running.load(Ordering::Acquire));
+        }
+    }
+}
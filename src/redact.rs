@@ -0,0 +1,179 @@
+//! Redacting the operator names of saved logs
+//!
+//! `ddshow redact` writes a copy of a log directory with every operator name replaced
+//! using [`ddshow_sink::redaction`]. Only timely logs contain operator names, so the
+//! other logs are copied over unchanged
+
+use crate::{
+    args::RedactArgs,
+    record::{RecordManifest, SourceKind},
+    replay_serve::log_files,
+    trim::rewrite_log_file,
+};
+use anyhow::{Context, Result};
+use ddshow_sink::Redactor;
+use ddshow_types::{timely_logging::TimelyEvent, WorkerId};
+use indicatif::{HumanBytes, ProgressBar, ProgressStyle};
+use std::{
+    fs::{self, File},
+    io::BufWriter,
+    path::Path,
+    time::Duration,
+};
+use timely::dataflow::operators::capture::Event;
+
+pub fn redact(redact_args: &RedactArgs) -> Result<()> {
+    let (log_dir, output_dir) = (&redact_args.log_dir, &redact_args.output_dir);
+
+    let canonical_log_dir = fs::canonicalize(log_dir)
+        .with_context(|| format!("failed to read log directory {}", log_dir.display()))?;
+    if matches!(fs::canonicalize(output_dir), Ok(output_dir) if output_dir == canonical_log_dir) {
+        anyhow::bail!("the redacted logs can't be written over the logs they were redacted from");
+    }
+
+    let mut sources = Vec::new();
+    let mut total_bytes = 0;
+    for kind in [
+        SourceKind::Timely,
+        SourceKind::Differential,
+        SourceKind::Progress,
+        SourceKind::Reachability,
+    ] {
+        for path in log_files(log_dir, kind.file_prefix())? {
+            total_bytes += fs::metadata(&path)
+                .with_context(|| format!("failed to read metadata of {}", path.display()))?
+                .len();
+
+            sources.push((kind, path));
+        }
+    }
+
+    if !sources.iter().any(|(kind, _)| *kind == SourceKind::Timely) {
+        anyhow::bail!("no timely logs were found in {}", log_dir.display());
+    }
+
+    fs::create_dir_all(output_dir)
+        .with_context(|| format!("failed to create output directory {}", output_dir.display()))?;
+
+    let mut redactor = match redact_args.mapping_file.as_deref() {
+        Some(mapping_file) => {
+            if is_within(mapping_file, output_dir)? {
+                anyhow::bail!(
+                    "the redaction mapping contains the original operator names and \
+                    can't be written alongside the redacted logs in {}",
+                    output_dir.display(),
+                );
+            }
+
+            let file = File::create(mapping_file).with_context(|| {
+                format!(
+                    "failed to create the redaction mapping {}",
+                    mapping_file.display(),
+                )
+            })?;
+
+            Redactor::with_mapping(redact_args.mode, BufWriter::new(file))
+                .context("failed to write the redaction mapping")?
+        }
+
+        None => Redactor::new(redact_args.mode),
+    };
+
+    let progress = ProgressBar::new(total_bytes)
+        .with_style(
+            ProgressStyle::default_bar()
+                .template("[{elapsed}] {prefix}: {wide_bar} {bytes}/{total_bytes}"),
+        )
+        .with_prefix(format!("Redacting logs from {}", log_dir.display()));
+
+    let mut written = Vec::with_capacity(sources.len());
+    let mut redacted_operators = 0;
+    for (kind, path) in sources {
+        let file_name = path
+            .file_name()
+            .context("log files always have a file name")?
+            .to_string_lossy()
+            .into_owned();
+        let output = output_dir.join(&file_name);
+
+        // Only timely events contain operator names
+        let bytes = if kind == SourceKind::Timely {
+            rewrite_log_file::<(Duration, WorkerId, TimelyEvent), _>(
+                &path,
+                &output,
+                &progress,
+                |mut event| {
+                    if let Event::Messages(_, data) = &mut event {
+                        for event in data.iter_mut() {
+                            redacted_operators += event.2.is_operates() as usize;
+                            redactor
+                                .redact(event)
+                                .context("failed to write the redaction mapping")?;
+                        }
+                    }
+
+                    Ok(Some(event))
+                },
+            )
+        } else {
+            let bytes = fs::copy(&path, &output)
+                .with_context(|| format!("failed to copy to {}", output.display()));
+            if let Ok(bytes) = bytes {
+                progress.inc(bytes);
+            }
+
+            bytes
+        }
+        .with_context(|| format!("failed to redact {}", path.display()))?;
+
+        written.push((file_name, bytes));
+    }
+    progress.finish_and_clear();
+
+    redactor
+        .flush()
+        .context("failed to write the redaction mapping")?;
+
+    // The manifest doesn't contain any names, but the sizes of the timely logs have changed
+    if let Some(mut manifest) = RecordManifest::read(log_dir)? {
+        for source in manifest.sources.iter_mut() {
+            if let Some((_, bytes)) = written.iter().find(|(file, _)| *file == source.file) {
+                source.bytes = *bytes;
+            }
+        }
+
+        manifest.write(output_dir)?;
+    }
+
+    println!(
+        "Redacted {} operator names from {} log files ({}), wrote the redacted logs to {}",
+        redacted_operators,
+        written.len(),
+        HumanBytes(written.iter().map(|(_, bytes)| bytes).sum()),
+        output_dir.display(),
+    );
+    if let Some(mapping_file) = redact_args.mapping_file.as_deref() {
+        println!(
+            "Wrote the redaction mapping to {}, keep it private since it contains the original names",
+            mapping_file.display(),
+        );
+    }
+
+    Ok(())
+}
+
+/// Returns `true` if `path` would be created somewhere within `dir`
+fn is_within(path: &Path, dir: &Path) -> Result<bool> {
+    let dir = fs::canonicalize(dir)
+        .with_context(|| format!("failed to read directory {}", dir.display()))?;
+
+    // The file itself may not exist yet, but its parent has to
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let parent = fs::canonicalize(parent)
+        .with_context(|| format!("failed to read directory {}", parent.display()))?;
+
+    Ok(parent.starts_with(dir))
+}
@@ -1,31 +1,46 @@
 use crate::{
-    args::{Args, StreamEncoding},
+    args::{Args, ReplayBound, StreamEncoding},
     dataflow::{
-        constants::{IDLE_EXTRACTION_FUEL, TCP_READ_TIMEOUT},
-        operators::{EventReader, Fuel, RkyvEventReader},
-        utils::{self, DifferentialLogBundle, ProgressLogBundle, TimelyLogBundle},
+        constants::TCP_READ_TIMEOUT,
+        operators::{EventIterator, Fuel, RkyvEventReader},
+        utils::{
+            self, DifferentialLogBundle, ProgressLogBundle, ReachabilityLogBundle, TimelyLogBundle,
+        },
         DataflowData, DataflowReceivers,
     },
+    forward::{self, AbomonationSource, Forwarding},
+    record::{RecordManifest, SourceKind, RECORD_MANIFEST_FILE},
 };
 use abomonation::Abomonation;
 use anyhow::{Context, Result};
 use bytecheck::CheckBytes;
 use crossbeam_channel::Receiver;
-use ddshow_sink::{DIFFERENTIAL_ARRANGEMENT_LOG_FILE, TIMELY_LOG_FILE, TIMELY_PROGRESS_LOG_FILE};
-use ddshow_types::progress_logging::TimelyProgressEvent;
+use ddshow_sink::{
+    DIFFERENTIAL_ARRANGEMENT_LOG_FILE, REACHABILITY_LOG_FILE, TIMELY_LOG_FILE,
+    TIMELY_PROGRESS_LOG_FILE,
+};
+use ddshow_types::{
+    differential_logging::DifferentialEvent,
+    progress_logging::TimelyProgressEvent,
+    reachability_logging::TrackerEvent,
+    timely_logging::{ScheduleEvent, StartStop, TimelyEvent},
+    OperatorId, WorkerId,
+};
 use differential_dataflow::logging::DifferentialEvent as RawDifferentialEvent;
 use indicatif::{ProgressBar, ProgressStyle};
 use rkyv::{
     de::deserializers::AllocDeserializer, validation::DefaultArchiveValidator, Archive, Deserialize,
 };
 use std::{
+    collections::HashSet,
     ffi::OsStr,
     fmt::Debug,
     fs::{self, File},
     hint,
     io::{self, BufReader, Read, Write},
     iter,
-    net::{SocketAddr, TcpListener, TcpStream},
+    marker::PhantomData,
+    net::{SocketAddr, TcpListener},
     num::NonZeroUsize,
     path::Path,
     sync::{
@@ -33,7 +48,7 @@ use std::{
         Arc, Barrier,
     },
     thread,
-    time::Duration,
+    time::{Duration, UNIX_EPOCH},
 };
 use timely::{
     communication::WorkerGuards, dataflow::operators::capture::Event,
@@ -42,25 +57,31 @@ use timely::{
 
 type AcquiredStreams<T, D1, D2> = EventReceivers<
     RkyvEventReader<T, D1, Box<dyn Read + Send + 'static>>,
-    EventReader<T, D2, TcpStream>,
+    AbomonationSource<T, D2>,
 >;
 
 pub(crate) type TimelyEventReceivers = Arc<[Receiver<TimelyReplaySource>]>;
 pub(crate) type TimelyReplaySource = ReplaySource<
     RkyvEventReader<Duration, TimelyLogBundle, Box<dyn Read + Send + 'static>>,
-    EventReader<Duration, (Duration, usize, RawTimelyEvent), TcpStream>,
+    AbomonationSource<Duration, (Duration, usize, RawTimelyEvent)>,
 >;
 
 pub(crate) type DifferentialEventReceivers = Option<Arc<[Receiver<DifferentialReplaySource>]>>;
 pub(crate) type DifferentialReplaySource = ReplaySource<
     RkyvEventReader<Duration, DifferentialLogBundle, Box<dyn Read + Send + 'static>>,
-    EventReader<Duration, (Duration, usize, RawDifferentialEvent), TcpStream>,
+    AbomonationSource<Duration, (Duration, usize, RawDifferentialEvent)>,
 >;
 
 pub(crate) type ProgressEventReceivers = Option<Arc<[Receiver<ProgressReplaySource>]>>;
 pub(crate) type ProgressReplaySource = ReplaySource<
     RkyvEventReader<Duration, ProgressLogBundle, Box<dyn Read + Send + 'static>>,
-    EventReader<Duration, (Duration, usize, TimelyProgressEvent), TcpStream>,
+    AbomonationSource<Duration, (Duration, usize, TimelyProgressEvent)>,
+>;
+
+pub(crate) type ReachabilityEventReceivers = Option<Arc<[Receiver<ReachabilityReplaySource>]>>;
+pub(crate) type ReachabilityReplaySource = ReplaySource<
+    RkyvEventReader<Duration, ReachabilityLogBundle, Box<dyn Read + Send + 'static>>,
+    AbomonationSource<Duration, (Duration, usize, TrackerEvent)>,
 >;
 
 #[derive(Debug)]
@@ -94,19 +115,364 @@ impl<R, A> ReplaySource<R, A> {
     }
 }
 
-#[tracing::instrument(skip(args))]
+/// The slice of a replayed trace selected by `--from` and `--until`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReplayWindow {
+    pub from: Option<Duration>,
+    pub until: Option<Duration>,
+}
+
+impl ReplayWindow {
+    /// Resolves the window given to `--replay-logs` by the user
+    pub fn from_args(args: &Args) -> Result<Self> {
+        match args.replay_logs.as_deref() {
+            Some(log_dir) => Self::resolve(args.from, args.until, log_dir),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Resolves a window over the logs within `log_dir`, reading its
+    /// manifest if any wall-clock times were given
+    pub fn resolve(
+        from: Option<ReplayBound>,
+        until: Option<ReplayBound>,
+        log_dir: &Path,
+    ) -> Result<Self> {
+        let mut started_at = None;
+        let mut resolve = |bound: Option<ReplayBound>| -> Result<Option<Duration>> {
+            let time = match bound {
+                Some(ReplayBound::Relative(duration)) => return Ok(Some(duration)),
+                Some(ReplayBound::WallClock(time)) => time,
+                None => return Ok(None),
+            };
+
+            // Only read the manifest when it's actually needed
+            let started_at = match started_at {
+                Some(started_at) => started_at,
+                None => {
+                    let manifest = RecordManifest::read(log_dir)?.with_context(|| {
+                        format!(
+                            "wall-clock times can only be used with log directories made by \
+                            `ddshow record`, {} has no manifest",
+                            log_dir.display(),
+                        )
+                    })?;
+
+                    *started_at.insert(UNIX_EPOCH + Duration::from_millis(manifest.started_at))
+                }
+            };
+
+            let offset = time.duration_since(started_at).map_err(|_| {
+                anyhow::anyhow!(
+                    "{} comes before the recording in {} started at {}",
+                    humantime::format_rfc3339_millis(time),
+                    log_dir.display(),
+                    humantime::format_rfc3339_millis(started_at),
+                )
+            })?;
+
+            Ok(Some(offset))
+        };
+
+        let window = Self {
+            from: resolve(from)?,
+            until: resolve(until)?,
+        };
+        if let (Some(from), Some(until)) = (window.from, window.until) {
+            if from >= until {
+                anyhow::bail!(
+                    "the time window is empty, `--from` ({:?}) must come before `--until` ({:?})",
+                    from,
+                    until,
+                );
+            }
+        }
+
+        Ok(window)
+    }
+
+    pub const fn is_unbounded(&self) -> bool {
+        self.from.is_none() && self.until.is_none()
+    }
+
+    pub fn contains(&self, time: Duration) -> bool {
+        !self.is_before(time) && !self.is_after(time)
+    }
+
+    /// Returns `true` if the time comes before the start of the window
+    pub fn is_before(&self, time: Duration) -> bool {
+        matches!(self.from, Some(from) if time < from)
+    }
+
+    /// Returns `true` if the time comes after the end of the window
+    pub fn is_after(&self, time: Duration) -> bool {
+        matches!(self.until, Some(until) if time >= until)
+    }
+
+    /// Returns `true` if an event should be kept in a replay of this window.
+    /// Structural events are always kept and accumulated events are kept from
+    /// the start of the trace since the state they build up is visible within the window
+    pub fn keeps<E>(&self, time: Duration, event: &E) -> bool
+    where
+        E: StructuralEvent,
+    {
+        event.is_structural()
+            || (event.is_accumulated() && !self.is_after(time))
+            || self.contains(time)
+    }
+}
+
+/// Events that describe the structure of the traced computation
+/// and so are kept no matter what replay window is selected
+pub trait StructuralEvent: Sized {
+    fn is_structural(&self) -> bool {
+        false
+    }
+
+    /// Returns `true` for events that accumulate into state, like the batches
+    /// making up an arrangement's size
+    fn is_accumulated(&self) -> bool {
+        false
+    }
+
+    /// The operator activation that this event starts or stops, if any
+    fn activation(&self) -> Option<(OperatorId, StartStop)> {
+        None
+    }
+
+    /// Makes an event starting an activation of the given operator
+    fn start_activation(_operator: OperatorId) -> Option<Self> {
+        None
+    }
+}
+
+impl StructuralEvent for TimelyEvent {
+    fn is_structural(&self) -> bool {
+        self.is_operates() || self.is_channels() || self.is_shutdown()
+    }
+
+    fn activation(&self) -> Option<(OperatorId, StartStop)> {
+        match self {
+            Self::Schedule(schedule) => Some((schedule.id, schedule.start_stop.clone())),
+            _ => None,
+        }
+    }
+
+    fn start_activation(operator: OperatorId) -> Option<Self> {
+        Some(Self::Schedule(ScheduleEvent {
+            id: operator,
+            start_stop: StartStop::Start,
+        }))
+    }
+}
+
+impl StructuralEvent for DifferentialEvent {
+    fn is_accumulated(&self) -> bool {
+        !self.is_merge_shortfall()
+    }
+}
+
+impl StructuralEvent for TimelyProgressEvent {}
+
+impl StructuralEvent for TrackerEvent {
+    fn is_accumulated(&self) -> bool {
+        true
+    }
+}
+
+/// Filters batches of events down to the ones kept within a [`ReplayWindow`].
+/// Operator activations that cross the window's bounds are clipped to it so
+/// that every start kept has a matching stop and vice versa
+#[derive(Debug)]
+pub struct WindowFilter {
+    window: ReplayWindow,
+    /// Activations that started before the window and haven't stopped yet
+    started_before: HashSet<(WorkerId, OperatorId)>,
+    /// Activations that started within the window and haven't stopped yet
+    started_within: HashSet<(WorkerId, OperatorId)>,
+}
+
+impl WindowFilter {
+    pub fn new(window: ReplayWindow) -> Self {
+        Self {
+            window,
+            started_before: HashSet::new(),
+            started_within: HashSet::new(),
+        }
+    }
+
+    /// Filters a batch of events captured at `batch_time`. The events of a batch are
+    /// processed in order, so clipped events are never moved before the batch's time
+    /// or before the events kept ahead of them
+    pub fn filter<E>(&mut self, batch_time: Duration, data: &mut Vec<(Duration, WorkerId, E)>)
+    where
+        E: StructuralEvent,
+    {
+        let window = self.window;
+        let mut latest = batch_time;
+
+        let mut kept = Vec::with_capacity(data.len());
+        for (time, worker, event) in data.drain(..) {
+            let clipped_stop = window
+                .until
+                .map_or(time, |until| time.min(until).max(latest));
+
+            match event.activation() {
+                Some((operator, StartStop::Start)) => {
+                    if window.contains(time) {
+                        self.started_within.insert((worker, operator));
+                        kept.push((time, worker, event));
+                    } else if window.is_before(time) {
+                        self.started_before.insert((worker, operator));
+                    }
+                }
+
+                Some((operator, StartStop::Stop)) => {
+                    if self.started_within.remove(&(worker, operator)) {
+                        kept.push((clipped_stop, worker, event));
+
+                    // Activations that began before the window are clipped to its start
+                    } else if self.started_before.remove(&(worker, operator)) {
+                        if !window.is_before(time) {
+                            if let Some(start) = E::start_activation(operator) {
+                                let start_time =
+                                    window.from.map_or(latest, |from| from.max(latest));
+
+                                kept.push((start_time, worker, start));
+                                kept.push((clipped_stop, worker, event));
+                            }
+                        }
+                    } else if window.contains(time) {
+                        kept.push((time, worker, event));
+                    }
+                }
+
+                None => {
+                    if window.keeps(time, &event) {
+                        kept.push((time, worker, event));
+                    }
+                }
+            }
+
+            if let Some(&(time, ..)) = kept.last() {
+                latest = latest.max(time);
+            }
+        }
+
+        *data = kept;
+    }
+}
+
+/// Converts the events of an abomonation replay source into ddshow's own event types
+#[derive(Debug)]
+pub struct ConvertedEvents<R, RawEvent, E> {
+    events: R,
+    __type: PhantomData<(RawEvent, E)>,
+}
+
+impl<R, RawEvent, E> ConvertedEvents<R, RawEvent, E> {
+    pub const fn new(events: R) -> Self {
+        Self {
+            events,
+            __type: PhantomData,
+        }
+    }
+}
+
+impl<R, RawEvent, E> EventIterator<Duration, (Duration, WorkerId, E)>
+    for ConvertedEvents<R, RawEvent, E>
+where
+    R: EventIterator<Duration, (Duration, usize, RawEvent)>,
+    E: From<RawEvent>,
+{
+    fn next(
+        &mut self,
+        is_finished: &mut bool,
+    ) -> io::Result<Option<Event<Duration, (Duration, WorkerId, E)>>> {
+        Ok(self.events.next(is_finished)?.map(|event| match event {
+            Event::Messages(time, data) => Event::Messages(
+                time,
+                data.into_iter()
+                    .map(|(time, worker, event)| (time, WorkerId::new(worker), E::from(event)))
+                    .collect(),
+            ),
+            Event::Progress(progress) => Event::Progress(progress),
+        }))
+    }
+}
+
+/// Filters the events of a replay source down to the ones within a [`ReplayWindow`],
+/// progress updates are always passed through so the replay's frontier still advances
+#[derive(Debug)]
+pub struct WindowedEvents<R> {
+    events: R,
+    filter: WindowFilter,
+}
+
+impl<R> WindowedEvents<R> {
+    pub fn new(events: R, window: ReplayWindow) -> Self {
+        Self {
+            events,
+            filter: WindowFilter::new(window),
+        }
+    }
+}
+
+impl<R, E> EventIterator<Duration, (Duration, WorkerId, E)> for WindowedEvents<R>
+where
+    R: EventIterator<Duration, (Duration, WorkerId, E)>,
+    E: StructuralEvent,
+{
+    fn next(
+        &mut self,
+        is_finished: &mut bool,
+    ) -> io::Result<Option<Event<Duration, (Duration, WorkerId, E)>>> {
+        let filter = &mut self.filter;
+
+        Ok(self.events.next(is_finished)?.map(|event| match event {
+            Event::Messages(time, mut data) if !filter.window.is_unbounded() => {
+                filter.filter(time, &mut data);
+                Event::Messages(time, data)
+            }
+
+            event => event,
+        }))
+    }
+}
+
+#[tracing::instrument(skip(args, running))]
+#[allow(clippy::type_complexity)]
 pub fn connect_to_sources(
     args: &Args,
+    running: &Arc<AtomicBool>,
 ) -> Result<
     Option<(
         TimelyEventReceivers,
         DifferentialEventReceivers,
         ProgressEventReceivers,
+        ReachabilityEventReceivers,
         usize,
     )>,
 > {
     let mut total_sources = 0;
 
+    for target in args.forward.iter() {
+        let enabled = match target.kind {
+            SourceKind::Timely => true,
+            SourceKind::Differential => args.differential_enabled,
+            SourceKind::Progress => args.progress_enabled,
+            SourceKind::Reachability => args.reachability_enabled,
+        };
+
+        if !enabled {
+            anyhow::bail!(
+                "can't forward {} logs to {} since they aren't being collected",
+                target.kind.name(),
+                target.address,
+            );
+        }
+    }
+
     let timely_listener = if !args.is_file_sourced() {
         Some(TcpListener::bind(args.timely_address).with_context(|| {
             anyhow::anyhow!("failed to bind to timely socket {}", args.timely_address)
@@ -137,9 +503,22 @@ pub fn connect_to_sources(
         None
     };
 
+    let reachability_listener = if args.reachability_enabled && !args.is_file_sourced() {
+        Some(
+            TcpListener::bind(args.reachability_address).with_context(|| {
+                anyhow::anyhow!(
+                    "failed to bind to reachability socket {}",
+                    args.reachability_address,
+                )
+            })?,
+        )
+    } else {
+        None
+    };
+
     // Connect to the timely sources
     let (timely_event_receivers, are_timely_sources, num_sources) = acquire_replay_sources(
-        &args,
+        args,
         args.timely_address,
         timely_listener,
         args.timely_connections,
@@ -147,13 +526,19 @@ pub fn connect_to_sources(
         args.replay_logs.as_deref(),
         TIMELY_LOG_FILE,
         "Timely",
+        Forwarding::new(
+            args,
+            SourceKind::Timely,
+            running,
+            forward::re_encode::<RawTimelyEvent, TimelyEvent>,
+        ),
     )?;
     total_sources += num_sources;
 
     // Connect to the differential sources
     let (differential_event_receivers, are_differential_sources) = if args.differential_enabled {
         let (receivers, are_sources, num_sources) = acquire_replay_sources(
-            &args,
+            args,
             args.differential_address,
             differential_listener,
             args.timely_connections,
@@ -161,6 +546,12 @@ pub fn connect_to_sources(
             args.replay_logs.as_deref(),
             DIFFERENTIAL_ARRANGEMENT_LOG_FILE,
             "Differential",
+            Forwarding::new(
+                args,
+                SourceKind::Differential,
+                running,
+                forward::re_encode::<RawDifferentialEvent, DifferentialEvent>,
+            ),
         )?;
         total_sources += num_sources;
 
@@ -172,7 +563,7 @@ pub fn connect_to_sources(
     // Connect to progress sources
     let (progress_event_receivers, are_progress_sources) = if args.progress_enabled {
         let (receivers, are_sources, num_sources) = acquire_replay_sources(
-            &args,
+            args,
             args.progress_address,
             progress_listener,
             args.timely_connections,
@@ -180,6 +571,37 @@ pub fn connect_to_sources(
             args.replay_logs.as_deref(),
             TIMELY_PROGRESS_LOG_FILE,
             "Progress",
+            Forwarding::new(
+                args,
+                SourceKind::Progress,
+                running,
+                forward::re_encode::<TimelyProgressEvent, TimelyProgressEvent>,
+            ),
+        )?;
+        total_sources += num_sources;
+
+        (Some(receivers), are_sources)
+    } else {
+        (None, true)
+    };
+
+    // Connect to reachability sources
+    let (reachability_event_receivers, are_reachability_sources) = if args.reachability_enabled {
+        let (receivers, are_sources, num_sources) = acquire_replay_sources(
+            args,
+            args.reachability_address,
+            reachability_listener,
+            args.timely_connections,
+            args.workers,
+            args.replay_logs.as_deref(),
+            REACHABILITY_LOG_FILE,
+            "Reachability",
+            Forwarding::new(
+                args,
+                SourceKind::Reachability,
+                running,
+                forward::re_encode::<TrackerEvent, TrackerEvent>,
+            ),
         )?;
         total_sources += num_sources;
 
@@ -189,13 +611,19 @@ pub fn connect_to_sources(
     };
 
     // If no replay sources were provided, exit early
-    if !are_timely_sources || !are_differential_sources || !are_progress_sources {
+    if !are_timely_sources
+        || !are_differential_sources
+        || !are_progress_sources
+        || !are_reachability_sources
+    {
         tracing::warn!(
             are_timely_sources = are_timely_sources,
             are_differential_sources = are_differential_sources,
             differential_enabled = args.differential_enabled,
             are_progress_sources = are_progress_sources,
             progress_enabled = args.progress_enabled,
+            are_reachability_sources = are_reachability_sources,
+            reachability_enabled = args.reachability_enabled,
             total_sources = total_sources,
             "no replay sources were provided",
         );
@@ -205,12 +633,13 @@ pub fn connect_to_sources(
         timely_event_receivers,
         differential_event_receivers,
         progress_event_receivers,
+        reachability_event_receivers,
         total_sources,
     )))
 }
 
 /// Connect to and prepare the replay sources
-#[tracing::instrument(skip(args))]
+#[tracing::instrument(skip(args, forwarding))]
 #[allow(clippy::too_many_arguments)]
 pub fn acquire_replay_sources<T, D1, D2>(
     args: &Args,
@@ -221,6 +650,7 @@ pub fn acquire_replay_sources<T, D1, D2>(
     log_dir: Option<&Path>,
     file_prefix: &str,
     target: &str,
+    forwarding: Forwarding<T, D2>,
 ) -> Result<(AcquiredStreams<T, D1, D2>, bool, usize)>
 where
     Event<T, D2>: Clone,
@@ -285,13 +715,20 @@ where
         }) {
             let replay_file = entry.path();
 
+            // Recorded log directories contain a manifest alongside the log files
+            if replay_file.file_name() == Some(OsStr::new(RECORD_MANIFEST_FILE)) {
+                continue;
+            }
+
             let is_file = entry.file_type().map_or(false, |file| file.is_file());
             let ends_with_ddshow = replay_file.extension() == Some(OsStr::new("ddshow"));
-            let starts_with_prefix = replay_file
-                .file_name()
-                .and_then(OsStr::to_str)
-                .and_then(|file| file.split('.').next())
-                .map_or(false, |prefix| prefix == file_prefix);
+            let starts_with_prefix = matches!(
+                replay_file
+                    .file_name()
+                    .and_then(OsStr::to_str)
+                    .and_then(|file| file.split('.').next()),
+                Some(prefix) if prefix == file_prefix
+            );
 
             if is_file && ends_with_ddshow && starts_with_prefix {
                 progress.set_message(replay_file.display().to_string());
@@ -302,9 +739,12 @@ where
                     format!("failed to open {} log file within replay directory", target)
                 })?;
 
-                replays.push(RkyvEventReader::new(
-                    Box::new(BufReader::new(timely_file)) as Box<dyn Read + Send + 'static>
-                ));
+                replays.push(
+                    RkyvEventReader::new(
+                        Box::new(BufReader::new(timely_file)) as Box<dyn Read + Send + 'static>
+                    )
+                    .with_source(replay_file.display().to_string()),
+                );
 
                 progress.inc(1);
                 num_sources += 1;
@@ -349,11 +789,15 @@ where
         let listener = listener.expect("a listener must be supplied for stream sources");
 
         let source = match args.stream_encoding {
-            StreamEncoding::Abomonation => {
-                wait_for_abominated_connections(listener, &address, connections, &progress)?
-            }
+            StreamEncoding::Abomonation => wait_for_abominated_connections(
+                listener,
+                &address,
+                connections,
+                &progress,
+                &forwarding,
+            )?,
             StreamEncoding::Rkyv => {
-                wait_for_rkyv_connections(listener, &address, connections, &progress)?
+                wait_for_rkyv_connections(listener, &address, connections, &progress, &forwarding)?
             }
         };
 
@@ -447,13 +891,14 @@ pub fn make_streams<R, A>(
 
 /// Connect to the given address and collect `connections` streams, returning all of them
 /// in non-blocking mode
-#[tracing::instrument(skip(progress))]
+#[tracing::instrument(skip(progress, forwarding))]
 pub fn wait_for_abominated_connections<T, D, R>(
     listener: TcpListener,
     addr: &SocketAddr,
     connections: NonZeroUsize,
     progress: &ProgressBar,
-) -> Result<ReplaySource<R, EventReader<T, D, TcpStream>>>
+    forwarding: &Forwarding<T, D>,
+) -> Result<ReplaySource<R, AbomonationSource<T, D>>>
 where
     Event<T, D>: Clone,
     T: Abomonation + Send + 'static,
@@ -498,7 +943,7 @@ where
             ));
             progress.inc(1);
 
-            Ok(EventReader::new(socket))
+            forwarding.abomonation_source(socket)
         })
         .collect::<Result<Vec<_>>>()?;
 
@@ -510,12 +955,13 @@ type ConnectedRkyvSource<T, D, A> =
 
 /// Connect to the given address and collect `connections` streams, returning all of them
 /// in non-blocking mode
-#[tracing::instrument(skip(progress))]
-pub fn wait_for_rkyv_connections<T, D, A>(
+#[tracing::instrument(skip(progress, forwarding))]
+pub fn wait_for_rkyv_connections<T, D, A, F1, F2>(
     listener: TcpListener,
     addr: &SocketAddr,
     connections: NonZeroUsize,
     progress: &ProgressBar,
+    forwarding: &Forwarding<F1, F2>,
 ) -> Result<ConnectedRkyvSource<T, D, A>>
 where
     T: Archive,
@@ -562,9 +1008,11 @@ where
             ));
             progress.inc(1);
 
-            Ok(RkyvEventReader::new(
-                Box::new(socket) as Box<dyn Read + Send + 'static>
-            ))
+            let peer = socket
+                .peer_addr()
+                .map_or_else(|_| "a log socket".to_owned(), |addr| addr.to_string());
+
+            Ok(RkyvEventReader::new(forwarding.rkyv_source(socket)?).with_source(peer))
         })
         .collect::<Result<Vec<_>>>()?;
 
@@ -615,10 +1063,12 @@ pub fn wait_for_input(
     barrier.wait();
 
     let (mut fuel, mut extractor) = (
-        Fuel::limited(IDLE_EXTRACTION_FUEL),
-        receivers.into_extractor(),
+        Fuel::limited(args.extraction_fuel),
+        receivers.into_extractor(args.timeline_memory_limit),
     );
     let num_threads = worker_guards.guards().len();
+    let mut reported_hangs = HashSet::new();
+    let mut extraction_error = None;
 
     loop {
         hint::spin_loop();
@@ -661,7 +1111,24 @@ pub fn wait_for_input(
 
         // After we've checked all of our exit conditions we can pull some
         // data from out of the target dataflow
-        extractor.extract_with_fuel(&mut fuel);
+        if let Err(err) = extractor.extract_with_fuel(&mut fuel) {
+            extraction_error = Some(err);
+            break;
+        }
+
+        // Let the user know about any hangs as soon as they're detected
+        for (suspect, &diff) in extractor.hang_suspects.1.iter() {
+            if diff >= 1 && reported_hangs.insert((suspect.worker, suspect.scope_addr.clone())) {
+                tracing::warn!(
+                    worker = %suspect.worker,
+                    scope = %suspect.scope_addr,
+                    kind = %suspect.kind,
+                    stalled_for = ?suspect.stalled_for,
+                    "possible hang detected: {}",
+                    suspect,
+                );
+            }
+        }
 
         tracing::debug!(
             used = ?fuel.used(),
@@ -695,8 +1162,310 @@ pub fn wait_for_input(
             .map_err(|err| anyhow::anyhow!("failed to join timely worker threads: {}", err))??;
     }
 
+    if let Some(err) = extraction_error {
+        return Err(err).context("failed to extract data from the dataflow");
+    }
+
     tracing::debug!("extracting all remaining data from the dataflow");
-    let data = extractor.extract_all();
+    let data = extractor
+        .extract_all()
+        .context("failed to extract data from the dataflow")?;
 
     Ok(data)
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        args::ReplayBound,
+        dataflow::operators::EventIterator,
+        record::RecordManifest,
+        replay_loading::{ConvertedEvents, ReplayWindow, WindowedEvents},
+    };
+    use ddshow_types::{
+        differential_logging::{BatchEvent, DifferentialEvent},
+        timely_logging::{
+            OperatesEvent, ParkEvent, ScheduleEvent, ShutdownEvent, StartStop, TimelyEvent,
+        },
+        OperatorAddr, OperatorId, WorkerId,
+    };
+    use std::{
+        fs, io, process,
+        time::{Duration, UNIX_EPOCH},
+        vec::IntoIter,
+    };
+    use timely::{
+        dataflow::operators::capture::Event,
+        logging::{
+            ScheduleEvent as RawScheduleEvent, StartStop as RawStartStop,
+            TimelyEvent as RawTimelyEvent,
+        },
+    };
+
+    type TestEvent = Event<Duration, (Duration, WorkerId, TimelyEvent)>;
+
+    struct Events<D>(IntoIter<Event<Duration, D>>);
+
+    impl<D> EventIterator<Duration, D> for Events<D> {
+        fn next(&mut self, is_finished: &mut bool) -> io::Result<Option<Event<Duration, D>>> {
+            let event = self.0.next();
+            *is_finished = event.is_none();
+
+            Ok(event)
+        }
+    }
+
+    fn collect<D, R>(mut events: R) -> Vec<Event<Duration, D>>
+    where
+        R: EventIterator<Duration, D>,
+    {
+        let mut collected = Vec::new();
+        let mut is_finished = false;
+        while let Some(event) = events.next(&mut is_finished).unwrap() {
+            collected.push(event);
+        }
+        assert!(is_finished);
+
+        collected
+    }
+
+    fn schedule(operator: usize, start_stop: StartStop) -> TimelyEvent {
+        TimelyEvent::Schedule(ScheduleEvent {
+            id: OperatorId::new(operator),
+            start_stop,
+        })
+    }
+
+    const fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn windowed_events_keep_structure() {
+        let worker = WorkerId::new(0);
+        let operates = TimelyEvent::Operates(OperatesEvent::new(
+            OperatorId::new(0),
+            OperatorAddr::from_elem(OperatorId::new(0)),
+            "foobar".to_owned(),
+        ));
+        let park = TimelyEvent::Park(ParkEvent::Unpark);
+
+        let events = vec![
+            Event::Messages(
+                Duration::from_secs(1),
+                vec![
+                    (Duration::from_secs(1), worker, operates.clone()),
+                    (Duration::from_secs(1), worker, park.clone()),
+                ],
+            ),
+            Event::Progress(vec![(Duration::from_secs(1), 1)]),
+            Event::Messages(
+                Duration::from_secs(5),
+                vec![
+                    (Duration::from_secs(5), worker, park.clone()),
+                    (Duration::from_secs(10), worker, park.clone()),
+                ],
+            ),
+        ];
+
+        let window = ReplayWindow {
+            from: Some(Duration::from_secs(2)),
+            until: Some(Duration::from_secs(10)),
+        };
+        let filtered: Vec<TestEvent> =
+            collect(WindowedEvents::new(Events(events.into_iter()), window));
+
+        let expected = vec![
+            Event::Messages(
+                Duration::from_secs(1),
+                vec![(Duration::from_secs(1), worker, operates)],
+            ),
+            Event::Progress(vec![(Duration::from_secs(1), 1)]),
+            Event::Messages(
+                Duration::from_secs(5),
+                vec![(Duration::from_secs(5), worker, park)],
+            ),
+        ];
+        assert_eq!(filtered, expected);
+    }
+
+    #[test]
+    fn windowed_events_clip_activations() {
+        let worker = WorkerId::new(0);
+        let shutdown = TimelyEvent::Shutdown(ShutdownEvent {
+            id: OperatorId::new(3),
+        });
+
+        let events = vec![
+            Event::Messages(
+                secs(1),
+                vec![
+                    (secs(1), worker, schedule(0, StartStop::Start)),
+                    (secs(1), worker, schedule(1, StartStop::Start)),
+                    (secs(1), worker, schedule(1, StartStop::Stop)),
+                    (secs(1), worker, shutdown.clone()),
+                ],
+            ),
+            Event::Messages(
+                secs(3),
+                vec![
+                    (secs(3), worker, schedule(0, StartStop::Stop)),
+                    (secs(4), worker, schedule(2, StartStop::Start)),
+                ],
+            ),
+            Event::Messages(
+                secs(8),
+                vec![
+                    (secs(8), worker, schedule(0, StartStop::Start)),
+                    (secs(12), worker, schedule(2, StartStop::Stop)),
+                ],
+            ),
+            Event::Messages(
+                secs(9),
+                vec![
+                    (secs(9), worker, schedule(1, StartStop::Start)),
+                    (secs(11), worker, shutdown.clone()),
+                    (secs(12), worker, schedule(1, StartStop::Stop)),
+                ],
+            ),
+            Event::Messages(
+                secs(20),
+                vec![(secs(20), worker, schedule(0, StartStop::Stop))],
+            ),
+        ];
+
+        let window = ReplayWindow {
+            from: Some(secs(2)),
+            until: Some(secs(10)),
+        };
+        let filtered: Vec<TestEvent> =
+            collect(WindowedEvents::new(Events(events.into_iter()), window));
+
+        // Activations crossing the window's bounds are clipped to it, but clipped
+        // events are never moved before their batch or the events ahead of them
+        let expected = vec![
+            Event::Messages(secs(1), vec![(secs(1), worker, shutdown.clone())]),
+            Event::Messages(
+                secs(3),
+                vec![
+                    (secs(3), worker, schedule(0, StartStop::Start)),
+                    (secs(3), worker, schedule(0, StartStop::Stop)),
+                    (secs(4), worker, schedule(2, StartStop::Start)),
+                ],
+            ),
+            Event::Messages(
+                secs(8),
+                vec![
+                    (secs(8), worker, schedule(0, StartStop::Start)),
+                    (secs(10), worker, schedule(2, StartStop::Stop)),
+                ],
+            ),
+            Event::Messages(
+                secs(9),
+                vec![
+                    (secs(9), worker, schedule(1, StartStop::Start)),
+                    (secs(11), worker, shutdown.clone()),
+                    (secs(11), worker, schedule(1, StartStop::Stop)),
+                ],
+            ),
+            Event::Messages(
+                secs(20),
+                vec![(secs(20), worker, schedule(0, StartStop::Stop))],
+            ),
+        ];
+        assert_eq!(filtered, expected);
+    }
+
+    #[test]
+    fn windowed_events_keep_accumulated_events() {
+        let worker = WorkerId::new(0);
+        let batch = DifferentialEvent::Batch(BatchEvent {
+            operator: OperatorId::new(0),
+            length: 10,
+        });
+
+        let events = vec![
+            Event::Messages(secs(1), vec![(secs(1), worker, batch.clone())]),
+            Event::Messages(secs(5), vec![(secs(5), worker, batch.clone())]),
+            Event::Messages(secs(15), vec![(secs(15), worker, batch.clone())]),
+        ];
+
+        let window = ReplayWindow {
+            from: Some(secs(2)),
+            until: Some(secs(10)),
+        };
+        let filtered = collect(WindowedEvents::new(Events(events.into_iter()), window));
+
+        let expected = vec![
+            Event::Messages(secs(1), vec![(secs(1), worker, batch.clone())]),
+            Event::Messages(secs(5), vec![(secs(5), worker, batch)]),
+            Event::Messages(secs(15), Vec::new()),
+        ];
+        assert_eq!(filtered, expected);
+    }
+
+    #[test]
+    fn windowed_abomonation_events() {
+        let raw_schedule =
+            |start_stop| RawTimelyEvent::Schedule(RawScheduleEvent { id: 0, start_stop });
+
+        let events = vec![
+            Event::Messages(
+                secs(1),
+                vec![(secs(1), 0, raw_schedule(RawStartStop::Start))],
+            ),
+            Event::Messages(
+                secs(5),
+                vec![(secs(5), 0, raw_schedule(RawStartStop::Stop))],
+            ),
+        ];
+
+        let window = ReplayWindow {
+            from: Some(secs(2)),
+            until: None,
+        };
+        let converted =
+            ConvertedEvents::<_, RawTimelyEvent, TimelyEvent>::new(Events(events.into_iter()));
+        let filtered = collect(WindowedEvents::new(converted, window));
+
+        let worker = WorkerId::new(0);
+        let expected = vec![
+            Event::Messages(secs(1), Vec::new()),
+            Event::Messages(
+                secs(5),
+                vec![
+                    (secs(5), worker, schedule(0, StartStop::Start)),
+                    (secs(5), worker, schedule(0, StartStop::Stop)),
+                ],
+            ),
+        ];
+        assert_eq!(filtered, expected);
+    }
+
+    #[test]
+    fn wall_clock_bounds_before_the_recording_are_rejected() {
+        let log_dir = std::env::temp_dir().join(format!("ddshow-window-test-{}", process::id()));
+        fs::create_dir_all(&log_dir).unwrap();
+
+        let manifest = RecordManifest {
+            started_at: 10_000,
+            ..RecordManifest::default()
+        };
+        manifest.write(&log_dir).unwrap();
+
+        let before = ReplayBound::WallClock(UNIX_EPOCH + secs(5));
+        let after = ReplayBound::WallClock(UNIX_EPOCH + secs(15));
+        let until = ReplayWindow::resolve(None, Some(after), &log_dir);
+        let from = ReplayWindow::resolve(Some(before), None, &log_dir);
+        fs::remove_dir_all(&log_dir).unwrap();
+
+        assert_eq!(
+            until.unwrap(),
+            ReplayWindow {
+                from: None,
+                until: Some(secs(5)),
+            },
+        );
+        assert!(from.is_err());
+    }
+}
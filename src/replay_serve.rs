@@ -0,0 +1,483 @@
+//! Sending saved logs back out over TCP
+//!
+//! `ddshow replay-serve` reads a log directory and sends each of its log files to the
+//! address of its kind of log as if it were a live timely worker, which allows re-running
+//! a saved computation's logs against ddshow or any other log consumer
+
+use crate::{
+    args::{Args, ReplayServeArgs, ReplaySpeed, StreamEncoding},
+    dataflow::{
+        operators::{EventIterator, RkyvEventReader},
+        utils::{DifferentialLogBundle, ProgressLogBundle, ReachabilityLogBundle, TimelyLogBundle},
+    },
+    record::SourceKind,
+};
+use abomonation::Abomonation;
+use anyhow::{Context, Result};
+use bytecheck::CheckBytes;
+use ddshow_sink::EventWriter;
+use ddshow_types::{
+    progress_logging::TimelyProgressEvent, reachability_logging::TrackerEvent, WorkerId,
+};
+use differential_dataflow::logging::DifferentialEvent as RawDifferentialEvent;
+use indicatif::{HumanBytes, ProgressBar, ProgressStyle};
+use rkyv::{
+    de::deserializers::AllocDeserializer, ser::serializers::AlignedSerializer,
+    validation::DefaultArchiveValidator, AlignedVec, Archive, Deserialize,
+    Serialize as RkyvSerialize,
+};
+use std::{
+    cell::{Cell, RefCell},
+    ffi::OsStr,
+    fmt::Debug,
+    fs::{self, File},
+    io::{self, BufReader, Read, Write},
+    net::{Shutdown, SocketAddr, TcpStream},
+    path::{Path, PathBuf},
+    rc::Rc,
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+use timely::{
+    dataflow::operators::capture::{Event, EventPusher},
+    logging::TimelyEvent as RawTimelyEvent,
+};
+
+/// A log file being sent to a consumer
+struct ServedFile {
+    path: PathBuf,
+    address: SocketAddr,
+    socket: TcpStream,
+}
+
+pub fn replay_serve(args: &Args, serve_args: &ReplayServeArgs) -> Result<()> {
+    if args.is_file_sourced() {
+        anyhow::bail!(
+            "`ddshow replay-serve` takes the log directory to send as an argument, \
+            `--replay-logs` can't be used with it",
+        );
+    }
+
+    // Reachability sources are only read from rkyv-encoded streams
+    if args.reachability_enabled && args.stream_encoding == StreamEncoding::Abomonation {
+        anyhow::bail!(
+            "reachability logs can only be sent rkyv-encoded, \
+            use `--stream-encoding rkyv` with `--reachability`",
+        );
+    }
+
+    let log_dir = &serve_args.log_dir;
+    let mut kinds = vec![(SourceKind::Timely, args.timely_address)];
+    if args.differential_enabled {
+        kinds.push((SourceKind::Differential, args.differential_address));
+    }
+    if args.progress_enabled {
+        kinds.push((SourceKind::Progress, args.progress_address));
+    }
+    if args.reachability_enabled {
+        kinds.push((SourceKind::Reachability, args.reachability_address));
+    }
+
+    // Connect everything up front so that consumers waiting on a number
+    // of connections see all of them before any data arrives
+    let mut served = Vec::with_capacity(kinds.len());
+    let mut total_bytes = 0;
+    for (kind, address) in kinds {
+        let files = log_files(log_dir, kind.file_prefix())?;
+        if files.is_empty() {
+            tracing::warn!(
+                "no {} logs were found in {}",
+                kind.name(),
+                log_dir.display(),
+            );
+        }
+
+        let mut connections = Vec::with_capacity(files.len());
+        for path in files {
+            total_bytes += fs::metadata(&path)
+                .with_context(|| format!("failed to read metadata of {}", path.display()))?
+                .len();
+
+            let socket = TcpStream::connect(address).with_context(|| {
+                format!(
+                    "failed to connect to the {} address {}, is the consumer listening?",
+                    kind.name(),
+                    address,
+                )
+            })?;
+            tracing::info!("sending {} to {}", path.display(), address);
+
+            connections.push(ServedFile {
+                path,
+                address,
+                socket,
+            });
+        }
+
+        println!(
+            "Sending {} {} log file{} to {}",
+            connections.len(),
+            kind.name(),
+            if connections.len() == 1 { "" } else { "s" },
+            address,
+        );
+        served.push((kind, connections));
+    }
+
+    let progress = ProgressBar::new(total_bytes)
+        .with_style(
+            ProgressStyle::default_bar()
+                .template("[{elapsed}] {prefix}: {wide_bar} {bytes}/{total_bytes}"),
+        )
+        .with_prefix(format!("Sending logs from {}", log_dir.display()));
+
+    let (encoding, speed, start) = (args.stream_encoding, serve_args.speed, Instant::now());
+    let mut handles = Vec::new();
+    for (kind, connections) in served {
+        for file in connections {
+            let handle = match kind {
+                SourceKind::Timely => spawn_sender::<TimelyLogBundle, RawTimelyEvent>(
+                    file, encoding, speed, start, &progress,
+                ),
+                SourceKind::Differential => {
+                    spawn_sender::<DifferentialLogBundle, RawDifferentialEvent>(
+                        file, encoding, speed, start, &progress,
+                    )
+                }
+                SourceKind::Progress => spawn_sender::<ProgressLogBundle, TimelyProgressEvent>(
+                    file, encoding, speed, start, &progress,
+                ),
+                SourceKind::Reachability => spawn_sender::<ReachabilityLogBundle, TrackerEvent>(
+                    file, encoding, speed, start, &progress,
+                ),
+            }?;
+
+            handles.push(handle);
+        }
+    }
+
+    let mut events = 0;
+    for handle in handles {
+        events += handle
+            .join()
+            .map_err(|_| anyhow::anyhow!("a log sending thread panicked"))??;
+    }
+
+    progress.finish();
+    println!(
+        "Sent {} events ({}) in {:#?}",
+        events,
+        HumanBytes(total_bytes),
+        start.elapsed(),
+    );
+
+    Ok(())
+}
+
+/// Finds all of the log files with the given prefix within a log directory
+pub(crate) fn log_files(log_dir: &Path, file_prefix: &str) -> Result<Vec<PathBuf>> {
+    let dir = fs::read_dir(log_dir)
+        .with_context(|| format!("failed to read log directory {}", log_dir.display()))?;
+
+    let mut files = Vec::new();
+    for entry in dir {
+        let entry = entry.context("failed to read log directory entry")?;
+        let path = entry.path();
+
+        let is_file = matches!(entry.file_type(), Ok(file) if file.is_file());
+        let is_log = path.extension() == Some(OsStr::new("ddshow"))
+            && path
+                .file_name()
+                .and_then(OsStr::to_str)
+                .and_then(|file| file.split('.').next())
+                == Some(file_prefix);
+
+        if is_file && is_log {
+            files.push(path);
+        }
+    }
+
+    // Keep the order of connections stable across runs
+    files.sort();
+
+    Ok(files)
+}
+
+fn spawn_sender<D, RawEvent>(
+    file: ServedFile,
+    encoding: StreamEncoding,
+    speed: ReplaySpeed,
+    start: Instant,
+    progress: &ProgressBar,
+) -> Result<JoinHandle<Result<u64>>>
+where
+    D: Archive + Send + 'static,
+    D::Archived: Deserialize<D, AllocDeserializer> + CheckBytes<DefaultArchiveValidator>,
+    D: for<'a> RkyvSerialize<AlignedSerializer<&'a mut AlignedVec>> + Debug,
+    D: IntoRawEvent<RawEvent>,
+    RawEvent: Abomonation + 'static,
+{
+    let progress = progress.clone();
+    let name = file
+        .path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let handle = thread::Builder::new()
+        .name(format!("ddshow-serve-{}", name))
+        .spawn(move || {
+            let (path, address) = (file.path.clone(), file.address);
+
+            let events = match encoding {
+                StreamEncoding::Rkyv => serve_rkyv::<D>(file, speed, start, &progress),
+                StreamEncoding::Abomonation => {
+                    serve_abomonation::<D, RawEvent>(file, speed, start, &progress)
+                }
+            };
+
+            events.with_context(|| format!("failed to send {} to {}", path.display(), address))
+        })
+        .context("failed to spawn log sending thread")?;
+
+    Ok(handle)
+}
+
+fn serve_rkyv<D>(
+    file: ServedFile,
+    speed: ReplaySpeed,
+    start: Instant,
+    progress: &ProgressBar,
+) -> Result<u64>
+where
+    D: Archive,
+    D::Archived: Deserialize<D, AllocDeserializer> + CheckBytes<DefaultArchiveValidator>,
+    D: for<'a> RkyvSerialize<AlignedSerializer<&'a mut AlignedVec>> + Debug,
+{
+    let error = Rc::new(RefCell::new(None));
+    let mut writer = EventWriter::new(TrackErrors {
+        writer: &file.socket,
+        error: error.clone(),
+    });
+
+    let events = send_events::<D, _>(&file.path, speed, start, progress, |event| {
+        writer.push(event);
+
+        // The rkyv writer only logs its errors so we have to pick them up ourselves
+        error.borrow_mut().take().map_or(Ok(()), Err)
+    })?;
+
+    shutdown(&file.socket);
+    Ok(events)
+}
+
+fn serve_abomonation<D, RawEvent>(
+    file: ServedFile,
+    speed: ReplaySpeed,
+    start: Instant,
+    progress: &ProgressBar,
+) -> Result<u64>
+where
+    D: Archive + IntoRawEvent<RawEvent>,
+    D::Archived: Deserialize<D, AllocDeserializer> + CheckBytes<DefaultArchiveValidator>,
+    RawEvent: Abomonation,
+{
+    let mut socket = &file.socket;
+    let events = send_events::<D, _>(&file.path, speed, start, progress, |event| {
+        let event = match event {
+            Event::Progress(progress) => Event::Progress(progress),
+            Event::Messages(time, data) => {
+                Event::Messages(time, data.into_iter().map(IntoRawEvent::into_raw).collect())
+            }
+        };
+
+        // Safety: This is the same encoding timely uses when sending its logs
+        unsafe { abomonation::encode(&event, &mut socket) }
+    })?;
+
+    shutdown(&file.socket);
+    Ok(events)
+}
+
+/// Reads every event from a log file and sends it, returning
+/// the number of events that were sent
+fn send_events<D, F>(
+    path: &Path,
+    speed: ReplaySpeed,
+    start: Instant,
+    progress: &ProgressBar,
+    mut send: F,
+) -> Result<u64>
+where
+    D: Archive,
+    D::Archived: Deserialize<D, AllocDeserializer> + CheckBytes<DefaultArchiveValidator>,
+    F: FnMut(Event<Duration, D>) -> io::Result<()>,
+{
+    let mut events = 0;
+    read_events::<D, _>(path, progress, |event| {
+        if let Event::Messages(time, data) = &event {
+            if let Some(delay) = speed.delay(*time) {
+                let send_at = start + delay;
+                let now = Instant::now();
+
+                if send_at > now {
+                    thread::sleep(send_at - now);
+                }
+            }
+
+            events += data.len() as u64;
+        }
+
+        send(event).context("failed to send event")
+    })?;
+
+    Ok(events)
+}
+
+/// Reads every event from a log file, passing each of them to `consume`
+pub(crate) fn read_events<D, F>(path: &Path, progress: &ProgressBar, mut consume: F) -> Result<()>
+where
+    D: Archive,
+    D::Archived: Deserialize<D, AllocDeserializer> + CheckBytes<DefaultArchiveValidator>,
+    F: FnMut(Event<Duration, D>) -> Result<()>,
+{
+    let file =
+        File::open(path).with_context(|| format!("failed to open log file {}", path.display()))?;
+
+    let reached_eof = Rc::new(Cell::new(false));
+    let mut reader = RkyvEventReader::<Duration, D, _>::new(NotifyEof {
+        reader: progress.wrap_read(BufReader::new(file)),
+        reached_eof: reached_eof.clone(),
+    });
+
+    loop {
+        let was_at_eof = reached_eof.get();
+
+        match EventIterator::next(&mut reader, &mut false)? {
+            Some(event) => consume(event)?,
+
+            // Once the file has been fully read, any reads that
+            // don't produce an event mean that we're finished
+            None if was_at_eof => break,
+            None => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn shutdown(socket: &TcpStream) {
+    if let Err(err) = socket.shutdown(Shutdown::Write) {
+        tracing::warn!("failed to shut down connection: {:?}", err);
+    }
+}
+
+/// Converts ddshow's log events into the ones sent by abomonation sources
+pub(crate) trait IntoRawEvent<RawEvent> {
+    fn into_raw(self) -> (Duration, usize, RawEvent);
+}
+
+impl<E, RawEvent> IntoRawEvent<RawEvent> for (Duration, WorkerId, E)
+where
+    E: Into<RawEvent>,
+{
+    fn into_raw(self) -> (Duration, usize, RawEvent) {
+        let (time, worker, event) = self;
+        (time, worker.into_inner(), event.into())
+    }
+}
+
+/// A reader that notes when it reaches the end of its input
+struct NotifyEof<R> {
+    reader: R,
+    reached_eof: Rc<Cell<bool>>,
+}
+
+impl<R: Read> Read for NotifyEof<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.reader.read(buf)?;
+        if len == 0 && !buf.is_empty() {
+            self.reached_eof.set(true);
+        }
+
+        Ok(len)
+    }
+}
+
+/// A writer that keeps the last error it encountered
+pub(crate) struct TrackErrors<W> {
+    pub(crate) writer: W,
+    pub(crate) error: Rc<RefCell<Option<io::Error>>>,
+}
+
+impl<W: Write> Write for TrackErrors<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.write(buf).map_err(|err| {
+            let kind = err.kind();
+            *self.error.borrow_mut() = Some(err);
+
+            io::Error::from(kind)
+        })
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        args::{Args, Command, ReplaySpeed},
+        replay_serve::replay_serve,
+    };
+    use std::time::Duration;
+    use structopt::StructOpt;
+
+    #[test]
+    fn replay_speeds() {
+        let time = Duration::from_secs(10);
+
+        let parse = |speed: &str| speed.parse::<ReplaySpeed>();
+        assert_eq!(parse("max").unwrap().delay(time), None);
+        assert_eq!(parse("realtime").unwrap().delay(time), Some(time));
+        assert_eq!(
+            parse("2x").unwrap().delay(time),
+            Some(Duration::from_secs(5)),
+        );
+        assert_eq!(
+            parse("0.5").unwrap().delay(time),
+            Some(Duration::from_secs(20)),
+        );
+
+        assert!(parse("0x").is_err());
+        assert!(parse("-1x").is_err());
+        assert!(parse("fast").is_err());
+    }
+
+    #[test]
+    fn abomonation_reachability_is_rejected() {
+        let serve = |encoding| {
+            let args = Args::from_iter_safe(vec![
+                "ddshow",
+                "--reachability",
+                "--stream-encoding",
+                encoding,
+                "replay-serve",
+                "missing-log-dir",
+            ])
+            .unwrap();
+
+            match args.command.as_ref() {
+                Some(Command::ReplayServe(serve_args)) => {
+                    replay_serve(&args, serve_args).unwrap_err().to_string()
+                }
+                command => panic!("expected `replay-serve`, got {:?}", command),
+            }
+        };
+
+        assert!(serve("abomonation").contains("reachability logs can only be sent rkyv-encoded"));
+        // rkyv streams get as far as looking for the logs to send
+        assert!(serve("rkyv").contains("failed to read log directory"));
+    }
+}
@@ -1,15 +1,20 @@
 mod tree;
 
-use crate::{args::Args, dataflow::DataflowData, report::tree::Tree};
+use crate::{
+    args::Args,
+    dataflow::{DataflowData, HangKind},
+    report::tree::Tree,
+};
 use anyhow::{Context, Result};
 use comfy_table::{presets::UTF8_FULL, Cell, ColumnConstraint, Row, Table as InnerTable};
-use ddshow_types::{OperatorAddr, OperatorId, WorkerId};
+use ddshow_types::{ChannelId, OperatorAddr, OperatorId, WorkerId};
 use std::{
     cmp::Reverse,
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap},
     fmt::{self, Display},
     fs::{self, File},
     io::Write,
+    time::Duration,
 };
 
 pub fn build_report(
@@ -44,38 +49,82 @@ pub fn build_report(
         tracing::debug!("creating report file: {}", args.report_file.display());
         let mut file = File::create(&args.report_file).context("failed to create report file")?;
 
-        let all_workers: HashSet<_> = data
-            .worker_stats
-            .iter()
-            .flatten()
-            .map(|&(worker, _)| worker)
-            .collect();
-
         program_overview(args, data, &mut file)?;
         worker_stats(args, data, &mut file)?;
-        operator_stats(
-            args,
-            data,
-            &mut file,
-            &name_lookup,
-            &addr_lookup,
-            &all_workers,
-        )?;
+        operator_stats(args, data, &mut file)?;
 
         if args.differential_enabled {
-            arrangement_stats(data, &mut file, &name_lookup, &addr_lookup, &all_workers)?;
+            arrangement_stats(data, &mut file)?;
         } else {
             tracing::debug!("differential logging is disabled, skipping arrangement stats table");
         }
 
-        operator_tree(data, &mut file, &name_lookup, &addr_lookup, &all_workers)?;
+        operator_tree(data, &mut file)?;
+
+        if !data.stragglers.is_empty() {
+            writeln!(&mut file)?;
+            stragglers(data, &mut file)?;
+        } else {
+            tracing::debug!("no worker dominated any operators, skipping stragglers table");
+        }
+
+        if data.edges.iter().any(|(.., stats)| stats.is_some()) {
+            writeln!(&mut file)?;
+            busiest_channels(data, &mut file)?;
+        } else {
+            tracing::debug!("no channel messages were recorded, skipping busiest channels table");
+        }
+
+        if data
+            .worker_exchanges
+            .iter()
+            .any(|exchange| exchange.source != exchange.target)
+        {
+            writeln!(&mut file)?;
+            worker_exchanges(data, &mut file)?;
+        } else {
+            tracing::debug!("no records were exchanged between workers, skipping exchange tables");
+        }
+
+        if !data.channel_latencies.is_empty() {
+            writeln!(&mut file)?;
+            channel_latencies(data, &mut file)?;
+        } else {
+            tracing::debug!("no message deliveries were recorded, skipping channel latency table");
+        }
 
         if args.progress_enabled {
             writeln!(&mut file)?;
             channel_traffic(data, &mut file)?;
+
+            if !data.epoch_completions.is_empty() {
+                writeln!(&mut file)?;
+                epoch_completions(data, &mut file, name_lookup, addr_lookup)?;
+            }
         } else {
             tracing::debug!("progress logging is disabled, skipping channel stats table");
         }
+
+        if args.reachability_enabled {
+            writeln!(&mut file)?;
+            pointstamp_holds(data, &mut file, name_lookup, addr_lookup)?;
+        } else {
+            tracing::debug!("reachability logging is disabled, skipping pointstamp holds table");
+        }
+
+        if !data.hang_suspects.is_empty() {
+            writeln!(&mut file)?;
+            hang_suspects(data, &mut file)?;
+        } else {
+            tracing::debug!("no hangs were detected, skipping hang suspects table");
+        }
+
+        if !data.capability_violations.is_empty() {
+            writeln!(&mut file)?;
+            capability_violations(data, &mut file, name_lookup, addr_lookup)?;
+        } else {
+            tracing::debug!("no capability violations were reported, skipping violations table");
+        }
     } else {
         tracing::debug!("report files are disabled, skipping generation");
     }
@@ -132,7 +181,7 @@ fn worker_stats(args: &Args, data: &DataflowData, file: &mut File) -> Result<()>
     if args.differential_enabled {
         headers.push("Arrangements");
     }
-    headers.extend(["Events", "Runtime"].iter());
+    headers.extend(["Events", "Runtime", "Parked", "Scheduled", "Overhead"].iter());
 
     table.set_header(headers);
 
@@ -153,9 +202,33 @@ fn worker_stats(args: &Args, data: &DataflowData, file: &mut File) -> Result<()>
                 row.push(Cell::new(stats.arrangements));
             }
 
+            // The fraction of the worker's time spent parked, within
+            // operators and within neither
+            let (parked, scheduled, overhead) = stats.time_buckets.iter().fold(
+                (Duration::ZERO, Duration::ZERO, Duration::ZERO),
+                |(parked, scheduled, overhead), bucket| {
+                    (
+                        parked + bucket.parked,
+                        scheduled + bucket.scheduled,
+                        overhead + bucket.overhead,
+                    )
+                },
+            );
+            let total = (parked + scheduled + overhead).as_secs_f64();
+            let percent = |time: Duration| {
+                if total == 0.0 {
+                    "N/A".to_owned()
+                } else {
+                    format!("{:.1}%", time.as_secs_f64() / total * 100.0)
+                }
+            };
+
             row.extend(vec![
                 Cell::new(stats.events),
                 Cell::new(format!("{:#?}", stats.runtime)),
+                Cell::new(percent(parked)),
+                Cell::new(percent(scheduled)),
+                Cell::new(percent(overhead)),
             ]);
 
             table.add_row(row);
@@ -173,19 +246,12 @@ fn worker_stats(args: &Args, data: &DataflowData, file: &mut File) -> Result<()>
     Ok(())
 }
 
-fn operator_stats(
-    args: &Args,
-    data: &DataflowData,
-    file: &mut File,
-    name_lookup: &HashMap<(WorkerId, OperatorId), String>,
-    addr_lookup: &HashMap<(WorkerId, OperatorId), OperatorAddr>,
-    all_workers: &HashSet<WorkerId>,
-) -> Result<()> {
+fn operator_stats(args: &Args, data: &DataflowData, file: &mut File) -> Result<()> {
     tracing::debug!("generating operator stats table");
 
     // TODO: Sort within timely
-    let mut operators_by_total_runtime = data.aggregated_operator_stats.clone();
-    operators_by_total_runtime.sort_by_key(|(_operator, stats)| Reverse(stats.total));
+    let mut operators_by_total_runtime: Vec<_> = data.aggregated_operator_stats.iter().collect();
+    operators_by_total_runtime.sort_by_key(|stats| Reverse(stats.total));
 
     let mut table = Table::new();
 
@@ -193,9 +259,11 @@ fn operator_stats(
         "Name",
         "Id",
         "Address",
+        "Workers",
         "Total Runtime",
         "Activations",
         "Average Activation Time",
+        "Activation Time p50 / p90 / p99",
         "Max Activation Time",
         "Min Activation Time",
     ];
@@ -205,6 +273,7 @@ fn operator_stats(
                 "Max Arrangement Size",
                 "Min Arrangement Size",
                 "Arrangement Batches",
+                "Batch Size p50 / p90 / p99",
             ]
             .iter(),
         );
@@ -212,50 +281,45 @@ fn operator_stats(
 
     table.set_header(headers);
 
-    for (operator, stats, addr, name) in operators_by_total_runtime
-        .iter()
-        .filter_map(|&(operator, ref stats)| {
-            all_workers.iter().find_map(|&worker| {
-                addr_lookup
-                    .get(&(worker, operator))
-                    .map(|addr| (operator, stats, addr))
-            })
-        })
-        .map(|(operator, stats, addr)| {
-            let name: Option<&str> = all_workers
-                .iter()
-                .find_map(|&worker| name_lookup.get(&(worker, operator)).map(|name| &**name));
-
-            (operator, stats, addr, name.unwrap_or("N/A"))
-        })
-    {
+    for stats in operators_by_total_runtime {
         let arrange = stats.arrangement_size.as_ref().map(|arrange| {
             (
                 format!("{}", arrange.max_size),
                 format!("{}", arrange.min_size),
                 format!("{}", arrange.batches),
+                format!("{}", arrange.batch_histogram.percentiles()),
             )
         });
 
         let mut row = vec![
-            Cell::new(name),
-            Cell::new(operator),
-            Cell::new(format!(
-                "[{}]",
-                addr.iter()
-                    .map(ToString::to_string)
-                    .collect::<Vec<_>>()
-                    .join(", "),
-            )),
+            Cell::new(&stats.name),
+            Cell::new(
+                stats
+                    .id()
+                    .map_or_else(|| "N/A".to_owned(), |id| id.to_string()),
+            ),
+            Cell::new(&stats.addr),
+            Cell::new(stats.workers()),
             Cell::new(format!("{:#?}", stats.total)),
             Cell::new(stats.activations),
             Cell::new(format!("{:#?}", stats.average)),
+            Cell::new(
+                stats
+                    .activation_histogram
+                    .percentiles()
+                    .map(Duration::from_nanos),
+            ),
             Cell::new(format!("{:#?}", stats.max)),
             Cell::new(format!("{:#?}", stats.min)),
         ];
 
-        if let Some((max, min, batches)) = arrange {
-            row.extend(vec![Cell::new(max), Cell::new(min), Cell::new(batches)]);
+        if let Some((max, min, batches, batch_sizes)) = arrange {
+            row.extend(vec![
+                Cell::new(max),
+                Cell::new(min),
+                Cell::new(batches),
+                Cell::new(batch_sizes),
+            ]);
         }
 
         table.add_row(row);
@@ -267,69 +331,50 @@ fn operator_stats(
     Ok(())
 }
 
-fn arrangement_stats(
-    data: &DataflowData,
-    file: &mut File,
-    name_lookup: &HashMap<(WorkerId, OperatorId), String>,
-    addr_lookup: &HashMap<(WorkerId, OperatorId), OperatorAddr>,
-    all_workers: &HashSet<WorkerId>,
-) -> Result<()> {
+fn arrangement_stats(data: &DataflowData, file: &mut File) -> Result<()> {
     tracing::debug!("generating arrangement stats table");
 
     let mut operators_by_arrangement_size: Vec<_> = data
         .aggregated_operator_stats
         .iter()
-        .filter_map(|&(operator, ref stats)| {
+        .filter_map(|stats| {
             stats
                 .arrangement_size
-                .map(|arrange| (operator, stats.clone(), arrange))
+                .as_ref()
+                .map(|arrange| (stats, arrange))
         })
         .collect();
 
-    operators_by_arrangement_size.sort_unstable_by_key(|(_, _, arrange)| Reverse(arrange.max_size));
+    operators_by_arrangement_size.sort_unstable_by_key(|(_, arrange)| Reverse(arrange.max_size));
 
     let mut table = Table::new();
     table.set_header(vec![
         "Name",
         "Id",
         "Address",
+        "Workers",
         "Total Runtime",
         "Max Arrangement Size",
         "Min Arrangement Size",
         "Arrangement Batches",
+        "Batch Size p50 / p90 / p99",
     ]);
 
-    for (operator, stats, arrange, addr, name) in operators_by_arrangement_size
-        .iter()
-        .filter_map(|&(operator, ref stats, arrange)| {
-            all_workers.iter().find_map(|&worker| {
-                addr_lookup
-                    .get(&(worker, operator))
-                    .map(|addr| (operator, stats, arrange, addr))
-            })
-        })
-        .map(|(operator, stats, arrange, addr)| {
-            let name: Option<&str> = all_workers
-                .iter()
-                .find_map(|&worker| name_lookup.get(&(worker, operator)).map(|name| &**name));
-
-            (operator, stats, arrange, addr, name.unwrap_or("N/A"))
-        })
-    {
+    for (stats, arrange) in operators_by_arrangement_size {
         table.add_row(vec![
-            Cell::new(name),
-            Cell::new(operator),
-            Cell::new(format!(
-                "[{}]",
-                addr.iter()
-                    .map(ToString::to_string)
-                    .collect::<Vec<_>>()
-                    .join(", "),
-            )),
+            Cell::new(&stats.name),
+            Cell::new(
+                stats
+                    .id()
+                    .map_or_else(|| "N/A".to_owned(), |id| id.to_string()),
+            ),
+            Cell::new(&stats.addr),
+            Cell::new(stats.workers()),
             Cell::new(format!("{:#?}", stats.total)),
             Cell::new(arrange.max_size),
             Cell::new(arrange.min_size),
             Cell::new(arrange.batches),
+            Cell::new(arrange.batch_histogram.percentiles()),
         ]);
     }
 
@@ -339,30 +384,18 @@ fn arrangement_stats(
     Ok(())
 }
 
-fn operator_tree(
-    data: &DataflowData,
-    file: &mut File,
-    name_lookup: &&HashMap<(WorkerId, OperatorId), String>,
-    addr_lookup: &&HashMap<(WorkerId, OperatorId), OperatorAddr>,
-    all_workers: &HashSet<WorkerId>,
-) -> Result<()> {
+fn operator_tree(data: &DataflowData, file: &mut File) -> Result<()> {
     tracing::debug!("generating operator tree");
 
     let mut tree = Tree::new(|writer, _, (total, name, addr)| {
         writeln!(writer, "{:#?}, {}, {}", total, name, addr)
     });
 
-    for &(operator, ref stats) in data.aggregated_operator_stats.iter() {
-        let addr = all_workers
-            .iter()
-            .find_map(|&worker| addr_lookup.get(&(worker, operator)))
-            .expect("missing operator addr");
-        let name = all_workers
-            .iter()
-            .find_map(|&worker| name_lookup.get(&(worker, operator)))
-            .expect("missing operator name");
-
-        tree.insert(addr.as_slice(), (stats.total, name, addr));
+    for stats in data.aggregated_operator_stats.iter() {
+        tree.insert(
+            stats.addr.as_slice(),
+            (stats.total, &stats.name, &stats.addr),
+        );
         // debug_assert_eq!(displaced, None);
     }
 
@@ -375,6 +408,348 @@ fn operator_tree(
     write!(file, "Operator Tree\n{}", tree).context("failed to write to report file")
 }
 
+fn stragglers(data: &DataflowData, file: &mut File) -> Result<()> {
+    tracing::debug!("generating stragglers table");
+
+    // Operators where the slowest worker spent the most extra time come first
+    let mut stragglers: Vec<_> = data.stragglers.iter().collect();
+    stragglers.sort_unstable_by_key(|straggler| {
+        (
+            Reverse(
+                straggler
+                    .total
+                    .slowest
+                    .saturating_sub(straggler.total.others_average),
+            ),
+            Reverse(straggler.max.slowest),
+            straggler.addr.clone(),
+        )
+    });
+
+    let format_ratio = |ratio: f64| {
+        if ratio.is_infinite() {
+            "∞".to_owned()
+        } else {
+            format!("{:.2}x", ratio)
+        }
+    };
+
+    let mut table = Table::new();
+    table.set_header(vec![
+        "Name",
+        "Address",
+        "Workers",
+        "Slowest Worker (Total)",
+        "Total Runtime",
+        "Others' Average Runtime",
+        "Runtime Ratio",
+        "Runtime Spread",
+        "Slowest Worker (Max)",
+        "Max Activation",
+        "Others' Average Max",
+        "Max Ratio",
+        "Max Spread",
+    ]);
+
+    for straggler in stragglers {
+        table.add_row(vec![
+            Cell::new(&straggler.name),
+            Cell::new(&straggler.addr),
+            Cell::new(straggler.workers),
+            Cell::new(straggler.total.slowest_worker),
+            Cell::new(format!("{:#?}", straggler.total.slowest)),
+            Cell::new(format!("{:#?}", straggler.total.others_average)),
+            Cell::new(format_ratio(straggler.total.ratio())),
+            Cell::new(format!("{:#?}", straggler.total.spread())),
+            Cell::new(straggler.max.slowest_worker),
+            Cell::new(format!("{:#?}", straggler.max.slowest)),
+            Cell::new(format!("{:#?}", straggler.max.others_average)),
+            Cell::new(format_ratio(straggler.max.ratio())),
+            Cell::new(format!("{:#?}", straggler.max.spread())),
+        ]);
+    }
+
+    writeln!(
+        file,
+        "Stragglers (operators where a single worker dominates)\n{}\n",
+        table,
+    )
+    .context("failed to write to report file")
+}
+
+/// A channel identified by the addresses of the operators it connects
+///
+/// Channel ids aren't guaranteed to line up between workers, so channels
+/// are matched across workers by their source and target addresses instead
+type ChannelAddrs = (OperatorAddr, OperatorAddr);
+
+/// Maps each worker's channel ids to the channel's addresses and a name made
+/// from the operators it connects
+fn channel_lookup(data: &DataflowData) -> HashMap<(WorkerId, ChannelId), (ChannelAddrs, String)> {
+    data.edges
+        .iter()
+        .map(|(worker, source, channel, target, _)| {
+            (
+                (*worker, channel.channel_id()),
+                (
+                    (channel.source_addr(), channel.target_addr()),
+                    format!("{} -> {}", source.name, target.name),
+                ),
+            )
+        })
+        .collect()
+}
+
+/// The messages sent over a single channel summed across every worker
+#[derive(Default)]
+struct ChannelTotals<'a> {
+    source: Option<&'a str>,
+    target: Option<&'a str>,
+    workers: BTreeSet<WorkerId>,
+    records: usize,
+    messages: usize,
+    max: usize,
+}
+
+fn busiest_channels(data: &DataflowData, file: &mut File) -> Result<()> {
+    tracing::debug!("generating busiest channels table");
+
+    let mut totals: HashMap<ChannelAddrs, ChannelTotals> = HashMap::new();
+    for (worker, source, channel, target, stats) in data.edges.iter() {
+        if let Some(stats) = stats {
+            let totals = totals
+                .entry((channel.source_addr(), channel.target_addr()))
+                .or_default();
+
+            totals.source = Some(&source.name);
+            totals.target = Some(&target.name);
+            totals.workers.insert(*worker);
+            totals.records += stats.total;
+            totals.messages += stats.invocations;
+            totals.max = totals.max.max(stats.max);
+        }
+    }
+
+    let mut channels: Vec<_> = totals.into_iter().collect();
+    channels.sort_unstable_by(|(left_addrs, left), (right_addrs, right)| {
+        (Reverse(left.records), left_addrs).cmp(&(Reverse(right.records), right_addrs))
+    });
+
+    let mut table = Table::new();
+    table.set_header(vec![
+        "Source",
+        "Target",
+        "Workers",
+        "Records Sent",
+        "Messages Sent",
+        "Average Records per Message",
+        "Max Records per Message",
+    ]);
+
+    for ((source_addr, target_addr), totals) in channels {
+        table.add_row(vec![
+            Cell::new(format!(
+                "{} {}",
+                totals.source.unwrap_or_default(),
+                source_addr,
+            )),
+            Cell::new(format!(
+                "{} {}",
+                totals.target.unwrap_or_default(),
+                target_addr,
+            )),
+            Cell::new(totals.workers.len()),
+            Cell::new(totals.records),
+            Cell::new(totals.messages),
+            Cell::new(totals.records / totals.messages.max(1)),
+            Cell::new(totals.max),
+        ]);
+    }
+
+    writeln!(file, "Busiest Channels (by records sent)\n{}\n", table,)
+        .context("failed to write to report file")
+}
+
+fn worker_exchanges(data: &DataflowData, file: &mut File) -> Result<()> {
+    tracing::debug!("generating worker exchange tables");
+
+    let workers: BTreeSet<_> = data
+        .worker_exchanges
+        .iter()
+        .flat_map(|exchange| vec![exchange.source, exchange.target])
+        .collect();
+
+    let worker_headers: Vec<_> = workers
+        .iter()
+        .map(|worker| format!("Worker {}", worker))
+        .collect();
+
+    let channels = channel_lookup(data);
+
+    // The records sent between each pair of workers across the entire program
+    let mut program_matrix: BTreeMap<_, BTreeMap<_, usize>> = BTreeMap::new();
+    // The records sent between each pair of workers for every channel, channels
+    // are looked up on the sending worker
+    let mut channel_matrices: BTreeMap<_, BTreeMap<_, BTreeMap<_, usize>>> = BTreeMap::new();
+    for exchange in data.worker_exchanges.iter() {
+        *program_matrix
+            .entry(exchange.source)
+            .or_default()
+            .entry(exchange.target)
+            .or_default() += exchange.records;
+
+        if let Some((addrs, name)) = channels.get(&(exchange.source, exchange.channel)) {
+            *channel_matrices
+                .entry((addrs, name))
+                .or_default()
+                .entry(exchange.source)
+                .or_default()
+                .entry(exchange.target)
+                .or_default() += exchange.records;
+        }
+    }
+
+    let mut header = vec!["Source \\ Target"];
+    header.extend(worker_headers.iter().map(|header| &**header));
+    header.push("Total");
+
+    let mut table = Table::new();
+    table.set_header(header);
+
+    for (source, targets) in program_matrix.iter() {
+        let mut row = vec![Cell::new(format!("Worker {}", source))];
+        row.extend(
+            workers
+                .iter()
+                .map(|target| Cell::new(targets.get(target).copied().unwrap_or(0))),
+        );
+        row.push(Cell::new(targets.values().sum::<usize>()));
+
+        table.add_row(row);
+    }
+
+    writeln!(
+        file,
+        "Records Exchanged Between Workers (source worker × target worker)\n{}\n",
+        table,
+    )
+    .context("failed to write to report file")?;
+
+    // Only channels that actually send data across workers are interesting here
+    let mut crossing: Vec<_> = channel_matrices
+        .into_iter()
+        .filter(|(_, sources)| {
+            sources
+                .iter()
+                .any(|(source, targets)| targets.keys().any(|target| target != source))
+        })
+        .collect();
+    crossing.sort_by_key(|&(channel, ref sources)| {
+        let total: usize = sources.values().flat_map(|targets| targets.values()).sum();
+        (Reverse(total), channel)
+    });
+
+    let mut table = Table::new();
+    let mut header = vec!["Channel Address", "Channel"];
+    header.extend(worker_headers.iter().map(|header| &**header));
+    header.push("Largest Target Share");
+    table.set_header(header);
+
+    for (((source_addr, target_addr), name), sources) in crossing {
+        for (source, targets) in sources {
+            let total: usize = targets.values().sum();
+            let largest = targets.values().copied().max().unwrap_or(0);
+
+            let mut row = vec![
+                Cell::new(format!("{} -> {}", source_addr, target_addr)),
+                Cell::new(format!("{} (from worker {})", name, source)),
+            ];
+            row.extend(
+                workers
+                    .iter()
+                    .map(|target| Cell::new(targets.get(target).copied().unwrap_or(0))),
+            );
+            row.push(Cell::new(format!(
+                "{:.1}%",
+                largest as f64 / total.max(1) as f64 * 100.0,
+            )));
+
+            table.add_row(row);
+        }
+    }
+
+    writeln!(
+        file,
+        "Records Exchanged Between Workers per Channel (channels that cross workers)\n{}\n",
+        table,
+    )
+    .context("failed to write to report file")
+}
+
+fn channel_latencies(data: &DataflowData, file: &mut File) -> Result<()> {
+    tracing::debug!("generating channel latency table");
+
+    // Latencies are recorded on the receiving worker, so channels are looked up there
+    let channels = channel_lookup(data);
+
+    let mut latencies: Vec<_> = data
+        .channel_latencies
+        .iter()
+        .map(|latency| (latency, channels.get(&(latency.worker, latency.channel))))
+        .collect();
+    latencies.sort_unstable_by_key(|&(latency, channel)| {
+        (
+            Reverse(latency.p99),
+            Reverse(latency.max),
+            latency.worker,
+            channel.map(|(addrs, _)| addrs),
+        )
+    });
+
+    let mut table = Table::new();
+    table.set_header(vec![
+        "Channel Address",
+        "Receiving Worker",
+        "Channel",
+        "Deliveries",
+        "Min Latency",
+        "Mean Latency",
+        "p50 Latency",
+        "p90 Latency",
+        "p99 Latency",
+        "Max Latency",
+    ]);
+
+    for (latency, channel) in latencies {
+        let (addrs, name) = match channel {
+            Some(((source_addr, target_addr), name)) => {
+                (format!("{} -> {}", source_addr, target_addr), &**name)
+            }
+            None => ("N/A".to_owned(), "N/A"),
+        };
+
+        table.add_row(vec![
+            Cell::new(addrs),
+            Cell::new(latency.worker),
+            Cell::new(name),
+            Cell::new(latency.deliveries),
+            Cell::new(format!("{:#?}", latency.min)),
+            Cell::new(format!("{:#?}", latency.mean)),
+            Cell::new(format!("{:#?}", latency.p50)),
+            Cell::new(format!("{:#?}", latency.p90)),
+            Cell::new(format!("{:#?}", latency.p99)),
+            Cell::new(format!("{:#?}", latency.max)),
+        ]);
+    }
+
+    writeln!(
+        file,
+        "Channel Delivery Latency (time from send to receive, by p99)\n{}\n",
+        table,
+    )
+    .context("failed to write to report file")
+}
+
 fn channel_traffic(data: &DataflowData, file: &mut File) -> Result<()> {
     let mut table = Table::new();
     table.set_header(vec![
@@ -400,6 +775,298 @@ fn channel_traffic(data: &DataflowData, file: &mut File) -> Result<()> {
     writeln!(file, "{}", table).context("failed to write to report file")
 }
 
+fn epoch_completions(
+    data: &DataflowData,
+    file: &mut File,
+    name_lookup: &HashMap<(WorkerId, OperatorId), String>,
+    addr_lookup: &HashMap<(WorkerId, OperatorId), OperatorAddr>,
+) -> Result<()> {
+    tracing::debug!("generating epoch completions table");
+
+    let names_by_addr: HashMap<_, _> = addr_lookup
+        .iter()
+        .filter_map(|(&(worker, operator), addr)| {
+            let name = name_lookup.get(&(worker, operator))?;
+            Some(((worker, addr), name))
+        })
+        .collect();
+
+    // Merge together the completions of each timestamp across every operator output,
+    // an epoch is complete once the last output has dropped its capabilities for it
+    let mut epochs = HashMap::new();
+    for completion in data.epoch_completions.iter() {
+        let (first_activation, completed, outputs, slowest) =
+            epochs.entry(&completion.timestamp).or_insert((
+                completion.first_activation,
+                completion.completed,
+                0,
+                completion,
+            ));
+
+        *first_activation = (*first_activation).min(completion.first_activation);
+        *completed = (*completed).max(completion.completed);
+        *outputs += 1;
+
+        if completion.lag() > slowest.lag() {
+            *slowest = completion;
+        }
+    }
+
+    let mut epochs: Vec<_> = epochs.into_iter().collect();
+    epochs.sort_unstable_by_key(|&(timestamp, (first_activation, completed, ..))| {
+        (first_activation, completed, timestamp)
+    });
+
+    let mut table = Table::new();
+    table.set_header(vec![
+        "Epoch",
+        "Operator Outputs",
+        "First Activation",
+        "Frontier Completed",
+        "Time to Completion",
+        "Slowest Operator",
+    ]);
+
+    for (timestamp, (first_activation, completed, outputs, slowest)) in epochs {
+        let name = names_by_addr
+            .get(&(slowest.worker, &slowest.operator_addr))
+            .map_or("N/A", |name| &***name);
+
+        table.add_row(vec![
+            Cell::new(timestamp),
+            Cell::new(outputs),
+            Cell::new(format!("{:#?}", first_activation)),
+            Cell::new(format!("{:#?}", completed)),
+            Cell::new(format!("{:#?}", completed - first_activation)),
+            Cell::new(format!(
+                "{} {} on worker {}, port {} ({:#?})",
+                name,
+                slowest.operator_addr,
+                slowest.worker,
+                slowest.port,
+                slowest.lag(),
+            )),
+        ]);
+    }
+
+    writeln!(
+        file,
+        "Time From First Activation to Frontier Completion per Epoch\n{}\n",
+        table,
+    )
+    .context("failed to write to report file")
+}
+
+fn hang_suspects(data: &DataflowData, file: &mut File) -> Result<()> {
+    tracing::debug!("generating hang suspects table");
+
+    let mut suspects = data.hang_suspects.clone();
+    suspects.sort_unstable_by_key(|suspect| (Reverse(suspect.stalled_for), suspect.worker));
+
+    let mut table = Table::new();
+    table.set_header(vec![
+        "Scope",
+        "Worker",
+        "Id",
+        "Address",
+        "Kind",
+        "Stalled Since",
+        "Stalled For",
+        "Last Frontier Change",
+        "Hottest Operators",
+    ]);
+
+    for suspect in suspects {
+        let hottest = suspect
+            .hottest_operators
+            .iter()
+            .map(|operator| {
+                format!(
+                    "{} {} ({:#?}, {} activations)",
+                    operator.name, operator.addr, operator.total_runtime, operator.activations,
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        table.add_row(vec![
+            Cell::new(&suspect.scope_name),
+            Cell::new(suspect.worker),
+            Cell::new(suspect.scope),
+            Cell::new(&suspect.scope_addr),
+            Cell::new(suspect.kind),
+            Cell::new(format!("{:#?}", suspect.stalled_since)),
+            Cell::new(match suspect.kind {
+                HangKind::Livelock => format!("{:#?}", suspect.stalled_for),
+                HangKind::Parked => "Indefinitely".to_owned(),
+            }),
+            Cell::new(
+                suspect
+                    .last_progress
+                    .map_or_else(|| "Never".to_owned(), |time| format!("{:#?}", time)),
+            ),
+            Cell::new(hottest),
+        ]);
+    }
+
+    writeln!(
+        file,
+        "Scopes Suspected of Hanging (running or parked without their frontier advancing)\n{}\n",
+        table,
+    )
+    .context("failed to write to report file")
+}
+
+fn capability_violations(
+    data: &DataflowData,
+    file: &mut File,
+    name_lookup: &HashMap<(WorkerId, OperatorId), String>,
+    addr_lookup: &HashMap<(WorkerId, OperatorId), OperatorAddr>,
+) -> Result<()> {
+    tracing::debug!("generating capability violations table");
+
+    // Merge together all violations from each operator output, keeping the first example
+    let mut violations = HashMap::new();
+    for &((worker, operator), ref violation) in data.capability_violations.iter() {
+        violations
+            .entry((worker, operator, violation.port))
+            .and_modify(|(total, _)| *total += violation.violations)
+            .or_insert((violation.violations, violation));
+    }
+
+    let mut violations: Vec<_> = violations.into_iter().collect();
+    violations.sort_unstable_by_key(|&((worker, operator, port), (total, _))| {
+        (Reverse(total), worker, operator, port)
+    });
+
+    let mut table = Table::new();
+    table.set_header(vec![
+        "Name",
+        "Worker",
+        "Id",
+        "Address",
+        "Port",
+        "Violating Updates",
+        "Message Capability",
+        "Example Update Time",
+    ]);
+
+    for ((worker, operator, port), (total, violation)) in violations {
+        let name = name_lookup
+            .get(&(worker, operator))
+            .map_or("N/A", |name| &**name);
+        let addr = addr_lookup
+            .get(&(worker, operator))
+            .unwrap_or(&violation.operator_addr);
+
+        table.add_row(vec![
+            Cell::new(name),
+            Cell::new(worker),
+            Cell::new(operator),
+            Cell::new(addr),
+            Cell::new(port),
+            Cell::new(total),
+            Cell::new(&violation.capability),
+            Cell::new(&violation.update_time),
+        ]);
+    }
+
+    writeln!(
+        file,
+        "Updates Sent Before Their Message's Capability\n{}\n",
+        table,
+    )
+    .context("failed to write to report file")
+}
+
+fn pointstamp_holds(
+    data: &DataflowData,
+    file: &mut File,
+    name_lookup: &HashMap<(WorkerId, OperatorId), String>,
+    addr_lookup: &HashMap<(WorkerId, OperatorId), OperatorAddr>,
+) -> Result<()> {
+    tracing::debug!("generating pointstamp holds table");
+
+    let names_by_addr: HashMap<_, _> = addr_lookup
+        .iter()
+        .filter_map(|(&(worker, operator), addr)| {
+            let name = name_lookup.get(&(worker, operator))?;
+            Some(((worker, addr), (operator, name)))
+        })
+        .collect();
+
+    // Merge together the timelines of every timestamp held at each location
+    let mut holds = HashMap::new();
+    for timeline in data.pointstamp_timelines.iter() {
+        let (total, longest, timestamps, still_held) = holds
+            .entry((
+                timeline.worker,
+                &timeline.operator_addr,
+                timeline.location,
+                timeline.port,
+            ))
+            .or_insert((Duration::from_secs(0), None, 0, 0));
+
+        *total += timeline.held_for;
+        *timestamps += 1;
+        *still_held += timeline.still_held as usize;
+
+        match longest {
+            Some((held_for, _)) if *held_for >= timeline.held_for => {}
+            _ => *longest = Some((timeline.held_for, &timeline.timestamp)),
+        }
+    }
+
+    let mut holds: Vec<_> = holds.into_iter().collect();
+    holds.sort_unstable_by_key(|&((worker, addr, location, port), (total, ..))| {
+        (Reverse(total), worker, addr, location, port)
+    });
+
+    let mut table = Table::new();
+    table.set_header(vec![
+        "Name",
+        "Worker",
+        "Id",
+        "Address",
+        "Location",
+        "Port",
+        "Total Time Held",
+        "Timestamps Held",
+        "Longest Held Timestamp",
+        "Still Held",
+    ]);
+
+    for ((worker, addr, location, port), (total, longest, timestamps, still_held)) in holds {
+        let (id, name) = names_by_addr
+            .get(&(worker, addr))
+            .map_or(("N/A".to_owned(), "N/A"), |(id, name)| {
+                (id.to_string(), &***name)
+            });
+
+        table.add_row(vec![
+            Cell::new(name),
+            Cell::new(worker),
+            Cell::new(id),
+            Cell::new(addr),
+            Cell::new(format!("{:?}", location)),
+            Cell::new(port),
+            Cell::new(format!("{:#?}", total)),
+            Cell::new(timestamps),
+            Cell::new(longest.map_or_else(String::new, |(held_for, timestamp)| {
+                format!("{} ({:#?})", timestamp, held_for)
+            })),
+            Cell::new(still_held),
+        ]);
+    }
+
+    writeln!(
+        file,
+        "Pointstamps Held by Each Operator (operators holding back frontiers)\n{}\n",
+        table,
+    )
+    .context("failed to write to report file")
+}
+
 struct Table {
     inner: InnerTable,
 }
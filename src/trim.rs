@@ -0,0 +1,615 @@
+//! Trimming saved logs down to the parts that matter
+//!
+//! `ddshow trim` reads a log directory and writes a copy of it that only contains the
+//! selected dataflows, workers, time range and kinds of events. When dataflows are
+//! selected the timely logs are read twice, once to work out which operators and
+//! channels belong to the selected dataflows and once more to write out the kept
+//! events, since timely only logs a scope after all of the operators within it
+
+use crate::{
+    args::TrimArgs,
+    record::{RecordManifest, RecordedSource, SourceKind},
+    replay_loading::{ReplayWindow, StructuralEvent, WindowFilter},
+    replay_serve::{log_files, read_events, TrackErrors},
+};
+use anyhow::{Context, Result};
+use bytecheck::CheckBytes;
+use ddshow_sink::{
+    framing::{Framing, StreamHeader, STREAM_HEADER_LEN},
+    LogWriter,
+};
+use ddshow_types::{
+    differential_logging::DifferentialEvent, progress_logging::TimelyProgressEvent,
+    reachability_logging::TrackerEvent, timely_logging::TimelyEvent, ChannelId, OperatorAddr,
+    OperatorId, PortId, WorkerId,
+};
+use indicatif::{HumanBytes, ProgressBar, ProgressStyle};
+use rkyv::{
+    de::deserializers::AllocDeserializer, ser::serializers::AlignedSerializer,
+    validation::DefaultArchiveValidator, AlignedVec, Archive, Deserialize,
+    Serialize as RkyvSerialize,
+};
+use std::{
+    cell::RefCell,
+    collections::HashSet,
+    fmt::Debug,
+    fs::{self, File},
+    io::{BufWriter, Read, Write},
+    path::Path,
+    rc::Rc,
+    time::Duration,
+};
+use timely::dataflow::operators::capture::{Event, EventPusher};
+
+/// The kinds of events that describe the structure of the traced dataflows
+pub(crate) const STRUCTURAL_EVENT_KINDS: &[&str] = &["operates", "channels"];
+
+/// The kinds of events that can be dropped with `ddshow trim --drop`
+pub(crate) const DROPPABLE_EVENT_KINDS: &[&str] = &[
+    // Timely events
+    "push-progress",
+    "messages",
+    "schedule",
+    "shutdown",
+    "application",
+    "guarded-message",
+    "guarded-progress",
+    "comm-channels",
+    "input",
+    "park",
+    "text",
+    // Differential events
+    "batch",
+    "merge",
+    "drop",
+    "merge-shortfall",
+    "trace-share",
+    // Progress and reachability logs
+    "progress",
+    "reachability",
+];
+
+pub fn trim(trim_args: &TrimArgs) -> Result<()> {
+    let (log_dir, output_dir) = (&trim_args.log_dir, &trim_args.output_dir);
+
+    let canonical_log_dir = fs::canonicalize(log_dir)
+        .with_context(|| format!("failed to read log directory {}", log_dir.display()))?;
+    if matches!(fs::canonicalize(output_dir), Ok(output_dir) if output_dir == canonical_log_dir) {
+        anyhow::bail!("the trimmed logs can't be written over the logs they were trimmed from");
+    }
+
+    let window = ReplayWindow::resolve(trim_args.from, trim_args.until, log_dir)?;
+
+    let mut sources = Vec::new();
+    let mut total_bytes = 0;
+    for kind in [
+        SourceKind::Timely,
+        SourceKind::Differential,
+        SourceKind::Progress,
+        SourceKind::Reachability,
+    ] {
+        for path in log_files(log_dir, kind.file_prefix())? {
+            total_bytes += fs::metadata(&path)
+                .with_context(|| format!("failed to read metadata of {}", path.display()))?
+                .len();
+
+            sources.push((kind, path));
+        }
+    }
+
+    let timely_files: Vec<_> = sources
+        .iter()
+        .filter(|(kind, _)| *kind == SourceKind::Timely)
+        .map(|(_, path)| path.as_path())
+        .collect();
+    if timely_files.is_empty() {
+        anyhow::bail!("no timely logs were found in {}", log_dir.display());
+    }
+
+    let style = ProgressStyle::default_bar()
+        .template("[{elapsed}] {prefix}: {wide_bar} {bytes}/{total_bytes}");
+
+    let selection = if trim_args.dataflows.is_empty() && trim_args.addresses.is_empty() {
+        DataflowSelection::everything()
+    } else {
+        let timely_bytes = timely_files
+            .iter()
+            .map(|path| fs::metadata(path).map_or(0, |metadata| metadata.len()))
+            .sum();
+        let progress = ProgressBar::new(timely_bytes)
+            .with_style(style.clone())
+            .with_prefix("Finding the selected dataflows");
+
+        let selection = DataflowSelection::scan(
+            &trim_args.dataflows,
+            &trim_args.addresses,
+            &timely_files,
+            &progress,
+        )?;
+        progress.finish_and_clear();
+
+        if selection.operators.is_empty() {
+            anyhow::bail!(
+                "none of the selected dataflows or addresses were found in {}",
+                log_dir.display(),
+            );
+        }
+
+        selection
+    };
+
+    let filter = TrimFilter {
+        selection,
+        window,
+        workers: trim_args
+            .workers
+            .iter()
+            .copied()
+            .map(WorkerId::new)
+            .collect(),
+        dropped_kinds: trim_args.dropped_kinds.iter().copied().collect(),
+    };
+
+    fs::create_dir_all(output_dir)
+        .with_context(|| format!("failed to create output directory {}", output_dir.display()))?;
+
+    let progress = ProgressBar::new(total_bytes)
+        .with_style(style)
+        .with_prefix(format!("Trimming logs from {}", log_dir.display()));
+
+    let mut trimmed = Vec::with_capacity(sources.len());
+    for (kind, path) in sources {
+        let file_name = path
+            .file_name()
+            .context("log files always have a file name")?
+            .to_owned();
+        let output = output_dir.join(&file_name);
+
+        let file = match kind {
+            SourceKind::Timely => trim_file::<TimelyEvent>(&path, &output, &filter, &progress),
+            SourceKind::Differential => {
+                trim_file::<DifferentialEvent>(&path, &output, &filter, &progress)
+            }
+            SourceKind::Progress => {
+                trim_file::<TimelyProgressEvent>(&path, &output, &filter, &progress)
+            }
+            SourceKind::Reachability => {
+                trim_file::<TrackerEvent>(&path, &output, &filter, &progress)
+            }
+        }
+        .with_context(|| format!("failed to trim {}", path.display()))?;
+
+        // Files that had everything trimmed out of them don't need to be kept around
+        if file.kept_events == 0 {
+            fs::remove_file(&output)
+                .with_context(|| format!("failed to remove {}", output.display()))?;
+        }
+
+        trimmed.push((kind, file_name.to_string_lossy().into_owned(), file));
+    }
+    progress.finish_and_clear();
+
+    // Carry the manifest over so that wall-clock times still work on the trimmed logs
+    if let Some(manifest) = RecordManifest::read(log_dir)? {
+        let sources = trimmed
+            .iter()
+            .filter(|(_, _, file)| file.kept_events != 0)
+            .map(|(kind, name, file)| RecordedSource {
+                kind: *kind,
+                file: name.clone(),
+                events: file.kept_events,
+                bytes: file.bytes,
+                complete: manifest
+                    .sources
+                    .iter()
+                    .filter(|source| &source.file == name)
+                    .all(|source| source.complete),
+            })
+            .collect();
+
+        RecordManifest {
+            sources,
+            ..manifest
+        }
+        .write(output_dir)?;
+    }
+
+    let (read_events, kept_events, kept_bytes, kept_files) =
+        trimmed
+            .iter()
+            .fold((0, 0, 0, 0), |(read, kept, bytes, files), (_, _, file)| {
+                (
+                    read + file.read_events,
+                    kept + file.kept_events,
+                    bytes + file.bytes,
+                    files + (file.kept_events != 0) as usize,
+                )
+            });
+    println!(
+        "Kept {} of {} events in {} of {} log files ({} of {}), wrote the trimmed logs to {}",
+        kept_events,
+        read_events,
+        kept_files,
+        trimmed.len(),
+        HumanBytes(kept_bytes),
+        HumanBytes(total_bytes),
+        output_dir.display(),
+    );
+
+    Ok(())
+}
+
+/// The events read from and kept within a single log file
+#[derive(Debug, Clone, Copy)]
+struct TrimmedFile {
+    read_events: u64,
+    kept_events: u64,
+    bytes: u64,
+}
+
+fn trim_file<E>(
+    input: &Path,
+    output: &Path,
+    filter: &TrimFilter,
+    progress: &ProgressBar,
+) -> Result<TrimmedFile>
+where
+    E: TrimmedEvent,
+    (Duration, WorkerId, E): Archive + for<'a> RkyvSerialize<AlignedSerializer<&'a mut AlignedVec>>,
+    <(Duration, WorkerId, E) as Archive>::Archived: Deserialize<(Duration, WorkerId, E), AllocDeserializer>
+        + CheckBytes<DefaultArchiveValidator>,
+    (Duration, WorkerId, E): Debug,
+{
+    let (mut read, mut kept) = (0, 0);
+    let mut window = WindowFilter::new(filter.window);
+
+    let bytes = rewrite_log_file::<(Duration, WorkerId, E), _>(input, output, progress, |event| {
+        Ok(match event {
+            Event::Messages(time, mut data) => {
+                read += data.len() as u64;
+                data.retain(|(_, worker, event)| filter.keeps(*worker, event));
+                window.filter(time, &mut data);
+                kept += data.len() as u64;
+
+                if data.is_empty() {
+                    None
+                } else {
+                    Some(Event::Messages(time, data))
+                }
+            }
+
+            // Progress updates are always kept so that the replay's frontier still advances
+            progress => Some(progress),
+        })
+    })?;
+
+    Ok(TrimmedFile {
+        read_events: read,
+        kept_events: kept,
+        bytes,
+    })
+}
+
+/// Rewrites a log file one event at a time, writing whatever `rewrite` returns for
+/// each event in place of it. Returns the number of bytes that were written
+pub(crate) fn rewrite_log_file<D, F>(
+    input: &Path,
+    output: &Path,
+    progress: &ProgressBar,
+    mut rewrite: F,
+) -> Result<u64>
+where
+    D: Archive + for<'a> RkyvSerialize<AlignedSerializer<&'a mut AlignedVec>> + Debug,
+    D::Archived: Deserialize<D, AllocDeserializer> + CheckBytes<DefaultArchiveValidator>,
+    F: FnMut(Event<Duration, D>) -> Result<Option<Event<Duration, D>>>,
+{
+    let file = File::create(output)
+        .with_context(|| format!("failed to create log file {}", output.display()))?;
+    let mut file = BufWriter::new(file);
+
+    // Files keep the framing and checksums they were written with
+    let framing = Framing::of(stream_header(input)?);
+
+    let error = Rc::new(RefCell::new(None));
+    {
+        let tracked = TrackErrors {
+            writer: &mut file,
+            error: error.clone(),
+        };
+        let mut writer = LogWriter::new(tracked, framing);
+
+        read_events::<D, _>(input, progress, |event| {
+            if let Some(event) = rewrite(event)? {
+                writer.push(event);
+            }
+
+            // The rkyv writer only logs its errors so we have to pick them up ourselves
+            let error = error.borrow_mut().take();
+            error
+                .map_or(Ok(()), Err)
+                .with_context(|| format!("failed to write to log file {}", output.display()))
+        })?;
+    }
+
+    // Batched writers write their last batch when they're dropped
+    let error = error.borrow_mut().take();
+    error
+        .map_or(Ok(()), Err)
+        .with_context(|| format!("failed to write to log file {}", output.display()))?;
+
+    file.flush()
+        .with_context(|| format!("failed to write to log file {}", output.display()))?;
+    let bytes = file
+        .get_ref()
+        .metadata()
+        .with_context(|| format!("failed to read metadata of {}", output.display()))?
+        .len();
+
+    Ok(bytes)
+}
+
+/// Reads the stream header of a log file, if it has one
+fn stream_header(path: &Path) -> Result<Option<StreamHeader>> {
+    let mut header = Vec::with_capacity(STREAM_HEADER_LEN);
+    File::open(path)
+        .and_then(|file| file.take(STREAM_HEADER_LEN as u64).read_to_end(&mut header))
+        .with_context(|| format!("failed to read log file {}", path.display()))?;
+
+    Ok(StreamHeader::decode(&header))
+}
+
+/// Decides which events are kept by `ddshow trim`
+#[derive(Debug)]
+struct TrimFilter {
+    selection: DataflowSelection,
+    window: ReplayWindow,
+    /// The workers to keep events from, all workers are kept if this is empty
+    workers: HashSet<WorkerId>,
+    dropped_kinds: HashSet<&'static str>,
+}
+
+impl TrimFilter {
+    fn keeps<E>(&self, worker: WorkerId, event: &E) -> bool
+    where
+        E: TrimmedEvent,
+    {
+        let is_selected = (self.workers.is_empty() || self.workers.contains(&worker))
+            && event.is_selected(worker, &self.selection);
+
+        // Structural events are kept regardless of their kind, the replay
+        // window is applied afterwards by a `WindowFilter`
+        is_selected && (event.is_structural() || !self.dropped_kinds.contains(event.kind()))
+    }
+}
+
+/// The operators and channels of the dataflows kept by `ddshow trim`
+#[derive(Debug, Default)]
+struct DataflowSelection {
+    everything: bool,
+    /// The operator addresses selected with `--operator-address`
+    addresses: Vec<OperatorAddr>,
+    /// The top-level operators of the dataflows selected with `--dataflow`
+    dataflows: HashSet<(WorkerId, OperatorId)>,
+    operators: HashSet<(WorkerId, OperatorId)>,
+    channels: HashSet<(WorkerId, ChannelId)>,
+}
+
+impl DataflowSelection {
+    fn everything() -> Self {
+        Self {
+            everything: true,
+            ..Self::default()
+        }
+    }
+
+    /// Reads the given timely logs to find the operators and channels of the selected dataflows
+    fn scan(
+        names: &[String],
+        addresses: &[OperatorAddr],
+        timely_files: &[&Path],
+        progress: &ProgressBar,
+    ) -> Result<Self> {
+        let mut selection = Self {
+            addresses: addresses.to_vec(),
+            ..Self::default()
+        };
+        let (mut operators, mut channels) = (Vec::new(), Vec::new());
+
+        for &path in timely_files {
+            read_events::<(Duration, WorkerId, TimelyEvent), _>(path, progress, |event| {
+                if let Event::Messages(_, data) = event {
+                    for (_, worker, event) in data {
+                        match event {
+                            TimelyEvent::Operates(operates) => {
+                                if operates.addr.len() == 1 && names.contains(&operates.name) {
+                                    selection.dataflows.insert((worker, operates.addr[0]));
+                                }
+
+                                operators.push((worker, operates.id, operates.addr));
+                            }
+
+                            TimelyEvent::Channels(channel) => channels.push((worker, channel)),
+                            _ => {}
+                        }
+                    }
+                }
+
+                Ok(())
+            })
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        }
+
+        for (worker, id, addr) in operators {
+            if selection.contains_addr(worker, &addr) {
+                selection.operators.insert((worker, id));
+            }
+        }
+
+        // Channels are kept if they're within a selected scope or if they
+        // connect two selected operators
+        for (worker, channel) in channels {
+            let endpoint = |port: [PortId; 2]| {
+                let mut addr = channel.scope_addr.to_vec();
+                addr.push(OperatorId::new(port[0].into_inner()));
+                addr
+            };
+
+            if selection.is_within(worker, &channel.scope_addr)
+                || (selection.is_within(worker, &endpoint(channel.source))
+                    && selection.is_within(worker, &endpoint(channel.target)))
+            {
+                selection.channels.insert((worker, channel.id));
+            }
+        }
+
+        Ok(selection)
+    }
+
+    /// Returns `true` if the address is within one of the selected dataflows or addresses
+    fn is_within(&self, worker: WorkerId, addr: &[OperatorId]) -> bool {
+        self.everything
+            || matches!(addr.first(), Some(&root) if self.dataflows.contains(&(worker, root)))
+            || self.addresses.iter().any(|prefix| addr.starts_with(prefix))
+    }
+
+    /// Returns `true` if the address is within the selection or is one
+    /// of the scopes that contain a selected address
+    fn contains_addr(&self, worker: WorkerId, addr: &[OperatorId]) -> bool {
+        self.is_within(worker, addr) || self.addresses.iter().any(|prefix| prefix.starts_with(addr))
+    }
+
+    fn contains_operator(&self, worker: WorkerId, operator: OperatorId) -> bool {
+        self.everything || self.operators.contains(&(worker, operator))
+    }
+
+    fn contains_channel(&self, worker: WorkerId, channel: ChannelId) -> bool {
+        self.everything || self.channels.contains(&(worker, channel))
+    }
+}
+
+/// A log event that can be filtered by `ddshow trim`
+trait TrimmedEvent: StructuralEvent {
+    /// The name used to drop this kind of event with `--drop`
+    fn kind(&self) -> &'static str;
+
+    /// Returns `true` if the event belongs to the selected dataflows
+    fn is_selected(&self, worker: WorkerId, selection: &DataflowSelection) -> bool;
+}
+
+impl TrimmedEvent for TimelyEvent {
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Operates(_) => "operates",
+            Self::Channels(_) => "channels",
+            Self::PushProgress(_) => "push-progress",
+            Self::Messages(_) => "messages",
+            Self::Schedule(_) => "schedule",
+            Self::Shutdown(_) => "shutdown",
+            Self::Application(_) => "application",
+            Self::GuardedMessage(_) => "guarded-message",
+            Self::GuardedProgress(_) => "guarded-progress",
+            Self::CommChannels(_) => "comm-channels",
+            Self::Input(_) => "input",
+            Self::Park(_) => "park",
+            Self::Text(_) => "text",
+        }
+    }
+
+    fn is_selected(&self, worker: WorkerId, selection: &DataflowSelection) -> bool {
+        match self {
+            Self::Operates(operates) => selection.contains_operator(worker, operates.id),
+            Self::Channels(channel) => selection.contains_channel(worker, channel.id),
+            Self::PushProgress(push) => selection.contains_operator(worker, push.op_id),
+            Self::Messages(messages) => selection.contains_channel(worker, messages.channel),
+            Self::Schedule(schedule) => selection.contains_operator(worker, schedule.id),
+            Self::Shutdown(shutdown) => selection.contains_operator(worker, shutdown.id),
+
+            // Everything else belongs to the worker rather than any one dataflow
+            Self::Application(_)
+            | Self::GuardedMessage(_)
+            | Self::GuardedProgress(_)
+            | Self::CommChannels(_)
+            | Self::Input(_)
+            | Self::Park(_)
+            | Self::Text(_) => true,
+        }
+    }
+}
+
+impl TrimmedEvent for DifferentialEvent {
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Batch(_) => "batch",
+            Self::Merge(_) => "merge",
+            Self::Drop(_) => "drop",
+            Self::MergeShortfall(_) => "merge-shortfall",
+            Self::TraceShare(_) => "trace-share",
+        }
+    }
+
+    fn is_selected(&self, worker: WorkerId, selection: &DataflowSelection) -> bool {
+        let operator = match self {
+            Self::Batch(batch) => batch.operator,
+            Self::Merge(merge) => merge.operator,
+            Self::Drop(drop) => drop.operator,
+            Self::MergeShortfall(shortfall) => shortfall.operator,
+            Self::TraceShare(share) => share.operator,
+        };
+
+        selection.contains_operator(worker, operator)
+    }
+}
+
+impl TrimmedEvent for TimelyProgressEvent {
+    fn kind(&self) -> &'static str {
+        "progress"
+    }
+
+    fn is_selected(&self, worker: WorkerId, selection: &DataflowSelection) -> bool {
+        selection.contains_addr(worker, &self.addr)
+    }
+}
+
+impl TrimmedEvent for TrackerEvent {
+    fn kind(&self) -> &'static str {
+        "reachability"
+    }
+
+    fn is_selected(&self, worker: WorkerId, selection: &DataflowSelection) -> bool {
+        selection.contains_addr(worker, self.tracker_id())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trim::DataflowSelection;
+    use ddshow_types::{OperatorAddr, OperatorId, WorkerId};
+
+    #[test]
+    fn address_selection_keeps_containing_scopes() {
+        let addr = |addr: &[usize]| OperatorAddr::from(addr.to_vec());
+        let worker = WorkerId::new(0);
+
+        let selection = DataflowSelection {
+            addresses: vec![addr(&[0, 3])],
+            ..DataflowSelection::default()
+        };
+
+        // The selected subgraph, its contents and the dataflow containing it are kept
+        assert!(selection.contains_addr(worker, &addr(&[0, 3])));
+        assert!(selection.contains_addr(worker, &addr(&[0, 3, 1])));
+        assert!(selection.contains_addr(worker, &addr(&[0])));
+
+        // But its siblings and other dataflows aren't
+        assert!(!selection.contains_addr(worker, &addr(&[0, 4])));
+        assert!(!selection.contains_addr(worker, &addr(&[1, 3])));
+
+        // Only the contents of the subgraph are actually within it
+        assert!(!selection.is_within(worker, &addr(&[0])));
+        assert!(selection.is_within(worker, &[OperatorId::new(0), OperatorId::new(3)]));
+
+        let everything = DataflowSelection::everything();
+        assert!(everything.contains_addr(worker, &addr(&[5, 2])));
+        assert!(everything.contains_operator(worker, OperatorId::new(10)));
+    }
+}
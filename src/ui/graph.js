@@ -15,8 +15,27 @@
  *     activation_durations: ActivationDuration[];
  *     max_arrangement_size: number | null;
  *     min_arrangement_size: number | null;
+ *     activation_percentiles: Percentiles<string>;
+ *     activation_histogram: Histogram;
+ *     batch_size_percentiles: Percentiles<number> | null;
+ *     time_series: OperatorTimeBucket[];
+ *     total_across_workers: string;
+ *     worker_totals: [number, string][];
  * }} RawNode
  *
+ * @typedef {{ buckets: [number, number][] }} Histogram
+ *
+ * @typedef {{
+ *     start: Duration;
+ *     width: Duration;
+ *     total: Duration;
+ *     activations: number;
+ *     batched_records: number;
+ * }} OperatorTimeBucket
+ *
+ * @template T
+ * @typedef {{ p50: T, p90: T, p99: T }} Percentiles
+ *
  * @typedef {{ activation_time: number, activated_at: number }} ActivationDuration
  * 
  * @typedef {{
@@ -39,6 +58,9 @@
  *     worker: number;
  *     channel_id: number;
  *     edge_kind: EdgeKind;
+ *     messages: number;
+ *     records: number;
+ *     delivery_latency: ChannelLatency | null;
  * }} Edge
  * 
  * @typedef {"Normal" | "Crossing"} EdgeKind
@@ -67,6 +89,55 @@
  *     messages: number;
  *     capability_updates: number;
  * }} ProgressStats
+ *
+ * @typedef {{
+ *     worker: number;
+ *     tracker: number[];
+ *     operator_addr: number[];
+ *     port: number;
+ *     location: "Source" | "Target";
+ *     timestamp: string;
+ *     counts: [Duration, number][];
+ *     held_for: Duration;
+ *     still_held: boolean;
+ * }} PointstampTimeline
+ *
+ * @typedef {{
+ *     worker: number;
+ *     operator_addr: number[];
+ *     port: number;
+ *     steps: [Duration, string[]][];
+ * }} FrontierTimeline
+ *
+ * @typedef {{
+ *     channel: number;
+ *     source: number;
+ *     target: number;
+ *     records: number;
+ *     messages: number;
+ * }} WorkerExchange
+ *
+ * @typedef {{
+ *     channel: number;
+ *     worker: number;
+ *     deliveries: number;
+ *     min: Duration;
+ *     max: Duration;
+ *     mean: Duration;
+ *     p50: Duration;
+ *     p90: Duration;
+ *     p99: Duration;
+ * }} ChannelLatency
+ *
+ * @typedef {{
+ *     start: Duration;
+ *     width: Duration;
+ *     parked: Duration;
+ *     scheduled: Duration;
+ *     overhead: Duration;
+ * }} WorkerTimeBucket
+ *
+ * @typedef {{ secs: number; nanos: number }} Duration
  * #}
  */
 
@@ -83,11 +154,23 @@ const raw_edges = {{ edges | json_encode() }};
 const palette_colors = {{ palette_colors | json_encode() }};
 
 /** @type {TimelineEvent[]} */
-const timeline_events = {{ timeline_events | json_encode() }};
+const timeline_events = /* @timeline_events@ */;
 
 /** @type {[number[], ProgressInfo][]} */
 const channel_progress = {{ channel_progress | json_encode() }};
 
+/** @type {PointstampTimeline[]} */
+const pointstamp_timelines = {{ pointstamp_timelines | json_encode() }};
+
+/** @type {FrontierTimeline[]} */
+const frontier_timelines = {{ frontier_timelines | json_encode() }};
+
+/** @type {WorkerExchange[]} */
+const worker_exchanges = {{ worker_exchanges | json_encode() }};
+
+/** @type {[number, WorkerTimeBucket[]][]} */
+const worker_time_buckets = {{ worker_time_buckets | json_encode() }};
+
 const dataflow_svg = d3.select("#dataflow-graph");
 const svg = dataflow_svg.append("g");
 
@@ -189,12 +272,48 @@ for (const subgraph of raw_subgraphs) {
     }
 }
 
+// Edges are scaled logarithmically by the number of records sent over them
+const max_edge_records = Math.max(0, ...raw_edges.map(edge => edge.records));
+
+/**
+ * Calculates the stroke width of an edge from the records sent over it
+ * @param {Edge} edge The edge to calculate the width of
+ * @returns number
+ */
+function edge_width(edge) {
+    if (max_edge_records === 0) {
+        return 1.5;
+    }
+
+    return 1.5 + 6.5 * (Math.log1p(edge.records) / Math.log1p(max_edge_records));
+}
+
+// Channels whose p90 delivery latency is within half of the slowest channel's
+// are highlighted, their messages spend the longest sitting in queues
+const max_edge_latency = Math.max(
+    0,
+    ...raw_edges
+        .filter(edge => edge.delivery_latency)
+        .map(edge => duration_nanos(edge.delivery_latency.p90)),
+);
+
+/**
+ * Returns true if the messages sent over the edge spend a long time queued
+ * @param {Edge} edge The edge to check
+ * @returns boolean
+ */
+function has_long_queue(edge) {
+    return max_edge_latency !== 0
+        && edge.delivery_latency !== null
+        && duration_nanos(edge.delivery_latency.p90) * 2 >= max_edge_latency;
+}
+
 for (const edge of raw_edges) {
-    let style = "";
+    let style = `stroke-width: ${edge_width(edge).toFixed(2)}px;`;
     switch (edge.edge_kind) {
         case "Crossing":
             // Blue
-            style = "stroke: #5d5de6; stroke-dasharray: 5, 2; fill: none;"
+            style += " stroke: #5d5de6; stroke-dasharray: 5, 2; fill: none;"
             break;
 
         case "Normal":
@@ -205,6 +324,11 @@ for (const edge of raw_edges) {
             break;
     }
 
+    if (has_long_queue(edge)) {
+        // Red
+        style += " stroke: #e6553d;";
+    }
+
     const src_id = format_addr(edge.src);
     const dest_id = format_addr(edge.dest);
 
@@ -249,12 +373,37 @@ svg.selectAll("g.node")
             average runtime of ${node.average_activation_time} \
             (max: ${node.max_activation_time}, min: ${node.min_activation_time})`;
 
+        if (node.kind === "Node") {
+            const percentiles = node.activation_percentiles;
+            html += `<br>p50: ${percentiles.p50}, p90: ${percentiles.p90}, p99: ${percentiles.p99}`;
+            html += histogram_html(node.activation_histogram);
+
+            if (node.time_series.length > 1) {
+                html += `<br>time spent over the program's runtime:${sparkline_html(node.time_series)}`;
+            }
+        }
+
         if (node.kind === "Node"
             && node.max_arrangement_size !== null
             && node.min_arrangement_size !== null
         ) {
             html += `<br>max arrangement size: ${node.max_arrangement_size}, \
                 min arrangement size: ${node.min_arrangement_size}`;
+
+            const batch_sizes = node.batch_size_percentiles;
+            if (batch_sizes !== null) {
+                html += `<br>batch size p50: ${batch_sizes.p50}, \
+                    p90: ${batch_sizes.p90}, p99: ${batch_sizes.p99}`;
+            }
+        }
+
+        if (node.kind === "Node" && node.worker_totals.length > 1) {
+            const worker_totals = node.worker_totals
+                .map(([worker, total]) => `worker ${worker}: ${total}`)
+                .join(", ");
+
+            html += `<br>ran for ${node.total_across_workers} across \
+                ${node.worker_totals.length} workers (${worker_totals})`;
         }
 
         const channel_stats = channel_progress.filter(([addr, _]) => node.addr === addr);
@@ -303,6 +452,21 @@ svg.selectAll("g.edgePath")
         const dest_name = get_node_name(edge.dest);
 
         let html = `channel from ${src_name} to ${dest_name}`;
+        if (edge.messages !== 0) {
+            html += `<br>sent ${edge.records} records over ${edge.messages} messages`;
+        }
+
+        const latency = edge.delivery_latency;
+        if (latency) {
+            html += `<br>delivered ${latency.deliveries} messages, latency \
+                p50 ${format_duration(duration_nanos(latency.p50))}, \
+                p90 ${format_duration(duration_nanos(latency.p90))}, \
+                p99 ${format_duration(duration_nanos(latency.p99))}, \
+                max ${format_duration(duration_nanos(latency.max))}`;
+            if (has_long_queue(edge)) {
+                html += "<br>messages spend a long time queued on this channel";
+            }
+        }
 
         const channel_stats = channel_progress.find(stats => edge.channel_id === stats[0]);
         if (channel_stats) {
@@ -684,3 +848,381 @@ function time_sink_radar(events) {
 }
 
 time_sink_radar(timeline_events);
+
+/**
+ * Renders a small bar chart of an activation duration histogram
+ * @param {Histogram} histogram The histogram to render, in nanoseconds
+ * @returns string
+ */
+function histogram_html(histogram) {
+    if (histogram.buckets.length === 0) {
+        return "";
+    }
+
+    const max_count = Math.max(...histogram.buckets.map(([_bucket, count]) => count));
+    const bars = histogram.buckets
+        .map(([bucket, count]) => {
+            const height = Math.max(1, Math.round(count / max_count * 100));
+            return `<div class="histogram-bar" style="height: ${height}%" \
+                title="${format_duration(bucket)}: ${count}"></div>`;
+        })
+        .join("");
+
+    const first = histogram.buckets[0][0];
+    const last = histogram.buckets[histogram.buckets.length - 1][0];
+
+    return `<div class="histogram">${bars}</div>\
+        <div class="histogram-range">\
+            <span>${format_duration(first)}</span><span>${format_duration(last)}</span>\
+        </div>`;
+}
+
+/**
+ * Renders a sparkline of the time an operator spent running within each time bucket
+ * @param {OperatorTimeBucket[]} time_series The operator's time buckets, sorted by start time
+ * @returns string
+ */
+function sparkline_html(time_series) {
+    const [width, height] = [200, 30];
+
+    const first_start = duration_nanos(time_series[0].start);
+    const last_start = duration_nanos(time_series[time_series.length - 1].start);
+    const max_total = Math.max(1, ...time_series.map(bucket => duration_nanos(bucket.total)));
+
+    const points = time_series
+        .map(bucket => {
+            const x = (duration_nanos(bucket.start) - first_start) / Math.max(1, last_start - first_start) * width;
+            const y = height - duration_nanos(bucket.total) / max_total * height;
+
+            return `${x.toFixed(1)},${y.toFixed(1)}`;
+        })
+        .join(" ");
+
+    const busiest = time_series.reduce((busiest, bucket) =>
+        duration_nanos(bucket.total) > duration_nanos(busiest.total) ? bucket : busiest
+    );
+
+    return `<svg class="sparkline" width="${width}" height="${height}" viewBox="0 0 ${width} ${height}">\
+            <polyline points="${points}"></polyline>\
+        </svg>\
+        <div class="histogram-range">\
+            <span>${format_duration(first_start)}</span><span>${format_duration(last_start)}</span>\
+        </div>\
+        busiest at ${format_duration(duration_nanos(busiest.start))} \
+        with ${format_duration(duration_nanos(busiest.total))} over ${busiest.activations} activations`;
+}
+
+/**
+ * Converts a serialized duration into nanoseconds
+ * @param {Duration} duration The duration to convert
+ * @returns number
+ */
+function duration_nanos(duration) {
+    return duration.secs * 1_000_000_000 + duration.nanos;
+}
+
+/**
+ * Creates a step chart of the number of pointstamps held at the locations
+ * that held onto pointstamps for the longest
+ * @param {PointstampTimeline[]} timelines The pointstamp timelines to chart
+ */
+function pointstamp_timeline_chart(timelines) {
+    if (timelines.length === 0) {
+        return;
+    }
+
+    const chart = echarts.init(document.getElementById("pointstamp-timelines"));
+
+    const longest_held = timelines
+        .slice()
+        .sort((left, right) => duration_nanos(right.held_for) - duration_nanos(left.held_for))
+        .slice(0, 20);
+
+    const series = longest_held.map(timeline => {
+        const addr = format_addr(timeline.operator_addr);
+        const node = raw_nodes.find(node => node.worker === timeline.worker
+            && format_addr(node.addr) === addr);
+        const name = node ? node.name : addr;
+
+        return {
+            name: `Worker ${timeline.worker}: ${name} ${timeline.location} ${timeline.port} @ ${timeline.timestamp}`,
+            type: "line",
+            step: "end",
+            data: timeline.counts.map(([time, count]) => [duration_nanos(time), count]),
+        };
+    });
+
+    chart.setOption({
+        title: {
+            text: "Longest Held Pointstamps",
+        },
+        legend: {
+            type: "scroll",
+            bottom: 0,
+        },
+        xAxis: {
+            type: "value",
+            axisLabel: {
+                formatter: format_duration,
+            },
+        },
+        yAxis: {
+            type: "value",
+            name: "Held Pointstamps",
+        },
+        series: series,
+        tooltip: {
+            trigger: "axis",
+        },
+    });
+}
+
+pointstamp_timeline_chart(pointstamp_timelines);
+
+/**
+ * Creates a step chart of the frontiers of the operator outputs and
+ * inputs whose frontiers changed the most. Frontiers made up entirely of numeric
+ * timestamps are plotted by their lowest timestamp, all others are
+ * plotted by the number of timestamps they hold
+ * @param {FrontierTimeline[]} timelines The frontier timelines to chart
+ */
+function frontier_timeline_chart(timelines) {
+    if (timelines.length === 0) {
+        return;
+    }
+
+    const chart = echarts.init(document.getElementById("frontier-timelines"));
+
+    const numeric = timelines.every(timeline => timeline.steps
+        .every(([_, timestamps]) => timestamps.every(timestamp => !isNaN(Number(timestamp)))));
+
+    const busiest = timelines
+        .slice()
+        .sort((left, right) => right.steps.length - left.steps.length)
+        .slice(0, 20);
+
+    const series = busiest.map(timeline => {
+        const addr = format_addr(timeline.operator_addr);
+        const node = raw_nodes.find(node => node.worker === timeline.worker
+            && format_addr(node.addr) === addr);
+        const name = node ? node.name : addr;
+        const port = timeline.location === "Source" ? "output" : "input";
+
+        return {
+            name: `Worker ${timeline.worker}: ${name} ${port} ${timeline.port}`,
+            type: "line",
+            step: "end",
+            data: timeline.steps.map(([time, timestamps]) => {
+                let value = timestamps.length;
+                if (numeric) {
+                    value = timestamps.length === 0
+                        ? null
+                        : Math.min(...timestamps.map(Number));
+                }
+
+                return [duration_nanos(time), value];
+            }),
+        };
+    });
+
+    chart.setOption({
+        title: {
+            text: "Operator Frontiers",
+        },
+        legend: {
+            type: "scroll",
+            bottom: 0,
+        },
+        xAxis: {
+            type: "value",
+            axisLabel: {
+                formatter: format_duration,
+            },
+        },
+        yAxis: {
+            type: "value",
+            name: numeric ? "Frontier" : "Held Timestamps",
+        },
+        series: series,
+        tooltip: {
+            trigger: "axis",
+        },
+    });
+}
+
+frontier_timeline_chart(frontier_timelines);
+
+/**
+ * Creates a heatmap of the records sent between each pair of workers,
+ * either across the whole program or for a single selected channel
+ * @param {WorkerExchange[]} exchanges The records exchanged between workers
+ */
+function worker_exchange_heatmap(exchanges) {
+    const selection = document.getElementById("exchange-channel-selection");
+    if (exchanges.length === 0) {
+        selection.style.display = "none";
+        return;
+    }
+
+    const chart = echarts.init(document.getElementById("worker-exchanges"));
+
+    const workers = [...new Set(exchanges.flatMap(exchange => [exchange.source, exchange.target]))]
+        .sort((left, right) => left - right);
+    const worker_labels = workers.map(worker => `Worker ${worker}`);
+
+    // Only offer channels that actually send records across workers
+    const channels = [...new Set(exchanges
+        .filter(exchange => exchange.source !== exchange.target)
+        .map(exchange => exchange.channel))]
+        .sort((left, right) => left - right);
+
+    const all_channels = document.createElement("option");
+    all_channels.value = "all";
+    all_channels.text = "All channels";
+    selection.add(all_channels);
+
+    for (const channel of channels) {
+        const option = document.createElement("option");
+        option.value = channel.toString();
+        option.text = `Channel ${channel}`;
+        selection.add(option);
+    }
+
+    const render_heatmap = () => {
+        const selected = selection.value === "all"
+            ? exchanges
+            : exchanges.filter(exchange => exchange.channel.toString() === selection.value);
+
+        const matrix = new Map();
+        for (const exchange of selected) {
+            const key = `${exchange.source}:${exchange.target}`;
+            matrix.set(key, (matrix.get(key) || 0) + exchange.records);
+        }
+
+        const data = [];
+        for (const [source_idx, source] of workers.entries()) {
+            for (const [target_idx, target] of workers.entries()) {
+                data.push([target_idx, source_idx, matrix.get(`${source}:${target}`) || 0]);
+            }
+        }
+
+        chart.setOption({
+            title: {
+                text: selection.value === "all"
+                    ? "Records Exchanged Between Workers"
+                    : `Records Exchanged Between Workers on Channel ${selection.value}`,
+            },
+            tooltip: {
+                position: "top",
+                formatter: params => `${worker_labels[params.value[1]]} sent ${params.value[2]} \
+                    records to ${worker_labels[params.value[0]]}`,
+            },
+            xAxis: {
+                type: "category",
+                name: "Target",
+                data: worker_labels,
+            },
+            yAxis: {
+                type: "category",
+                name: "Source",
+                data: worker_labels,
+            },
+            visualMap: {
+                min: 0,
+                max: Math.max(1, ...data.map(([_target, _source, records]) => records)),
+                calculable: true,
+                orient: "horizontal",
+                left: "center",
+                bottom: 0,
+            },
+            series: [{
+                name: "Records",
+                type: "heatmap",
+                data: data,
+                label: {
+                    show: true,
+                },
+            }],
+        });
+    };
+
+    selection.addEventListener("change", render_heatmap);
+    render_heatmap();
+}
+
+worker_exchange_heatmap(worker_exchanges);
+
+/**
+ * Creates a stacked chart of the fraction of time each worker spent parked,
+ * within operators and in neither over time
+ * @param {[number, WorkerTimeBucket[]][]} workers The time buckets of every worker
+ */
+function worker_utilization_chart(workers) {
+    const selection = document.getElementById("utilization-worker-selection");
+    if (workers.every(([_worker, buckets]) => buckets.length === 0)) {
+        selection.style.display = "none";
+        return;
+    }
+
+    const chart = echarts.init(document.getElementById("worker-utilization"));
+
+    for (const [worker, _buckets] of workers) {
+        const option = document.createElement("option");
+        option.value = worker.toString();
+        option.text = `Worker ${worker}`;
+        selection.add(option);
+    }
+
+    const render_utilization = () => {
+        const [worker, buckets] = workers.find(([worker, _buckets]) => worker.toString() === selection.value);
+
+        const percent = (bucket, time) => {
+            const total = duration_nanos(bucket.parked)
+                + duration_nanos(bucket.scheduled)
+                + duration_nanos(bucket.overhead);
+
+            return total === 0 ? 0 : duration_nanos(time) / total * 100;
+        };
+        const series = (name, field) => ({
+            name: name,
+            type: "bar",
+            stack: "time",
+            data: buckets.map(bucket => percent(bucket, bucket[field])),
+        });
+
+        chart.setOption({
+            title: {
+                text: `Worker ${worker} Time Allocation`,
+            },
+            tooltip: {
+                trigger: "axis",
+                formatter: params => [params[0].axisValue]
+                    .concat(params.map(param => `${param.marker}${param.seriesName}: ${param.value.toFixed(1)}%`))
+                    .join("<br>"),
+            },
+            legend: {
+                data: ["Scheduled", "Overhead", "Parked"],
+            },
+            xAxis: {
+                type: "category",
+                name: "Time",
+                data: buckets.map(bucket => format_duration(duration_nanos(bucket.start))),
+            },
+            yAxis: {
+                type: "value",
+                name: "Time (%)",
+                max: 100,
+            },
+            series: [
+                series("Scheduled", "scheduled"),
+                series("Overhead", "overhead"),
+                series("Parked", "parked"),
+            ],
+        });
+    };
+
+    selection.addEventListener("change", render_utilization);
+    render_utilization();
+}
+
+worker_utilization_chart(worker_time_buckets);
@@ -2,7 +2,12 @@
 
 use crate::{
     args::Args,
-    dataflow::{ProgressInfo, TimelineEvent as RawTimelineEvent},
+    dataflow::{
+        constants::HISTOGRAM_PRECISION_BITS,
+        utils::{round_to_precision, sorted_percentile},
+        ChannelLatency, FrontierTimeline, PointstampTimeline, ProgressInfo, SpillBuffer,
+        TimelineEvent as RawTimelineEvent, WorkerExchange,
+    },
 };
 use abomonation_derive::Abomonation;
 use anyhow::{Context as _, Result};
@@ -10,7 +15,12 @@ use bytecheck::CheckBytes;
 use ddshow_types::{ChannelId, OperatorAddr, OperatorId, PortId, WorkerId};
 use rkyv::{Archive, Deserialize as RkyvDeserialize, Serialize as RkyvSerialize};
 use serde::{Deserialize, Serialize};
-use std::{fs, time::Duration};
+use std::{
+    fmt::{self, Debug, Display},
+    fs::{self, File},
+    io::{BufWriter, Write},
+    time::Duration,
+};
 use tera::{Context, Tera};
 
 const GRAPH_HTML: &str = include_str!("graph.html");
@@ -20,14 +30,23 @@ const D3_JS: &str = include_str!("d3.v5.js");
 const DAGRE_JS: &str = include_str!("dagre-d3.js");
 const ECHARTS_JS: &str = include_str!("echarts.min.js");
 
+/// Marks where timeline events are written into the rendered `graph.js`, they're
+/// streamed in separately since they can be much larger than everything else
+const TIMELINE_EVENTS_PLACEHOLDER: &str = "/* @timeline_events@ */";
+
+#[allow(clippy::too_many_arguments)]
 pub fn render(
     args: &Args,
     nodes: Vec<Node>,
     subgraphs: Vec<Subgraph>,
     edges: Vec<Edge>,
     palette_colors: Vec<String>,
-    timeline_events: Vec<RawTimelineEvent>,
+    timeline_events: &SpillBuffer<RawTimelineEvent>,
     channel_progress: Vec<(OperatorAddr, ProgressInfo)>,
+    pointstamp_timelines: Vec<PointstampTimeline>,
+    frontier_timelines: Vec<FrontierTimeline>,
+    worker_exchanges: Vec<WorkerExchange>,
+    worker_time_buckets: Vec<(WorkerId, Vec<WorkerTimeBucket>)>,
 ) -> Result<()> {
     let output_dir = &args.output_dir;
     tracing::info!(output_dir = ?output_dir, "writing graph files to disk");
@@ -54,8 +73,11 @@ pub fn render(
         subgraphs,
         edges,
         palette_colors,
-        timeline_events,
         channel_progress,
+        pointstamp_timelines,
+        frontier_timelines,
+        worker_exchanges,
+        worker_time_buckets,
     };
 
     // // TODO: This shouldn't be here
@@ -75,7 +97,21 @@ pub fn render(
     let rendered_js =
         Tera::one_off(GRAPH_JS, &context, false).context("failed to render output graph")?;
 
-    fs::write(output_dir.join("graph.js"), rendered_js)
+    let (prefix, suffix) = rendered_js
+        .split_once(TIMELINE_EVENTS_PLACEHOLDER)
+        .context("failed to find the timeline events within the output graph")?;
+
+    let mut graph_js = BufWriter::new(
+        File::create(output_dir.join("graph.js")).context("failed to create output graph file")?,
+    );
+    graph_js
+        .write_all(prefix.as_bytes())
+        .context("failed to write output graph to file")?;
+    serde_json::to_writer(&mut graph_js, timeline_events)
+        .context("failed to write timeline events to output graph")?;
+    graph_js
+        .write_all(suffix.as_bytes())
+        .and_then(|()| graph_js.flush())
         .context("failed to write output graph to file")?;
 
     Ok(())
@@ -110,6 +146,7 @@ pub struct DDShowStats {
     pub nodes: Vec<NodeStats>,
     pub channels: Vec<ChannelStats>,
     pub arrangements: Vec<ArrangementStats>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub events: Vec<TimelineEvent>,
     pub differential_enabled: bool,
     pub progress_enabled: bool,
@@ -199,9 +236,77 @@ pub struct WorkerStats {
     pub events: usize,
     pub runtime: Duration,
     pub dataflow_addrs: Vec<OperatorAddr>,
+    /// How the worker's time was split up, sorted by the start of each bucket
+    pub time_buckets: Vec<WorkerTimeBucket>,
     // TODO: Missing nodes & edges
 }
 
+/// The time a worker spent parked, within operators and in neither
+/// (scheduler overhead) over a single window of time
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Default,
+    Deserialize,
+    Serialize,
+    Abomonation,
+    Archive,
+    RkyvSerialize,
+    RkyvDeserialize,
+)]
+#[archive(strict, derive(CheckBytes))]
+pub struct WorkerTimeBucket {
+    pub start: Duration,
+    pub width: Duration,
+    pub parked: Duration,
+    pub scheduled: Duration,
+    pub overhead: Duration,
+}
+
+impl WorkerTimeBucket {
+    /// The total amount of time the worker was active for within the bucket
+    pub fn total(&self) -> Duration {
+        self.parked + self.scheduled + self.overhead
+    }
+}
+
+/// The activity of a single operator over a single window of time
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Default,
+    Deserialize,
+    Serialize,
+    Abomonation,
+    Archive,
+    RkyvSerialize,
+    RkyvDeserialize,
+)]
+#[archive(strict, derive(CheckBytes))]
+pub struct OperatorTimeBucket {
+    pub start: Duration,
+    pub width: Duration,
+    /// The time spent within the operator
+    pub total: Duration,
+    /// The number of activations that started within the bucket
+    pub activations: usize,
+    /// The number of records added to the operator's arrangement, only
+    /// tracked for arrangements when differential logging is enabled
+    pub batched_records: usize,
+}
+
 // - Dataflow stats
 //   - creation time
 //   - drop time
@@ -279,6 +384,11 @@ pub struct NodeStats {
     pub lifespan: Lifespan,
     pub kind: NodeKind,
     pub activations: AggregatedStats<Duration>,
+    pub activation_percentiles: Percentiles<Duration>,
+    /// The distribution of activation durations in nanoseconds
+    pub activation_histogram: Histogram,
+    /// The operator's activity over time, summed across every worker
+    pub time_series: Vec<OperatorTimeBucket>,
 }
 
 #[derive(
@@ -381,6 +491,10 @@ pub struct ChannelStats {
     pub dest_node: OperatorId,
     pub kind: ChannelKind,
     pub lifespan: Lifespan,
+    /// The number of messages sent over the channel
+    pub messages: usize,
+    /// The number of records sent over the channel
+    pub records: usize,
 }
 
 #[derive(
@@ -478,6 +592,177 @@ pub struct AggregatedStats<T> {
     // TODO: Standard deviation, standard error
 }
 
+/// A mergeable histogram that keeps each value to within a fixed relative
+/// error, values are bucketed by their [`HISTOGRAM_PRECISION_BITS`] most
+/// significant bits so the number of buckets grows logarithmically
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Default,
+    Deserialize,
+    Serialize,
+    Abomonation,
+    Archive,
+    RkyvSerialize,
+    RkyvDeserialize,
+)]
+#[archive(strict, derive(CheckBytes))]
+pub struct Histogram {
+    /// The lower bound of every non-empty bucket along with the
+    /// number of values within it, sorted by bucket
+    pub buckets: Vec<(u64, u64)>,
+}
+
+impl Histogram {
+    pub const fn new() -> Self {
+        Self {
+            buckets: Vec::new(),
+        }
+    }
+
+    /// Creates a histogram from `(bucket, count)` pairs sorted by bucket,
+    /// as returned by [`Histogram::bucket()`]
+    pub fn from_sorted_buckets<I>(buckets: I) -> Self
+    where
+        I: IntoIterator<Item = (u64, u64)>,
+    {
+        let mut histogram = Self::new();
+        for (bucket, count) in buckets {
+            match histogram.buckets.last_mut() {
+                Some((last, total)) if *last == bucket => *total += count,
+                _ => histogram.buckets.push((bucket, count)),
+            }
+        }
+
+        histogram
+    }
+
+    /// Gets the bucket that `value` falls into
+    pub const fn bucket(value: u64) -> u64 {
+        round_to_precision(value, HISTOGRAM_PRECISION_BITS)
+    }
+
+    /// Records `count` occurrences of `value`
+    pub fn record(&mut self, value: u64, count: u64) {
+        let bucket = Self::bucket(value);
+
+        match self
+            .buckets
+            .binary_search_by_key(&bucket, |&(bucket, _)| bucket)
+        {
+            Ok(idx) => self.buckets[idx].1 += count,
+            Err(idx) => self.buckets.insert(idx, (bucket, count)),
+        }
+    }
+
+    /// Adds all of the values from `other` into the current histogram
+    pub fn merge(&mut self, other: &Self) {
+        let mut merged = Vec::with_capacity(self.buckets.len().max(other.buckets.len()));
+        let (mut left, mut right) = (
+            self.buckets.iter().copied().peekable(),
+            other.buckets.iter().copied().peekable(),
+        );
+
+        // Both histograms are sorted by bucket so they can be merged in one pass
+        loop {
+            let next = match (left.peek().copied(), right.peek().copied()) {
+                (Some((left_bucket, left_count)), Some((right_bucket, right_count)))
+                    if left_bucket == right_bucket =>
+                {
+                    left.next();
+                    right.next();
+                    (left_bucket, left_count + right_count)
+                }
+                (Some((left_bucket, _)), Some((right_bucket, _))) if left_bucket < right_bucket => {
+                    left.next().unwrap()
+                }
+                (_, Some(_)) => right.next().unwrap(),
+                (Some(_), None) => left.next().unwrap(),
+                (None, None) => break,
+            };
+
+            merged.push(next);
+        }
+
+        self.buckets = merged;
+    }
+
+    /// The total number of values within the histogram
+    pub fn count(&self) -> u64 {
+        self.buckets.iter().map(|&(_, count)| count).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buckets.is_empty()
+    }
+
+    /// Gets the `percent`th percentile of the histogram's values,
+    /// zero if the histogram is empty
+    pub fn percentile(&self, percent: u64) -> u64 {
+        sorted_percentile(self.buckets.iter().copied(), self.count(), percent).unwrap_or(0)
+    }
+
+    /// Gets the 50th, 90th and 99th percentiles of the histogram's values
+    pub fn percentiles(&self) -> Percentiles<u64> {
+        Percentiles {
+            p50: self.percentile(50),
+            p90: self.percentile(90),
+            p99: self.percentile(99),
+        }
+    }
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Default,
+    Deserialize,
+    Serialize,
+    Abomonation,
+    Archive,
+    RkyvSerialize,
+    RkyvDeserialize,
+)]
+#[archive(strict, derive(CheckBytes))]
+pub struct Percentiles<T> {
+    pub p50: T,
+    pub p90: T,
+    pub p99: T,
+}
+
+impl<T> Percentiles<T> {
+    pub fn map<F, U>(self, mut map: F) -> Percentiles<U>
+    where
+        F: FnMut(T) -> U,
+    {
+        Percentiles {
+            p50: map(self.p50),
+            p90: map(self.p90),
+            p99: map(self.p99),
+        }
+    }
+}
+
+impl<T> Display for Percentiles<T>
+where
+    T: Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#?} / {:#?} / {:#?}", self.p50, self.p90, self.p99)
+    }
+}
+
 // - Timeline events
 //   - event id (is this actually needed?)
 //   - worker
@@ -515,8 +800,11 @@ pub struct GraphData {
     pub subgraphs: Vec<Subgraph>,
     pub edges: Vec<Edge>,
     pub palette_colors: Vec<String>,
-    pub timeline_events: Vec<RawTimelineEvent>,
     pub channel_progress: Vec<(OperatorAddr, ProgressInfo)>,
+    pub pointstamp_timelines: Vec<PointstampTimeline>,
+    pub frontier_timelines: Vec<FrontierTimeline>,
+    pub worker_exchanges: Vec<WorkerExchange>,
+    pub worker_time_buckets: Vec<(WorkerId, Vec<WorkerTimeBucket>)>,
 }
 
 #[derive(Debug, Clone, PartialEq, PartialOrd, Deserialize, Serialize)]
@@ -535,6 +823,16 @@ pub struct Node {
     pub activation_durations: Vec<ActivationDuration>,
     pub max_arrangement_size: Option<usize>,
     pub min_arrangement_size: Option<usize>,
+    pub activation_percentiles: Percentiles<String>,
+    /// The distribution of activation durations in nanoseconds
+    pub activation_histogram: Histogram,
+    pub batch_size_percentiles: Option<Percentiles<u64>>,
+    /// The operator's activity over time, summed across every worker
+    pub time_series: Vec<OperatorTimeBucket>,
+    /// The operator's total activation time summed across every worker
+    pub total_across_workers: String,
+    /// The operator's total activation time on each worker it exists on
+    pub worker_totals: Vec<(WorkerId, String)>,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Deserialize, Serialize)]
@@ -565,6 +863,12 @@ pub struct Edge {
     pub worker: WorkerId,
     pub channel_id: ChannelId,
     pub edge_kind: EdgeKind,
+    /// The number of messages sent over the channel
+    pub messages: usize,
+    /// The number of records sent over the channel
+    pub records: usize,
+    /// The delivery latency of messages received over the channel
+    pub delivery_latency: Option<ChannelLatency>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
@@ -0,0 +1,877 @@
+//! Checking saved logs for truncated or corrupted frames
+//!
+//! `ddshow verify` walks every frame of every log file within a directory, checking
+//! that each frame's length fits within the file and that its archive passes
+//! validation, or every archive of its batch for batched streams. Frames always
+//! start on a 16 byte boundary (8 bytes for batched streams), so with `--resync`
+//! the verifier steps forward one alignment at a time after a corrupted frame until
+//! it finds the next frame that validates, which lets `--repair` salvage everything
+//! around a corrupted region instead of only what came before it. While resyncing,
+//! candidates claiming to be longer than any frame seen so far are rejected from their
+//! header alone so that corrupted regions don't have their claimed payloads read at
+//! every step. Streams with checksums have every frame's checksum checked before it's
+//! validated
+
+use crate::{
+    args::VerifyArgs,
+    dataflow::operators::rkyv_capture::{check_archive, check_checksum},
+    record::{RecordManifest, SourceKind},
+    replay_serve::log_files,
+};
+use anyhow::{Context, Result};
+use bytecheck::CheckBytes;
+use ddshow_sink::framing::{
+    self, crc32c, FrameHeader, Framing, StreamHeader, FRAME_HEADER_LEN, STREAM_HEADER_LEN,
+};
+use ddshow_types::{
+    differential_logging::DifferentialEvent, progress_logging::TimelyProgressEvent,
+    reachability_logging::TrackerEvent, timely_logging::TimelyEvent, ArchivedEvent, WorkerId,
+};
+use indicatif::{HumanBytes, ProgressBar, ProgressStyle};
+use rkyv::{validation::DefaultArchiveValidator, AlignedVec, Archive};
+use std::{
+    convert::TryInto,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::Path,
+    time::Duration,
+};
+
+pub fn verify(verify_args: &VerifyArgs) -> Result<()> {
+    let log_dir = &verify_args.log_dir;
+    let repair_dir = verify_args.repair.as_deref();
+    let resync = verify_args.resync || repair_dir.is_some();
+
+    if let Some(repair_dir) = repair_dir {
+        let canonical_log_dir = fs::canonicalize(log_dir)
+            .with_context(|| format!("failed to read log directory {}", log_dir.display()))?;
+        if matches!(fs::canonicalize(repair_dir), Ok(repair_dir) if repair_dir == canonical_log_dir)
+        {
+            anyhow::bail!(
+                "the repaired logs can't be written over the logs they were repaired from"
+            );
+        }
+    }
+
+    let mut sources = Vec::new();
+    let mut total_bytes = 0;
+    for kind in [
+        SourceKind::Timely,
+        SourceKind::Differential,
+        SourceKind::Progress,
+        SourceKind::Reachability,
+    ] {
+        for path in log_files(log_dir, kind.file_prefix())? {
+            total_bytes += fs::metadata(&path)
+                .with_context(|| format!("failed to read metadata of {}", path.display()))?
+                .len();
+
+            sources.push((kind, path));
+        }
+    }
+
+    if sources.is_empty() {
+        anyhow::bail!("no log files were found in {}", log_dir.display());
+    }
+
+    if let Some(repair_dir) = repair_dir {
+        fs::create_dir_all(repair_dir).with_context(|| {
+            format!("failed to create output directory {}", repair_dir.display())
+        })?;
+    }
+
+    let progress = ProgressBar::new(total_bytes)
+        .with_style(
+            ProgressStyle::default_bar()
+                .template("[{elapsed}] {prefix}: {wide_bar} {bytes}/{total_bytes}"),
+        )
+        .with_prefix(format!("Verifying logs from {}", log_dir.display()));
+
+    let mut verified = Vec::with_capacity(sources.len());
+    for (kind, path) in sources {
+        let file_name = path
+            .file_name()
+            .context("log files always have a file name")?
+            .to_string_lossy()
+            .into_owned();
+        let repaired = repair_dir.map(|repair_dir| repair_dir.join(&file_name));
+        let repaired = repaired.as_deref();
+
+        let file = match kind {
+            SourceKind::Timely => verify_file::<TimelyEvent>(&path, resync, repaired, &progress),
+            SourceKind::Differential => {
+                verify_file::<DifferentialEvent>(&path, resync, repaired, &progress)
+            }
+            SourceKind::Progress => {
+                verify_file::<TimelyProgressEvent>(&path, resync, repaired, &progress)
+            }
+            SourceKind::Reachability => {
+                verify_file::<TrackerEvent>(&path, resync, repaired, &progress)
+            }
+        }
+        .with_context(|| format!("failed to verify {}", path.display()))?;
+
+        progress.println(file.describe(&file_name));
+        verified.push((file_name, file));
+    }
+    progress.finish_and_clear();
+
+    let damaged = verified
+        .iter()
+        .filter(|(_, file)| !file.problems.is_empty())
+        .count();
+
+    if let Some(repair_dir) = repair_dir {
+        if let Some(mut manifest) = RecordManifest::read(log_dir)? {
+            for source in manifest.sources.iter_mut() {
+                if let Some((_, file)) = verified.iter().find(|(name, _)| *name == source.file) {
+                    source.events = file.events;
+                    source.bytes = file.written;
+                }
+            }
+
+            manifest.write(repair_dir)?;
+        }
+
+        println!(
+            "Wrote the {} valid frames of {} log files ({}) to {}",
+            verified.iter().map(|(_, file)| file.frames).sum::<u64>(),
+            verified.len(),
+            HumanBytes(verified.iter().map(|(_, file)| file.written).sum()),
+            repair_dir.display(),
+        );
+    }
+
+    if damaged == 0 {
+        println!(
+            "All {} log files in {} are intact",
+            verified.len(),
+            log_dir.display(),
+        );
+    } else if repair_dir.is_none() {
+        anyhow::bail!(
+            "found problems in {} of {} log files in {}",
+            damaged,
+            verified.len(),
+            log_dir.display(),
+        );
+    }
+
+    Ok(())
+}
+
+/// Something that's wrong with a log file
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Problem {
+    /// The file's stream header has a version or flags that aren't supported
+    Unsupported { reason: String },
+    /// The file ends partway through a frame
+    Truncated {
+        /// The index of the partial frame
+        frame: u64,
+        /// The offset of the partial frame
+        offset: u64,
+        /// The number of bytes left over in the file
+        len: u64,
+    },
+    /// A region of the file doesn't contain valid frames
+    Corrupted {
+        /// The index of the first invalid frame
+        frame: u64,
+        /// The offset of the first invalid frame
+        offset: u64,
+        /// The number of bytes that were skipped over, or everything after
+        /// `offset` if the verifier couldn't resynchronize
+        len: u64,
+        /// Whether a valid frame was found after the corrupted region
+        resynced: bool,
+        /// Why the first invalid frame didn't validate
+        reason: String,
+    },
+}
+
+#[derive(Debug, Default)]
+struct VerifiedFile {
+    /// The number of valid frames in the file
+    frames: u64,
+    /// The number of events within the valid frames
+    events: u64,
+    /// The number of bytes taken up by valid frames
+    valid_bytes: u64,
+    /// The number of bytes written to the repaired file
+    written: u64,
+    problems: Vec<Problem>,
+}
+
+impl VerifiedFile {
+    fn describe(&self, file_name: &str) -> String {
+        let mut description = format!(
+            "{}: {} valid frames ({})",
+            file_name,
+            self.frames,
+            HumanBytes(self.valid_bytes),
+        );
+
+        if self.problems.is_empty() {
+            description.push_str(", ok");
+        }
+
+        for problem in self.problems.iter() {
+            description.push_str("\n    ");
+
+            match problem {
+                Problem::Unsupported { reason } => {
+                    description.push_str(&format!("can't be read, {}", reason))
+                }
+
+                Problem::Truncated { frame, offset, len } => description.push_str(&format!(
+                    "truncated frame {} at byte {}, the last {} bytes are incomplete",
+                    frame, offset, len,
+                )),
+
+                Problem::Corrupted {
+                    frame,
+                    offset,
+                    len,
+                    resynced,
+                    reason,
+                } => {
+                    description.push_str(&format!(
+                        "corrupted frame {} at byte {} ({})",
+                        frame, offset, reason,
+                    ));
+
+                    if *resynced {
+                        description.push_str(&format!(
+                            ", skipped {} bytes to the next valid frame at byte {}",
+                            len,
+                            offset + len,
+                        ));
+                    } else {
+                        description.push_str(&format!(
+                            ", the remaining {} bytes couldn't be checked",
+                            len,
+                        ));
+                    }
+                }
+            }
+        }
+
+        description
+    }
+}
+
+/// The result of checking for a frame at a given offset
+enum Frame {
+    /// A frame that validated, the payload is left in the scanner's buffer
+    Valid { payload_len: u64, events: u64 },
+    /// The frame's header or payload runs past the end of the file
+    PastEnd { reason: String },
+    /// The frame's checksum or archives failed validation, or it
+    /// claimed to be longer than the caller allowed
+    Invalid { reason: String },
+}
+
+/// Reads frames from arbitrary offsets of a log file
+struct FrameScanner {
+    reader: BufReader<File>,
+    position: u64,
+    len: u64,
+    /// The file's stream header, if it has one
+    stream_header: Option<StreamHeader>,
+    /// How the file's frames are laid out
+    framing: Framing,
+    payload: AlignedVec,
+}
+
+impl FrameScanner {
+    fn new(path: &Path) -> Result<Self> {
+        let file = File::open(path)
+            .with_context(|| format!("failed to open log file {}", path.display()))?;
+        let len = file
+            .metadata()
+            .with_context(|| format!("failed to read metadata of {}", path.display()))?
+            .len();
+
+        let mut scanner = Self {
+            reader: BufReader::new(file),
+            position: 0,
+            len,
+            stream_header: None,
+            framing: Framing::of(None),
+            payload: AlignedVec::new(),
+        };
+
+        if len >= STREAM_HEADER_LEN as u64 {
+            let mut header = [0; STREAM_HEADER_LEN];
+            scanner
+                .read_exact(&mut header)
+                .with_context(|| format!("failed to read {}", path.display()))?;
+
+            scanner.stream_header = StreamHeader::decode(&header);
+            scanner.framing = Framing::of(scanner.stream_header);
+        }
+
+        Ok(scanner)
+    }
+
+    /// The offset of the file's first frame
+    fn first_frame(&self) -> u64 {
+        if self.stream_header.is_some() {
+            STREAM_HEADER_LEN as u64
+        } else {
+            0
+        }
+    }
+
+    fn seek_to(&mut self, offset: u64) -> io::Result<()> {
+        if offset != self.position {
+            // Seeking a little way forward can usually stay within the buffer
+            match (offset - self.position.min(offset)).try_into() {
+                Ok(forward) if offset > self.position => self.reader.seek_relative(forward)?,
+                _ => {
+                    self.reader.seek(SeekFrom::Start(offset))?;
+                }
+            }
+
+            self.position = offset;
+        }
+
+        Ok(())
+    }
+
+    fn read_exact(&mut self, buffer: &mut [u8]) -> io::Result<()> {
+        self.reader.read_exact(buffer)?;
+        self.position += buffer.len() as u64;
+
+        Ok(())
+    }
+
+    /// Checks for a frame starting at `offset`, which must be aligned. Frames with payloads
+    /// longer than `max_payload_len` are invalid without their payload being read
+    fn frame_at<D>(&mut self, offset: u64, max_payload_len: u64) -> Result<Frame>
+    where
+        D: Archive,
+        D::Archived: CheckBytes<DefaultArchiveValidator>,
+    {
+        let header_len = self.framing.header_len();
+        debug_assert_eq!(offset % self.framing.alignment() as u64, 0);
+
+        let remaining = self.len - offset;
+        if remaining < header_len as u64 {
+            return Ok(Frame::PastEnd {
+                reason: format!(
+                    "only {} bytes of the frame's header were written",
+                    remaining
+                ),
+            });
+        }
+
+        self.seek_to(offset)?;
+        let mut header = [0; FRAME_HEADER_LEN];
+        self.read_exact(&mut header[..header_len])?;
+
+        let header = FrameHeader::decode(&header, self.framing)
+            .context("frame headers can always be decoded from enough bytes")?;
+        let payload_len = header.len;
+        let available = remaining - header_len as u64;
+        if payload_len > available {
+            return Ok(Frame::PastEnd {
+                reason: format!(
+                    "its length of {} bytes runs past the end of the file",
+                    payload_len,
+                ),
+            });
+        } else if payload_len > max_payload_len {
+            return Ok(Frame::Invalid {
+                reason: format!(
+                    "its length of {} bytes is longer than any other frame",
+                    payload_len,
+                ),
+            });
+        }
+
+        self.payload.clear();
+        let copied = io::copy(&mut (&mut self.reader).take(payload_len), &mut self.payload)?;
+        self.position += copied;
+
+        if copied != payload_len {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+
+        let events = check_checksum(header, &self.payload)
+            .map_err(|kind| kind.to_string())
+            .and_then(|()| {
+                if self.framing.is_batched() {
+                    let mut events = 0;
+                    for (event, entry) in framing::batch_entries(&self.payload)?
+                        .into_iter()
+                        .enumerate()
+                    {
+                        events += check_events::<D>(&self.payload[entry]).map_err(|reason| {
+                            format!("event {} of the batch, {}", event, reason)
+                        })?;
+                    }
+
+                    Ok(events)
+                } else {
+                    check_events::<D>(&self.payload)
+                }
+            });
+
+        Ok(match events {
+            Ok(events) => Frame::Valid {
+                payload_len,
+                events,
+            },
+            Err(reason) => Frame::Invalid { reason },
+        })
+    }
+}
+
+/// Validates the archive of a single event, returning the number of log events it holds
+fn check_events<D>(archive: &[u8]) -> Result<u64, String>
+where
+    D: Archive,
+    D::Archived: CheckBytes<DefaultArchiveValidator>,
+{
+    check_archive::<Duration, (Duration, WorkerId, D)>(archive)
+        .map(|archive| match archive {
+            ArchivedEvent::Messages(_, data) => data.len() as u64,
+            ArchivedEvent::Progress(_) => 0,
+        })
+        .map_err(|reason| format!("invalid archive ({})", reason))
+}
+
+/// Checks every frame of a log file, writing the valid frames to `repaired` if it's given
+fn verify_file<D>(
+    path: &Path,
+    resync: bool,
+    repaired: Option<&Path>,
+    progress: &ProgressBar,
+) -> Result<VerifiedFile>
+where
+    D: Archive,
+    D::Archived: CheckBytes<DefaultArchiveValidator>,
+{
+    let mut scanner = FrameScanner::new(path)?;
+    let mut output = repaired
+        .map(|repaired| {
+            File::create(repaired)
+                .map(BufWriter::new)
+                .with_context(|| format!("failed to create {}", repaired.display()))
+        })
+        .transpose()?;
+
+    let (start, mut offset) = (progress.position(), scanner.first_frame());
+    let mut file = VerifiedFile::default();
+
+    if let Some(reason) = scanner
+        .stream_header
+        .as_ref()
+        .and_then(StreamHeader::unsupported)
+    {
+        file.problems.push(Problem::Unsupported { reason });
+        progress.set_position(start + scanner.len);
+
+        return Ok(file);
+    }
+
+    let (alignment, header_len) = (scanner.framing.alignment(), scanner.framing.header_len());
+    let mut longest_payload = 0;
+    loop {
+        offset = align(offset, alignment);
+        progress.set_position(start + offset.min(scanner.len));
+        if offset >= scanner.len {
+            break;
+        }
+
+        let (reason, past_end) = match scanner.frame_at::<D>(offset, u64::MAX)? {
+            Frame::Valid {
+                payload_len,
+                events,
+            } => {
+                file.events += events;
+
+                if let Some(output) = output.as_mut() {
+                    file.written = write_frame(
+                        output,
+                        file.written,
+                        scanner.stream_header,
+                        &scanner.payload,
+                    )
+                    .with_context(|| format!("failed to write to {}", path.display()))?;
+                }
+
+                let frame_end = offset + (header_len as u64) + payload_len;
+                longest_payload = longest_payload.max(payload_len);
+                file.valid_bytes += frame_end - offset;
+                file.frames += 1;
+                offset = frame_end;
+
+                continue;
+            }
+
+            Frame::PastEnd { reason } => (reason, true),
+            Frame::Invalid { reason } => (reason, false),
+        };
+
+        let next_frame = if resync {
+            let max_payload_len = longest_payload.max(MIN_RESYNC_PAYLOAD_LEN);
+            resynchronize::<D>(&mut scanner, offset, max_payload_len)?
+        } else {
+            None
+        };
+
+        let problem = match next_frame {
+            Some(next_frame) => Problem::Corrupted {
+                frame: file.frames,
+                offset,
+                len: next_frame - offset,
+                resynced: true,
+                reason,
+            },
+
+            // A frame that runs past the end with nothing valid after it is
+            // the last frame of a log that was cut off while being written
+            None if past_end => Problem::Truncated {
+                frame: file.frames,
+                offset,
+                len: scanner.len - offset,
+            },
+
+            None => Problem::Corrupted {
+                frame: file.frames,
+                offset,
+                len: scanner.len - offset,
+                resynced: false,
+                reason,
+            },
+        };
+        file.problems.push(problem);
+
+        match next_frame {
+            Some(next_frame) => offset = next_frame,
+            None => break,
+        }
+    }
+
+    progress.set_position(start + scanner.len);
+    if let Some(mut output) = output {
+        output
+            .flush()
+            .with_context(|| format!("failed to flush {}", path.display()))?;
+    }
+
+    Ok(file)
+}
+
+/// Steps forward from a bad frame at `offset` one alignment at a time, returning the
+/// offset of the next valid frame with a payload of at most `max_payload_len` bytes
+fn resynchronize<D>(
+    scanner: &mut FrameScanner,
+    offset: u64,
+    max_payload_len: u64,
+) -> Result<Option<u64>>
+where
+    D: Archive,
+    D::Archived: CheckBytes<DefaultArchiveValidator>,
+{
+    let (alignment, header_len) = (
+        scanner.framing.alignment() as u64,
+        scanner.framing.header_len() as u64,
+    );
+
+    let mut candidate = offset + alignment;
+    while candidate + header_len <= scanner.len {
+        if let Frame::Valid { .. } = scanner.frame_at::<D>(candidate, max_payload_len)? {
+            return Ok(Some(candidate));
+        }
+
+        candidate += alignment;
+    }
+
+    Ok(None)
+}
+
+/// Writes a frame to `output` the same way that `EventWriter` and `BatchedEventWriter`
+/// do, starting with the stream's header if there is one and nothing's been written
+/// yet. Returns the position of `output` after the frame
+fn write_frame<W>(
+    output: &mut W,
+    mut position: u64,
+    stream_header: Option<StreamHeader>,
+    payload: &[u8],
+) -> io::Result<u64>
+where
+    W: Write,
+{
+    if let (Some(stream_header), 0) = (stream_header, position) {
+        output.write_all(&stream_header.encode())?;
+        position += STREAM_HEADER_LEN as u64;
+    }
+
+    let framing = Framing::of(stream_header);
+    let header = FrameHeader {
+        len: payload.len() as u64,
+        checksum: if framing.has_checksums() {
+            Some(crc32c(payload))
+        } else {
+            None
+        },
+    };
+
+    let padding = (align(position, framing.alignment()) - position) as usize;
+    output.write_all(&[0; FRAME_HEADER_LEN][..padding])?;
+    output.write_all(&header.encode(framing)[..framing.header_len()])?;
+    output.write_all(payload)?;
+
+    Ok(position + (padding + framing.header_len() + payload.len()) as u64)
+}
+
+/// The longest payload that a frame found while resyncing can have before
+/// the file has had any frames longer than it
+const MIN_RESYNC_PAYLOAD_LEN: u64 = 1 << 20;
+
+const fn align(offset: u64, alignment: usize) -> u64 {
+    let alignment = alignment as u64;
+    (offset + alignment - 1) & !(alignment - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{align, verify_file, Problem, VerifiedFile};
+    use crate::dataflow::operators::{EventIterator, RkyvEventReader};
+    use ddshow_sink::{framing::Framing, LogWriter};
+    use ddshow_types::{
+        timely_logging::{OperatesEvent, TimelyEvent},
+        OperatorAddr, OperatorId, WorkerId,
+    };
+    use indicatif::ProgressBar;
+    use std::{
+        fs::{self, File},
+        path::{Path, PathBuf},
+        process,
+        time::Duration,
+    };
+    use timely::dataflow::operators::capture::{Event, EventPusher};
+
+    type LogEvent = Event<Duration, (Duration, WorkerId, TimelyEvent)>;
+
+    const FRAMINGS: [Framing; 4] = [
+        Framing::Single { checksums: false },
+        Framing::Single { checksums: true },
+        Framing::Batched { checksums: false },
+        Framing::Batched { checksums: true },
+    ];
+
+    /// Pairs of messages and progress updates, batched streams
+    /// are written with a batch for each pair
+    fn events() -> Vec<LogEvent> {
+        (0..6)
+            .flat_map(|idx| {
+                let time = Duration::from_secs(idx);
+                let operator = OperatorId::new(idx as usize);
+
+                vec![
+                    Event::Messages(
+                        time,
+                        vec![(
+                            time,
+                            WorkerId::new(0),
+                            TimelyEvent::Operates(OperatesEvent::new(
+                                operator,
+                                OperatorAddr::from_elem(operator),
+                                format!("operator {}", idx),
+                            )),
+                        )],
+                    ),
+                    Event::Progress(vec![(time, 1)]),
+                ]
+            })
+            .collect()
+    }
+
+    fn encode(events: &[LogEvent], framing: Framing) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        let mut writer = match LogWriter::new(&mut buffer, framing) {
+            // Each batch holds a message and the progress update after it
+            LogWriter::Batched(writer) => LogWriter::Batched(
+                writer
+                    .with_batch_limits(2, usize::MAX)
+                    .with_batch_delay(Duration::from_secs(60 * 60)),
+            ),
+            writer => writer,
+        };
+        for event in events {
+            writer.push(event.clone());
+        }
+        drop(writer);
+
+        buffer
+    }
+
+    fn read(path: &Path) -> Vec<LogEvent> {
+        let mut reader = RkyvEventReader::new(File::open(path).unwrap());
+        let (mut is_finished, mut events) = (false, Vec::new());
+        while !is_finished {
+            if let Some(event) = EventIterator::next(&mut reader, &mut is_finished).unwrap() {
+                events.push(event);
+            }
+        }
+
+        events
+    }
+
+    fn verify(path: &Path, resync: bool, repaired: Option<&Path>) -> VerifiedFile {
+        verify_file::<TimelyEvent>(path, resync, repaired, &ProgressBar::hidden()).unwrap()
+    }
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("ddshow-verify-test-{}-{}", process::id(), name));
+        fs::create_dir_all(&dir).unwrap();
+
+        dir
+    }
+
+    #[test]
+    fn resync_skips_corrupted_frames() {
+        let events = events();
+        let dir = test_dir("resync");
+
+        for framing in FRAMINGS.iter().copied() {
+            // The corrupted frame holds the third message, along with
+            // the progress update after it in batched streams
+            let (corrupted_events, frames) = if framing.is_batched() {
+                (4..6, 6)
+            } else {
+                (4..5, 12)
+            };
+            let corrupted_frame = if framing.is_batched() { 2 } else { 4 };
+
+            let corrupted_start = encode(&events[..corrupted_events.start], framing).len();
+            let corrupted_end = encode(&events[..corrupted_events.end], framing).len();
+            let (offset, next_frame) = (
+                align(corrupted_start as u64, framing.alignment()),
+                align(corrupted_end as u64, framing.alignment()),
+            );
+
+            let mut corruptions = vec![("length", offset as usize + framing.header_len() / 2 - 1)];
+            if framing.has_checksums() {
+                corruptions.push(("payload", corrupted_end - 1));
+            }
+
+            for (corruption, corrupted_byte) in corruptions {
+                let context = format!("{:?} with a corrupted {}", framing, corruption);
+                let (path, repaired) = (dir.join("timely.worker-0.ddshow"), dir.join("repaired"));
+
+                let mut buffer = encode(&events, framing);
+                buffer[corrupted_byte] ^= 0x7F;
+                fs::write(&path, &buffer).unwrap();
+
+                // Without resyncing, everything after the corrupted frame is lost
+                let file = verify(&path, false, None);
+                assert_eq!(file.frames, corrupted_frame, "{}", context);
+                match &file.problems[..] {
+                    [Problem::Corrupted {
+                        frame,
+                        offset: problem_offset,
+                        len,
+                        resynced: false,
+                        ..
+                    }] => {
+                        assert_eq!(*frame, corrupted_frame, "{}", context);
+                        assert_eq!(*problem_offset, offset, "{}", context);
+                        assert_eq!(*len, buffer.len() as u64 - offset, "{}", context);
+                    }
+
+                    // A length that runs past the end looks like a truncated frame
+                    [Problem::Truncated {
+                        frame,
+                        offset: problem_offset,
+                        len,
+                    }] if corruption == "length" => {
+                        assert_eq!(*frame, corrupted_frame, "{}", context);
+                        assert_eq!(*problem_offset, offset, "{}", context);
+                        assert_eq!(*len, buffer.len() as u64 - offset, "{}", context);
+                    }
+
+                    problems => panic!("unexpected problems for {}: {:?}", context, problems),
+                }
+
+                // Resyncing skips to the frame after the corrupted one
+                let file = verify(&path, true, Some(&repaired));
+                assert_eq!(file.frames, frames - 1, "{}", context);
+                assert_eq!(file.events, 5, "{}", context);
+                match &file.problems[..] {
+                    [Problem::Corrupted {
+                        frame,
+                        offset: problem_offset,
+                        len,
+                        resynced: true,
+                        ..
+                    }] => {
+                        assert_eq!(*frame, corrupted_frame, "{}", context);
+                        assert_eq!(*problem_offset, offset, "{}", context);
+                        assert_eq!(*len, next_frame - offset, "{}", context);
+                    }
+
+                    problems => panic!("unexpected problems for {}: {:?}", context, problems),
+                }
+
+                // The repaired file holds every event but the corrupted ones and is intact
+                let expected: Vec<_> = events
+                    .iter()
+                    .enumerate()
+                    .filter(|(idx, _)| !corrupted_events.contains(idx))
+                    .map(|(_, event)| event.clone())
+                    .collect();
+                assert_eq!(read(&repaired), expected, "{}", context);
+                assert_eq!(
+                    fs::metadata(&repaired).unwrap().len(),
+                    file.written,
+                    "{}",
+                    context,
+                );
+
+                let repaired = verify(&repaired, false, None);
+                assert_eq!(repaired.problems, Vec::new(), "{}", context);
+                assert_eq!(repaired.frames, frames - 1, "{}", context);
+            }
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn truncated_frames_are_reported() {
+        let events = events();
+        let dir = test_dir("truncated");
+        let path = dir.join("timely.worker-0.ddshow");
+
+        for framing in FRAMINGS.iter().copied() {
+            // Batched streams hold the last message and progress update in the same frame
+            let last_frame = if framing.is_batched() { 2 } else { 1 };
+            let complete_len = encode(&events[..events.len() - last_frame], framing).len() as u64;
+            let mut buffer = encode(&events, framing);
+            buffer.truncate(buffer.len() - 3);
+            fs::write(&path, &buffer).unwrap();
+
+            let offset = align(complete_len, framing.alignment());
+            let file = verify(&path, true, None);
+            assert_eq!(
+                file.problems,
+                vec![Problem::Truncated {
+                    frame: file.frames,
+                    offset,
+                    len: buffer.len() as u64 - offset,
+                }],
+                "{:?}",
+                framing,
+            );
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}